@@ -119,6 +119,70 @@ pub fn handle_camera(
         };
     }
 
+    if keys.just_pressed(KeyCode::Key6) {
+        camera_render_graph.set(bevy_strolle::graph::NAME);
+
+        camera.mode = match camera.mode {
+            st::CameraMode::Depth => st::CameraMode::Normals,
+            st::CameraMode::Normals => st::CameraMode::BaseColor,
+            st::CameraMode::BaseColor => st::CameraMode::RoughnessMetallic,
+            st::CameraMode::RoughnessMetallic => st::CameraMode::Emissive,
+            st::CameraMode::Emissive => st::CameraMode::MotionVectors,
+            st::CameraMode::MotionVectors => st::CameraMode::Reprojection,
+            _ => st::CameraMode::Depth,
+        };
+    }
+
+    if keys.just_pressed(KeyCode::Key7) {
+        camera_render_graph.set(bevy_strolle::graph::NAME);
+
+        let diagnostic = match camera.mode {
+            st::CameraMode::Diagnostic(diagnostic) => match diagnostic {
+                st::CameraDiagnostic::DiReservoirM => {
+                    st::CameraDiagnostic::DiReservoirW
+                }
+                st::CameraDiagnostic::DiReservoirW => {
+                    st::CameraDiagnostic::DiConfidence
+                }
+                st::CameraDiagnostic::DiConfidence => {
+                    st::CameraDiagnostic::GiReservoirM
+                }
+                st::CameraDiagnostic::GiReservoirM => {
+                    st::CameraDiagnostic::GiReservoirW
+                }
+                st::CameraDiagnostic::GiReservoirW => {
+                    st::CameraDiagnostic::GiConfidence
+                }
+                st::CameraDiagnostic::GiConfidence => {
+                    st::CameraDiagnostic::DiHistory
+                }
+                st::CameraDiagnostic::DiHistory => {
+                    st::CameraDiagnostic::GiHistory
+                }
+                st::CameraDiagnostic::GiHistory => {
+                    st::CameraDiagnostic::ReprojectionRejection
+                }
+                st::CameraDiagnostic::ReprojectionRejection => {
+                    st::CameraDiagnostic::DiVariance
+                }
+                st::CameraDiagnostic::DiVariance => {
+                    st::CameraDiagnostic::GiVariance
+                }
+                st::CameraDiagnostic::GiVariance => {
+                    st::CameraDiagnostic::ShadowRayBvhNodes
+                }
+                st::CameraDiagnostic::ShadowRayBvhNodes => {
+                    st::CameraDiagnostic::DiReservoirM
+                }
+            },
+            _ => st::CameraDiagnostic::DiReservoirM,
+        };
+
+        info!("Diagnostic: {}", diagnostic.legend().label);
+
+        camera.mode = st::CameraMode::Diagnostic(diagnostic);
+    }
+
     if keys.just_pressed(KeyCode::Key8) {
         camera_render_graph.set(bevy_strolle::graph::NAME);
 
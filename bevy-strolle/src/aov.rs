@@ -0,0 +1,41 @@
+use std::sync::{Arc, Mutex};
+
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+use crate::st;
+
+/// Arbitrary output variables (e.g. depth or normals) that cameras in one of
+/// the AOV modes have most recently rendered, as read back from the GPU.
+///
+/// Similarly to [`crate::StrolleExposures`], this resource is shared between
+/// the main world and the render world.
+///
+/// See: [`st::Engine::camera_aov()`](crate::st::Engine::camera_aov).
+#[derive(Clone, Debug, Default, Resource)]
+pub struct StrolleAovs {
+    aovs: Arc<Mutex<HashMap<Entity, Arc<st::CameraAov>>>>,
+}
+
+impl StrolleAovs {
+    /// Returns the AOV given camera has most recently rendered, or `None` if
+    /// the camera is not in one of the AOV modes or the data is not known
+    /// (yet).
+    pub fn get(&self, camera: Entity) -> Option<Arc<st::CameraAov>> {
+        self.aovs.lock().unwrap().get(&camera).cloned()
+    }
+
+    pub(crate) fn set(&self, camera: Entity, aov: Option<Arc<st::CameraAov>>) {
+        let mut aovs = self.aovs.lock().unwrap();
+
+        if let Some(aov) = aov {
+            aovs.insert(camera, aov);
+        } else {
+            aovs.remove(&camera);
+        }
+    }
+
+    pub(crate) fn retain(&self, mut f: impl FnMut(Entity) -> bool) {
+        self.aovs.lock().unwrap().retain(|camera, _| f(*camera));
+    }
+}
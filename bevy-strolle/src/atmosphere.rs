@@ -0,0 +1,26 @@
+use std::ops::{Deref, DerefMut};
+
+use bevy::prelude::Resource;
+use strolle as st;
+
+/// Physical properties of the atmosphere, used to render the sky.
+///
+/// See: [`strolle::AtmosphereSettings`].
+#[derive(Clone, Debug, Default, Resource)]
+pub struct StrolleAtmosphere {
+    settings: st::AtmosphereSettings,
+}
+
+impl Deref for StrolleAtmosphere {
+    type Target = st::AtmosphereSettings;
+
+    fn deref(&self) -> &Self::Target {
+        &self.settings
+    }
+}
+
+impl DerefMut for StrolleAtmosphere {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.settings
+    }
+}
@@ -8,4 +8,55 @@ use strolle as st;
 #[derive(Clone, Debug, Default, Component)]
 pub struct StrolleCamera {
     pub mode: st::CameraMode,
+    pub anti_aliasing: st::CameraAntiAliasing,
+
+    /// When set, the camera renders a 360° panorama instead of using Bevy's
+    /// projection.
+    ///
+    /// See: [`st::Camera::panorama`].
+    pub panorama: Option<st::CameraPanorama>,
+
+    /// When set, the image gets rendered at the viewport's size multiplied by
+    /// this factor (e.g. `Some(0.5)` renders at half of the resolution) and
+    /// then temporally upscaled.
+    ///
+    /// See: [`st::CameraViewport::internal_size`].
+    pub render_scale: Option<f32>,
+
+    /// When set, the resolution gets adjusted each frame (on top of
+    /// `render_scale`) so that rendering takes approximately the target time.
+    ///
+    /// Measuring the GPU time requires `WgpuFeatures::TIMESTAMP_QUERY` to be
+    /// enabled in `WgpuSettings` - otherwise the entire frame's time is used.
+    ///
+    /// See: [`st::CameraDynamicResolution`].
+    pub dynamic_resolution: Option<st::CameraDynamicResolution>,
+
+    /// Exposure the image gets scaled by; with [`st::CameraExposure::Auto`],
+    /// the current exposure can be read through [`crate::StrolleExposures`].
+    ///
+    /// See: [`st::CameraExposure`].
+    pub exposure: st::CameraExposure,
+
+    /// Tonemapping applied by Strolle; keep it at
+    /// [`st::CameraTonemapping::None`] when using Bevy's own tonemapping.
+    ///
+    /// See: [`st::CameraTonemapping`].
+    pub tonemapping: st::CameraTonemapping,
+
+    /// See: [`st::CameraBloom`].
+    pub bloom: Option<st::CameraBloom>,
+
+    /// See: [`st::CameraDepthOfField`].
+    pub depth_of_field: Option<st::CameraDepthOfField>,
+
+    /// See: [`st::CameraMotionBlur`].
+    pub motion_blur: Option<st::CameraMotionBlur>,
+
+    /// See: [`st::Camera::diagnostic_max`].
+    pub diagnostic_max: Option<f32>,
+
+    /// See: [`st::Camera::reference_samples`]; progress can be read through
+    /// [`crate::StrolleReferences`].
+    pub reference_samples: Option<u32>,
 }
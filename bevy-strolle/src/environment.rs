@@ -0,0 +1,97 @@
+use std::array;
+
+use bevy::math::{uvec2, vec3};
+use bevy::prelude::*;
+use bevy::render::render_resource::TextureFormat;
+use half::f16;
+use strolle as st;
+
+/// Determines what's visible in the background and where the light that
+/// doesn't hit any geometry comes from.
+///
+/// Note that Bevy's `EnvironmentMapLight` is not used by Strolle, since its
+/// maps are usually prefiltered and compressed, which makes them unsuitable
+/// for ray-tracing.
+///
+/// See: [`strolle::Environment`].
+#[derive(Clone, Debug, Default, Resource)]
+pub enum StrolleEnvironment {
+    /// Procedural atmosphere, lit by [`crate::StrolleSun`].
+    #[default]
+    Atmosphere,
+
+    /// HDR environment map.
+    ///
+    /// The image must be either equirectangular (e.g. a `.hdr` file) or a
+    /// cubemap (six layers), in the `Rgba32Float` or `Rgba16Float` format.
+    Map {
+        image: Handle<Image>,
+        intensity: f32,
+    },
+}
+
+/// Converts Bevy's image into Strolle's environment map; returns `None` if the
+/// image's format or shape is not supported.
+pub(crate) fn environment_map(
+    image: &Image,
+    intensity: f32,
+) -> Option<st::EnvironmentMap> {
+    let pixels: Vec<_> = match image.texture_descriptor.format {
+        TextureFormat::Rgba32Float => image
+            .data
+            .chunks_exact(16)
+            .map(|pixel| {
+                let channel = |idx: usize| {
+                    f32::from_le_bytes([
+                        pixel[4 * idx],
+                        pixel[4 * idx + 1],
+                        pixel[4 * idx + 2],
+                        pixel[4 * idx + 3],
+                    ])
+                };
+
+                vec3(channel(0), channel(1), channel(2))
+            })
+            .collect(),
+
+        TextureFormat::Rgba16Float => image
+            .data
+            .chunks_exact(8)
+            .map(|pixel| {
+                let channel = |idx: usize| {
+                    f16::from_bits(u16::from_le_bytes([
+                        pixel[2 * idx],
+                        pixel[2 * idx + 1],
+                    ]))
+                    .to_f32()
+                };
+
+                vec3(channel(0), channel(1), channel(2))
+            })
+            .collect(),
+
+        _ => return None,
+    };
+
+    let size = image.texture_descriptor.size;
+
+    let map = match size.depth_or_array_layers {
+        1 => {
+            st::EnvironmentMap::equirect(uvec2(size.width, size.height), pixels)
+        }
+
+        6 if size.width == size.height => {
+            let face_len = (size.width * size.height) as usize;
+
+            let faces = array::from_fn(|idx| {
+                pixels[idx * face_len..(idx + 1) * face_len].to_vec()
+            });
+
+            st::EnvironmentMap::cubemap(size.width, faces)
+        }
+
+        _ => return None,
+    };
+
+    Some(map.with_intensity(intensity))
+}
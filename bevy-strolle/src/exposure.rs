@@ -0,0 +1,41 @@
+use std::sync::{Arc, Mutex};
+
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+/// Exposure values (in EVs) that cameras have most recently rendered with, as
+/// read back from the GPU; useful e.g. for showing the current exposure in UI.
+///
+/// This resource is shared between the main world and the render world, so it
+/// can be read directly from the main world's systems.
+///
+/// See: [`st::Engine::camera_exposure()`](crate::st::Engine::camera_exposure).
+#[derive(Clone, Debug, Default, Resource)]
+pub struct StrolleExposures {
+    exposures: Arc<Mutex<HashMap<Entity, f32>>>,
+}
+
+impl StrolleExposures {
+    /// Returns the exposure given camera has most recently rendered with, or
+    /// `None` if it's not known (yet).
+    pub fn get(&self, camera: Entity) -> Option<f32> {
+        self.exposures.lock().unwrap().get(&camera).copied()
+    }
+
+    pub(crate) fn set(&self, camera: Entity, ev: Option<f32>) {
+        let mut exposures = self.exposures.lock().unwrap();
+
+        if let Some(ev) = ev {
+            exposures.insert(camera, ev);
+        } else {
+            exposures.remove(&camera);
+        }
+    }
+
+    pub(crate) fn retain(&self, mut f: impl FnMut(Entity) -> bool) {
+        self.exposures
+            .lock()
+            .unwrap()
+            .retain(|camera, _| f(*camera));
+    }
+}
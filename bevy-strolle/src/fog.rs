@@ -0,0 +1,76 @@
+use std::ops::{Deref, DerefMut};
+
+use bevy::pbr::{FogFalloff, FogSettings};
+use bevy::prelude::*;
+use strolle as st;
+
+use crate::utils::color_to_vec3;
+
+/// Fog that fills the world, lit by the lights.
+///
+/// When this resource doesn't contain any fog (the default), Strolle falls
+/// back to Bevy's `FogSettings` attached to the camera, if any - see
+/// [`fog_from_bevy()`] for details.
+///
+/// See: [`strolle::Fog`].
+#[derive(Clone, Debug, Default, Resource)]
+pub struct StrolleFog {
+    fog: st::Fog,
+}
+
+impl Deref for StrolleFog {
+    type Target = st::Fog;
+
+    fn deref(&self) -> &Self::Target {
+        &self.fog
+    }
+}
+
+impl DerefMut for StrolleFog {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.fog
+    }
+}
+
+/// Converts Bevy's fog into Strolle's one.
+///
+/// Strolle's fog is homogeneous and gets lit by the actual lights, so:
+///
+/// - fog's color is used as its albedo (i.e. the fraction of light that gets
+///   scattered instead of absorbed),
+///
+/// - `FogFalloff::ExponentialSquared` is approximated with an exponential fog
+///   of the same density,
+///
+/// - `FogFalloff::Linear` is approximated with an exponential fog that reaches
+///   ~95% opacity at `end` (`start` is ignored),
+///
+/// - directional light's glow is ignored, since the light gets scattered
+///   around the sun anyway.
+pub fn fog_from_bevy(fog: &FogSettings) -> st::Fog {
+    let albedo = color_to_vec3(fog.color);
+
+    let (scattering, absorption) = match fog.falloff {
+        FogFalloff::Linear { end, .. } => {
+            let extinction = 3.0 / end.max(0.001);
+
+            (albedo * extinction, (1.0 - albedo) * extinction)
+        }
+
+        FogFalloff::Exponential { density }
+        | FogFalloff::ExponentialSquared { density } => {
+            (albedo * density, (1.0 - albedo) * density)
+        }
+
+        FogFalloff::Atmospheric {
+            extinction,
+            inscattering,
+        } => (inscattering, (extinction - inscattering).max(Vec3::ZERO)),
+    };
+
+    st::Fog {
+        scattering,
+        absorption,
+        ..Default::default()
+    }
+}
@@ -1,7 +1,14 @@
+mod aov;
+mod atmosphere;
 mod camera;
 mod debug;
+mod environment;
 mod event;
+mod exposure;
+mod fog;
 pub mod graph;
+mod light_linking;
+mod reference;
 mod rendering_node;
 mod stages;
 mod state;
@@ -20,9 +27,16 @@ use bevy::render::renderer::RenderDevice;
 use bevy::render::RenderApp;
 pub use strolle as st;
 
+pub use self::aov::*;
+pub use self::atmosphere::*;
 pub use self::camera::*;
 pub use self::debug::*;
+pub use self::environment::*;
 pub use self::event::*;
+pub use self::exposure::*;
+pub use self::fog::*;
+pub use self::light_linking::*;
+pub use self::reference::*;
 pub(crate) use self::rendering_node::*;
 pub(crate) use self::state::*;
 pub use self::sun::*;
@@ -33,9 +47,29 @@ impl Plugin for StrollePlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<StrolleEvent>();
         app.insert_resource(StrolleSun::default());
+        app.insert_resource(StrolleMoon::default());
+        app.insert_resource(StrolleAtmosphere::default());
+        app.insert_resource(StrolleEnvironment::default());
+        app.insert_resource(StrolleFog::default());
+
+        let exposures = StrolleExposures::default();
+        let aovs = StrolleAovs::default();
+        let references = StrolleReferences::default();
+
+        app.insert_resource(exposures.clone());
+        app.insert_resource(aovs.clone());
+        app.insert_resource(references.clone());
+
+        app.add_systems(
+            Update,
+            sun::animate.run_if(resource_exists::<StrolleSunClock>()),
+        );
 
         if let Ok(render_app) = app.get_sub_app_mut(RenderApp) {
             render_app.insert_resource(SyncedState::default());
+            render_app.insert_resource(exposures);
+            render_app.insert_resource(aovs);
+            render_app.insert_resource(references);
 
             stages::setup(render_app);
             graph::setup(render_app);
@@ -0,0 +1,16 @@
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+
+/// Restricts which entities a light (`PointLight`, `SpotLight` or
+/// `DirectionalLight`) affects.
+///
+/// See: [`strolle::LightLinking`].
+#[derive(Clone, Debug, Component)]
+pub enum StrolleLightLinking {
+    /// Light affects only given entities.
+    Include(HashSet<Entity>),
+
+    /// Light affects all entities except the given ones.
+    Exclude(HashSet<Entity>),
+}
@@ -0,0 +1,48 @@
+use std::sync::{Arc, Mutex};
+
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+use crate::st;
+
+/// Progress of cameras in [`st::CameraMode::Reference`] - number of samples
+/// accumulated so far, per-pixel variance and whether the target number of
+/// samples has been reached.
+///
+/// Similarly to [`crate::StrolleExposures`], this resource is shared between
+/// the main world and the render world.
+///
+/// See: [`st::Engine::camera_reference()`](crate::st::Engine::camera_reference).
+#[derive(Clone, Debug, Default, Resource)]
+pub struct StrolleReferences {
+    references: Arc<Mutex<HashMap<Entity, st::CameraReference>>>,
+}
+
+impl StrolleReferences {
+    /// Returns progress of given camera, or `None` if the camera is not in
+    /// the reference mode.
+    pub fn get(&self, camera: Entity) -> Option<st::CameraReference> {
+        self.references.lock().unwrap().get(&camera).cloned()
+    }
+
+    pub(crate) fn set(
+        &self,
+        camera: Entity,
+        reference: Option<st::CameraReference>,
+    ) {
+        let mut references = self.references.lock().unwrap();
+
+        if let Some(reference) = reference {
+            references.insert(camera, reference);
+        } else {
+            references.remove(&camera);
+        }
+    }
+
+    pub(crate) fn retain(&self, mut f: impl FnMut(Entity) -> bool) {
+        self.references
+            .lock()
+            .unwrap()
+            .retain(|camera, _| f(*camera));
+    }
+}
@@ -40,6 +40,21 @@ pub(crate) fn setup(render_app: &mut App) {
         extract::sun.in_set(RenderSet::ExtractCommands),
     );
 
+    render_app.add_systems(
+        ExtractSchedule,
+        extract::atmosphere.in_set(RenderSet::ExtractCommands),
+    );
+
+    render_app.add_systems(
+        ExtractSchedule,
+        extract::environment.in_set(RenderSet::ExtractCommands),
+    );
+
+    render_app.add_systems(
+        ExtractSchedule,
+        extract::fog.in_set(RenderSet::ExtractCommands),
+    );
+
     render_app.add_systems(Render, prepare::meshes.in_set(RenderSet::Prepare));
 
     render_app
@@ -56,6 +71,13 @@ pub(crate) fn setup(render_app: &mut App) {
     render_app.add_systems(Render, prepare::images.in_set(RenderSet::Prepare));
     render_app.add_systems(Render, prepare::lights.in_set(RenderSet::Prepare));
     render_app.add_systems(Render, prepare::sun.in_set(RenderSet::Prepare));
+
+    render_app
+        .add_systems(Render, prepare::atmosphere.in_set(RenderSet::Prepare));
+
+    render_app
+        .add_systems(Render, prepare::environment.in_set(RenderSet::Prepare));
+    render_app.add_systems(Render, prepare::fog.in_set(RenderSet::Prepare));
     render_app.add_systems(Render, prepare::cameras.in_set(RenderSet::Prepare));
 
     render_app
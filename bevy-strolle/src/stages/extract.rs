@@ -1,5 +1,6 @@
 use std::f32::consts::PI;
 
+use bevy::pbr::FogSettings;
 use bevy::prelude::*;
 use bevy::render::camera::{CameraProjection, CameraRenderGraph};
 use bevy::render::texture::{ImageSampler, ImageSamplerDescriptor};
@@ -8,14 +9,19 @@ use bevy::render::Extract;
 use bevy::utils::HashSet;
 use strolle as st;
 
+use crate::environment::environment_map;
 use crate::state::{
-    ExtractedCamera, ExtractedImage, ExtractedImageData, ExtractedImages,
-    ExtractedInstance, ExtractedInstances, ExtractedLight, ExtractedLights,
+    ExtractedAtmosphere, ExtractedCamera, ExtractedEnvironment, ExtractedFog,
+    ExtractedImage, ExtractedImageData, ExtractedImages, ExtractedInstance,
+    ExtractedInstances, ExtractedLight, ExtractedLightLinking, ExtractedLights,
     ExtractedMaterial, ExtractedMaterials, ExtractedMesh, ExtractedMeshes,
     ExtractedSun,
 };
 use crate::utils::color_to_vec3;
-use crate::{StrolleCamera, StrolleEvent, StrolleSun};
+use crate::{
+    fog_from_bevy, StrolleAtmosphere, StrolleCamera, StrolleEnvironment,
+    StrolleEvent, StrolleFog, StrolleLightLinking, StrolleMoon, StrolleSun,
+};
 
 pub(crate) fn meshes(
     mut commands: Commands,
@@ -256,6 +262,12 @@ pub(crate) fn instances(
     commands.insert_resource(ExtractedInstances { changed, removed });
 }
 
+/// Exposure used by Bevy to convert directional lights' illuminance into
+/// luminous intensity, corresponding to EV100 of `log2(4.0^2 / (1/250))`.
+///
+/// See: `bevy_pbr::render::light::prepare_lights()`.
+const DIRECTIONAL_LIGHT_EXPOSURE: f32 = 1.0 / (4000.0 * 1.2);
+
 #[allow(clippy::type_complexity)]
 pub(crate) fn lights(
     mut commands: Commands,
@@ -271,12 +283,26 @@ pub(crate) fn lights(
             Or<(Changed<SpotLight>, Changed<GlobalTransform>)>,
         >,
     >,
+    changed_directional_lights: Extract<
+        Query<
+            (Entity, &DirectionalLight, &GlobalTransform),
+            Or<(Changed<DirectionalLight>, Changed<GlobalTransform>)>,
+        >,
+    >,
     mut removed_point_lights: Extract<RemovedComponents<PointLight>>,
     mut removed_spot_lights: Extract<RemovedComponents<SpotLight>>,
+    mut removed_directional_lights: Extract<
+        RemovedComponents<DirectionalLight>,
+    >,
+    changed_links: Extract<
+        Query<(Entity, &StrolleLightLinking), Changed<StrolleLightLinking>>,
+    >,
+    mut removed_links: Extract<RemovedComponents<StrolleLightLinking>>,
 ) {
     let mut removed: Vec<_> = removed_point_lights
         .read()
         .chain(removed_spot_lights.read())
+        .chain(removed_directional_lights.read())
         .collect();
 
     let changed_point_lights: Vec<_> = changed_point_lights
@@ -289,11 +315,18 @@ pub(crate) fn lights(
                 return None;
             }
 
+            let (_, rotation, translation) =
+                xform.to_scale_rotation_translation();
+
             let light = st::Light::Point {
-                position: xform.translation(),
+                position: translation,
                 radius: light.radius,
                 color: color_to_vec3(light.color) * intensity,
                 range: light.range,
+                direction: (rotation * -Vec3::Y).normalize(),
+                ies_reference: (rotation * Vec3::X).normalize(),
+                casts_shadows: light.shadows_enabled,
+                ies_profile: None,
             };
 
             Some(ExtractedLight { handle, light })
@@ -320,6 +353,35 @@ pub(crate) fn lights(
                 range: light.range,
                 direction: -(rotation * Vec3::Z).normalize(),
                 angle: light.outer_angle,
+                ies_reference: (rotation * Vec3::X).normalize(),
+                casts_shadows: light.shadows_enabled,
+                ies_profile: None,
+            };
+
+            Some(ExtractedLight { handle, light })
+        })
+        .collect();
+
+    let changed_directional_lights: Vec<_> = changed_directional_lights
+        .iter()
+        .filter_map(|(handle, light, xform)| {
+            // Bevy's illuminance is expressed in lux, which it then converts
+            // into candelas using a hard-coded exposure - let's do the same so
+            // that the scenes look alike
+            let intensity = light.illuminance * DIRECTIONAL_LIGHT_EXPOSURE;
+
+            if intensity < 0.0001 {
+                removed.push(handle);
+                return None;
+            }
+
+            let (_, rotation, _) = xform.to_scale_rotation_translation();
+
+            let light = st::Light::Directional {
+                direction: -(rotation * Vec3::Z).normalize(),
+                color: color_to_vec3(light.color) * intensity,
+                angular_diameter: st::Sun::ANGULAR_DIAMETER,
+                casts_shadows: light.shadows_enabled,
             };
 
             Some(ExtractedLight { handle, light })
@@ -329,9 +391,33 @@ pub(crate) fn lights(
     let changed = changed_point_lights
         .into_iter()
         .chain(changed_spot_lights)
+        .chain(changed_directional_lights)
         .collect();
 
-    commands.insert_resource(ExtractedLights { changed, removed });
+    let changed_links = changed_links
+        .iter()
+        .map(|(handle, linking)| {
+            let linking = match linking {
+                StrolleLightLinking::Include(entities) => {
+                    st::LightLinking::Include(entities.clone())
+                }
+                StrolleLightLinking::Exclude(entities) => {
+                    st::LightLinking::Exclude(entities.clone())
+                }
+            };
+
+            ExtractedLightLinking { handle, linking }
+        })
+        .collect();
+
+    let removed_links = removed_links.read().collect();
+
+    commands.insert_resource(ExtractedLights {
+        changed,
+        removed,
+        changed_links,
+        removed_links,
+    });
 }
 
 #[allow(clippy::type_complexity)]
@@ -367,10 +453,132 @@ pub(crate) fn cameras(
             transform: transform.compute_matrix(),
             projection: projection.get_projection_matrix(),
             mode: strolle_camera.map(|camera| camera.mode),
+            anti_aliasing: strolle_camera.map(|camera| camera.anti_aliasing),
+            panorama: strolle_camera.and_then(|camera| camera.panorama),
+            render_scale: strolle_camera.and_then(|camera| camera.render_scale),
+            dynamic_resolution: strolle_camera
+                .and_then(|camera| camera.dynamic_resolution),
+            exposure: strolle_camera.map(|camera| camera.exposure),
+            tonemapping: strolle_camera
+                .map(|camera| camera.tonemapping.clone()),
+            bloom: strolle_camera.and_then(|camera| camera.bloom),
+            depth_of_field: strolle_camera
+                .and_then(|camera| camera.depth_of_field),
+            motion_blur: strolle_camera.and_then(|camera| camera.motion_blur),
+            diagnostic_max: strolle_camera
+                .and_then(|camera| camera.diagnostic_max),
+            reference_samples: strolle_camera
+                .and_then(|camera| camera.reference_samples),
         });
     }
 }
 
-pub(crate) fn sun(mut commands: Commands, sun: Extract<Res<StrolleSun>>) {
-    commands.insert_resource(ExtractedSun { sun: Some(***sun) });
+pub(crate) fn sun(
+    mut commands: Commands,
+    sun: Extract<Res<StrolleSun>>,
+    moon: Extract<Res<StrolleMoon>>,
+) {
+    // Changing the sun regenerates atmosphere's lookup textures, so - just like
+    // below - let's do it only when necessary
+    let sun = (sun.is_changed() || moon.is_changed()).then(|| ***sun);
+
+    commands.insert_resource(ExtractedSun { sun, moon: ***moon });
+}
+
+pub(crate) fn atmosphere(
+    mut commands: Commands,
+    atmosphere: Extract<Res<StrolleAtmosphere>>,
+) {
+    // Changing the atmosphere regenerates its lookup textures, so let's do it
+    // only when necessary
+    let settings = atmosphere.is_changed().then(|| ***atmosphere);
+
+    commands.insert_resource(ExtractedAtmosphere { settings });
+}
+
+pub(crate) fn environment(
+    mut commands: Commands,
+    environment: Extract<Res<StrolleEnvironment>>,
+    mut asset_events: Extract<EventReader<AssetEvent<Image>>>,
+    images: Extract<Res<Assets<Image>>>,
+    mut is_pending: Local<bool>,
+) {
+    let mut is_dirty = environment.is_changed() || *is_pending;
+
+    for event in asset_events.read() {
+        if let (
+            AssetEvent::Added { id } | AssetEvent::Modified { id },
+            StrolleEnvironment::Map { image, .. },
+        ) = (event, &**environment)
+        {
+            is_dirty |= *id == image.id();
+        }
+    }
+
+    let environment = if is_dirty {
+        match &**environment {
+            StrolleEnvironment::Atmosphere => {
+                *is_pending = false;
+
+                Some(st::Environment::Atmosphere)
+            }
+
+            StrolleEnvironment::Map { image, intensity } => {
+                // If the image is not loaded yet, let's wait for it
+                *is_pending = true;
+
+                images.get(image).map(|image| {
+                    *is_pending = false;
+
+                    if let Some(map) = environment_map(image, *intensity) {
+                        st::Environment::Map(map)
+                    } else {
+                        warn!(
+                            "Environment map has unsupported format ({:?}) or \
+                             shape ({:?}) - falling back to the atmosphere",
+                            image.texture_descriptor.format,
+                            image.texture_descriptor.size,
+                        );
+
+                        st::Environment::Atmosphere
+                    }
+                })
+            }
+        }
+    } else {
+        None
+    };
+
+    commands.insert_resource(ExtractedEnvironment { environment });
+}
+
+pub(crate) fn fog(
+    mut commands: Commands,
+    fog: Extract<Res<StrolleFog>>,
+    cameras: Extract<Query<(&Camera, &CameraRenderGraph, &FogSettings)>>,
+    mut known_fog: Local<Option<st::Fog>>,
+) {
+    let fog = if fog.is_active() {
+        (***fog).clone()
+    } else {
+        cameras
+            .iter()
+            .find(|(camera, camera_render_graph, _)| {
+                camera.is_active && ***camera_render_graph == crate::graph::NAME
+            })
+            .map(|(_, _, fog)| fog_from_bevy(fog))
+            .unwrap_or_default()
+    };
+
+    // Bevy's fog lives on cameras, so we can't rely on change detection here -
+    // instead, let's compare the fog against the one we've sent last time
+    let fog = if known_fog.as_ref() == Some(&fog) {
+        None
+    } else {
+        *known_fog = Some(fog.clone());
+
+        Some(fog)
+    };
+
+    commands.insert_resource(ExtractedFog { fog });
 }
@@ -12,12 +12,13 @@ use bevy::utils::HashSet;
 use strolle as st;
 
 use crate::state::{
-    ExtractedCamera, ExtractedImageData, ExtractedImages, ExtractedInstances,
-    ExtractedLights, ExtractedMaterials, ExtractedMeshes, ExtractedSun,
-    SyncedCamera, SyncedState,
+    ExtractedAtmosphere, ExtractedCamera, ExtractedEnvironment, ExtractedFog,
+    ExtractedImageData, ExtractedImages, ExtractedInstances, ExtractedLights,
+    ExtractedMaterials, ExtractedMeshes, ExtractedSun, SyncedCamera,
+    SyncedState,
 };
 use crate::utils::color_to_vec4;
-use crate::EngineResource;
+use crate::{EngineResource, StrolleAovs, StrolleExposures, StrolleReferences};
 
 pub(crate) fn meshes(
     mut engine: ResMut<EngineResource>,
@@ -261,14 +262,50 @@ pub(crate) fn lights(
     for entry in mem::take(&mut lights.changed) {
         engine.insert_light(entry.handle, entry.light);
     }
+
+    for handle in &lights.removed_links {
+        engine.remove_light_linking(*handle);
+    }
+
+    for entry in mem::take(&mut lights.changed_links) {
+        engine.insert_light_linking(entry.handle, entry.linking);
+    }
 }
 
 pub(crate) fn sun(
     mut engine: ResMut<EngineResource>,
     mut sun: ResMut<ExtractedSun>,
 ) {
-    if let Some(sun) = sun.sun.take() {
-        engine.update_sun(sun);
+    if let Some(new_sun) = sun.sun.take() {
+        engine.update_sun(new_sun);
+        engine.update_moon(sun.moon);
+    }
+}
+
+pub(crate) fn atmosphere(
+    mut engine: ResMut<EngineResource>,
+    mut atmosphere: ResMut<ExtractedAtmosphere>,
+) {
+    if let Some(settings) = atmosphere.settings.take() {
+        engine.update_atmosphere(settings);
+    }
+}
+
+pub(crate) fn environment(
+    mut engine: ResMut<EngineResource>,
+    mut environment: ResMut<ExtractedEnvironment>,
+) {
+    if let Some(environment) = environment.environment.take() {
+        engine.update_environment(environment);
+    }
+}
+
+pub(crate) fn fog(
+    mut engine: ResMut<EngineResource>,
+    mut fog: ResMut<ExtractedFog>,
+) {
+    if let Some(fog) = fog.fog.take() {
+        engine.update_fog(fog);
     }
 }
 
@@ -276,6 +313,9 @@ pub(crate) fn cameras(
     device: Res<RenderDevice>,
     mut state: ResMut<SyncedState>,
     mut engine: ResMut<EngineResource>,
+    exposures: Res<StrolleExposures>,
+    aovs: Res<StrolleAovs>,
+    references: Res<StrolleReferences>,
     mut cameras: Query<(
         Entity,
         &ViewTarget,
@@ -306,28 +346,51 @@ pub(crate) fn cameras(
                     .map(|v| v.physical_position)
                     .unwrap_or_default();
 
+                let internal_size = ext_camera.render_scale.map(|scale| {
+                    (size.as_vec2() * scale).round().as_uvec2().max(UVec2::ONE)
+                });
+
                 st::CameraViewport {
                     format,
                     size,
                     position,
+                    internal_size,
                 }
             },
 
             transform: ext_camera.transform,
             projection: ext_camera.projection,
+            panorama: ext_camera.panorama,
+            anti_aliasing: ext_camera.anti_aliasing.unwrap_or_default(),
+            dynamic_resolution: ext_camera.dynamic_resolution,
+            exposure: ext_camera.exposure.unwrap_or_default(),
+            tonemapping: ext_camera.tonemapping.clone().unwrap_or_default(),
+            bloom: ext_camera.bloom,
+            depth_of_field: ext_camera.depth_of_field,
+            motion_blur: ext_camera.motion_blur,
+            diagnostic_max: ext_camera.diagnostic_max,
+            reference_samples: ext_camera.reference_samples,
         };
 
-        match state.cameras.entry(entity) {
+        let handle = match state.cameras.entry(entity) {
             Entry::Occupied(entry) => {
-                engine.update_camera(device, entry.into_mut().handle, camera);
+                let handle = entry.into_mut().handle;
+
+                engine.update_camera(device, handle, camera);
+                handle
             }
 
             Entry::Vacant(entry) => {
-                entry.insert(SyncedCamera {
-                    handle: engine.create_camera(device, camera),
-                });
+                let handle = engine.create_camera(device, camera);
+
+                entry.insert(SyncedCamera { handle });
+                handle
             }
-        }
+        };
+
+        exposures.set(entity, engine.camera_exposure(handle));
+        aovs.set(entity, engine.camera_aov(handle));
+        references.set(entity, engine.camera_reference(handle));
 
         alive_cameras.insert(entity);
     }
@@ -344,6 +407,10 @@ pub(crate) fn cameras(
 
             is_alive
         });
+
+        exposures.retain(|entity| alive_cameras.contains(&entity));
+        aovs.retain(|entity| alive_cameras.contains(&entity));
+        references.retain(|entity| alive_cameras.contains(&entity));
     }
 }
 
@@ -95,6 +95,8 @@ pub(crate) struct ExtractedInstance {
 pub(crate) struct ExtractedLights {
     pub changed: Vec<ExtractedLight>,
     pub removed: Vec<Entity>,
+    pub changed_links: Vec<ExtractedLightLinking>,
+    pub removed_links: Vec<Entity>,
 }
 
 #[derive(Debug)]
@@ -103,14 +105,47 @@ pub(crate) struct ExtractedLight {
     pub light: st::Light,
 }
 
+#[derive(Debug)]
+pub(crate) struct ExtractedLightLinking {
+    pub handle: Entity,
+    pub linking: st::LightLinking<EngineParams>,
+}
+
 #[derive(Debug, Component)]
 pub(crate) struct ExtractedCamera {
     pub transform: Mat4,
     pub projection: Mat4,
     pub mode: Option<st::CameraMode>,
+    pub anti_aliasing: Option<st::CameraAntiAliasing>,
+    pub panorama: Option<st::CameraPanorama>,
+    pub render_scale: Option<f32>,
+    pub dynamic_resolution: Option<st::CameraDynamicResolution>,
+    pub exposure: Option<st::CameraExposure>,
+    pub tonemapping: Option<st::CameraTonemapping>,
+    pub bloom: Option<st::CameraBloom>,
+    pub depth_of_field: Option<st::CameraDepthOfField>,
+    pub motion_blur: Option<st::CameraMotionBlur>,
+    pub diagnostic_max: Option<f32>,
+    pub reference_samples: Option<u32>,
 }
 
 #[derive(Debug, Resource)]
 pub(crate) struct ExtractedSun {
     pub sun: Option<st::Sun>,
+    pub moon: Option<st::Moon>,
+}
+
+#[derive(Debug, Resource)]
+pub(crate) struct ExtractedAtmosphere {
+    pub settings: Option<st::AtmosphereSettings>,
+}
+
+#[derive(Debug, Resource)]
+pub(crate) struct ExtractedEnvironment {
+    pub environment: Option<st::Environment>,
+}
+
+#[derive(Debug, Resource)]
+pub(crate) struct ExtractedFog {
+    pub fog: Option<st::Fog>,
 }
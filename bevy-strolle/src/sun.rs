@@ -1,9 +1,9 @@
 use std::ops::{Deref, DerefMut};
 
-use bevy::prelude::Resource;
+use bevy::prelude::*;
 use strolle as st;
 
-#[derive(Clone, Debug, Default, Resource)]
+#[derive(Clone, Debug, Default, PartialEq, Resource)]
 pub struct StrolleSun {
     sun: st::Sun,
 }
@@ -21,3 +21,96 @@ impl DerefMut for StrolleSun {
         &mut self.sun
     }
 }
+
+/// Moon that lights the scene during the night; `None` (the default) places a
+/// full moon on the opposite side of the sky than the sun.
+///
+/// See: [`strolle::Moon`].
+#[derive(Clone, Debug, Default, PartialEq, Resource)]
+pub struct StrolleMoon {
+    moon: Option<st::Moon>,
+}
+
+impl Deref for StrolleMoon {
+    type Target = Option<st::Moon>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.moon
+    }
+}
+
+impl DerefMut for StrolleMoon {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.moon
+    }
+}
+
+/// Simulated clock that drives [`StrolleSun`] and [`StrolleMoon`] from a
+/// geographic location and date-time.
+///
+/// This resource is not inserted by default - when present, it overwrites
+/// [`StrolleSun`] and [`StrolleMoon`] whenever the simulated time (or the
+/// location) changes.
+///
+/// See: [`strolle::Sun::from_geo()`].
+#[derive(Clone, Debug, Resource)]
+pub struct StrolleSunClock {
+    /// Latitude, in degrees (positive towards the north).
+    pub latitude: f32,
+
+    /// Longitude, in degrees (positive towards the east).
+    pub longitude: f32,
+
+    /// Angle (in radians) by which the geographic north is rotated from -Z,
+    /// clockwise when looking from above.
+    pub north_offset: f32,
+
+    /// Current (simulated) date-time.
+    pub datetime: st::UtcDateTime,
+
+    /// How many simulated seconds pass per one real second; e.g. `60.0` makes
+    /// a minute pass each second, while `0.0` stops the clock.
+    pub time_scale: f32,
+}
+
+impl Default for StrolleSunClock {
+    fn default() -> Self {
+        Self {
+            latitude: 0.0,
+            longitude: 0.0,
+            north_offset: 0.0,
+            datetime: st::UtcDateTime::new(2000, 6, 21, 12, 0, 0.0),
+            time_scale: 1.0,
+        }
+    }
+}
+
+pub(crate) fn animate(
+    time: Res<Time>,
+    mut clock: ResMut<StrolleSunClock>,
+    mut sun: ResMut<StrolleSun>,
+    mut moon: ResMut<StrolleMoon>,
+) {
+    if clock.time_scale != 0.0 {
+        clock.datetime = clock
+            .datetime
+            .add_seconds(time.delta_seconds_f64() * (clock.time_scale as f64));
+    }
+
+    // Updating the sun causes the atmosphere to get regenerated (and resets the
+    // reference mode), so let's avoid doing that when the clock is paused
+    let mut new_sun =
+        st::Sun::from_geo(clock.latitude, clock.longitude, clock.datetime);
+
+    new_sun.azimuth += clock.north_offset;
+
+    let mut new_moon =
+        st::Moon::from_geo(clock.latitude, clock.longitude, clock.datetime);
+
+    new_moon.azimuth += clock.north_offset;
+
+    sun.set_if_neq(StrolleSun { sun: new_sun });
+    moon.set_if_neq(StrolleMoon {
+        moon: Some(new_moon),
+    });
+}
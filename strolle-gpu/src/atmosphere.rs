@@ -1,14 +1,17 @@
 use core::f32::consts::PI;
 
+use bytemuck::{Pod, Zeroable};
 use glam::{uvec2, vec2, vec3, UVec2, Vec3, Vec4Swizzles};
 #[cfg(target_arch = "spirv")]
 use spirv_std::num_traits::Float;
 use spirv_std::Sampler;
 
-use crate::{F32Ext, Ray, Tex};
+use crate::{CloudsSettings, F32Ext, Ray, Tex, World};
 
 #[derive(Clone, Copy)]
 pub struct Atmosphere<'a> {
+    world: &'a World,
+    settings: &'a AtmosphereSettings,
     transmittance_lut_tex: Tex<'a>,
     transmittance_lut_sampler: &'a Sampler,
     sky_lut_tex: Tex<'a>,
@@ -18,7 +21,7 @@ pub struct Atmosphere<'a> {
 impl<'a> Atmosphere<'a> {
     /// Resolution of the transmittance lookup texture.
     ///
-    /// This texture is generated just once, when Strolle is booting.
+    /// This texture is regenerated only when atmosphere's settings change.
     pub const TRANSMITTANCE_LUT_RESOLUTION: UVec2 = uvec2(256, 64);
 
     /// Quality of the transmittance lookup texture.
@@ -26,7 +29,7 @@ impl<'a> Atmosphere<'a> {
 
     /// Resolution of the scattering lookup texture.
     ///
-    /// This texture is generated just once, when Strolle is booting.
+    /// This texture is regenerated only when atmosphere's settings change.
     pub const SCATTERING_LUT_RESOLUTION: UVec2 = uvec2(32, 32);
 
     /// Quality of the scattering lookup texture.
@@ -37,45 +40,34 @@ impl<'a> Atmosphere<'a> {
 
     /// Resolution of the sky lookup texture.
     ///
-    /// This texture is regenerated each time sun's position changes so it's
-    /// important not to go too crazy in here.
+    /// This texture is regenerated each time sun's position (or atmosphere's
+    /// settings) change so it's important not to go too crazy in here.
     pub const SKY_LUT_RESOLUTION: UVec2 = uvec2(256, 256);
 
     /// Quality of the sky lookup texture.
     pub const SKY_LUT_STEPS: f32 = 32.0;
 
-    /// Radius of the planet, in mega-meters.
-    pub const GROUND_RADIUS_MM: f32 = 6.360;
-
-    /// Radius of the atmosphere, in mega-meters;
-    pub const ATMOSPHERE_RADIUS_MM: f32 = 6.460;
-
-    pub const RAYLEIGH_SCATTERING_BASE: Vec3 = vec3(5.802, 13.558, 33.1);
-    pub const RAYLEIGH_ABSORPTION_BASE: f32 = 0.0;
-
-    pub const MIE_SCATTERING_BASE: f32 = 3.996;
-    pub const MIE_ABSORPTION_BASE: f32 = 4.4;
-
-    pub const OZONE_ABSORPTION_BASE: Vec3 = vec3(0.650, 1.881, 0.085);
-
-    pub const GROUND_ALBEDO: Vec3 = Vec3::splat(0.25);
-
-    pub const EXPOSURE: f32 = 20.0;
-
-    /// Position of the observer in world.
+    /// Altitude of the observer, in mega-meters.
     ///
     /// This is a constant because the atmosphere generally doesn't change that
     /// much when camera is moving (unless one's travelling in a spaceship) and
     /// so it's just more practical to use a hard-coded value here.
-    pub const VIEW_POS: Vec3 = vec3(0.0, Self::GROUND_RADIUS_MM + 0.0002, 0.0);
+    pub const VIEW_ALTITUDE_MM: f32 = 0.0002;
+
+    /// Angular diameter of the moon, as seen from the Earth, in radians.
+    pub const MOON_ANGULAR_DIAMETER: f32 = 0.009;
 
     pub fn new(
+        world: &'a World,
+        settings: &'a AtmosphereSettings,
         transmittance_lut_tex: Tex<'a>,
         transmittance_lut_sampler: &'a Sampler,
         sky_lut_tex: Tex<'a>,
         sky_lut_sampler: &'a Sampler,
     ) -> Self {
         Self {
+            world,
+            settings,
             transmittance_lut_tex,
             transmittance_lut_sampler,
             sky_lut_tex,
@@ -83,34 +75,276 @@ impl<'a> Atmosphere<'a> {
         }
     }
 
-    pub fn sample(self, sun_dir: Vec3, ray_dir: Vec3) -> Vec3 {
+    /// Returns sky's radiance as seen by a camera ray, i.e. together with the
+    /// clouds.
+    ///
+    /// Raymarching clouds is expensive, so it's done only for rays that come
+    /// straight from the camera - for other rays see [`Self::sample()`].
+    pub fn sample_primary(self, ray_dir: Vec3) -> Vec3 {
+        self.sample_ex(ray_dir, true, true)
+    }
+
+    /// Returns sky's radiance as seen by a secondary (e.g. diffuse) ray.
+    ///
+    /// Clouds are skipped, since those rays are noisy enough not to notice
+    /// them, while raymarching the clouds on each miss would be too slow.
+    pub fn sample(self, ray_dir: Vec3) -> Vec3 {
+        self.sample_ex(ray_dir, true, false)
+    }
+
+    /// Same as [`Self::sample()`], but without the sun's and moon's disks.
+    ///
+    /// Useful for paths that already light surfaces through the directional
+    /// light that represents the sun (or the moon), which would otherwise get
+    /// accounted for twice.
+    pub fn sample_without_disks(self, ray_dir: Vec3) -> Vec3 {
+        self.sample_ex(ray_dir, false, false)
+    }
+
+    fn sample_ex(
+        self,
+        ray_dir: Vec3,
+        with_disks: bool,
+        with_clouds: bool,
+    ) -> Vec3 {
+        let sun_dir = self.world.sun_dir();
         let mut lum = self.sample_sky_lut(ray_dir, sun_dir);
-        let mut sun_lum = self.evaluate_bloom(ray_dir, sun_dir);
 
-        sun_lum = self.interpolate_bloom(sun_lum);
+        let mut sun_lum = if with_disks {
+            self.interpolate_bloom(self.evaluate_bloom(ray_dir, sun_dir))
+        } else {
+            Vec3::ZERO
+        };
 
         if sun_lum.length_squared() > 0.0 {
-            let ray = Ray::new(Self::VIEW_POS, ray_dir);
+            let view_pos = self.settings.view_pos();
+            let ray = Ray::new(view_pos, ray_dir);
 
-            if ray.intersect_sphere(Self::GROUND_RADIUS_MM) >= 0.0 {
+            if ray.intersect_sphere(self.settings.ground_radius) >= 0.0 {
                 sun_lum = Vec3::ZERO;
             } else {
-                sun_lum *=
-                    self.sample_transmittance_lut(Self::VIEW_POS, sun_dir);
+                sun_lum *= self.sample_transmittance_lut(view_pos, sun_dir);
             }
         }
 
         lum += sun_lum;
-        lum *= Self::EXPOSURE;
+        lum += self.sample_night_sky(sun_dir, ray_dir, with_disks);
+
+        if !with_clouds {
+            return lum;
+        }
+
+        let (clouds_lum, clouds_transmittance) =
+            self.sample_clouds(sun_dir, ray_dir);
+
+        lum * clouds_transmittance + clouds_lum
+    }
+
+    /// Returns radiance of the moon and stars.
+    fn sample_night_sky(
+        self,
+        sun_dir: Vec3,
+        ray_dir: Vec3,
+        with_moon: bool,
+    ) -> Vec3 {
+        let view_pos = self.settings.view_pos();
+
+        if Ray::new(view_pos, ray_dir)
+            .intersect_sphere(self.settings.ground_radius)
+            >= 0.0
+        {
+            return Vec3::ZERO;
+        }
+
+        let mut lum = Vec3::ZERO;
+
+        // ---
+        // Moon
+
+        let moon_dir = self.world.moon_dir();
+        let moon_radius = 0.5 * Self::MOON_ANGULAR_DIAMETER;
+        let moon_cos_theta = ray_dir.dot(moon_dir);
+
+        if with_moon && moon_cos_theta >= moon_radius.cos() {
+            // Offset from moon's center, where `1.0` lies on moon's edge
+            let offset =
+                (ray_dir - moon_dir * moon_cos_theta) / moon_radius.sin();
+
+            let normal = offset
+                - moon_dir * (1.0 - offset.length_squared()).max(0.0).sqrt();
+
+            // Instead of using the actual sun's direction, we rotate the light
+            // according to moon's phase - this way the phase can be configured
+            // independently from sun's and moon's positions; the light goes
+            // from behind the moon (new moon) to behind the viewer (full moon)
+            // - note that `moon_dir` points away from the viewer
+            let light_dir = {
+                let side = sun_dir - moon_dir * sun_dir.dot(moon_dir);
+
+                let side = if side.length_squared() > 0.0001 {
+                    side.normalize()
+                } else {
+                    moon_dir.cross(Vec3::X).normalize()
+                };
+
+                let angle = 2.0 * PI * self.world.moon_phase;
+
+                moon_dir * angle.cos() + side * angle.sin()
+            };
+
+            lum += vec3(0.9, 0.9, 0.85)
+                * self.world.moon_intensity
+                * normal.dot(light_dir).saturate()
+                * self.sample_transmittance_lut(view_pos, moon_dir);
+        }
+
+        // ---
+        // Stars
+
+        // Stars become visible only once the sky gets dark enough
+        let darkness = (-sun_dir.y * 10.0).saturate();
+
+        if darkness > 0.0 {
+            const GRID_SIZE: f32 = 150.0;
+            const DENSITY: f32 = 0.04;
+
+            let cell = (ray_dir * GRID_SIZE).floor();
+            let seed = hash(cell);
+
+            if seed.x < DENSITY {
+                let star_pos = (cell + seed) / GRID_SIZE;
+                let star_dir = star_pos.normalize();
+
+                let brightness = 0.01 * seed.y * seed.y;
+
+                // ~1 milliradian
+                let falloff = (1.0 - ray_dir.dot(star_dir)) * 1_000_000.0;
+
+                // Each star gets a bit of tint, from reddish to bluish
+                let tint = vec3(1.0, 0.9, 0.8) + seed.z * vec3(-0.2, 0.0, 0.2);
+
+                lum += tint
+                    * brightness
+                    * darkness
+                    * (-falloff).exp()
+                    * self.sample_transmittance_lut(view_pos, ray_dir);
+            }
+        }
+
         lum
     }
 
+    /// Raymarches the cloud layer; returns the light scattered by the clouds
+    /// towards the viewer and the fraction of light that gets through them.
+    fn sample_clouds(self, sun_dir: Vec3, ray_dir: Vec3) -> (Vec3, f32) {
+        let clouds = self.settings.clouds;
+
+        if !clouds.is_active() {
+            return (Vec3::ZERO, 1.0);
+        }
+
+        let view_pos = self.settings.view_pos();
+        let ray = Ray::new(view_pos, ray_dir);
+
+        if ray.intersect_sphere(self.settings.ground_radius) >= 0.0 {
+            return (Vec3::ZERO, 1.0);
+        }
+
+        // (atmosphere is expressed in mega-meters, clouds in kilometers)
+        let t_min = 1000.0
+            * ray
+                .intersect_sphere(
+                    self.settings.ground_radius + clouds.bottom / 1000.0,
+                )
+                .max(0.0);
+
+        if t_min >= CloudsSettings::MAX_DISTANCE {
+            return (Vec3::ZERO, 1.0);
+        }
+
+        let t_max = 1000.0
+            * ray.intersect_sphere(
+                self.settings.ground_radius + clouds.top / 1000.0,
+            );
+
+        let t_max = t_max.min(t_min + CloudsSettings::MAX_DISTANCE);
+        let dt = (t_max - t_min) / (CloudsSettings::STEPS as f32);
+        let fade = 1.0 - t_min / CloudsSettings::MAX_DISTANCE;
+
+        let sun_lum = self.sample_transmittance_lut(view_pos, sun_dir);
+        let sky_lum = self.sample_sky_lut(Vec3::Y, sun_dir);
+        let phase = clouds.phase(ray_dir.dot(sun_dir));
+
+        let light_dt = (clouds.top - clouds.bottom)
+            / sun_dir.y.max(0.1)
+            / (CloudsSettings::LIGHT_STEPS as f32);
+
+        let mut lum = Vec3::ZERO;
+        let mut transmittance = 1.0;
+        let mut i = 0;
+
+        while i < CloudsSettings::STEPS {
+            let t = t_min + (i as f32 + 0.5) * dt;
+
+            let pos = {
+                let pos = view_pos + ray_dir * (t / 1000.0);
+
+                vec3(
+                    1000.0 * (pos.x - view_pos.x),
+                    1000.0 * (pos.length() - self.settings.ground_radius),
+                    1000.0 * (pos.z - view_pos.z),
+                )
+            };
+
+            let extinction = clouds.extinction(pos) * fade;
+
+            if extinction > 0.0 {
+                let mut light_depth = 0.0;
+                let mut j = 0;
+
+                while j < CloudsSettings::LIGHT_STEPS {
+                    let light_pos =
+                        pos + sun_dir * ((j as f32 + 0.5) * light_dt);
+
+                    light_depth += clouds.extinction(light_pos) * light_dt;
+                    j += 1;
+                }
+
+                // Tops of the clouds see more of the sky than their bottoms
+                let height =
+                    (pos.y - clouds.bottom) / (clouds.top - clouds.bottom);
+
+                let in_scattering = sun_lum * phase * (-light_depth).exp()
+                    + sky_lum * (0.5 + 0.5 * height);
+
+                let sample_transmittance = (-extinction * dt).exp();
+
+                // (clouds barely absorb any light, so extinction is pretty
+                // much equal to scattering - hence there's no albedo here)
+                lum += transmittance
+                    * in_scattering
+                    * (1.0 - sample_transmittance);
+
+                transmittance *= sample_transmittance;
+
+                if transmittance < 0.01 {
+                    break;
+                }
+            }
+
+            i += 1;
+        }
+
+        (lum, transmittance)
+    }
+
     fn sample_sky_lut(self, ray_dir: Vec3, sun_dir: Vec3) -> Vec3 {
-        let height = Self::VIEW_POS.length();
-        let up = Self::VIEW_POS / height;
+        let view_pos = self.settings.view_pos();
+        let height = view_pos.length();
+        let up = view_pos / height;
 
         let horizon = {
-            let t = height.sqr() - Self::GROUND_RADIUS_MM.sqr();
+            let t = height.sqr() - self.settings.ground_radius.sqr();
             let t = t.sqrt() / height;
 
             t.clamp(-1.0, 1.0).acos()
@@ -173,6 +407,7 @@ impl<'a> Atmosphere<'a> {
 
     fn sample_transmittance_lut(self, pos: Vec3, sun_dir: Vec3) -> Vec3 {
         Self::sample_lut(
+            self.settings,
             self.transmittance_lut_tex,
             self.transmittance_lut_sampler,
             pos,
@@ -181,6 +416,7 @@ impl<'a> Atmosphere<'a> {
     }
 
     pub fn sample_lut(
+        settings: &AtmosphereSettings,
         lut_tex: Tex,
         lut_sampler: &Sampler,
         pos: Vec3,
@@ -193,8 +429,8 @@ impl<'a> Atmosphere<'a> {
         let uv = {
             let u = (0.5 + 0.5 * sun_cos_zenith_angle).saturate();
 
-            let v = ((height - Self::GROUND_RADIUS_MM)
-                / (Self::ATMOSPHERE_RADIUS_MM - Self::GROUND_RADIUS_MM))
+            let v = ((height - settings.ground_radius)
+                / (settings.atmosphere_radius - settings.ground_radius))
                 .saturate();
 
             vec2(u, v)
@@ -203,3 +439,53 @@ impl<'a> Atmosphere<'a> {
         lut_tex.sample_by_lod(*lut_sampler, uv, 0.0).xyz()
     }
 }
+
+/// Physical properties of the atmosphere.
+///
+/// Scattering and absorption coefficients are expressed per mega-meter, at the
+/// ground level - they get attenuated exponentially with the altitude.
+#[repr(C)]
+#[derive(Clone, Copy, Default, PartialEq, Pod, Zeroable)]
+#[cfg_attr(not(target_arch = "spirv"), derive(Debug))]
+pub struct AtmosphereSettings {
+    pub rayleigh_scattering: Vec3,
+    pub rayleigh_absorption: f32,
+    pub ozone_absorption: Vec3,
+    pub mie_scattering: f32,
+    pub ground_albedo: Vec3,
+    pub mie_absorption: f32,
+
+    /// Radius of the planet, in mega-meters.
+    pub ground_radius: f32,
+
+    /// Radius of the atmosphere, in mega-meters.
+    pub atmosphere_radius: f32,
+
+    /// Anisotropy of Mie scattering, from -1.0 to 1.0; the larger the value,
+    /// the more light gets scattered forward (e.g. creating a halo around the
+    /// sun).
+    pub mie_anisotropy: f32,
+
+    // (keeps clouds aligned the same way on both sides)
+    pub _padding: f32,
+
+    pub clouds: CloudsSettings,
+}
+
+impl AtmosphereSettings {
+    /// Position of the observer in world.
+    pub fn view_pos(&self) -> Vec3 {
+        vec3(0.0, self.ground_radius + Atmosphere::VIEW_ALTITUDE_MM, 0.0)
+    }
+}
+
+/// Returns three pseudo-random numbers in the range of `0.0..1.0`.
+fn hash(pos: Vec3) -> Vec3 {
+    let pos = vec3(
+        pos.dot(vec3(127.1, 311.7, 74.7)),
+        pos.dot(vec3(269.5, 183.3, 246.1)),
+        pos.dot(vec3(113.5, 271.9, 124.6)),
+    );
+
+    (vec3(pos.x.sin(), pos.y.sin(), pos.z.sin()) * 43758.547).fract()
+}
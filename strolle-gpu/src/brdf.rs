@@ -4,7 +4,7 @@ use glam::{Vec3, Vec4Swizzles};
 #[cfg(target_arch = "spirv")]
 use spirv_std::num_traits::Float;
 
-use crate::{F32Ext, GBufferEntry, WhiteNoise};
+use crate::{F32Ext, GBufferEntry, Vec3Ext, WhiteNoise};
 
 #[derive(Clone, Copy)]
 pub struct DiffuseBrdf {
@@ -30,6 +30,12 @@ impl DiffuseBrdf {
             radiance: self.eval(),
         }
     }
+
+    /// Returns pdf of sampling given direction with
+    /// [`WhiteNoise::sample_cosine_hemisphere()`].
+    pub fn pdf(self, l: Vec3) -> f32 {
+        self.gbuffer.normal.dot(l).saturate() / PI
+    }
 }
 
 #[derive(Clone, Copy)]
@@ -44,12 +50,18 @@ impl SpecularBrdf {
 
     // TODO separate eval_luma()
     pub fn eval(self, l: Vec3, v: Vec3) -> Vec3 {
-        let Self { gbuffer } = self;
-
-        if gbuffer.metallic <= 0.0 {
+        if self.gbuffer.metallic <= 0.0 {
             return Vec3::ZERO;
         }
 
+        self.eval_full(l, v)
+    }
+
+    /// Returns the specular lobe for given directions; contrary to
+    /// [`Self::eval()`], this includes dielectrics' highlights.
+    pub fn eval_full(self, l: Vec3, v: Vec3) -> Vec3 {
+        let Self { gbuffer } = self;
+
         let a = gbuffer.clamped_roughness();
         let n = gbuffer.normal;
         let h = (l + v).normalize();
@@ -65,19 +77,28 @@ impl SpecularBrdf {
         let d = ggx_distribution(n_dot_h, a);
         let g = ggx_schlick_masking_term(n_dot_l, n_dot_v, a);
 
-        let f = {
-            let f0 = 0.16
-                * gbuffer.reflectance
-                * gbuffer.reflectance
-                * (1.0 - gbuffer.metallic)
-                + gbuffer.base_color.xyz() * gbuffer.metallic;
-
-            ggx_schlick_fresnel(f0, l_dot_h)
-        };
+        let f = ggx_schlick_fresnel(self.f0(), l_dot_h);
 
         d * g * f / (4.0 * n_dot_l * n_dot_v)
     }
 
+    /// Returns the approximate fraction of light reflected by this lobe when
+    /// looking from given direction.
+    pub fn albedo(self, v: Vec3) -> f32 {
+        let n_dot_v = self.gbuffer.normal.dot(v).saturate();
+
+        ggx_schlick_fresnel(self.f0(), n_dot_v).luma()
+    }
+
+    fn f0(self) -> Vec3 {
+        let Self { gbuffer } = self;
+
+        0.16 * gbuffer.reflectance
+            * gbuffer.reflectance
+            * (1.0 - gbuffer.metallic)
+            + gbuffer.base_color.xyz() * gbuffer.metallic
+    }
+
     // TODO implement VNDF
     pub fn sample(self, wnoise: &mut WhiteNoise, v: Vec3) -> BrdfSample {
         let Self { gbuffer } = self;
@@ -111,8 +132,26 @@ impl SpecularBrdf {
             radiance: self.eval(dir, v),
         }
     }
+
+    /// Returns pdf of sampling given direction with [`Self::sample()`].
+    pub fn pdf(self, l: Vec3, v: Vec3) -> f32 {
+        let Self { gbuffer } = self;
+
+        let a = gbuffer.clamped_roughness();
+        let n = gbuffer.normal;
+        let h = (l + v).normalize();
+        let n_dot_h = n.dot(h).saturate();
+        let h_dot_v = h.dot(v).saturate();
+
+        if h_dot_v <= 0.0 {
+            return 0.0;
+        }
+
+        ggx_distribution(n_dot_h, a) * n_dot_h / (4.0 * h_dot_v)
+    }
 }
 
+#[derive(Clone, Copy)]
 pub struct LayeredBrdf {
     gbuffer: GBufferEntry,
 }
@@ -136,6 +175,77 @@ impl LayeredBrdf {
 
         sample
     }
+
+    /// Returns the complete BRDF (both lobes, including dielectrics'
+    /// highlights) for given directions.
+    pub fn eval(self, l: Vec3, v: Vec3) -> Vec3 {
+        let Self { gbuffer } = self;
+
+        if gbuffer.normal.dot(l) <= 0.0 {
+            return Vec3::ZERO;
+        }
+
+        DiffuseBrdf::new(gbuffer).eval()
+            + SpecularBrdf::new(gbuffer).eval_full(l, v)
+    }
+
+    /// Returns pdf of sampling given direction with [`Self::sample_full()`].
+    pub fn pdf(self, l: Vec3, v: Vec3) -> f32 {
+        let Self { gbuffer } = self;
+        let spec_prob = self.spec_prob(v);
+
+        let spec_pdf = if spec_prob > 0.0 {
+            SpecularBrdf::new(gbuffer).pdf(l, v)
+        } else {
+            0.0
+        };
+
+        let diff_pdf = DiffuseBrdf::new(gbuffer).pdf(l);
+
+        spec_prob * spec_pdf + (1.0 - spec_prob) * diff_pdf
+    }
+
+    /// Returns probability of [`Self::sample_full()`] picking the specular
+    /// lobe - proportional to its albedo, with the diffuse lobe weighted by
+    /// what's left after the reflection, i.e. `(1 - F) * (1 - metallic)`.
+    fn spec_prob(self, v: Vec3) -> f32 {
+        let spec = SpecularBrdf::new(self.gbuffer).albedo(v);
+        let diff = (1.0 - spec) * (1.0 - self.gbuffer.metallic);
+
+        if spec + diff > 0.0 {
+            spec / (spec + diff)
+        } else {
+            0.0
+        }
+    }
+
+    /// Picks one of the lobes and samples it, returning the complete BRDF and
+    /// the combined pdf of both lobes.
+    ///
+    /// Contrary to [`Self::sample()`], which is tuned for the real-time
+    /// passes, this one is unbiased and can be combined with light sampling
+    /// through [`crate::mis_weight()`].
+    pub fn sample_full(self, wnoise: &mut WhiteNoise, v: Vec3) -> BrdfSample {
+        let Self { gbuffer } = self;
+
+        let dir = if wnoise.sample() < self.spec_prob(v) {
+            SpecularBrdf::new(gbuffer).sample(wnoise, v).dir
+        } else {
+            wnoise.sample_cosine_hemisphere(gbuffer.normal)
+        };
+
+        let pdf = self.pdf(dir, v);
+
+        if pdf <= 0.0 {
+            return BrdfSample::invalid();
+        }
+
+        BrdfSample {
+            dir,
+            pdf,
+            radiance: self.eval(dir, v),
+        }
+    }
 }
 
 #[derive(Clone, Copy)]
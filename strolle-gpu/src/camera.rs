@@ -1,9 +1,9 @@
 use bytemuck::{Pod, Zeroable};
-use glam::{vec2, IVec2, Mat4, UVec2, Vec2, Vec3, Vec4, Vec4Swizzles};
+use glam::{vec2, vec3, IVec2, Mat4, UVec2, Vec2, Vec3, Vec4, Vec4Swizzles};
 #[cfg(target_arch = "spirv")]
 use spirv_std::num_traits::Float;
 
-use crate::Ray;
+use crate::{EnvironmentMapView, Ray};
 
 #[repr(C)]
 #[derive(Clone, Copy, Default, Pod, Zeroable)]
@@ -11,16 +11,47 @@ use crate::Ray;
 pub struct Camera {
     pub projection_view: Mat4,
     pub ndc_to_world: Mat4,
+
+    /// Camera's approximate origin (see: [`Self::approx_origin()`]) and, in
+    /// w, its panoramic projection (see: [`Self::panorama()`]).
     pub origin: Vec4,
+
     pub screen: Vec4,
+    pub extent: Vec4,
+
+    /// Physical parameters of the camera's lens: x is the aperture's radius
+    /// (in world units), y is the focus distance, z is the projection's
+    /// `1 / tan(fov / 2)` and w is the fraction of a frame the shutter stays
+    /// open for.
+    pub lens: Vec4,
 }
 
 impl Camera {
+    pub const PANORAMA_NONE: u32 = 0;
+    pub const PANORAMA_EQUIRECT: u32 = 1;
+    pub const PANORAMA_CUBEMAP: u32 = 2;
+
     /// Given a point in world-coordinates, returns it in clip-coordinates.
     pub fn world_to_clip(self, pos: Vec3) -> Vec4 {
         self.projection_view * pos.extend(1.0)
     }
 
+    /// Given a point in world-coordinates, returns it in clip-coordinates,
+    /// shifted so that pixel centers get sampled at the camera's sub-pixel
+    /// jitter (i.e. the same positions as [`Self::ray()`] goes through).
+    ///
+    /// This is what the rasterizer should use for placing the vertices, while
+    /// things like velocity should rely on [`Self::world_to_clip()`] so that
+    /// the jitter doesn't show up as motion.
+    pub fn world_to_jittered_clip(self, pos: Vec3) -> Vec4 {
+        let mut clip = self.world_to_clip(pos);
+        let offset = 2.0 * self.jitter() / self.screen.xy();
+
+        clip.x -= offset.x * clip.w;
+        clip.y += offset.y * clip.w;
+        clip
+    }
+
     /// Given a point in world-coordinates, returns it in screen-coordinates.
     pub fn world_to_screen(self, pos: Vec3) -> Vec2 {
         self.clip_to_screen(self.world_to_clip(pos))
@@ -36,8 +67,12 @@ impl Camera {
 
     /// Given a point in screen-coordinates, returns a unique index for it; used
     /// to index screen-space structures.
+    ///
+    /// Note that the index is based on the size of camera's buffers and not on
+    /// the screen's size, so that it stays stable across frames even if the
+    /// screen gets resized by the dynamic resolution.
     pub fn screen_to_idx(self, pos: UVec2) -> usize {
-        (pos.y * (self.screen.x as u32) + pos.x) as usize
+        (pos.y * (self.extent.x as u32) + pos.x) as usize
     }
 
     /// Returns size of the camera's viewport in pixels.
@@ -50,6 +85,12 @@ impl Camera {
         self.screen.xy().as_uvec2()
     }
 
+    /// Returns sub-pixel offset by which camera's projection is shifted in the
+    /// current frame; non-zero only when temporal anti-aliasing is enabled.
+    pub fn jitter(self) -> Vec2 {
+        self.screen.zw()
+    }
+
     /// Checks if given coordinates match camera's screen size and, if not,
     /// wraps them.
     ///
@@ -77,9 +118,27 @@ impl Camera {
     }
 
     /// Casts a ray from camera's center to given screen-coordinates.
+    ///
+    /// See: [`Self::ray_through()`].
     pub fn ray(self, screen_pos: UVec2) -> Ray {
+        self.ray_through(screen_pos.as_vec2() + vec2(0.5, 0.5) + self.jitter())
+    }
+
+    /// Casts a ray from camera's center through given point on the screen;
+    /// contrary to [`Self::ray()`], the point is not snapped to any pixel and
+    /// doesn't get jittered.
+    ///
+    /// The ray is found by unprojecting the point onto the near- and the
+    /// far-plane, which works for any projection - for perspective ones the
+    /// rays diverge from the eye, while for orthographic and oblique ones (see:
+    /// [`Self::is_orthographic()`]) they're parallel and start on the
+    /// near-plane.
+    pub fn ray_through(self, screen_pos: Vec2) -> Ray {
         let screen_size = self.screen.xy();
-        let screen_pos = screen_pos.as_vec2() + vec2(0.5, 0.5);
+
+        if self.is_panoramic() {
+            return self.panoramic_ray(screen_pos / screen_size);
+        }
 
         let ndc = screen_pos * 2.0 / screen_size - Vec2::ONE;
         let ndc = vec2(ndc.x, -ndc.y);
@@ -92,17 +151,161 @@ impl Camera {
         Ray::new(near_plane, (far_plane - near_plane).normalize())
     }
 
+    /// Casts a ray from camera's origin through given point of the panorama,
+    /// laid out the same way environment maps are - so that the rendered image
+    /// can be used as one.
+    ///
+    /// For panoramic cameras, `ndc_to_world` is just the camera's transform.
+    fn panoramic_ray(self, uv: Vec2) -> Ray {
+        let dir = if self.panorama() == Self::PANORAMA_EQUIRECT {
+            EnvironmentMapView::uv_to_dir(uv)
+        } else {
+            let face = ((uv.x * 6.0) as u32).min(5);
+
+            Self::cubemap_dir(face, vec2(uv.x * 6.0 - (face as f32), uv.y))
+        };
+
+        Ray::new(
+            self.approx_origin(),
+            self.ndc_to_world.transform_vector3(dir).normalize(),
+        )
+    }
+
+    /// Returns direction pointed by given point of a cubemap's face, with faces
+    /// in the usual order of +X, -X, +Y, -Y, +Z, -Z.
+    pub fn cubemap_dir(face: u32, uv: Vec2) -> Vec3 {
+        let st = 2.0 * uv - 1.0;
+
+        let dir = match face {
+            0 => vec3(1.0, -st.y, -st.x),
+            1 => vec3(-1.0, -st.y, st.x),
+            2 => vec3(st.x, 1.0, st.y),
+            3 => vec3(st.x, -1.0, -st.y),
+            4 => vec3(st.x, -st.y, 1.0),
+            _ => vec3(-st.x, -st.y, -1.0),
+        };
+
+        dir.normalize()
+    }
+
+    /// Casts a ray from a random point on camera's lens to given
+    /// screen-coordinates, simulating a thin lens; `sample` should be a
+    /// uniform sample inside of a unit disk.
+    ///
+    /// All rays cast through the same pixel converge on the focus plane, so
+    /// averaging them yields the ground truth for the depth of field.
+    ///
+    /// When the camera doesn't have any aperture, returns the same ray as
+    /// [`Self::ray()`].
+    pub fn lens_ray(self, screen_pos: UVec2, sample: Vec2) -> Ray {
+        let ray = self.ray(screen_pos);
+
+        if self.aperture_radius() <= 0.0 {
+            return ray;
+        }
+
+        let (forward, right, up) = self.basis();
+
+        let focus_point = ray.origin()
+            + ray.dir() * (self.focus_distance() / ray.dir().dot(forward));
+
+        let origin = ray.origin()
+            + (right * sample.x + up * sample.y) * self.aperture_radius();
+
+        Ray::new(origin, (focus_point - origin).normalize())
+    }
+
+    /// Returns the radius (in pixels) of the circle of confusion, i.e. of the
+    /// blur that a point lying at given distance along the camera's view axis
+    /// gets spread into by the lens.
+    pub fn circle_of_confusion(self, distance: f32) -> f32 {
+        if self.aperture_radius() <= 0.0 || distance <= 0.0 {
+            return 0.0;
+        }
+
+        // Blur's radius on the focus plane, in world units
+        let radius = self.aperture_radius()
+            * (distance - self.focus_distance()).abs()
+            / distance;
+
+        // ... projected into pixels
+        radius * 0.5 * self.screen.y * self.lens.z / self.focus_distance()
+    }
+
+    /// Returns radius of the camera's aperture, in world units; zero when the
+    /// depth of field is disabled.
+    pub fn aperture_radius(self) -> f32 {
+        self.lens.x
+    }
+
+    /// Returns distance at which objects are in perfect focus.
+    pub fn focus_distance(self) -> f32 {
+        self.lens.y
+    }
+
+    /// Returns fraction of the frame's time during which the shutter stays
+    /// open; zero when the motion blur is disabled.
+    pub fn shutter(self) -> f32 {
+        self.lens.w
+    }
+
+    /// Returns camera's forward, right and up vectors.
+    pub fn basis(self) -> (Vec3, Vec3, Vec3) {
+        let center = self.ndc_to_world.project_point3(vec3(0.0, 0.0, 1.0));
+        let right = self.ndc_to_world.project_point3(vec3(1.0, 0.0, 1.0));
+        let up = self.ndc_to_world.project_point3(vec3(0.0, 1.0, 1.0));
+
+        let forward = self.ray_through(self.screen.xy() * 0.5).dir();
+
+        (
+            forward,
+            (right - center).normalize(),
+            (up - center).normalize(),
+        )
+    }
+
     /// Returns camera's approximate origin, without taking into account the
     /// near-plane.
     ///
-    /// Faster than `self.ray(...).origin()`, but somewhat less accurate.
+    /// Faster than `self.ray(...).origin()`, but somewhat less accurate; for
+    /// orthographic projections, where rays don't share a single origin, this
+    /// is the center of the near-plane.
     pub fn approx_origin(self) -> Vec3 {
         self.origin.xyz()
     }
 
+    /// Returns whether camera's projection is a parallel one - orthographic or
+    /// oblique (e.g. cabinet) - in which case all rays go in the same direction
+    /// and start at different points of the near-plane.
+    pub fn is_orthographic(self) -> bool {
+        // Perspective projections divide by the view-space depth, which makes
+        // the last row depend on the position; parallel ones keep it constant
+        !self.is_panoramic()
+            && self
+                .projection_view
+                .row(3)
+                .xyz()
+                .abs_diff_eq(Vec3::ZERO, 1e-6)
+    }
+
+    /// Returns camera's panoramic projection, one of `Self::PANORAMA_*`.
+    ///
+    /// Panoramic cameras cover all directions around their origin and can't
+    /// be rasterized - `projection_view` contains then just the inverse of
+    /// camera's transform.
+    pub fn panorama(self) -> u32 {
+        self.origin.w as u32
+    }
+
+    pub fn is_panoramic(self) -> bool {
+        self.panorama() != Self::PANORAMA_NONE
+    }
+
     pub fn is_eq(self, rhs: Self) -> bool {
         self.projection_view
             .abs_diff_eq(rhs.projection_view, 0.0025)
+            && self.lens == rhs.lens
+            && self.origin.w == rhs.origin.w
     }
 }
 
@@ -145,7 +348,7 @@ impl CameraContains<Vec2> for Camera {
 
 #[cfg(test)]
 mod tests {
-    use glam::{ivec2, uvec2, vec4};
+    use glam::{ivec2, uvec2, vec3, vec4};
 
     use super::*;
 
@@ -156,6 +359,8 @@ mod tests {
             ndc_to_world: Default::default(),
             origin: Default::default(),
             screen: vec4(1024.0, 768.0, 0.0, 0.0),
+            extent: vec4(1024.0, 768.0, 0.0, 0.0),
+            lens: Default::default(),
         };
 
         // Case: minimum point inside the screen
@@ -173,4 +378,225 @@ mod tests {
         assert_eq!(target.contain(ivec2(1030, 768)), uvec2(1017, 767));
         assert_eq!(target.contain(ivec2(1030, 783)), uvec2(1017, 752));
     }
+
+    #[test]
+    fn world_to_jittered_clip() {
+        let target = Camera {
+            projection_view: Mat4::perspective_rh(1.0, 1.0, 0.1, 100.0),
+            ndc_to_world: Default::default(),
+            origin: Default::default(),
+            screen: vec4(100.0, 100.0, 0.25, -0.5),
+            extent: vec4(100.0, 100.0, 0.0, 0.0),
+            lens: Default::default(),
+        };
+
+        let point = vec3(1.0, 2.0, -5.0);
+
+        let actual =
+            target.clip_to_screen(target.world_to_jittered_clip(point));
+
+        let expected = target.world_to_screen(point) - target.jitter();
+
+        assert!(actual.abs_diff_eq(expected, 1e-3));
+    }
+
+    #[test]
+    fn lens_ray() {
+        let transform = Mat4::from_translation(vec3(1.0, 2.0, 3.0));
+        let projection = Mat4::perspective_infinite_reverse_rh(1.0, 1.0, 0.1);
+
+        let mut target = Camera {
+            projection_view: projection * transform.inverse(),
+            ndc_to_world: transform * projection.inverse(),
+            origin: vec4(1.0, 2.0, 3.0, 0.0),
+            screen: vec4(100.0, 100.0, 0.0, 0.0),
+            extent: vec4(100.0, 100.0, 0.0, 0.0),
+            lens: vec4(0.0, 5.0, projection.y_axis.y, 0.0),
+        };
+
+        let screen_pos = uvec2(20, 70);
+        let pinhole = target.ray(screen_pos);
+
+        // Case: no aperture
+        let actual = target.lens_ray(screen_pos, vec2(0.5, -0.5));
+
+        assert!(actual.origin().abs_diff_eq(pinhole.origin(), 1e-4));
+        assert!(actual.dir().abs_diff_eq(pinhole.dir(), 1e-4));
+
+        // Case: with aperture, rays should converge on the focus plane
+        target.lens.x = 0.1;
+
+        let (forward, ..) = target.basis();
+
+        assert!(forward.abs_diff_eq(vec3(0.0, 0.0, -1.0), 1e-4));
+
+        let focus_point = |ray: Ray| {
+            let t = (5.0 - (ray.origin() - pinhole.origin()).dot(forward))
+                / ray.dir().dot(forward);
+
+            ray.origin() + ray.dir() * t
+        };
+
+        let expected = focus_point(pinhole);
+
+        for sample in [vec2(1.0, 0.0), vec2(0.0, -1.0), vec2(-0.5, 0.5)] {
+            let ray = target.lens_ray(screen_pos, sample);
+
+            assert!(!ray.origin().abs_diff_eq(pinhole.origin(), 1e-3));
+            assert!(focus_point(ray).abs_diff_eq(expected, 1e-3));
+        }
+
+        // Circle of confusion should vanish on the focus plane and grow away
+        // from it
+        assert_eq!(0.0, target.circle_of_confusion(5.0));
+        assert!(target.circle_of_confusion(2.0) > 0.0);
+        assert!(target.circle_of_confusion(20.0) > 0.0);
+        assert!(!target.is_orthographic());
+    }
+
+    #[test]
+    fn orthographic() {
+        let transform = Mat4::from_translation(vec3(1.0, 2.0, 3.0));
+
+        // (reverse-z, similarly to Bevy)
+        let projection =
+            Mat4::orthographic_rh(-2.0, 2.0, -1.0, 1.0, 100.0, 0.0);
+
+        let target = Camera {
+            projection_view: projection * transform.inverse(),
+            ndc_to_world: transform * projection.inverse(),
+            origin: vec4(1.0, 2.0, 3.0, 0.0),
+            screen: vec4(100.0, 100.0, 0.0, 0.0),
+            extent: vec4(100.0, 100.0, 0.0, 0.0),
+            lens: Default::default(),
+        };
+
+        assert!(target.is_orthographic());
+
+        let ray_a = target.ray(uvec2(10, 20));
+        let ray_b = target.ray(uvec2(90, 70));
+
+        // Rays should be parallel, going along the view axis...
+        assert!(ray_a.dir().abs_diff_eq(vec3(0.0, 0.0, -1.0), 1e-4));
+        assert!(ray_b.dir().abs_diff_eq(vec3(0.0, 0.0, -1.0), 1e-4));
+
+        // ... starting at different points of the near-plane
+        assert!(ray_a.origin().abs_diff_eq(vec3(-0.58, 2.59, 3.0), 1e-4));
+        assert!(ray_b.origin().abs_diff_eq(vec3(2.62, 1.59, 3.0), 1e-4));
+
+        // ... and going through their pixels at any depth
+        for depth in [0.0, 1.0, 50.0] {
+            assert!(target
+                .world_to_screen(ray_a.at(depth))
+                .abs_diff_eq(vec2(10.5, 20.5), 1e-2));
+
+            assert!(target
+                .world_to_screen(ray_b.at(depth))
+                .abs_diff_eq(vec2(90.5, 70.5), 1e-2));
+        }
+    }
+
+    #[test]
+    fn oblique() {
+        let transform = Mat4::from_translation(vec3(1.0, 2.0, 3.0));
+
+        // Cabinet projection, i.e. orthographic one with the depth receding
+        // at 45° and half of its length
+        let shear = Mat4::from_cols(
+            Vec4::X,
+            Vec4::Y,
+            vec4(-0.35355, -0.35355, 1.0, 0.0),
+            Vec4::W,
+        );
+
+        let projection =
+            Mat4::orthographic_rh(-2.0, 2.0, -2.0, 2.0, 100.0, 0.0) * shear;
+
+        let target = Camera {
+            projection_view: projection * transform.inverse(),
+            ndc_to_world: transform * projection.inverse(),
+            origin: Default::default(),
+            screen: vec4(100.0, 100.0, 0.0, 0.0),
+            extent: vec4(100.0, 100.0, 0.0, 0.0),
+            lens: Default::default(),
+        };
+
+        assert!(target.is_orthographic());
+
+        let ray_a = target.ray(uvec2(10, 20));
+        let ray_b = target.ray(uvec2(90, 70));
+
+        // Rays should be parallel, but skewed away from the view axis
+        assert!(ray_a.dir().abs_diff_eq(ray_b.dir(), 1e-4));
+        assert!(ray_a
+            .dir()
+            .abs_diff_eq(vec3(-0.3162, -0.3162, -0.8944), 1e-3));
+
+        for depth in [0.0, 1.0, 50.0] {
+            assert!(target
+                .world_to_screen(ray_a.at(depth))
+                .abs_diff_eq(vec2(10.5, 20.5), 1e-2));
+
+            assert!(target
+                .world_to_screen(ray_b.at(depth))
+                .abs_diff_eq(vec2(90.5, 70.5), 1e-2));
+        }
+    }
+
+    #[test]
+    fn panorama() {
+        let transform = Mat4::from_translation(vec3(1.0, 2.0, 3.0));
+
+        // Case: equirectangular
+        let target = Camera {
+            projection_view: transform.inverse(),
+            ndc_to_world: transform,
+            origin: vec4(1.0, 2.0, 3.0, Camera::PANORAMA_EQUIRECT as f32),
+            screen: vec4(200.0, 100.0, 0.0, 0.0),
+            extent: vec4(200.0, 100.0, 0.0, 0.0),
+            lens: Default::default(),
+        };
+
+        assert!(target.is_panoramic());
+        assert!(!target.is_orthographic());
+
+        for screen_pos in [uvec2(0, 0), uvec2(37, 81), uvec2(150, 50)] {
+            let ray = target.ray(screen_pos);
+
+            assert_eq!(vec3(1.0, 2.0, 3.0), ray.origin());
+
+            // Pixels should match the environment map's layout, so that the
+            // panorama can be used as one
+            let uv = (screen_pos.as_vec2() + 0.5) / vec2(200.0, 100.0);
+
+            assert!(
+                EnvironmentMapView::dir_to_uv(ray.dir()).abs_diff_eq(uv, 1e-4)
+            );
+        }
+
+        // Case: cubemap
+        let target = Camera {
+            origin: vec4(1.0, 2.0, 3.0, Camera::PANORAMA_CUBEMAP as f32),
+            screen: vec4(600.0, 100.0, 0.0, 0.0),
+            extent: vec4(600.0, 100.0, 0.0, 0.0),
+            ..target
+        };
+
+        let faces = [
+            vec3(1.0, 0.0, 0.0),
+            vec3(-1.0, 0.0, 0.0),
+            vec3(0.0, 1.0, 0.0),
+            vec3(0.0, -1.0, 0.0),
+            vec3(0.0, 0.0, 1.0),
+            vec3(0.0, 0.0, -1.0),
+        ];
+
+        for (face, expected) in faces.into_iter().enumerate() {
+            let actual = target
+                .ray_through(vec2(100.0 * (face as f32) + 50.0, 50.0))
+                .dir();
+
+            assert!(actual.abs_diff_eq(expected, 1e-4));
+        }
+    }
 }
@@ -0,0 +1,198 @@
+use core::f32::consts::PI;
+
+use bytemuck::{Pod, Zeroable};
+use glam::{vec3, Vec2, Vec3};
+#[cfg(target_arch = "spirv")]
+use spirv_std::num_traits::Float;
+
+use crate::F32Ext;
+
+/// Layer of volumetric clouds, rendered as a part of the atmosphere.
+///
+/// Altitudes, scale and wind offset are expressed in kilometers, while density
+/// is expressed as extinction per kilometer.
+#[repr(C)]
+#[derive(Clone, Copy, Default, PartialEq, Pod, Zeroable)]
+#[cfg_attr(not(target_arch = "spirv"), derive(Debug))]
+pub struct CloudsSettings {
+    /// Fraction of the sky covered by clouds, from 0.0 (clear sky) to 1.0
+    /// (overcast).
+    pub coverage: f32,
+    pub density: f32,
+    pub bottom: f32,
+    pub top: f32,
+    pub wind_offset: Vec2,
+    pub scale: f32,
+    pub anisotropy: f32,
+}
+
+impl CloudsSettings {
+    /// Number of steps taken along the view ray.
+    pub const STEPS: u32 = 24;
+
+    /// Number of steps taken towards the sun, for each step along the view
+    /// ray.
+    pub const LIGHT_STEPS: u32 = 4;
+
+    /// Distance (in kilometers) after which the clouds fade out; this avoids
+    /// marching extremely long rays near the horizon, where the noise would
+    /// alias anyway.
+    pub const MAX_DISTANCE: f32 = 60.0;
+
+    pub fn is_active(&self) -> bool {
+        self.coverage > 0.0 && self.density > 0.0 && self.top > self.bottom
+    }
+
+    /// Returns clouds' extinction at given point.
+    ///
+    /// The point is given in kilometers, relative to the observer - except for
+    /// `pos.y`, which is the altitude above the ground.
+    pub fn extinction(&self, pos: Vec3) -> f32 {
+        let height = (pos.y - self.bottom) / (self.top - self.bottom);
+
+        if height <= 0.0 || height >= 1.0 {
+            return 0.0;
+        }
+
+        // Round the bottom of the clouds a bit and make the tops wispier
+        let gradient =
+            (4.0 * height).saturate() * (2.0 * (1.0 - height)).saturate();
+
+        let noise = fbm(vec3(
+            pos.x + self.wind_offset.x,
+            pos.y,
+            pos.z + self.wind_offset.y,
+        ) / self.scale.max(0.001));
+
+        let shape =
+            ((noise - (1.0 - self.coverage)) / self.coverage).saturate();
+
+        shape * gradient * self.density
+    }
+
+    /// Returns the fraction of light scattered towards the viewer, where
+    /// `cos_theta` is the cosine between view- and sun-direction.
+    pub fn phase(&self, cos_theta: f32) -> f32 {
+        // Mix of a strong forward-scattering lobe (silver lining) and a weak
+        // backward-scattering one
+        0.8 * henyey_greenstein(self.anisotropy, cos_theta)
+            + 0.2 * henyey_greenstein(-0.3, cos_theta)
+    }
+
+    /// Returns the average fraction of sun's light that gets through the
+    /// clouds, given sun's direction.
+    ///
+    /// This is used to dim the sun's directional light - since the light gets
+    /// scattered over the entire scene anyway, it's more practical to use an
+    /// average here instead of tracing through the actual noise.
+    pub fn sun_transmittance(&self, sun_dir: Vec3) -> f32 {
+        if !self.is_active() {
+            return 1.0;
+        }
+
+        // On average, the noise yields `coverage / 2` and the vertical
+        // gradient yields ~0.6 of the peak density
+        let extinction = self.density * 0.5 * self.coverage * 0.6;
+        let distance = (self.top - self.bottom) / sun_dir.y.max(0.05);
+
+        (-extinction * distance).exp()
+    }
+}
+
+fn henyey_greenstein(g: f32, cos_theta: f32) -> f32 {
+    let denom = 1.0 + g * g - 2.0 * g * cos_theta;
+
+    (1.0 - g * g) / (4.0 * PI * denom * denom.sqrt())
+}
+
+fn hash(pos: Vec3) -> f32 {
+    let pos = (pos * 0.3183099 + 0.1).fract() * 17.0;
+
+    (pos.x * pos.y * pos.z * (pos.x + pos.y + pos.z)).fract()
+}
+
+fn value_noise(pos: Vec3) -> f32 {
+    let i = pos.floor();
+    let f = pos - i;
+    let u = f * f * (3.0 - 2.0 * f);
+
+    let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+
+    let x0 = lerp(hash(i), hash(i + vec3(1.0, 0.0, 0.0)), u.x);
+    let x1 = lerp(
+        hash(i + vec3(0.0, 1.0, 0.0)),
+        hash(i + vec3(1.0, 1.0, 0.0)),
+        u.x,
+    );
+
+    let x2 = lerp(
+        hash(i + vec3(0.0, 0.0, 1.0)),
+        hash(i + vec3(1.0, 0.0, 1.0)),
+        u.x,
+    );
+
+    let x3 = lerp(
+        hash(i + vec3(0.0, 1.0, 1.0)),
+        hash(i + vec3(1.0, 1.0, 1.0)),
+        u.x,
+    );
+
+    lerp(lerp(x0, x1, u.y), lerp(x2, x3, u.y), u.z)
+}
+
+fn fbm(pos: Vec3) -> f32 {
+    let noise = 0.5 * value_noise(pos)
+        + 0.25 * value_noise(2.03 * pos)
+        + 0.125 * value_noise(4.01 * pos);
+
+    noise / 0.875
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn target() -> CloudsSettings {
+        CloudsSettings {
+            coverage: 0.5,
+            density: 1.0,
+            bottom: 1.5,
+            top: 4.0,
+            wind_offset: Vec2::ZERO,
+            scale: 5.0,
+            anisotropy: 0.6,
+        }
+    }
+
+    #[test]
+    fn extinction() {
+        let target = target();
+
+        assert_eq!(0.0, target.extinction(vec3(0.0, 1.0, 0.0)));
+        assert_eq!(0.0, target.extinction(vec3(0.0, 4.5, 0.0)));
+
+        let has_clouds = (0..100).any(|x| {
+            target.extinction(vec3(x as f32 * 0.7, 2.5, x as f32 * 0.3)) > 0.0
+        });
+
+        assert!(has_clouds);
+    }
+
+    #[test]
+    fn sun_transmittance() {
+        let target = target();
+        let zenith = target.sun_transmittance(Vec3::Y);
+
+        let horizon = target.sun_transmittance(vec3(1.0, 0.1, 0.0).normalize());
+
+        assert!(zenith < 1.0);
+        assert!(horizon < zenith);
+
+        let clear = CloudsSettings {
+            coverage: 0.0,
+            ..target
+        };
+
+        assert_eq!(1.0, clear.sun_transmittance(Vec3::Y));
+    }
+}
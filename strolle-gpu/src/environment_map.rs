@@ -0,0 +1,178 @@
+use core::f32::consts::PI;
+
+use glam::{vec2, vec3, Vec2, Vec3, Vec4Swizzles};
+use spirv_std::arch::IndexUnchecked;
+#[cfg(target_arch = "spirv")]
+use spirv_std::num_traits::Float;
+use spirv_std::Sampler;
+
+use crate::{Light, Tex, Vec3Ext, WhiteNoise};
+
+/// Environment map (aka HDRI) that, when active, replaces the atmosphere.
+///
+/// The map itself is stored as an equirectangular texture, while the buffer
+/// contains metadata and CDFs used to importance-sample the map:
+///
+/// ```text
+/// [0]                        - intensity (zero if the map is not active)
+/// [1]                        - (as u32) width
+/// [2]                        - (as u32) height
+/// [3]                        - pdf's normalization factor
+/// [4..4+height]              - marginal CDF (of rows)
+/// [4+height+y*width..+width] - conditional CDF (of texels within row y)
+/// ```
+///
+/// Texels are importance-sampled proportionally to `luma * sin(theta)`, where
+/// the sine compensates for equirectangular projection stretching the poles.
+#[derive(Clone, Copy)]
+pub struct EnvironmentMapView<'a> {
+    tex: Tex<'a>,
+    sampler: &'a Sampler,
+    buffer: &'a [f32],
+}
+
+impl<'a> EnvironmentMapView<'a> {
+    pub const HEADER_SIZE: usize = 4;
+
+    pub fn new(tex: Tex<'a>, sampler: &'a Sampler, buffer: &'a [f32]) -> Self {
+        Self {
+            tex,
+            sampler,
+            buffer,
+        }
+    }
+
+    fn get(self, idx: usize) -> f32 {
+        unsafe { *self.buffer.index_unchecked(idx) }
+    }
+
+    pub fn is_active(self) -> bool {
+        self.intensity() > 0.0
+    }
+
+    pub fn intensity(self) -> f32 {
+        self.get(0)
+    }
+
+    fn width(self) -> u32 {
+        self.get(1).to_bits()
+    }
+
+    fn height(self) -> u32 {
+        self.get(2).to_bits()
+    }
+
+    fn pdf_norm(self) -> f32 {
+        self.get(3)
+    }
+
+    /// Returns radiance coming from given direction.
+    pub fn radiance(self, dir: Vec3) -> Vec3 {
+        self.texel(dir) * self.intensity()
+    }
+
+    fn texel(self, dir: Vec3) -> Vec3 {
+        self.tex
+            .sample_by_lod(*self.sampler, Self::dir_to_uv(dir), 0.0)
+            .xyz()
+    }
+
+    /// Picks a direction proportionally to the radiance coming from it;
+    /// returns the direction together with its (solid-angle) probability.
+    pub fn sample(self, wnoise: &mut WhiteNoise) -> (Vec3, f32) {
+        let width = self.width();
+        let height = self.height();
+
+        let y = self.search(Self::HEADER_SIZE, height, wnoise.sample());
+
+        let x = self.search(
+            Self::HEADER_SIZE + (height + y * width) as usize,
+            width,
+            wnoise.sample(),
+        );
+
+        let uv = vec2(
+            (x as f32 + wnoise.sample()) / (width as f32),
+            (y as f32 + wnoise.sample()) / (height as f32),
+        );
+
+        let dir = Self::uv_to_dir(uv);
+
+        (dir, self.pdf(dir))
+    }
+
+    /// Returns (solid-angle) probability of [`Self::sample()`] picking given
+    /// direction.
+    pub fn pdf(self, dir: Vec3) -> f32 {
+        self.texel(dir).luma() * self.pdf_norm()
+    }
+
+    /// Returns a directional light that represents radiance coming from given
+    /// direction; used to treat environment map's samples just like samples
+    /// of other lights.
+    pub fn light(self, dir: Vec3) -> Light {
+        // Light's size roughly corresponds to a single texel, which keeps the
+        // shadows soft-ish and the specular highlights non-degenerate
+        let angular_diameter = PI / (self.height() as f32);
+
+        Light::directional(-dir, self.radiance(dir), angular_diameter)
+    }
+
+    /// Finds the first item in given CDF that's larger than `u`.
+    fn search(self, offset: usize, len: u32, u: f32) -> u32 {
+        let mut lo = 0;
+        let mut hi = len - 1;
+
+        while lo < hi {
+            let mid = (lo + hi) / 2;
+
+            if self.get(offset + mid as usize) > u {
+                hi = mid;
+            } else {
+                lo = mid + 1;
+            }
+        }
+
+        lo
+    }
+
+    pub fn dir_to_uv(dir: Vec3) -> Vec2 {
+        vec2(
+            0.5 + dir.z.atan2(dir.x) / (2.0 * PI),
+            dir.y.clamp(-1.0, 1.0).acos() / PI,
+        )
+    }
+
+    pub fn uv_to_dir(uv: Vec2) -> Vec3 {
+        let phi = (uv.x - 0.5) * 2.0 * PI;
+        let theta = uv.y * PI;
+
+        vec3(
+            theta.sin() * phi.cos(),
+            theta.cos(),
+            theta.sin() * phi.sin(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uv_roundtrip() {
+        for dir in [
+            vec3(1.0, 0.0, 0.0),
+            vec3(0.0, 0.0, -1.0),
+            vec3(0.3, 0.8, -0.2),
+            vec3(-0.7, -0.1, 0.4),
+        ] {
+            let dir = dir.normalize();
+            let actual = EnvironmentMapView::uv_to_dir(
+                EnvironmentMapView::dir_to_uv(dir),
+            );
+
+            assert!(actual.distance(dir) < 0.0001, "{dir} != {actual}");
+        }
+    }
+}
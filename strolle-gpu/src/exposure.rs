@@ -0,0 +1,30 @@
+use bytemuck::{Pod, Zeroable};
+#[cfg(target_arch = "spirv")]
+use spirv_std::num_traits::Float;
+
+/// Number of bins in the luminance histogram used for metering the exposure.
+pub const EXPOSURE_HISTOGRAM_BINS: usize = 64;
+
+/// Luminance that auto-exposure maps the scene's average luminance onto.
+pub const MIDDLE_GRAY: f32 = 0.18;
+
+/// Exposure the camera's image gets scaled by; kept on the GPU so that the
+/// auto-exposure can adapt it without any round-trips to the host.
+#[repr(C)]
+#[derive(Clone, Copy, Default, Pod, Zeroable)]
+#[cfg_attr(not(target_arch = "spirv"), derive(Debug))]
+pub struct Exposure {
+    /// Temporally smoothed, metered exposure value (i.e. without the exposure
+    /// compensation)
+    pub metered_ev: f32,
+
+    /// Exposure value that's actually applied onto the image
+    pub ev: f32,
+}
+
+impl Exposure {
+    /// Returns factor the image's colors should be multiplied by.
+    pub fn multiplier(self) -> f32 {
+        (-self.ev).exp2()
+    }
+}
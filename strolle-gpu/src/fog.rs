@@ -0,0 +1,279 @@
+use core::f32::consts::PI;
+
+use glam::{vec2, vec3, Vec2, Vec3, Vec4, Vec4Swizzles};
+use spirv_std::arch::IndexUnchecked;
+#[cfg(target_arch = "spirv")]
+use spirv_std::num_traits::Float;
+
+use crate::{Ray, Vec3Ext, WhiteNoise};
+
+/// Homogeneous participating media (aka fog) - a global one that fills the
+/// entire world, plus optional axis-aligned boxes of additional fog.
+///
+/// ```text
+/// [0]         - xyz: global scattering coefficient, w: phase anisotropy
+/// [1]         - xyz: global absorption coefficient, w: (as u32) volume count
+/// [2 + 4 * n] - xyz: n-th volume's min
+/// [3 + 4 * n] - xyz: n-th volume's max
+/// [4 + 4 * n] - xyz: n-th volume's scattering coefficient
+/// [5 + 4 * n] - xyz: n-th volume's absorption coefficient
+/// ```
+///
+/// Coefficients are expressed per world-space unit; overlapping volumes add up.
+#[derive(Clone, Copy)]
+pub struct FogView<'a> {
+    buffer: &'a [Vec4],
+}
+
+impl<'a> FogView<'a> {
+    pub const HEADER_SIZE: usize = 2;
+    pub const VOLUME_SIZE: usize = 4;
+
+    /// How far the fog is integrated for rays that don't hit anything (i.e.
+    /// go towards the sky).
+    pub const MAX_DISTANCE: f32 = 1000.0;
+
+    /// Optical depth after which the fog is considered opaque; we don't bother
+    /// sampling points further than that, since they can't contribute more
+    /// than ~1% of the light anyway.
+    const MAX_OPTICAL_DEPTH: f32 = 4.6;
+
+    pub fn new(buffer: &'a [Vec4]) -> Self {
+        Self { buffer }
+    }
+
+    fn get(self, idx: usize) -> Vec4 {
+        unsafe { *self.buffer.index_unchecked(idx) }
+    }
+
+    pub fn is_active(self) -> bool {
+        self.volume_count() > 0 || self.global_extinction().max_element() > 0.0
+    }
+
+    /// Returns the phase function's anisotropy, from -1.0 (back-scattering)
+    /// through 0.0 (isotropic) to 1.0 (forward-scattering).
+    pub fn anisotropy(self) -> f32 {
+        self.get(0).w
+    }
+
+    fn global_scattering(self) -> Vec3 {
+        self.get(0).xyz()
+    }
+
+    fn global_extinction(self) -> Vec3 {
+        self.global_scattering() + self.get(1).xyz()
+    }
+
+    fn volume_count(self) -> u32 {
+        self.get(1).w.to_bits()
+    }
+
+    fn volume_ptr(nth: u32) -> usize {
+        Self::HEADER_SIZE + Self::VOLUME_SIZE * (nth as usize)
+    }
+
+    fn volume_scattering(self, nth: u32) -> Vec3 {
+        self.get(Self::volume_ptr(nth) + 2).xyz()
+    }
+
+    fn volume_extinction(self, nth: u32) -> Vec3 {
+        self.volume_scattering(nth) + self.get(Self::volume_ptr(nth) + 3).xyz()
+    }
+
+    /// Returns the range of distances (clamped to ray's length) at which given
+    /// ray travels through n-th volume; the range is empty (x >= y) if the ray
+    /// misses the volume.
+    fn volume_overlap(self, nth: u32, ray: Ray) -> Vec2 {
+        let ptr = Self::volume_ptr(nth);
+        let inv_dir = 1.0 / ray.dir();
+
+        let t1 = (self.get(ptr).xyz() - ray.origin()) * inv_dir;
+        let t2 = (self.get(ptr + 1).xyz() - ray.origin()) * inv_dir;
+
+        let tmin = t1.min(t2).max_element().max(0.0);
+        let tmax = t1.max(t2).min_element().min(ray.len());
+
+        vec2(tmin, tmax)
+    }
+
+    /// Returns fog's scattering coefficient at given point.
+    pub fn scattering_at(self, point: Vec3) -> Vec3 {
+        let mut out = self.global_scattering();
+        let mut nth = 0;
+
+        while nth < self.volume_count() {
+            let ptr = Self::volume_ptr(nth);
+
+            if point.cmpge(self.get(ptr).xyz()).all()
+                && point.cmple(self.get(ptr + 1).xyz()).all()
+            {
+                out += self.volume_scattering(nth);
+            }
+
+            nth += 1;
+        }
+
+        out
+    }
+
+    /// Returns the fraction of light that gets through the fog along given ray
+    /// (up to its length).
+    pub fn transmittance(self, ray: Ray) -> Vec3 {
+        let depth = self.global_extinction() * ray.len()
+            + self.volumes_optical_depth(ray);
+
+        Self::exp(-depth)
+    }
+
+    /// Returns the fraction of light that gets through fog volumes along given
+    /// ray, ignoring the global fog.
+    ///
+    /// This is used for directional lights - they are infinitely far away, so
+    /// taking the global fog into account would extinguish them completely.
+    pub fn volumes_transmittance(self, ray: Ray) -> Vec3 {
+        Self::exp(-self.volumes_optical_depth(ray))
+    }
+
+    fn volumes_optical_depth(self, ray: Ray) -> Vec3 {
+        let mut out = Vec3::ZERO;
+        let mut nth = 0;
+
+        while nth < self.volume_count() {
+            let overlap = self.volume_overlap(nth, ray);
+
+            out +=
+                self.volume_extinction(nth) * (overlap.y - overlap.x).max(0.0);
+            nth += 1;
+        }
+
+        out
+    }
+
+    /// Picks a point along given ray, proportionally to the amount of fog that
+    /// scatters light there; returns the distance to the point together with
+    /// its probability density.
+    ///
+    /// Returned probability is zero if there's no fog along the ray.
+    pub fn sample_distance(
+        self,
+        wnoise: &mut WhiteNoise,
+        ray: Ray,
+    ) -> (f32, f32) {
+        // Points further than this are covered so thickly by the global fog
+        // that there's no point in sampling them
+        let len = ray.len().min(
+            Self::MAX_OPTICAL_DEPTH / self.global_extinction().min_element(),
+        );
+
+        let ray = ray.with_len(len);
+
+        // Each piece of fog (the global one and volumes) is a segment along the
+        // ray; we pick one of the segments proportionally to its total amount
+        // of scattering and then a point uniformly within that segment, which
+        // makes the pdf proportional to the scattering coefficient
+        let global_weight = self.global_scattering().luma() * len;
+        let mut total_weight = global_weight;
+        let mut nth = 0;
+
+        while nth < self.volume_count() {
+            let overlap = self.volume_overlap(nth, ray);
+
+            total_weight += self.volume_scattering(nth).luma()
+                * (overlap.y - overlap.x).max(0.0);
+
+            nth += 1;
+        }
+
+        if total_weight <= 0.0 {
+            return (0.0, 0.0);
+        }
+
+        let mut target = wnoise.sample() * total_weight;
+        let mut t = 0.0;
+
+        if target < global_weight {
+            t = target / global_weight * len;
+        } else {
+            target -= global_weight;
+            nth = 0;
+
+            while nth < self.volume_count() {
+                let overlap = self.volume_overlap(nth, ray);
+                let length = (overlap.y - overlap.x).max(0.0);
+                let weight = self.volume_scattering(nth).luma() * length;
+
+                if weight > 0.0 && target < weight {
+                    t = overlap.x + target / weight * length;
+                    break;
+                }
+
+                target -= weight;
+                nth += 1;
+            }
+        }
+
+        let pdf = self.scattering_at(ray.at(t)).luma() / total_weight;
+
+        (t, pdf)
+    }
+
+    /// Returns the fraction of light scattered towards the viewer, where
+    /// `cos_theta` is the cosine between view- and light-direction (both
+    /// pointing away from the scattering point).
+    pub fn phase(self, cos_theta: f32) -> f32 {
+        // Henyey-Greenstein
+        let g = self.anisotropy();
+        let denom = 1.0 + g * g + 2.0 * g * cos_theta;
+
+        (1.0 - g * g) / (4.0 * PI * denom * denom.sqrt())
+    }
+
+    fn exp(v: Vec3) -> Vec3 {
+        vec3(v.x.exp(), v.y.exp(), v.z.exp())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::vec4;
+
+    use super::*;
+
+    fn buffer() -> [Vec4; 6] {
+        [
+            vec4(0.01, 0.01, 0.01, 0.0),
+            vec4(0.0, 0.0, 0.0, f32::from_bits(1)),
+            vec4(-1.0, -1.0, -1.0, 0.0),
+            vec4(1.0, 1.0, 1.0, 0.0),
+            vec4(0.5, 0.5, 0.5, 0.0),
+            vec4(0.5, 0.5, 0.5, 0.0),
+        ]
+    }
+
+    #[test]
+    fn transmittance() {
+        let buffer = buffer();
+        let target = FogView::new(&buffer);
+
+        let ray = Ray::new(vec3(-5.0, 0.0, 0.0), Vec3::X).with_len(10.0);
+
+        // 10 units of global fog + 2 units of the volume
+        let expected = (-(0.01 * 10.0 + 1.0 * 2.0f32)).exp();
+        let actual = target.transmittance(ray);
+
+        assert!((actual.x - expected).abs() < 0.0001);
+
+        let ray = Ray::new(vec3(-5.0, 5.0, 0.0), Vec3::X).with_len(10.0);
+
+        assert_eq!(Vec3::ONE, target.volumes_transmittance(ray));
+    }
+
+    #[test]
+    fn scattering_at() {
+        let buffer = buffer();
+        let target = FogView::new(&buffer);
+
+        assert_eq!(Vec3::splat(0.51), target.scattering_at(Vec3::ZERO));
+        assert_eq!(Vec3::splat(0.01), target.scattering_at(Vec3::splat(2.0)));
+    }
+}
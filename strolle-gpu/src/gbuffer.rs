@@ -13,22 +13,47 @@ pub struct GBufferEntry {
     pub roughness: f32,
     pub reflectance: f32,
     pub depth: f32,
+
+    /// Bitmask of linked lights that don't affect this surface, see
+    /// [`crate::Light::is_linked_to()`].
+    ///
+    /// Only [`crate::Light::MAX_LINKED_LIGHTS`] lowest bits are stored.
+    pub excluded_lights: u32,
 }
 
 impl GBufferEntry {
+    /// Encodes given set of excluded lights into a byte that's meant to be
+    /// stored as the most significant byte of a float.
+    ///
+    /// Lowest bit is always set so that the float's exponent stays away from
+    /// denormals, while the mask is kept short enough to not run into NaNs.
+    pub fn encode_excluded_lights(excluded_lights: u32) -> u32 {
+        1 | ((excluded_lights & 0b11111) << 1)
+    }
+
+    /// Reverses [`Self::encode_excluded_lights()`].
+    pub fn decode_excluded_lights(byte: u32) -> u32 {
+        byte >> 1
+    }
+
     pub fn unpack([d0, d1]: [Vec4; 2]) -> Self {
         let depth = d0.x;
         let normal = Normal::decode(d0.yz());
 
-        let (metallic, roughness, reflectance) = {
-            let [metallic, roughness, reflectance, ..] =
+        let (metallic, roughness, reflectance, excluded_lights) = {
+            let [metallic, roughness, reflectance, excluded_lights] =
                 d0.w.to_bits().to_bytes();
 
             let metallic = metallic as f32 / 255.0;
             let roughness = (roughness as f32 / 255.0).sqr();
             let reflectance = reflectance as f32 / 255.0;
 
-            (metallic, roughness, reflectance)
+            (
+                metallic,
+                roughness,
+                reflectance,
+                Self::decode_excluded_lights(excluded_lights),
+            )
         };
 
         let emissive = d1.xyz();
@@ -53,6 +78,7 @@ impl GBufferEntry {
             roughness,
             reflectance,
             depth,
+            excluded_lights,
         }
     }
 
@@ -70,7 +96,7 @@ impl GBufferEntry {
                     metallic as u32,
                     roughness as u32,
                     reflectance as u32,
-                    1,
+                    Self::encode_excluded_lights(self.excluded_lights),
                 ]))
             };
 
@@ -139,6 +165,7 @@ mod tests {
             roughness: 0.05,
             reflectance: 0.25,
             depth: 123.456,
+            excluded_lights: 0b10101,
         };
 
         let target = GBufferEntry::unpack(target.pack());
@@ -161,5 +188,6 @@ mod tests {
         assert_relative_eq!(target.roughness, 0.05, epsilon = EPSILON);
         assert_relative_eq!(target.reflectance, 0.25, epsilon = EPSILON);
         assert_relative_eq!(target.depth, 123.456, epsilon = EPSILON);
+        assert_eq!(target.excluded_lights, 0b10101);
     }
 }
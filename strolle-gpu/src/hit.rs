@@ -2,7 +2,7 @@ use glam::{Vec2, Vec3, Vec4, Vec4Swizzles};
 #[cfg(target_arch = "spirv")]
 use spirv_std::num_traits::Float;
 
-use crate::{GBufferEntry, MaterialId, Normal, Ray, Surface, Vec3Ext};
+use crate::{GBufferEntry, Light, MaterialId, Normal, Ray, Surface, Vec3Ext};
 
 #[derive(Clone, Copy, Default)]
 pub struct Hit {
@@ -79,9 +79,17 @@ pub struct TriangleHit {
     pub normal: Vec3,
     pub uv: Vec2,
     pub material_id: MaterialId,
+
+    /// Bitmask of linked lights that don't affect the instance this triangle
+    /// belongs to, see [`GBufferEntry::excluded_lights`].
+    pub excluded_lights: u32,
 }
 
 impl TriangleHit {
+    /// Number of bits taken by the material id when packed, the remaining ones
+    /// hold the excluded lights.
+    const MATERIAL_ID_BITS: u32 = 32 - Light::MAX_LINKED_LIGHTS;
+
     pub fn none() -> Self {
         Self {
             distance: f32::MAX,
@@ -89,6 +97,7 @@ impl TriangleHit {
             normal: Default::default(),
             uv: Default::default(),
             material_id: MaterialId::new(0),
+            excluded_lights: 0,
         }
     }
 
@@ -98,19 +107,26 @@ impl TriangleHit {
         } else {
             let normal = Normal::decode(d1.xy());
             let point = d0.xyz();
+            let payload = d0.w.to_bits();
 
             Self {
                 distance: 0.0,
                 point,
                 normal,
                 uv: d1.zw(),
-                material_id: MaterialId::new(d0.w.to_bits()),
+                material_id: MaterialId::new(
+                    payload & ((1 << Self::MATERIAL_ID_BITS) - 1),
+                ),
+                excluded_lights: payload >> Self::MATERIAL_ID_BITS,
             }
         }
     }
 
     pub fn pack(self) -> [Vec4; 2] {
-        let d0 = self.point.extend(f32::from_bits(self.material_id.get()));
+        let payload = self.material_id.get()
+            | (self.excluded_lights << Self::MATERIAL_ID_BITS);
+
+        let d0 = self.point.extend(f32::from_bits(payload));
 
         let d1 = Normal::encode(self.normal)
             .extend(self.uv.x)
@@ -0,0 +1,107 @@
+use core::f32::consts::PI;
+
+use glam::{vec2, Vec3};
+#[cfg(target_arch = "spirv")]
+use spirv_std::num_traits::Float;
+use spirv_std::Sampler;
+
+use crate::{Light, Tex};
+
+/// Lookup texture containing photometric profiles (IES) of lights.
+///
+/// Each profile occupies `LUT_WIDTH x LUT_HEIGHT` texels, with profiles stacked
+/// vertically one after another:
+///
+/// ```text
+/// x - vertical angle, from 0° (light's direction) up to 180°
+/// y - horizontal angle, from 0° up to 360° (around light's direction)
+/// ```
+///
+/// Intensities are normalized, i.e. the brightest direction of each profile
+/// has intensity of 1.0, and stored as half-precision floats, so that dim
+/// tails of the distribution don't get rounded down to zero.
+#[derive(Clone, Copy)]
+pub struct IesProfilesView<'a> {
+    lut_tex: Tex<'a>,
+    lut_sampler: &'a Sampler,
+}
+
+impl<'a> IesProfilesView<'a> {
+    /// Number of texels along the vertical angle; narrow beams change quickly
+    /// along this axis, so it gets a finer resolution (~0.7° per texel).
+    pub const LUT_WIDTH: u32 = 256;
+
+    /// Number of texels along the horizontal angle.
+    pub const LUT_HEIGHT: u32 = 32;
+    pub const MAX_PROFILES: u32 = 32;
+
+    pub fn new(lut_tex: Tex<'a>, lut_sampler: &'a Sampler) -> Self {
+        Self {
+            lut_tex,
+            lut_sampler,
+        }
+    }
+
+    /// Returns how much of light's intensity reaches given point, according
+    /// to the light's profile; returns 1.0 for lights without any profile.
+    ///
+    /// Profile's vertical angle of 0° points along the light's direction, while
+    /// its horizontal angle of 0° points towards the light's reference
+    /// direction (projected onto the plane perpendicular to light's direction),
+    /// so that the profile follows the light's rotation.
+    pub fn eval(self, light: Light, point: Vec3) -> f32 {
+        if !light.has_ies_profile() {
+            return 1.0;
+        }
+
+        let axis = light.dir();
+        let dir = (point - light.center()).normalize();
+
+        let (tangent, bitangent) = {
+            let reference = light.ies_reference();
+            let tangent = reference - axis * axis.dot(reference);
+
+            if tangent.length_squared() > 1e-6 {
+                let tangent = tangent.normalize();
+
+                (tangent, axis.cross(tangent))
+            } else {
+                // Reference direction is parallel to the axis, so it doesn't
+                // determine any rotation - pick whatever
+                axis.any_orthonormal_pair()
+            }
+        };
+
+        let vertical = axis.dot(dir).clamp(-1.0, 1.0).acos();
+
+        let horizontal = {
+            let angle = dir.dot(bitangent).atan2(dir.dot(tangent));
+
+            if angle < 0.0 {
+                angle + 2.0 * PI
+            } else {
+                angle
+            }
+        };
+
+        let uv = {
+            let u = vertical / PI;
+
+            // Clamp `v` to the profile's own texels, so that linear filtering
+            // doesn't bleed into neighbouring profiles
+            let v = {
+                let height = Self::LUT_HEIGHT as f32;
+
+                let v =
+                    (horizontal / (2.0 * PI) * height).clamp(0.5, height - 0.5);
+
+                (light.ies_profile() as f32 * height + v)
+                    / (height * Self::MAX_PROFILES as f32)
+            };
+
+            vec2(u, v)
+        };
+
+        self.lut_tex.sample_by_lod(*self.lut_sampler, uv, 0.0).x
+    }
+}
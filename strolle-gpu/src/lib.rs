@@ -9,10 +9,16 @@ mod atmosphere;
 mod brdf;
 mod bvh_view;
 mod camera;
+mod clouds;
+mod environment_map;
+mod exposure;
+mod fog;
 mod frame;
 mod gbuffer;
 mod hit;
+mod ies_profiles;
 mod light;
+mod light_tree;
 mod lights;
 mod material;
 mod materials;
@@ -23,6 +29,7 @@ mod ray;
 mod reprojection;
 mod reservoir;
 mod surface;
+mod tonemapping;
 mod triangle;
 mod triangles;
 mod utils;
@@ -32,10 +39,16 @@ pub use self::atmosphere::*;
 pub use self::brdf::*;
 pub use self::bvh_view::*;
 pub use self::camera::*;
+pub use self::clouds::*;
+pub use self::environment_map::*;
+pub use self::exposure::*;
+pub use self::fog::*;
 pub use self::frame::*;
 pub use self::gbuffer::*;
 pub use self::hit::*;
+pub use self::ies_profiles::*;
 pub use self::light::*;
+pub use self::light_tree::*;
 pub use self::lights::*;
 pub use self::material::*;
 pub use self::materials::*;
@@ -46,6 +59,7 @@ pub use self::ray::*;
 pub use self::reprojection::*;
 pub use self::reservoir::*;
 pub use self::surface::*;
+pub use self::tonemapping::*;
 pub use self::triangle::*;
 pub use self::triangles::*;
 pub use self::utils::*;
@@ -7,7 +7,8 @@ use glam::{vec2, vec4, Vec2, Vec3, Vec4, Vec4Swizzles};
 use spirv_std::num_traits::Float;
 
 use crate::{
-    DiffuseBrdf, F32Ext, Hit, Normal, Ray, SpecularBrdf, Vec3Ext, WhiteNoise,
+    DiffuseBrdf, F32Ext, Hit, IesProfilesView, Normal, Ray, SpecularBrdf,
+    Vec3Ext, WhiteNoise,
 };
 
 #[repr(C)]
@@ -27,40 +28,67 @@ pub struct Light {
     pub d1: Vec4,
 
     /// x - (as u32) light type
-    /// y - if it's a spot light: direction
-    /// z - if it's a spot light: direction
+    /// y - direction (for point lights: axis of their IES profile)
+    /// z - direction (for point lights: axis of their IES profile)
     /// w - if it's a spot light: angle
+    ///     if it's a directional light: angular diameter
     pub d2: Vec4,
 
     /// x - (as u32) see the "slot" functions below
+    /// y - (as u32) flags, see `FLAG_*`
+    /// z - (as u32) light-linking bit, see [`Self::is_linked_to()`]
+    /// w - (as u32) if it's a point or spot light: IES profile + 1, or zero
+    ///     if the light doesn't have any profile
     pub d3: Vec4,
 
+    /// x - if it's a point or spot light: IES profile's reference direction
+    /// y - if it's a point or spot light: IES profile's reference direction
+    /// z - unused
+    /// w - unused
+    pub d4: Vec4,
+
     // Light's data from the previous frame
     pub prev_d0: Vec4,
     pub prev_d1: Vec4,
     pub prev_d2: Vec4,
+    pub prev_d4: Vec4,
 }
 
 impl Light {
     pub const TYPE_NONE: u32 = 0;
     pub const TYPE_POINT: u32 = 1;
     pub const TYPE_SPOT: u32 = 2;
+    pub const TYPE_DIRECTIONAL: u32 = 3;
+
+    pub const FLAG_CASTS_SHADOWS: u32 = 1;
+
+    /// How many lights can have light-linking active at once; limited by the
+    /// space available in the GBuffer.
+    pub const MAX_LINKED_LIGHTS: u32 = 5;
+
+    /// How far away directional lights are assumed to be when casting shadow
+    /// rays towards them.
+    pub const DIRECTIONAL_DISTANCE: f32 = 1000.0;
+
+    /// Creates a shadow-casting directional light that shines along given
+    /// direction.
+    pub fn directional(dir: Vec3, color: Vec3, angular_diameter: f32) -> Self {
+        let dir = Normal::encode(dir);
+        let ty = f32::from_bits(Self::TYPE_DIRECTIONAL);
+
+        let d1 = color.extend(f32::INFINITY);
+        let d2 = vec4(ty, dir.x, dir.y, angular_diameter);
 
-    pub fn sun(position: Vec3, color: Vec3) -> Self {
         Self {
-            // TODO incorrect
-            d0: position.extend(25.0),
-            d1: color.extend(f32::INFINITY),
-            d2: vec4(
-                f32::from_bits(Self::TYPE_POINT),
-                Default::default(),
-                Default::default(),
-                Default::default(),
-            ),
-            d3: Default::default(),
-            prev_d0: Default::default(),
-            prev_d1: Default::default(),
-            prev_d2: Default::default(),
+            d0: Vec4::ZERO,
+            d1,
+            d2,
+            d3: vec4(0.0, f32::from_bits(Self::FLAG_CASTS_SHADOWS), 0.0, 0.0),
+            d4: Vec4::ZERO,
+            prev_d0: Vec4::ZERO,
+            prev_d1: d1,
+            prev_d2: d2,
+            prev_d4: Vec4::ZERO,
         }
     }
 
@@ -81,7 +109,9 @@ impl Light {
     }
 
     pub fn contains(self, point: Vec3) -> bool {
-        self.center().distance(point) <= self.radius()
+        // Directional lights are infinitely far away, so any point we've
+        // sampled on them is considered valid
+        self.is_directional() || self.center().distance(point) <= self.radius()
     }
 
     fn ty(self) -> u32 {
@@ -96,14 +126,82 @@ impl Light {
         self.ty() == Self::TYPE_POINT
     }
 
-    pub fn spot_dir(self) -> Vec3 {
+    pub fn is_spot(self) -> bool {
+        self.ty() == Self::TYPE_SPOT
+    }
+
+    pub fn is_directional(self) -> bool {
+        self.ty() == Self::TYPE_DIRECTIONAL
+    }
+
+    /// Returns direction of this light; for point lights that's the axis of
+    /// their IES profile (i.e. where its vertical angle of 0° points).
+    pub fn dir(self) -> Vec3 {
         Normal::decode(self.d2.yz())
     }
 
+    /// Returns direction of the IES profile's horizontal angle of 0°; valid
+    /// only for point and spot lights.
+    ///
+    /// The direction doesn't have to be perpendicular to [`Self::dir()`] -
+    /// see [`IesProfilesView::eval()`].
+    pub fn ies_reference(self) -> Vec3 {
+        Normal::decode(self.d4.xy())
+    }
+
     pub fn spot_angle(self) -> f32 {
         self.d2.w
     }
 
+    pub fn angular_diameter(self) -> f32 {
+        self.d2.w
+    }
+
+    pub fn casts_shadows(self) -> bool {
+        self.d3.y.to_bits() & Self::FLAG_CASTS_SHADOWS > 0
+    }
+
+    /// Returns whether this light affects surface with given set of excluded
+    /// lights (see [`crate::GBufferEntry::excluded_lights`]).
+    ///
+    /// Lights without any linking have their bit set to zero, so they affect
+    /// all surfaces.
+    pub fn is_linked_to(self, excluded_lights: u32) -> bool {
+        self.d3.z.to_bits() & excluded_lights == 0
+    }
+
+    pub fn has_ies_profile(self) -> bool {
+        self.d3.w.to_bits() > 0
+    }
+
+    /// Returns index of light's IES profile; valid only if
+    /// [`Self::has_ies_profile()`].
+    pub fn ies_profile(self) -> u32 {
+        self.d3.w.to_bits() - 1
+    }
+
+    pub fn link_bit(self) -> u32 {
+        self.d3.z.to_bits()
+    }
+
+    pub fn set_link_bit(&mut self, bit: u32) {
+        self.d3.z = f32::from_bits(bit);
+    }
+
+    /// Returns vector pointing from given point towards this light, together
+    /// with the light's radius as seen from that point.
+    ///
+    /// For directional lights the vector is normalized and the radius is
+    /// expressed in terms of a light placed one unit away, which allows to
+    /// handle all light types uniformly.
+    fn to_light(self, point: Vec3) -> (Vec3, f32) {
+        if self.is_directional() {
+            (-self.dir(), (0.5 * self.angular_diameter()).tan())
+        } else {
+            (self.center() - point, self.radius())
+        }
+    }
+
     pub fn is_slot_remapped(self) -> bool {
         self.d3.x.to_bits() > 0 && self.d3.x.to_bits() != 0xcafebabe
     }
@@ -132,30 +230,98 @@ impl Light {
         self.prev_d0 = self.d0;
         self.prev_d1 = self.d1;
         self.prev_d2 = self.d2;
+        self.prev_d4 = self.d4;
     }
 
     pub fn rollback(&mut self) {
         self.d0 = self.prev_d0;
         self.d1 = self.prev_d1;
         self.d2 = self.prev_d2;
+        self.d4 = self.prev_d4;
     }
 
-    pub fn radiance(self, hit: Hit) -> LightRadiance {
-        let l = self.center() - hit.point;
+    /// Returns normalized direction from given point towards this light.
+    pub fn dir_from(self, point: Vec3) -> Vec3 {
+        self.to_light(point).0.normalize()
+    }
 
-        let f_angle = if self.is_point() {
-            1.0
+    /// Returns squared sine of the half-angle of the cone this light subtends
+    /// when seen from given point.
+    ///
+    /// Lights without any size (and points inside of the light) yield zero,
+    /// i.e. such lights can be only sampled explicitly.
+    fn cone_sin2(self, point: Vec3) -> f32 {
+        if self.is_directional() {
+            (0.5 * self.angular_diameter()).sin().sqr()
         } else {
-            let angle =
-                self.spot_dir().angle_between(hit.point - self.center());
+            let d2 = self.center().distance_squared(point);
+            let r2 = self.radius().sqr();
+
+            if d2 > r2 {
+                r2 / d2
+            } else {
+                0.0
+            }
+        }
+    }
+
+    /// Returns cosine of the half-angle of the cone this light subtends when
+    /// seen from given point.
+    ///
+    /// See: [`Self::solid_angle()`].
+    pub fn cone_cos(self, point: Vec3) -> f32 {
+        (1.0 - self.cone_sin2(point)).sqrt()
+    }
+
+    /// Returns solid angle this light subtends when seen from given point, or
+    /// zero if the light has no size.
+    pub fn solid_angle(self, point: Vec3) -> f32 {
+        let sin2 = self.cone_sin2(point);
+
+        // (1 - cos) = sin^2 / (1 + cos), which doesn't suffer from
+        // catastrophic cancellation for small lights
+        2.0 * PI * sin2 / (1.0 + (1.0 - sin2).sqrt())
+    }
+
+    /// Returns distance from given point to this light's surface, along given
+    /// direction.
+    pub fn distance_along(self, point: Vec3, dir: Vec3) -> f32 {
+        if self.is_directional() {
+            return Self::DIRECTIONAL_DISTANCE;
+        }
+
+        let to_center = self.center() - point;
+        let b = dir.dot(to_center);
+        let c = to_center.length_squared() - self.radius().sqr();
+        let disc = b * b - c;
+
+        if disc >= 0.0 {
+            (b - disc.sqrt()).max(0.0)
+        } else {
+            to_center.length()
+        }
+    }
+
+    /// Returns how much light arrives at given point, without taking into
+    /// account any surface that might be there (i.e. without the cosine term
+    /// and BRDFs).
+    pub fn intensity(self, ies_profiles: IesProfilesView, point: Vec3) -> Vec3 {
+        let f_angle = if self.has_ies_profile() {
+            // Profile already describes the entire distribution, including
+            // spot light's cone
+            ies_profiles.eval(self, point)
+        } else if self.is_spot() {
+            let angle = self.dir().angle_between(point - self.center());
 
             (1.0 - (angle / self.spot_angle()).powf(3.0)).saturate()
+        } else {
+            1.0
         };
 
         let f_dist = if self.range() == f32::INFINITY {
             1.0
         } else {
-            let l2 = l.length_squared();
+            let l2 = self.to_light(point).0.length_squared();
             let inv_r2 = 1.0 / self.range().sqr();
 
             let factor = l2 * inv_r2;
@@ -165,6 +331,20 @@ impl Light {
             attenuation / l2.max(0.0001)
         };
 
+        self.color() * f_angle * f_dist
+    }
+
+    pub fn radiance(
+        self,
+        ies_profiles: IesProfilesView,
+        hit: Hit,
+    ) -> LightRadiance {
+        if !self.is_linked_to(hit.gbuffer.excluded_lights) {
+            return Default::default();
+        }
+
+        let (l, radius) = self.to_light(hit.point);
+
         let f_cosine = hit.gbuffer.normal.dot(l.normalize()).saturate();
 
         let diff_brdf = DiffuseBrdf::new(hit.gbuffer).eval();
@@ -177,8 +357,8 @@ impl Light {
             let center_to_ray = l.dot(r) * r - l;
 
             let closest_point = {
-                let t = self.radius()
-                    * center_to_ray.dot(center_to_ray).inverse_sqrt();
+                let t =
+                    radius * center_to_ray.dot(center_to_ray).inverse_sqrt();
 
                 l + center_to_ray * t.saturate()
             };
@@ -188,7 +368,7 @@ impl Light {
 
             let i_roughness = {
                 let t = hit.gbuffer.clamped_roughness()
-                    + self.radius() * 0.5 * l_spec_length_inverse;
+                    + radius * 0.5 * l_spec_length_inverse;
 
                 hit.gbuffer.clamped_roughness() / t.saturate()
             };
@@ -200,13 +380,19 @@ impl Light {
         };
 
         LightRadiance {
-            radiance: self.color() * f_angle * f_dist * f_cosine,
+            radiance: self.intensity(ies_profiles, hit.point) * f_cosine,
             diff_brdf,
             spec_brdf,
         }
     }
 
     pub fn ray_wnoise(self, noise: &mut WhiteNoise, hit_point: Vec3) -> Ray {
+        if self.is_directional() {
+            let sample = vec2(noise.sample(), noise.sample());
+
+            return self.ray_bnoise(sample, hit_point);
+        }
+
         let light_pos = self.center() + self.radius() * noise.sample_sphere();
         let light_to_hit = hit_point - light_pos;
 
@@ -215,10 +401,18 @@ impl Light {
     }
 
     pub fn ray_bnoise(self, sample: Vec2, hit_point: Vec3) -> Ray {
-        let to_light = self.center() - hit_point;
+        let (to_light, radius) = self.to_light(hit_point);
         let light_dir = to_light.normalize();
-        let light_distance = to_light.length();
-        let light_radius = self.radius() / light_distance;
+
+        let light_distance = if self.is_directional() {
+            Self::DIRECTIONAL_DISTANCE
+        } else {
+            to_light.length()
+        };
+
+        // For directional lights `to_light` is normalized, so this gives us
+        // the tangent of light's angular radius, i.e. a cone of directions
+        let light_radius = radius / to_light.length();
         let (light_tangent, light_bitangent) = light_dir.any_orthonormal_pair();
 
         let disk_point = {
@@ -252,6 +446,10 @@ impl LightId {
         Self::new(u32::MAX)
     }
 
+    pub fn is_sky(self) -> bool {
+        self == Self::sky()
+    }
+
     pub fn get(self) -> u32 {
         self.0
     }
@@ -283,3 +481,34 @@ impl Mul<f32> for LightRadiance {
         self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use glam::vec3;
+
+    use super::*;
+
+    #[test]
+    fn cone() {
+        let light = Light {
+            d0: vec4(0.0, 0.0, 2.0, 1.0),
+            d2: vec4(f32::from_bits(Light::TYPE_POINT), 0.0, 0.0, 0.0),
+            ..Default::default()
+        };
+
+        let point = Vec3::ZERO;
+
+        assert!((light.cone_cos(point) - 0.75f32.sqrt()).abs() < 1e-6);
+
+        assert!(
+            (light.solid_angle(point) - 2.0 * PI * (1.0 - 0.75f32.sqrt()))
+                .abs()
+                < 1e-6
+        );
+
+        assert_eq!(1.0, light.distance_along(point, vec3(0.0, 0.0, 1.0)));
+
+        // Point inside of the light
+        assert_eq!(0.0, light.solid_angle(vec3(0.0, 0.0, 1.5)));
+    }
+}
@@ -0,0 +1,341 @@
+use core::f32::consts::PI;
+
+use glam::{Vec3, Vec4, Vec4Swizzles};
+use spirv_std::arch::IndexUnchecked;
+#[cfg(target_arch = "spirv")]
+use spirv_std::num_traits::Float;
+
+use crate::{
+    BvhStack, F32Ext, LightId, Normal, Ray, WhiteNoise, BVH_STACK_SIZE,
+};
+
+/// Hierarchy of lights, used to importance-sample them.
+///
+/// Each node is represented by three Vec4s:
+///
+/// ```text
+/// d0.xyz - bounding box's min
+/// d0.w   - power
+///
+/// d1.xyz - bounding box's max
+/// d1.w   - (as u32) if it's an internal node: pointer to the right child
+///                   if it's a leaf: light id | LEAF_BIT
+///
+/// d2.xy  - encoded cone axis
+/// d2.z   - cone's theta_o (spread of light's normals)
+/// d2.w   - cone's theta_e (spread of light's emission)
+/// ```
+///
+/// Left children are stored right after their parents, so they don't need a
+/// pointer of their own.
+///
+/// Nodes are followed by paths leading to each light, used to compute the
+/// probability of picking given light - each Vec4 contains paths of four
+/// consecutive lights, with n-th bit of a path set if n-th step from the root
+/// goes into the right child.
+///
+/// Nodes that contain directional lights have their bounding boxes inverted
+/// (min > max), which marks them as infinitely large.
+///
+/// Thanks to:
+///
+/// - https://fpsunflower.github.io/ckulla/data/many-lights-hpg2018.pdf
+///   (Importance Sampling of Many Lights with Adaptive Tree Splitting)
+#[derive(Clone, Copy)]
+pub struct LightTreeView<'a> {
+    buffer: &'a [Vec4],
+}
+
+impl<'a> LightTreeView<'a> {
+    pub fn new(buffer: &'a [Vec4]) -> Self {
+        Self { buffer }
+    }
+
+    pub fn get(self, ptr: u32) -> LightTreeNode {
+        let ptr = 3 * ptr as usize;
+
+        unsafe {
+            LightTreeNode {
+                d0: *self.buffer.index_unchecked(ptr),
+                d1: *self.buffer.index_unchecked(ptr + 1),
+                d2: *self.buffer.index_unchecked(ptr + 2),
+            }
+        }
+    }
+
+    /// Picks a light proportionally to its estimated contribution towards
+    /// given point; returns the light together with its probability.
+    ///
+    /// Returned probability is zero if no light is able to reach the point.
+    ///
+    /// `normal` can be zero for points that don't lie on any surface (e.g.
+    /// inside fog), in which case lights from all directions are considered.
+    ///
+    /// Note that the tree must contain at least one light.
+    pub fn sample(
+        self,
+        wnoise: &mut WhiteNoise,
+        point: Vec3,
+        normal: Vec3,
+    ) -> (LightId, f32) {
+        let mut ptr = 0;
+        let mut pdf = 1.0;
+
+        loop {
+            let node = self.get(ptr);
+
+            if node.is_leaf() {
+                return (node.light_id(), pdf);
+            }
+
+            let left_ptr = ptr + 1;
+            let right_ptr = node.right_ptr();
+
+            let left_imp = self.get(left_ptr).importance(point, normal);
+            let right_imp = self.get(right_ptr).importance(point, normal);
+            let total_imp = left_imp + right_imp;
+
+            if total_imp <= 0.0 {
+                return (LightId::new(0), 0.0);
+            }
+
+            let left_prob = left_imp / total_imp;
+
+            if wnoise.sample() < left_prob {
+                ptr = left_ptr;
+                pdf *= left_prob;
+            } else {
+                ptr = right_ptr;
+                pdf *= 1.0 - left_prob;
+            }
+        }
+    }
+
+    /// Returns the probability with which [`Self::sample()`] picks given
+    /// light.
+    ///
+    /// `paths_ptr` comes from [`crate::World::light_tree_paths`].
+    pub fn pdf(
+        self,
+        paths_ptr: u32,
+        light_id: LightId,
+        point: Vec3,
+        normal: Vec3,
+    ) -> f32 {
+        let path = {
+            let idx = light_id.get() as usize;
+
+            let paths = unsafe {
+                *self.buffer.index_unchecked(paths_ptr as usize + idx / 4)
+            };
+
+            let path = match idx % 4 {
+                0 => paths.x,
+                1 => paths.y,
+                2 => paths.z,
+                _ => paths.w,
+            };
+
+            path.to_bits()
+        };
+
+        let mut ptr = 0;
+        let mut pdf = 1.0;
+        let mut depth = 0;
+
+        loop {
+            let node = self.get(ptr);
+
+            if node.is_leaf() {
+                return pdf;
+            }
+
+            let left_ptr = ptr + 1;
+            let right_ptr = node.right_ptr();
+
+            let left_imp = self.get(left_ptr).importance(point, normal);
+            let right_imp = self.get(right_ptr).importance(point, normal);
+            let total_imp = left_imp + right_imp;
+
+            if total_imp <= 0.0 {
+                return 0.0;
+            }
+
+            let left_prob = left_imp / total_imp;
+
+            if (path >> depth) & 1 == 0 {
+                ptr = left_ptr;
+                pdf *= left_prob;
+            } else {
+                ptr = right_ptr;
+                pdf *= 1.0 - left_prob;
+            }
+
+            depth += 1;
+        }
+    }
+
+    /// Calls `f` for each of the (non-directional) lights whose bounds given
+    /// ray passes through before travelling `max_distance`.
+    ///
+    /// Subtrees the ray misses get skipped as a whole, so finding lights that
+    /// a ray can hit doesn't require going through all of them; the traversal
+    /// uses the same stack as the BVH, so the tree mustn't be deeper than
+    /// [`BVH_STACK_SIZE`] levels.
+    ///
+    /// Note that the tree must contain at least one light.
+    pub fn for_each_hit(
+        self,
+        local_idx: u32,
+        stack: BvhStack,
+        ray: Ray,
+        max_distance: f32,
+        mut f: impl FnMut(LightId),
+    ) {
+        // (see: `Ray::traverse()`)
+        let stack_begins_at = (local_idx as usize) * BVH_STACK_SIZE;
+        let mut stack_ptr = stack_begins_at;
+        let mut ptr = 0;
+
+        loop {
+            let node = self.get(ptr);
+
+            // Infinite nodes can't be pruned by their bounds, but directional
+            // lights can't be hit by rays either
+            let is_hit = if node.is_infinite() {
+                !node.is_leaf()
+            } else {
+                ray.intersect_box(node.d0.xyz(), node.d1.xyz()) < max_distance
+            };
+
+            if is_hit {
+                if !node.is_leaf() {
+                    unsafe {
+                        *stack.index_unchecked_mut(stack_ptr) =
+                            node.right_ptr();
+                    }
+
+                    stack_ptr += 1;
+                    ptr += 1;
+                    continue;
+                }
+
+                f(node.light_id());
+            }
+
+            if stack_ptr > stack_begins_at {
+                stack_ptr -= 1;
+                ptr = unsafe { *stack.index_unchecked(stack_ptr) };
+            } else {
+                break;
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct LightTreeNode {
+    pub d0: Vec4,
+    pub d1: Vec4,
+    pub d2: Vec4,
+}
+
+impl LightTreeNode {
+    pub const LEAF_BIT: u32 = 1 << 31;
+
+    pub fn is_leaf(self) -> bool {
+        self.d1.w.to_bits() & Self::LEAF_BIT > 0
+    }
+
+    pub fn is_infinite(self) -> bool {
+        self.d0.x > self.d1.x
+    }
+
+    pub fn light_id(self) -> LightId {
+        LightId::new(self.d1.w.to_bits() & !Self::LEAF_BIT)
+    }
+
+    pub fn right_ptr(self) -> u32 {
+        self.d1.w.to_bits()
+    }
+
+    pub fn power(self) -> f32 {
+        self.d0.w
+    }
+
+    pub fn axis(self) -> Vec3 {
+        Normal::decode(self.d2.xy())
+    }
+
+    pub fn theta_o(self) -> f32 {
+        self.d2.z
+    }
+
+    pub fn theta_e(self) -> f32 {
+        self.d2.w
+    }
+
+    /// Returns a conservative estimate of how much light this node emits
+    /// towards given point.
+    pub fn importance(self, point: Vec3, normal: Vec3) -> f32 {
+        if self.is_infinite() {
+            return self.infinite_importance(normal);
+        }
+
+        let center = 0.5 * (self.d0.xyz() + self.d1.xyz());
+        let radius = 0.5 * self.d0.xyz().distance(self.d1.xyz());
+        let to_point = point - center;
+        let dist = to_point.length();
+
+        // If the point is inside the node's bounds, we can't say much about
+        // the orientation - just assume the worst
+        if dist <= radius {
+            return self.power() / radius.sqr().max(0.0001);
+        }
+
+        let to_point = to_point / dist;
+        let theta_u = (radius / dist).asin();
+
+        let theta = self.axis().dot(to_point).clamp(-1.0, 1.0).acos();
+        let theta = (theta - self.theta_o() - theta_u).max(0.0);
+
+        if theta >= self.theta_e() {
+            return 0.0;
+        }
+
+        let f_cosine = if normal == Vec3::ZERO {
+            1.0
+        } else {
+            let theta_i = normal.dot(-to_point).clamp(-1.0, 1.0).acos();
+            let theta_i = (theta_i - theta_u).max(0.0);
+
+            if theta_i >= 0.5 * PI {
+                return 0.0;
+            }
+
+            theta_i.cos()
+        };
+
+        self.power() * theta.cos() * f_cosine / dist.sqr()
+    }
+
+    /// Directional lights don't fall off with distance - their power already
+    /// corresponds to the irradiance they cast on a perpendicular surface, so
+    /// for them to be comparable with the finite nodes we only have to account
+    /// for the angle of incidence.
+    fn infinite_importance(self, normal: Vec3) -> f32 {
+        if normal == Vec3::ZERO {
+            return self.power();
+        }
+
+        // (axis points where the light travels, i.e. away from the lights)
+        let theta_i = normal.dot(-self.axis()).clamp(-1.0, 1.0).acos();
+        let theta_i = (theta_i - self.theta_o()).max(0.0);
+
+        if theta_i >= 0.5 * PI {
+            return 0.0;
+        }
+
+        self.power() * theta_i.cos()
+    }
+}
@@ -1,15 +1,25 @@
 use spirv_std::arch::IndexUnchecked;
 
-use crate::{Light, LightId};
+use crate::{EnvironmentMapView, IesProfilesView, Light, LightId};
 
 #[derive(Clone, Copy)]
 pub struct LightsView<'a> {
     items: &'a [Light],
+    ies_profiles: IesProfilesView<'a>,
+    env_map: EnvironmentMapView<'a>,
 }
 
 impl<'a> LightsView<'a> {
-    pub fn new(items: &'a [Light]) -> Self {
-        Self { items }
+    pub fn new(
+        items: &'a [Light],
+        ies_profiles: IesProfilesView<'a>,
+        env_map: EnvironmentMapView<'a>,
+    ) -> Self {
+        Self {
+            items,
+            ies_profiles,
+            env_map,
+        }
     }
 
     pub fn get(self, id: LightId) -> Light {
@@ -23,6 +33,14 @@ impl<'a> LightsView<'a> {
         light
     }
 
+    pub fn ies_profiles(self) -> IesProfilesView<'a> {
+        self.ies_profiles
+    }
+
+    pub fn env_map(self) -> EnvironmentMapView<'a> {
+        self.env_map
+    }
+
     pub fn len(self) -> usize {
         self.items.len()
     }
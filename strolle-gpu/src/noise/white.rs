@@ -80,4 +80,25 @@ impl WhiteNoise {
 
         (t * phi.cos() + b * phi.sin()) * sin_theta + normal * cos_theta
     }
+
+    /// Generates a cosine-weighted sample on a hemisphere around given normal;
+    /// the sample's pdf is `cos_theta / PI`.
+    pub fn sample_cosine_hemisphere(&mut self, normal: Vec3) -> Vec3 {
+        let disk = self.sample_disk();
+        let cos_theta = (1.0 - disk.length_squared()).max(0.0).sqrt();
+        let (t, b) = normal.any_orthonormal_pair();
+
+        t * disk.x + b * disk.y + normal * cos_theta
+    }
+
+    /// Generates a uniform sample inside of a cone with given axis and cosine
+    /// of its half-angle; the sample's pdf is `1 / (2 * PI * (1 - cos_max))`.
+    pub fn sample_cone(&mut self, axis: Vec3, cos_max: f32) -> Vec3 {
+        let cos_theta = 1.0 - self.sample() * (1.0 - cos_max);
+        let sin_theta = (1.0f32 - cos_theta.sqr()).max(0.0).sqrt();
+        let phi = 2.0 * PI * self.sample();
+        let (t, b) = axis.any_orthonormal_pair();
+
+        (t * phi.cos() + b * phi.sin()) * sin_theta + axis * cos_theta
+    }
 }
@@ -1,7 +1,9 @@
 use bytemuck::{Pod, Zeroable};
-use glam::{vec3a, vec4, Affine3A, Mat3A, Vec4};
+use glam::{vec3a, vec4, Affine3A, Mat3A, UVec2, Vec4};
+#[cfg(target_arch = "spirv")]
+use spirv_std::num_traits::Float;
 
-use crate::Frame;
+use crate::{Frame, EXPOSURE_HISTOGRAM_BINS, MIDDLE_GRAY};
 
 #[repr(C)]
 #[derive(Clone, Copy, Default, Pod, Zeroable)]
@@ -33,6 +35,10 @@ impl PrimRasterPassParams {
         self.payload.y.to_bits()
     }
 
+    pub fn excluded_lights(self) -> u32 {
+        self.payload.z.to_bits()
+    }
+
     pub fn curr_xform_inv(self) -> Affine3A {
         Self::decode_affine([
             self.curr_xform_inv_d0,
@@ -103,6 +109,86 @@ pub struct FrameDenoisingWaveletPassParams {
 #[cfg_attr(not(target_arch = "spirv"), derive(Debug))]
 pub struct FrameCompositionPassParams {
     pub camera_mode: u32,
+    pub has_fog: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default, Pod, Zeroable)]
+#[cfg_attr(not(target_arch = "spirv"), derive(Debug))]
+pub struct FrameDiagnosticsPassParams {
+    pub diagnostic: u32,
+    pub max: f32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default, Pod, Zeroable)]
+#[cfg_attr(not(target_arch = "spirv"), derive(Debug))]
+pub struct FrameAntialiasingPassParams {
+    pub output_size: UVec2,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default, Pod, Zeroable)]
+#[cfg_attr(not(target_arch = "spirv"), derive(Debug))]
+pub struct FrameBloomPassParams {
+    pub src_size: UVec2,
+    pub dst_size: UVec2,
+    pub radius: f32,
+    pub is_first: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default, Pod, Zeroable)]
+#[cfg_attr(not(target_arch = "spirv"), derive(Debug))]
+pub struct FrameOutputPassParams {
+    pub output_size: UVec2,
+    pub bloom_size: UVec2,
+    pub bloom_levels: u32,
+    pub bloom_intensity: f32,
+    pub tonemapping: u32,
+    pub tonemapping_lut_size: u32,
+    pub needs_srgb_encoding: u32,
+    pub camera_mode: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default, Pod, Zeroable)]
+#[cfg_attr(not(target_arch = "spirv"), derive(Debug))]
+pub struct FrameExposurePassParams {
+    pub min_ev: f32,
+    pub max_ev: f32,
+    pub speed_up: f32,
+    pub speed_down: f32,
+    pub compensation: f32,
+    pub delta_time: f32,
+}
+
+impl FrameExposurePassParams {
+    /// Returns index of the histogram's bin given luminance falls into.
+    ///
+    /// Bins span evenly (in the logarithmic space) from `min_ev` to `max_ev`,
+    /// with luminances outside of this range getting clamped into the first
+    /// or the last bin.
+    pub fn bin(self, luminance: f32) -> usize {
+        if luminance <= 0.0 {
+            return 0;
+        }
+
+        let ev = (luminance / MIDDLE_GRAY).log2();
+        let t = (ev - self.min_ev) / (self.max_ev - self.min_ev);
+
+        (t * (EXPOSURE_HISTOGRAM_BINS as f32))
+            .clamp(0.0, (EXPOSURE_HISTOGRAM_BINS - 1) as f32) as usize
+    }
+
+    /// Returns exposure value at the center of given histogram's bin.
+    ///
+    /// See: [`Self::bin()`].
+    pub fn bin_ev(self, bin: usize) -> f32 {
+        let t = (bin as f32 + 0.5) / (EXPOSURE_HISTOGRAM_BINS as f32);
+
+        self.min_ev + t * (self.max_ev - self.min_ev)
+    }
 }
 
 #[repr(C)]
@@ -112,6 +198,11 @@ pub struct RefPassParams {
     pub seed: u32,
     pub frame: Frame,
     pub depth: u32,
+    pub max_depth: u32,
+
+    /// Number of samples accumulated so far, including the one being traced
+    /// now; `1` means the accumulation is starting over.
+    pub samples: u32,
 }
 
 #[repr(C)]
@@ -131,3 +222,33 @@ pub struct GiResolvingPassParams {
     pub frame: Frame,
     pub source: u32,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exposure_bins() {
+        let params = FrameExposurePassParams {
+            min_ev: -8.0,
+            max_ev: 8.0,
+            ..Default::default()
+        };
+
+        // Case: luminances inside the histogram's range
+        for ev in [-7.5, -2.0, 0.0, 0.1, 3.3, 7.9] {
+            let bin = params.bin(MIDDLE_GRAY * f32::exp2(ev));
+
+            assert!((params.bin_ev(bin) - ev).abs() <= 0.125, "ev={ev}");
+        }
+
+        // Case: luminances outside of the histogram's range
+        assert_eq!(0, params.bin(0.0));
+        assert_eq!(0, params.bin(MIDDLE_GRAY * f32::exp2(-100.0)));
+
+        assert_eq!(
+            EXPOSURE_HISTOGRAM_BINS - 1,
+            params.bin(MIDDLE_GRAY * f32::exp2(100.0)),
+        );
+    }
+}
@@ -64,7 +64,7 @@ impl Ray {
     ) -> (TriangleHit, usize) {
         let mut hit = TriangleHit::none();
 
-        let used_memory = self.traverse(
+        let (used_memory, _) = self.traverse(
             local_idx,
             stack,
             triangles,
@@ -91,12 +91,36 @@ impl Ray {
         atlas_tex: Tex,
         atlas_sampler: &Sampler,
     ) -> bool {
+        self.intersect_ex(
+            local_idx,
+            stack,
+            triangles,
+            bvh,
+            materials,
+            atlas_tex,
+            atlas_sampler,
+        )
+        .0
+    }
+
+    /// Same as [`Self::intersect()`], but also returns the number of BVH nodes
+    /// visited on the way; useful for debugging.
+    pub fn intersect_ex(
+        self,
+        local_idx: u32,
+        stack: BvhStack,
+        triangles: TrianglesView,
+        bvh: BvhView,
+        materials: MaterialsView,
+        atlas_tex: Tex,
+        atlas_sampler: &Sampler,
+    ) -> (bool, u32) {
         let mut hit = TriangleHit {
             distance: self.len,
             ..TriangleHit::none()
         };
 
-        self.traverse(
+        let (_, visited_nodes) = self.traverse(
             local_idx,
             stack,
             triangles,
@@ -108,7 +132,7 @@ impl Ray {
             &mut hit,
         );
 
-        hit.distance < self.len
+        (hit.distance < self.len, visited_nodes)
     }
 
     fn traverse(
@@ -122,10 +146,12 @@ impl Ray {
         atlas_sampler: &Sampler,
         tracing: Tracing,
         hit: &mut TriangleHit,
-    ) -> usize {
-        // An estimation of the memory used when travelling the BVH; useful for
-        // debugging
+    ) -> (usize, u32) {
+        // An estimation of the memory used when travelling the BVH and the
+        // number of nodes visited (counting each of leaf's triangles as a
+        // separate node); useful for debugging
         let mut used_memory = 0;
+        let mut visited_nodes = 0;
 
         // Index into the `bvh` array; points at the currently processed node
         let mut bvh_ptr = 0;
@@ -139,6 +165,7 @@ impl Ray {
 
         loop {
             used_memory += mem::size_of::<Vec4>();
+            visited_nodes += 1;
 
             let d0 = bvh.get(bvh_ptr);
             let is_internal_node = d0.w.to_bits() == 0;
@@ -200,6 +227,10 @@ impl Ray {
                 // hit is actually opaque at that particular hit-point.
                 let has_alpha_blending = flags & 2 == 2;
 
+                // Light-linking of the instance this triangle belongs to,
+                // see: `TriangleHit::excluded_lights`
+                let excluded_lights = flags >> 2;
+
                 let triangle_id = TriangleId::new(d0.y.to_bits());
                 let material_id = MaterialId::new(d0.z.to_bits());
 
@@ -230,6 +261,7 @@ impl Ray {
 
                 if found_hit {
                     hit.material_id = material_id;
+                    hit.excluded_lights = excluded_lights;
 
                     if let Tracing::ReturnFirst = tracing {
                         break;
@@ -262,7 +294,7 @@ impl Ray {
             hit.point = self.at(hit.distance);
         }
 
-        used_memory
+        (used_memory, visited_nodes)
     }
 
     /// Checks whether this ray hits given bounding-box and returns their
@@ -94,23 +94,45 @@ pub struct DiSample {
 
 impl DiSample {
     pub fn pdf(self, lights: LightsView, hit: Hit) -> f32 {
-        let light = lights.get(self.light_id);
+        let light = self.light(lights, hit.point);
 
-        self.pdf_ex(light, hit)
+        self.pdf_ex(lights, light, hit)
     }
 
     pub fn pdf_prev(self, lights: LightsView, hit: Hit) -> f32 {
-        let light = lights.get_prev(self.light_id);
+        let light = self.light_prev(lights, hit.point);
 
-        self.pdf_ex(light, hit)
+        self.pdf_ex(lights, light, hit)
     }
 
-    fn pdf_ex(self, light: Light, mut hit: Hit) -> f32 {
+    /// Returns light this sample refers to, as seen from given point.
+    ///
+    /// Samples of the environment map don't have any light of their own, so
+    /// we represent them as directional lights pointing from `light_point`.
+    pub fn light(self, lights: LightsView, hit_point: Vec3) -> Light {
+        if self.light_id.is_sky() {
+            lights
+                .env_map()
+                .light((self.light_point - hit_point).normalize())
+        } else {
+            lights.get(self.light_id)
+        }
+    }
+
+    pub fn light_prev(self, lights: LightsView, hit_point: Vec3) -> Light {
+        if self.light_id.is_sky() {
+            self.light(lights, hit_point)
+        } else {
+            lights.get_prev(self.light_id)
+        }
+    }
+
+    fn pdf_ex(self, lights: LightsView, light: Light, mut hit: Hit) -> f32 {
         hit.gbuffer.base_color = Vec4::ONE;
 
         if !light.is_none() && light.contains(self.light_point) {
             // TODO use a cheaper proxy
-            light.radiance(hit).sum().luma()
+            light.radiance(lights.ies_profiles(), hit).sum().luma()
         } else {
             0.0
         }
@@ -1,8 +1,10 @@
 use core::ops::{Deref, DerefMut};
 
+use glam::Vec3;
+
 use crate::{
-    Hit, LightId, LightRadiance, LightsView, Reservoir, Vec3Ext, WhiteNoise,
-    World,
+    FogView, Hit, Light, LightId, LightRadiance, LightTreeView, LightsView,
+    Reservoir, Vec3Ext, WhiteNoise, World,
 };
 
 #[derive(Clone, Copy, Default)]
@@ -14,40 +16,134 @@ impl EphemeralReservoir {
     pub fn build(
         wnoise: &mut WhiteNoise,
         lights: LightsView,
+        light_tree: LightTreeView,
         world: World,
         hit: Hit,
+    ) -> Self {
+        Self::build_ex(
+            wnoise,
+            lights,
+            light_tree,
+            world,
+            hit.point,
+            hit.gbuffer.normal,
+            |light| light.radiance(lights.ies_profiles(), hit),
+        )
+    }
+
+    /// Builds a reservoir for a point inside fog, where lights are weighted by
+    /// the phase function instead of a surface's BRDF.
+    ///
+    /// `view_dir` points from the point towards the viewer.
+    pub fn build_for_fog(
+        wnoise: &mut WhiteNoise,
+        lights: LightsView,
+        light_tree: LightTreeView,
+        world: World,
+        fog: FogView,
+        point: Vec3,
+        view_dir: Vec3,
+    ) -> Self {
+        Self::build_ex(
+            wnoise,
+            lights,
+            light_tree,
+            world,
+            point,
+            Vec3::ZERO,
+            |light| {
+                let phase = fog.phase(view_dir.dot(light.dir_from(point)));
+
+                LightRadiance {
+                    radiance: light.intensity(lights.ies_profiles(), point)
+                        * phase,
+                    diff_brdf: Vec3::ONE,
+                    spec_brdf: Vec3::ZERO,
+                }
+            },
+        )
+    }
+
+    fn build_ex(
+        wnoise: &mut WhiteNoise,
+        lights: LightsView,
+        light_tree: LightTreeView,
+        world: World,
+        point: Vec3,
+        normal: Vec3,
+        radiance: impl Fn(Light) -> LightRadiance,
     ) -> Self {
         let mut res = EphemeralReservoir::default();
         let mut res_pdf = 0.0;
+        let env_map = lights.env_map();
 
-        // TODO rust-gpu seems to miscompile `.min()`
-        let max_samples = if world.light_count < 16 {
-            world.light_count
+        if world.light_count == 0 && !env_map.is_active() {
+            return res;
+        }
+
+        // Probability of taking candidate from the environment map instead of
+        // the light tree
+        let env_map_pdf = if !env_map.is_active() {
+            0.0
+        } else if world.light_count == 0 {
+            1.0
         } else {
+            0.5
+        };
+
+        // TODO rust-gpu seems to miscompile `.min()`
+        let max_samples = if env_map.is_active() || world.light_count >= 16 {
             16
+        } else {
+            world.light_count
         };
 
-        let sample_ipdf = world.light_count as f32;
         let mut sample_nth = 0;
 
         while sample_nth < max_samples {
-            let light_id =
-                LightId::new(wnoise.sample_int() % world.light_count);
+            sample_nth += 1;
+
+            let light_id;
+            let light_dir;
+            let light;
+            let light_pdf;
+
+            if wnoise.sample() < env_map_pdf {
+                let (dir, pdf) = env_map.sample(wnoise);
+
+                light_id = LightId::sky();
+                light_dir = dir;
+                light = env_map.light(dir);
+                light_pdf = env_map_pdf * pdf;
+            } else {
+                let (id, pdf) = light_tree.sample(wnoise, point, normal);
+
+                light_id = id;
+                light_dir = Vec3::ZERO;
+                light = lights.get(id);
+                light_pdf = (1.0 - env_map_pdf) * pdf;
+            }
 
-            let light_rad = lights.get(light_id).radiance(hit);
+            if light_pdf <= 0.0 {
+                // Tree has led us into a branch that can't reach this point
+                // (or we've picked a black texel of the environment map);
+                // count it as a rejected candidate so that the estimate stays
+                // unbiased
+                res.m += 1.0;
+                continue;
+            }
 
             let sample = EphemeralSample {
                 light_id,
-                light_rad,
+                light_dir,
+                light_rad: radiance(light),
             };
 
             let sample_pdf = sample.pdf();
 
-            if res.update(wnoise, sample, sample_pdf * sample_ipdf) {
+            if res.update(wnoise, sample, sample_pdf / light_pdf) {
                 res_pdf = sample_pdf;
             }
-
-            sample_nth += 1;
         }
 
         res.norm_avg(res_pdf);
@@ -72,10 +168,20 @@ impl DerefMut for EphemeralReservoir {
 #[derive(Clone, Copy, Default)]
 pub struct EphemeralSample {
     pub light_id: LightId,
+    /// Direction towards the sample; valid only for [`LightId::sky()`].
+    pub light_dir: Vec3,
     pub light_rad: LightRadiance,
 }
 
 impl EphemeralSample {
+    pub fn light(self, lights: LightsView) -> Light {
+        if self.light_id.is_sky() {
+            lights.env_map().light(self.light_dir)
+        } else {
+            lights.get(self.light_id)
+        }
+    }
+
     pub fn pdf(self) -> f32 {
         self.light_rad.radiance.perc_luma()
     }
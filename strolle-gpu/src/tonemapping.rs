@@ -0,0 +1,130 @@
+//! Tonemapping operators, i.e. functions that map linear HDR colors into the
+//! displayable `0.0..=1.0` range.
+//!
+//! All of the operators take and return linear colors - encoding them for the
+//! display is done separately, see: [`linear_to_srgb()`].
+
+use glam::{vec3, Mat3, Vec3};
+#[cfg(target_arch = "spirv")]
+use spirv_std::num_traits::Float;
+
+use crate::Vec3Ext;
+
+/// Reinhard operator, applied on the color's luminance so that hues don't get
+/// shifted.
+pub fn reinhard(color: Vec3) -> Vec3 {
+    color / (1.0 + color.luma())
+}
+
+/// Fitted ACES (RRT + ODT) operator.
+///
+/// See:
+/// - https://github.com/TheRealMJP/BakingLab/blob/master/BakingLab/ACES.hlsl
+pub fn aces(color: Vec3) -> Vec3 {
+    let input = Mat3::from_cols(
+        vec3(0.59719, 0.07600, 0.02840),
+        vec3(0.35458, 0.90834, 0.13383),
+        vec3(0.04823, 0.01566, 0.83777),
+    );
+
+    let output = Mat3::from_cols(
+        vec3(1.60475, -0.10208, -0.00327),
+        vec3(-0.53108, 1.10813, -0.07276),
+        vec3(-0.07367, -0.00605, 1.07602),
+    );
+
+    let v = input * color;
+
+    let a = v * (v + 0.0245786) - 0.000090537;
+    let b = v * (0.983729 * v + 0.4329510) + 0.238081;
+
+    (output * (a / b)).clamp(Vec3::ZERO, Vec3::ONE)
+}
+
+/// AgX operator, with the default ("base") look.
+///
+/// See:
+/// - https://iolite-engine.com/blog_posts/minimal_agx_implementation
+pub fn agx(color: Vec3) -> Vec3 {
+    const MIN_EV: f32 = -12.47393;
+    const MAX_EV: f32 = 4.026069;
+
+    let inset = Mat3::from_cols(
+        vec3(0.842479062253094, 0.0423282422610123, 0.0423756549057051),
+        vec3(0.0784335999999992, 0.878468636469772, 0.0784336),
+        vec3(0.0792237451477643, 0.0791661274605434, 0.879142973793104),
+    );
+
+    let outset = Mat3::from_cols(
+        vec3(1.19687900512017, -0.0528968517574562, -0.0529716355144438),
+        vec3(-0.0980208811401368, 1.15190312990417, -0.0980434501171241),
+        vec3(-0.0990297440797205, -0.0989611768448433, 1.15107367264116),
+    );
+
+    let v = (inset * color).max(Vec3::splat(1e-10));
+    let v = vec3(v.x.log2(), v.y.log2(), v.z.log2());
+    let v = (v.clamp(Vec3::splat(MIN_EV), Vec3::splat(MAX_EV)) - MIN_EV)
+        / (MAX_EV - MIN_EV);
+
+    // Sigmoid, approximated with a polynomial
+    let v2 = v * v;
+    let v4 = v2 * v2;
+
+    let v = 15.5 * v4 * v2 - 40.14 * v4 * v + 31.96 * v4 - 6.868 * v2 * v
+        + 0.4298 * v2
+        + 0.1191 * v
+        - 0.00232;
+
+    let v = (outset * v).clamp(Vec3::ZERO, Vec3::ONE);
+
+    // AgX's output is display-encoded, so let's go back to linear
+    vec3(v.x.powf(2.2), v.y.powf(2.2), v.z.powf(2.2))
+}
+
+/// Encodes given linear color for an sRGB display.
+pub fn linear_to_srgb(color: Vec3) -> Vec3 {
+    fn encode(x: f32) -> f32 {
+        if x <= 0.0031308 {
+            x * 12.92
+        } else {
+            1.055 * x.powf(1.0 / 2.4) - 0.055
+        }
+    }
+
+    let color = color.clamp(Vec3::ZERO, Vec3::ONE);
+
+    vec3(encode(color.x), encode(color.y), encode(color.z))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn operators() {
+        for op in [reinhard, aces, agx] {
+            // Case: black stays black
+            assert!(op(Vec3::ZERO).abs_diff_eq(Vec3::ZERO, 0.01));
+
+            // Case: colors are brought into the displayable range, without
+            // inverting the order of brightness
+            let mut prev = Vec3::ZERO;
+
+            for x in [0.01, 0.1, 0.5, 1.0, 4.0, 16.0, 256.0] {
+                let curr = op(Vec3::splat(x));
+
+                assert!(curr.cmpge(prev).all(), "x={x}");
+                assert!(curr.cmple(Vec3::ONE).all(), "x={x}");
+
+                prev = curr;
+            }
+        }
+    }
+
+    #[test]
+    fn linear_to_srgb() {
+        let actual = super::linear_to_srgb(vec3(0.0, 0.2140, 1.0));
+
+        assert!(actual.abs_diff_eq(vec3(0.0, 0.5, 1.0), 0.001));
+    }
+}
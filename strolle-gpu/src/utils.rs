@@ -6,7 +6,7 @@ mod vec3_ext;
 
 use core::ops;
 
-use glam::{uvec2, UVec2};
+use glam::{uvec2, UVec2, Vec3};
 use spirv_std::Image;
 
 pub use self::bilinear_filter::*;
@@ -30,6 +30,20 @@ where
     a + (b - a) * t.clamp(0.0, 1.0)
 }
 
+/// Returns weight of a sample drawn from a strategy with pdf `pdf`, when it's
+/// combined with another strategy with pdf `other_pdf` through multiple
+/// importance sampling (using the power heuristic).
+pub fn mis_weight(pdf: f32, other_pdf: f32) -> f32 {
+    let pdf2 = pdf * pdf;
+    let other_pdf2 = other_pdf * other_pdf;
+
+    if pdf2 + other_pdf2 > 0.0 {
+        pdf2 / (pdf2 + other_pdf2)
+    } else {
+        0.0
+    }
+}
+
 pub fn resolve_checkerboard(global_id: UVec2, frame: u32) -> UVec2 {
     global_id * uvec2(2, 1) + uvec2((frame + global_id.y) % 2, 0)
 }
@@ -41,3 +55,30 @@ pub fn resolve_checkerboard_alt(global_id: UVec2, frame: u32) -> UVec2 {
 pub fn got_checkerboard_at(screen_pos: UVec2, frame: u32) -> bool {
     screen_pos == resolve_checkerboard(screen_pos / uvec2(2, 1), frame)
 }
+
+/// Maps given value (within `<0.0, 1.0>`) onto a gradient with evenly spaced
+/// colors.
+pub fn gradient<const N: usize>(colors: [Vec3; N], progress: f32) -> Vec3 {
+    if progress <= 0.0 {
+        return colors[0];
+    }
+
+    let step = 1.0 / (N as f32 - 1.0);
+    let mut i = 0;
+
+    while i < (N - 1) {
+        let min = step * (i as f32);
+        let max = step * (i as f32 + 1.0);
+
+        if progress >= min && progress <= max {
+            let rhs = (progress - min) / step;
+            let lhs = 1.0 - rhs;
+
+            return lhs * colors[i] + rhs * colors[i + 1];
+        }
+
+        i += 1;
+    }
+
+    colors[N - 1]
+}
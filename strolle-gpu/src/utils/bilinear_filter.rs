@@ -76,6 +76,31 @@ impl BilinearFilter {
         }
     }
 
+    /// Samples a texture of given size at given point, with texel centers
+    /// lying at `+0.5`; points outside of the texture get clamped to its
+    /// edges.
+    pub fn sample(
+        size: UVec2,
+        pos: Vec2,
+        sample: impl Fn(UVec2) -> Vec4,
+    ) -> Vec4 {
+        let pos = pos - 0.5;
+        let p00 = pos.floor().as_ivec2();
+        let max = size.as_ivec2() - IVec2::ONE;
+
+        let sample =
+            move |pos: IVec2| sample(pos.clamp(IVec2::ZERO, max).as_uvec2());
+
+        Self {
+            s00: sample(p00),
+            s10: sample(p00 + ivec2(1, 0)),
+            s01: sample(p00 + ivec2(0, 1)),
+            s11: sample(p00 + ivec2(1, 1)),
+            weights: Vec4::ONE,
+        }
+        .eval(pos - pos.floor())
+    }
+
     pub fn reprojection_coords(prev_x: f32, prev_y: f32) -> [IVec2; 4] {
         let p00 = ivec2(prev_x.floor() as i32, prev_y.floor() as i32);
         let p10 = ivec2(prev_x.ceil() as i32, prev_y.floor() as i32);
@@ -107,3 +132,28 @@ impl BilinearFilter {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use glam::uvec2;
+
+    use super::*;
+
+    #[test]
+    fn sample() {
+        let size = uvec2(2, 2);
+        let tex = |pos: UVec2| Vec4::splat((pos.x + 2 * pos.y) as f32);
+
+        // Case: texel centers
+        assert_eq!(0.0, BilinearFilter::sample(size, vec2(0.5, 0.5), tex).x);
+        assert_eq!(3.0, BilinearFilter::sample(size, vec2(1.5, 1.5), tex).x);
+
+        // Case: between texels
+        assert_eq!(0.5, BilinearFilter::sample(size, vec2(1.0, 0.5), tex).x);
+        assert_eq!(1.5, BilinearFilter::sample(size, vec2(1.0, 1.0), tex).x);
+
+        // Case: outside of the texture
+        assert_eq!(0.0, BilinearFilter::sample(size, vec2(-4.0, 0.0), tex).x);
+        assert_eq!(3.0, BilinearFilter::sample(size, vec2(9.0, 9.0), tex).x);
+    }
+}
@@ -10,6 +10,18 @@ pub struct World {
     pub light_count: u32,
     pub sun_azimuth: f32,
     pub sun_altitude: f32,
+    pub moon_azimuth: f32,
+    pub moon_altitude: f32,
+
+    /// Moon's phase, from 0.0 (new moon) through 0.5 (full moon) to 1.0 (new
+    /// moon again).
+    pub moon_phase: f32,
+
+    pub moon_intensity: f32,
+
+    /// Index of the Vec4 from which the light tree stores paths leading to
+    /// each light, see: [`crate::LightTreeView::pdf()`].
+    pub light_tree_paths: u32,
 }
 
 impl World {
@@ -17,14 +29,23 @@ impl World {
     pub const SUN_DISTANCE: f32 = 1000.0;
 
     pub fn sun_dir(self) -> Vec3 {
-        vec3(
-            self.sun_altitude.cos() * self.sun_azimuth.sin(),
-            self.sun_altitude.sin(),
-            -self.sun_altitude.cos() * self.sun_azimuth.cos(),
-        )
+        Self::dir(self.sun_azimuth, self.sun_altitude)
     }
 
-    pub fn sun_pos(self) -> Vec3 {
-        self.sun_dir() * Self::SUN_DISTANCE
+    pub fn moon_dir(self) -> Vec3 {
+        Self::dir(self.moon_azimuth, self.moon_altitude)
+    }
+
+    /// Returns the illuminated fraction of moon's disk.
+    pub fn moon_illumination(self) -> f32 {
+        0.5 - 0.5 * (2.0 * core::f32::consts::PI * self.moon_phase).cos()
+    }
+
+    fn dir(azimuth: f32, altitude: f32) -> Vec3 {
+        vec3(
+            altitude.cos() * azimuth.sin(),
+            altitude.sin(),
+            -altitude.cos() * azimuth.cos(),
+        )
     }
 }
@@ -43,13 +43,16 @@ use strolle_gpu::prelude::*;
 #[spirv(compute(threads(8, 8)))]
 pub fn generate_scattering_lut(
     #[spirv(global_invocation_id)] global_id: UVec3,
-    #[spirv(descriptor_set = 0, binding = 0)] transmittance_lut_tex: Tex,
-    #[spirv(descriptor_set = 0, binding = 1)]
+    #[spirv(descriptor_set = 0, binding = 0, uniform)]
+    settings: &AtmosphereSettings,
+    #[spirv(descriptor_set = 0, binding = 1)] transmittance_lut_tex: Tex,
+    #[spirv(descriptor_set = 0, binding = 2)]
     transmittance_lut_sampler: &Sampler,
-    #[spirv(descriptor_set = 0, binding = 2)] out: TexRgba16,
+    #[spirv(descriptor_set = 0, binding = 3)] out: TexRgba16,
 ) {
     generate_scattering_lut::main(
         global_id,
+        settings,
         transmittance_lut_tex,
         transmittance_lut_sampler,
         out,
@@ -60,16 +63,19 @@ pub fn generate_scattering_lut(
 pub fn generate_sky_lut(
     #[spirv(global_invocation_id)] global_id: UVec3,
     #[spirv(descriptor_set = 0, binding = 0, uniform)] world: &World,
-    #[spirv(descriptor_set = 0, binding = 1)] transmittance_lut_tex: Tex,
-    #[spirv(descriptor_set = 0, binding = 2)]
+    #[spirv(descriptor_set = 0, binding = 1, uniform)]
+    settings: &AtmosphereSettings,
+    #[spirv(descriptor_set = 0, binding = 2)] transmittance_lut_tex: Tex,
+    #[spirv(descriptor_set = 0, binding = 3)]
     transmittance_lut_sampler: &Sampler,
-    #[spirv(descriptor_set = 0, binding = 3)] scattering_lut_tex: Tex,
-    #[spirv(descriptor_set = 0, binding = 4)] scattering_lut_sampler: &Sampler,
-    #[spirv(descriptor_set = 0, binding = 5)] out: TexRgba16,
+    #[spirv(descriptor_set = 0, binding = 4)] scattering_lut_tex: Tex,
+    #[spirv(descriptor_set = 0, binding = 5)] scattering_lut_sampler: &Sampler,
+    #[spirv(descriptor_set = 0, binding = 6)] out: TexRgba16,
 ) {
     generate_sky_lut::main(
         global_id,
         world,
+        settings,
         transmittance_lut_tex,
         transmittance_lut_sampler,
         scattering_lut_tex,
@@ -81,7 +87,9 @@ pub fn generate_sky_lut(
 #[spirv(compute(threads(8, 8)))]
 pub fn generate_transmittance_lut(
     #[spirv(global_invocation_id)] global_id: UVec3,
-    #[spirv(descriptor_set = 0, binding = 0)] out: TexRgba16,
+    #[spirv(descriptor_set = 0, binding = 0, uniform)]
+    settings: &AtmosphereSettings,
+    #[spirv(descriptor_set = 0, binding = 1)] out: TexRgba16,
 ) {
-    generate_transmittance_lut::main(global_id, out);
+    generate_transmittance_lut::main(global_id, settings, out);
 }
@@ -4,6 +4,7 @@ use super::utils::*;
 
 pub fn main(
     global_id: UVec3,
+    settings: &AtmosphereSettings,
     transmittance_lut_tex: Tex,
     transmittance_lut_sampler: &Sampler,
     out: TexRgba16,
@@ -17,8 +18,8 @@ pub fn main(
     let sun_theta = sun_cos_theta.clamp(-1.0, 1.0).acos();
 
     let height = lerp(
-        Atmosphere::GROUND_RADIUS_MM,
-        Atmosphere::ATMOSPHERE_RADIUS_MM,
+        settings.ground_radius,
+        settings.atmosphere_radius,
         uv.y.max(0.01),
     );
 
@@ -26,6 +27,7 @@ pub fn main(
     let sun_dir = vec3(0.0, sun_cos_theta, -sun_theta.sin()).normalize();
 
     let (lum, f_ms) = eval(
+        settings,
         transmittance_lut_tex,
         transmittance_lut_sampler,
         pos,
@@ -40,6 +42,7 @@ pub fn main(
 }
 
 pub fn eval(
+    settings: &AtmosphereSettings,
     transmittance_lut_tex: Tex,
     transmittance_lut_sampler: &Sampler,
     pos: Vec3,
@@ -70,10 +73,10 @@ pub fn eval(
             let ray_dir = spherical_direction(theta, phi);
 
             let atmosphere_distance = Ray::new(pos, ray_dir)
-                .intersect_sphere(Atmosphere::ATMOSPHERE_RADIUS_MM);
+                .intersect_sphere(settings.atmosphere_radius);
 
-            let ground_distance = Ray::new(pos, ray_dir)
-                .intersect_sphere(Atmosphere::GROUND_RADIUS_MM);
+            let ground_distance =
+                Ray::new(pos, ray_dir).intersect_sphere(settings.ground_radius);
 
             let t_max = if ground_distance > 0.0 {
                 ground_distance
@@ -82,7 +85,8 @@ pub fn eval(
             };
 
             let cos_theta = ray_dir.dot(sun_dir);
-            let mie_phase_value = eval_mie_phase(cos_theta);
+            let mie_phase_value =
+                eval_mie_phase(settings.mie_anisotropy, cos_theta);
             let rayleigh_phase_value = eval_rayleigh_phase(-cos_theta);
 
             let mut lum = Vec3::default();
@@ -103,7 +107,7 @@ pub fn eval(
                 let new_pos = pos + t * ray_dir;
 
                 let (rayleigh_scattering, mie_scattering, extinction) =
-                    eval_scattering(new_pos);
+                    eval_scattering(settings, new_pos);
 
                 let sample_transmittance = (-dt * extinction).exp();
 
@@ -116,6 +120,7 @@ pub fn eval(
                 lum_factor += transmittance * scattering_f;
 
                 let sun_transmittance = Atmosphere::sample_lut(
+                    settings,
                     transmittance_lut_tex,
                     transmittance_lut_sampler,
                     new_pos,
@@ -144,12 +149,12 @@ pub fn eval(
                 let mut hit_pos = pos + ground_distance * ray_dir;
 
                 if pos.dot(sun_dir) > 0.0 {
-                    hit_pos =
-                        hit_pos.normalize() * Atmosphere::GROUND_RADIUS_MM;
+                    hit_pos = hit_pos.normalize() * settings.ground_radius;
 
                     lum += transmittance
-                        * Atmosphere::GROUND_ALBEDO
+                        * settings.ground_albedo
                         * Atmosphere::sample_lut(
+                            settings,
                             transmittance_lut_tex,
                             transmittance_lut_sampler,
                             hit_pos,
@@ -5,6 +5,7 @@ use super::utils::*;
 pub fn main(
     global_id: UVec3,
     world: &World,
+    settings: &AtmosphereSettings,
     transmittance_lut_tex: Tex,
     transmittance_lut_sampler: &Sampler,
     scattering_lut_tex: Tex,
@@ -29,8 +30,8 @@ pub fn main(
             };
 
             let horizon = {
-                let height = Atmosphere::VIEW_POS.length();
-                let t = height.sqr() - Atmosphere::GROUND_RADIUS_MM.sqr();
+                let height = settings.view_pos().length();
+                let t = height.sqr() - settings.ground_radius.sqr();
                 let t = t.sqrt() / height;
 
                 t.clamp(-1.0, 1.0).acos() - 0.5 * PI
@@ -62,11 +63,11 @@ pub fn main(
         }
     };
 
-    let atmosphere_distance = Ray::new(Atmosphere::VIEW_POS, ray_dir)
-        .intersect_sphere(Atmosphere::ATMOSPHERE_RADIUS_MM);
+    let atmosphere_distance = Ray::new(settings.view_pos(), ray_dir)
+        .intersect_sphere(settings.atmosphere_radius);
 
-    let ground_distance = Ray::new(Atmosphere::VIEW_POS, ray_dir)
-        .intersect_sphere(Atmosphere::GROUND_RADIUS_MM);
+    let ground_distance = Ray::new(settings.view_pos(), ray_dir)
+        .intersect_sphere(settings.ground_radius);
 
     let t_max = if ground_distance < 0.0 {
         atmosphere_distance
@@ -75,11 +76,12 @@ pub fn main(
     };
 
     let out_val = eval(
+        settings,
         transmittance_lut_tex,
         transmittance_lut_sampler,
         scattering_lut_tex,
         scattering_lut_sampler,
-        Atmosphere::VIEW_POS,
+        settings.view_pos(),
         ray_dir,
         sun_dir,
         t_max,
@@ -92,6 +94,7 @@ pub fn main(
 }
 
 pub fn eval(
+    settings: &AtmosphereSettings,
     transmittance_lut_tex: Tex,
     transmittance_lut_sampler: &Sampler,
     scattering_lut_tex: Tex,
@@ -103,7 +106,7 @@ pub fn eval(
     num_steps: f32,
 ) -> Vec3 {
     let cos_theta = ray_dir.dot(sun_dir);
-    let mie_phase_value = eval_mie_phase(cos_theta);
+    let mie_phase_value = eval_mie_phase(settings.mie_anisotropy, cos_theta);
     let rayleigh_phase_value = eval_rayleigh_phase(-cos_theta);
 
     let mut lum = Vec3::default();
@@ -120,11 +123,12 @@ pub fn eval(
         let new_pos = pos + t * ray_dir;
 
         let (rayleigh_scattering, mie_scattering, extinction) =
-            eval_scattering(new_pos);
+            eval_scattering(settings, new_pos);
 
         let sample_transmittance = (-dt * extinction).exp();
 
         let sun_transmittance = Atmosphere::sample_lut(
+            settings,
             transmittance_lut_tex,
             transmittance_lut_sampler,
             new_pos,
@@ -132,6 +136,7 @@ pub fn eval(
         );
 
         let psi_ms = Atmosphere::sample_lut(
+            settings,
             scattering_lut_tex,
             scattering_lut_sampler,
             new_pos,
@@ -2,7 +2,7 @@ use strolle_gpu::prelude::*;
 
 use super::utils::*;
 
-pub fn main(global_id: UVec3, out: TexRgba16) {
+pub fn main(global_id: UVec3, settings: &AtmosphereSettings, out: TexRgba16) {
     let global_id = global_id.xy();
 
     let uv = global_id.as_vec2()
@@ -11,30 +11,24 @@ pub fn main(global_id: UVec3, out: TexRgba16) {
     let sun_cos_theta = 2.0 * uv.x - 1.0;
     let sun_theta = sun_cos_theta.clamp(-1.0, 1.0).acos();
 
-    let height = lerp(
-        Atmosphere::GROUND_RADIUS_MM,
-        Atmosphere::ATMOSPHERE_RADIUS_MM,
-        uv.y,
-    );
+    let height = lerp(settings.ground_radius, settings.atmosphere_radius, uv.y);
 
     let pos = vec3(0.0, height, 0.0);
     let sun_dir = vec3(0.0, sun_cos_theta, -sun_theta.sin()).normalize();
-    let out_val = eval(pos, sun_dir);
+    let out_val = eval(settings, pos, sun_dir);
 
     unsafe {
         out.write(global_id, out_val.extend(1.0));
     }
 }
 
-pub fn eval(pos: Vec3, sun_dir: Vec3) -> Vec3 {
-    if Ray::new(pos, sun_dir).intersect_sphere(Atmosphere::GROUND_RADIUS_MM)
-        > 0.0
-    {
+pub fn eval(settings: &AtmosphereSettings, pos: Vec3, sun_dir: Vec3) -> Vec3 {
+    if Ray::new(pos, sun_dir).intersect_sphere(settings.ground_radius) > 0.0 {
         return Default::default();
     }
 
-    let atmosphere_distance = Ray::new(pos, sun_dir)
-        .intersect_sphere(Atmosphere::ATMOSPHERE_RADIUS_MM);
+    let atmosphere_distance =
+        Ray::new(pos, sun_dir).intersect_sphere(settings.atmosphere_radius);
 
     let mut t = 0.0;
     let mut transmittance = Vec3::splat(1.0);
@@ -49,7 +43,7 @@ pub fn eval(pos: Vec3, sun_dir: Vec3) -> Vec3 {
         t = new_t;
 
         let new_pos = pos + t * sun_dir;
-        let (_, _, extinction) = eval_scattering(new_pos);
+        let (_, _, extinction) = eval_scattering(settings, new_pos);
 
         transmittance *= (-dt * extinction).exp();
         i += 1.0;
@@ -1,20 +1,20 @@
 use strolle_gpu::prelude::*;
 
-pub fn eval_scattering(pos: Vec3) -> (Vec3, f32, Vec3) {
-    let altitude_km = (pos.length() - Atmosphere::GROUND_RADIUS_MM) * 1000.0;
+pub fn eval_scattering(
+    settings: &AtmosphereSettings,
+    pos: Vec3,
+) -> (Vec3, f32, Vec3) {
+    let altitude_km = (pos.length() - settings.ground_radius) * 1000.0;
     let rayleigh_density = (-altitude_km / 8.0).exp();
     let mie_density = (-altitude_km / 1.2).exp();
 
-    let rayleigh_scattering =
-        Atmosphere::RAYLEIGH_SCATTERING_BASE * rayleigh_density;
+    let rayleigh_scattering = settings.rayleigh_scattering * rayleigh_density;
+    let rayleigh_absorption = settings.rayleigh_absorption * rayleigh_density;
 
-    let rayleigh_absorption =
-        Atmosphere::RAYLEIGH_ABSORPTION_BASE * rayleigh_density;
+    let mie_scattering = settings.mie_scattering * mie_density;
+    let mie_absorption = settings.mie_absorption * mie_density;
 
-    let mie_scattering = Atmosphere::MIE_SCATTERING_BASE * mie_density;
-    let mie_absorption = Atmosphere::MIE_ABSORPTION_BASE * mie_density;
-
-    let ozone_absorption = Atmosphere::OZONE_ABSORPTION_BASE
+    let ozone_absorption = settings.ozone_absorption
         * (1.0 - (altitude_km - 25.0).abs() / 15.0).max(0.0);
 
     let extinction = rayleigh_scattering
@@ -26,12 +26,11 @@ pub fn eval_scattering(pos: Vec3) -> (Vec3, f32, Vec3) {
     (rayleigh_scattering, mie_scattering, extinction)
 }
 
-pub fn eval_mie_phase(cos_theta: f32) -> f32 {
-    const G: f32 = 0.8;
+pub fn eval_mie_phase(g: f32, cos_theta: f32) -> f32 {
     const SCALE: f32 = 3.0 / (8.0 * PI);
 
-    let num = (1.0 - G * G) * (1.0 + cos_theta * cos_theta);
-    let denom = (2.0 + G * G) * (1.0 + G * G - 2.0 * G * cos_theta).powf(1.5);
+    let num = (1.0 - g * g) * (1.0 + cos_theta * cos_theta);
+    let denom = (2.0 + g * g) * (1.0 + g * g - 2.0 * g * cos_theta).powf(1.5);
 
     SCALE * num / denom
 }
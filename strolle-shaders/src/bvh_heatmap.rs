@@ -50,28 +50,3 @@ pub fn main(
         output.write(screen_pos, color.extend(1.0));
     }
 }
-
-fn gradient<const N: usize>(colors: [Vec3; N], progress: f32) -> Vec3 {
-    if progress <= 0.0 {
-        return colors[0];
-    }
-
-    let step = 1.0 / (N as f32 - 1.0);
-    let mut i = 0;
-
-    while i < (N - 1) {
-        let min = step * (i as f32);
-        let max = step * (i as f32 + 1.0);
-
-        if progress >= min && progress <= max {
-            let rhs = (progress - min) / step;
-            let lhs = 1.0 - rhs;
-
-            return lhs * colors[i] + rhs * colors[i + 1];
-        }
-
-        i += 1;
-    }
-
-    colors[N - 1]
-}
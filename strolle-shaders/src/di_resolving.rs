@@ -12,9 +12,17 @@ pub fn main(
     materials: &[Material],
     #[spirv(descriptor_set = 0, binding = 3, storage_buffer)]
     lights: &[Light],
-    #[spirv(descriptor_set = 0, binding = 4)] atlas_tex: Tex,
-    #[spirv(descriptor_set = 0, binding = 5)] atlas_sampler: &Sampler,
-    #[spirv(descriptor_set = 0, binding = 6, uniform)] world: &World,
+    #[spirv(descriptor_set = 0, binding = 4)] ies_lut_tex: Tex,
+    #[spirv(descriptor_set = 0, binding = 5)] ies_lut_sampler: &Sampler,
+    #[spirv(descriptor_set = 0, binding = 6)] env_map_tex: Tex,
+    #[spirv(descriptor_set = 0, binding = 7)] env_map_sampler: &Sampler,
+    #[spirv(descriptor_set = 0, binding = 8, storage_buffer)]
+    env_map_buffer: &[f32],
+    #[spirv(descriptor_set = 0, binding = 9)] atlas_tex: Tex,
+    #[spirv(descriptor_set = 0, binding = 10)] atlas_sampler: &Sampler,
+    #[spirv(descriptor_set = 0, binding = 11, uniform)] world: &World,
+    #[spirv(descriptor_set = 0, binding = 12, uniform)]
+    atmosphere_settings: &AtmosphereSettings,
     #[spirv(descriptor_set = 1, binding = 0, uniform)] camera: &Camera,
     #[spirv(descriptor_set = 1, binding = 1)]
     atmosphere_transmittance_lut_tex: Tex,
@@ -37,8 +45,13 @@ pub fn main(
     let triangles = TrianglesView::new(triangles);
     let bvh = BvhView::new(bvh);
     let materials = MaterialsView::new(materials);
-    let lights = LightsView::new(lights);
+    let ies_profiles = IesProfilesView::new(ies_lut_tex, ies_lut_sampler);
+    let env_map =
+        EnvironmentMapView::new(env_map_tex, env_map_sampler, env_map_buffer);
+    let lights = LightsView::new(lights, ies_profiles, env_map);
     let atmosphere = Atmosphere::new(
+        world,
+        atmosphere_settings,
         atmosphere_transmittance_lut_tex,
         atmosphere_transmittance_lut_sampler,
         atmosphere_sky_lut_tex,
@@ -66,15 +79,18 @@ pub fn main(
     let radiance;
 
     if hit.is_some() {
-        let is_occluded = res.sample.ray(hit.point).intersect(
-            local_idx,
-            stack,
-            triangles,
-            bvh,
-            materials,
-            atlas_tex,
-            atlas_sampler,
-        );
+        let light = res.sample.light(lights, hit.point);
+
+        let is_occluded = light.casts_shadows()
+            && res.sample.ray(hit.point).intersect(
+                local_idx,
+                stack,
+                triangles,
+                bvh,
+                materials,
+                atlas_tex,
+                atlas_sampler,
+            );
 
         confidence = if res.sample.is_occluded == is_occluded {
             res.sample.confidence
@@ -88,13 +104,17 @@ pub fn main(
         radiance = if res.sample.is_occluded {
             LightRadiance::default()
         } else {
-            lights.get(res.sample.light_id).radiance(hit) * res.w
+            light.radiance(ies_profiles, hit) * res.w
         };
     } else {
         confidence = 1.0;
 
         radiance = LightRadiance {
-            radiance: atmosphere.sample(world.sun_dir(), hit.dir),
+            radiance: if env_map.is_active() {
+                env_map.radiance(hit.dir)
+            } else {
+                atmosphere.sample_primary(hit.dir)
+            },
             diff_brdf: Vec3::ONE,
             spec_brdf: Vec3::ZERO,
         };
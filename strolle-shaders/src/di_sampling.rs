@@ -14,9 +14,17 @@ pub fn main(
     materials: &[Material],
     #[spirv(descriptor_set = 0, binding = 4, storage_buffer)]
     lights: &[Light],
-    #[spirv(descriptor_set = 0, binding = 5)] atlas_tex: Tex,
-    #[spirv(descriptor_set = 0, binding = 6)] atlas_sampler: &Sampler,
-    #[spirv(descriptor_set = 0, binding = 7, uniform)] world: &World,
+    #[spirv(descriptor_set = 0, binding = 5)] ies_lut_tex: Tex,
+    #[spirv(descriptor_set = 0, binding = 6)] ies_lut_sampler: &Sampler,
+    #[spirv(descriptor_set = 0, binding = 7)] env_map_tex: Tex,
+    #[spirv(descriptor_set = 0, binding = 8)] env_map_sampler: &Sampler,
+    #[spirv(descriptor_set = 0, binding = 9, storage_buffer)]
+    env_map_buffer: &[f32],
+    #[spirv(descriptor_set = 0, binding = 10, storage_buffer)]
+    light_tree: &[Vec4],
+    #[spirv(descriptor_set = 0, binding = 11)] atlas_tex: Tex,
+    #[spirv(descriptor_set = 0, binding = 12)] atlas_sampler: &Sampler,
+    #[spirv(descriptor_set = 0, binding = 13, uniform)] world: &World,
     #[spirv(descriptor_set = 1, binding = 0, uniform)] camera: &Camera,
     #[spirv(descriptor_set = 1, binding = 1)] prim_gbuffer_d0: TexRgba32,
     #[spirv(descriptor_set = 1, binding = 2)] prim_gbuffer_d1: TexRgba32,
@@ -30,7 +38,11 @@ pub fn main(
     let triangles = TrianglesView::new(triangles);
     let bvh = BvhView::new(bvh);
     let materials = MaterialsView::new(materials);
-    let lights = LightsView::new(lights);
+    let ies_profiles = IesProfilesView::new(ies_lut_tex, ies_lut_sampler);
+    let env_map =
+        EnvironmentMapView::new(env_map_tex, env_map_sampler, env_map_buffer);
+    let lights = LightsView::new(lights, ies_profiles, env_map);
+    let light_tree = LightTreeView::new(light_tree);
 
     if !camera.contains(screen_pos) {
         return;
@@ -52,22 +64,23 @@ pub fn main(
 
     // ---
 
-    let mut res = EphemeralReservoir::build(&mut wnoise, lights, *world, hit);
+    let mut res =
+        EphemeralReservoir::build(&mut wnoise, lights, light_tree, *world, hit);
 
     let res = if res.m > 0.0 {
-        let ray = lights
-            .get(res.sample.light_id)
-            .ray_bnoise(bnoise.first_sample(), hit.point);
+        let light = res.sample.light(lights);
+        let ray = light.ray_bnoise(bnoise.first_sample(), hit.point);
 
-        let is_occluded = ray.intersect(
-            local_idx,
-            stack,
-            triangles,
-            bvh,
-            materials,
-            atlas_tex,
-            atlas_sampler,
-        );
+        let is_occluded = light.casts_shadows()
+            && ray.intersect(
+                local_idx,
+                stack,
+                triangles,
+                bvh,
+                materials,
+                atlas_tex,
+                atlas_sampler,
+            );
 
         if is_occluded {
             res.w = 0.0;
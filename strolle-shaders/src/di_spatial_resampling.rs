@@ -6,6 +6,12 @@ pub fn pick(
     #[spirv(push_constant)] params: &PassParams,
     #[spirv(descriptor_set = 0, binding = 0, storage_buffer)]
     lights: &[Light],
+    #[spirv(descriptor_set = 0, binding = 1)] ies_lut_tex: Tex,
+    #[spirv(descriptor_set = 0, binding = 2)] ies_lut_sampler: &Sampler,
+    #[spirv(descriptor_set = 0, binding = 3)] env_map_tex: Tex,
+    #[spirv(descriptor_set = 0, binding = 4)] env_map_sampler: &Sampler,
+    #[spirv(descriptor_set = 0, binding = 5, storage_buffer)]
+    env_map_buffer: &[f32],
     #[spirv(descriptor_set = 1, binding = 0, uniform)] camera: &Camera,
     #[spirv(descriptor_set = 1, binding = 1)] prim_gbuffer_d0: TexRgba32,
     #[spirv(descriptor_set = 1, binding = 2)] prim_gbuffer_d1: TexRgba32,
@@ -18,7 +24,10 @@ pub fn pick(
     let lhs_pos = resolve_checkerboard_alt(global_id, params.frame.get() / 2);
     let lhs_idx = camera.screen_to_idx(lhs_pos);
     let mut wnoise = WhiteNoise::new(params.seed, lhs_pos);
-    let lights = LightsView::new(lights);
+    let ies_profiles = IesProfilesView::new(ies_lut_tex, ies_lut_sampler);
+    let env_map =
+        EnvironmentMapView::new(env_map_tex, env_map_sampler, env_map_buffer);
+    let lights = LightsView::new(lights, ies_profiles, env_map);
 
     let buf_pos_a = global_id * uvec2(2, 1);
     let buf_pos_b = buf_pos_a + uvec2(1, 0);
@@ -113,13 +122,19 @@ pub fn pick(
     let lhs_rhs_pdf = lhs.sample.pdf(lights, rhs_hit);
     let rhs_lhs_pdf = rhs.sample.pdf(lights, lhs_hit);
 
-    let ray_a = if lhs_rhs_pdf > 0.0 {
+    // Lights that don't cast shadows get an empty ray, which is then treated
+    // as visible without being traced
+    let ray_a = if lhs_rhs_pdf > 0.0
+        && lhs.sample.light(lights, rhs_hit.point).casts_shadows()
+    {
         lhs.sample.ray(rhs_hit.point)
     } else {
         Default::default()
     };
 
-    let ray_b = if rhs_lhs_pdf > 0.0 {
+    let ray_b = if rhs_lhs_pdf > 0.0
+        && rhs.sample.light(lights, lhs_hit.point).casts_shadows()
+    {
         rhs.sample.ray(lhs_hit.point)
     } else {
         Default::default()
@@ -188,15 +203,16 @@ pub fn trace(
     let ray =
         Ray::new(ray_d0.xyz(), Normal::decode(ray_d1.xy())).with_len(ray_d0.w);
 
-    let is_occluded = ray.intersect(
-        local_idx,
-        stack,
-        triangles,
-        bvh,
-        materials,
-        atlas_tex,
-        atlas_sampler,
-    );
+    let is_occluded = ray.len() > 0.0
+        && ray.intersect(
+            local_idx,
+            stack,
+            triangles,
+            bvh,
+            materials,
+            atlas_tex,
+            atlas_sampler,
+        );
 
     let visibility = if is_occluded { 0.0 } else { 1.0 };
 
@@ -6,6 +6,12 @@ pub fn main(
     #[spirv(push_constant)] params: &PassParams,
     #[spirv(descriptor_set = 0, binding = 0, storage_buffer)]
     lights: &[Light],
+    #[spirv(descriptor_set = 0, binding = 1)] ies_lut_tex: Tex,
+    #[spirv(descriptor_set = 0, binding = 2)] ies_lut_sampler: &Sampler,
+    #[spirv(descriptor_set = 0, binding = 3)] env_map_tex: Tex,
+    #[spirv(descriptor_set = 0, binding = 4)] env_map_sampler: &Sampler,
+    #[spirv(descriptor_set = 0, binding = 5, storage_buffer)]
+    env_map_buffer: &[f32],
     #[spirv(descriptor_set = 1, binding = 0, uniform)] curr_camera: &Camera,
     #[spirv(descriptor_set = 1, binding = 1, uniform)] prev_camera: &Camera,
     #[spirv(descriptor_set = 1, binding = 2)] reprojection_map: TexRgba32,
@@ -21,7 +27,10 @@ pub fn main(
     let lhs_pos = global_id.xy();
     let lhs_idx = curr_camera.screen_to_idx(lhs_pos);
     let mut wnoise = WhiteNoise::new(params.seed, lhs_pos);
-    let lights = LightsView::new(lights);
+    let ies_profiles = IesProfilesView::new(ies_lut_tex, ies_lut_sampler);
+    let env_map =
+        EnvironmentMapView::new(env_map_tex, env_map_sampler, env_map_buffer);
+    let lights = LightsView::new(lights, ies_profiles, env_map);
     let reprojection_map = ReprojectionMap::new(reprojection_map);
 
     if !curr_camera.contains(lhs_pos) {
@@ -69,13 +78,17 @@ pub fn main(
         rhs.clamp_m(64.0);
 
         if !rhs.is_empty() {
-            let rhs_light = lights.get(rhs.sample.light_id);
-
-            if rhs_light.is_slot_killed() {
-                rhs.w = 0.0;
-                rhs_killed = true;
-            } else if rhs_light.is_slot_remapped() {
-                rhs.sample.light_id = rhs_light.slot_remapped_to();
+            // (environment map doesn't occupy any slot, so there's nothing to
+            // remap there)
+            if !rhs.sample.light_id.is_sky() {
+                let rhs_light = lights.get(rhs.sample.light_id);
+
+                if rhs_light.is_slot_killed() {
+                    rhs.w = 0.0;
+                    rhs_killed = true;
+                } else if rhs_light.is_slot_remapped() {
+                    rhs.sample.light_id = rhs_light.slot_remapped_to();
+                }
             }
 
             rhs_hit = Hit::new(
@@ -0,0 +1,138 @@
+use strolle_gpu::prelude::*;
+
+#[spirv(compute(threads(8, 8)))]
+pub fn main(
+    #[spirv(global_invocation_id)] global_id: UVec3,
+    #[spirv(push_constant)] params: &PassParams,
+    #[spirv(local_invocation_index)] local_idx: u32,
+    #[spirv(workgroup)] stack: BvhStack,
+    #[spirv(descriptor_set = 0, binding = 0, storage_buffer)]
+    triangles: &[Triangle],
+    #[spirv(descriptor_set = 0, binding = 1, storage_buffer)] bvh: &[Vec4],
+    #[spirv(descriptor_set = 0, binding = 2, storage_buffer)]
+    materials: &[Material],
+    #[spirv(descriptor_set = 0, binding = 3, storage_buffer)]
+    lights: &[Light],
+    #[spirv(descriptor_set = 0, binding = 4)] ies_lut_tex: Tex,
+    #[spirv(descriptor_set = 0, binding = 5)] ies_lut_sampler: &Sampler,
+    #[spirv(descriptor_set = 0, binding = 6)] env_map_tex: Tex,
+    #[spirv(descriptor_set = 0, binding = 7)] env_map_sampler: &Sampler,
+    #[spirv(descriptor_set = 0, binding = 8, storage_buffer)]
+    env_map_buffer: &[f32],
+    #[spirv(descriptor_set = 0, binding = 9, storage_buffer)]
+    light_tree: &[Vec4],
+    #[spirv(descriptor_set = 0, binding = 10)] atlas_tex: Tex,
+    #[spirv(descriptor_set = 0, binding = 11)] atlas_sampler: &Sampler,
+    #[spirv(descriptor_set = 0, binding = 12, uniform)] world: &World,
+    #[spirv(descriptor_set = 0, binding = 13, storage_buffer)] fog: &[Vec4],
+    #[spirv(descriptor_set = 1, binding = 0, uniform)] camera: &Camera,
+    #[spirv(descriptor_set = 1, binding = 1, uniform)] prev_camera: &Camera,
+    #[spirv(descriptor_set = 1, binding = 2)] prim_gbuffer_d0: TexRgba32,
+    #[spirv(descriptor_set = 1, binding = 3)] prim_gbuffer_d1: TexRgba32,
+    #[spirv(descriptor_set = 1, binding = 4)] transmittance_output: TexRgba32,
+    #[spirv(descriptor_set = 1, binding = 5)] prev_scattering: TexRgba32,
+    #[spirv(descriptor_set = 1, binding = 6)] curr_scattering: TexRgba32,
+) {
+    let screen_pos = global_id.xy();
+    let mut wnoise = WhiteNoise::new(params.seed, screen_pos);
+    let triangles = TrianglesView::new(triangles);
+    let bvh = BvhView::new(bvh);
+    let materials = MaterialsView::new(materials);
+    let ies_profiles = IesProfilesView::new(ies_lut_tex, ies_lut_sampler);
+    let env_map =
+        EnvironmentMapView::new(env_map_tex, env_map_sampler, env_map_buffer);
+    let lights = LightsView::new(lights, ies_profiles, env_map);
+    let light_tree = LightTreeView::new(light_tree);
+    let fog = FogView::new(fog);
+
+    if !camera.contains(screen_pos) {
+        return;
+    }
+
+    // -------------------------------------------------------------------------
+
+    let gbuffer = GBufferEntry::unpack([
+        prim_gbuffer_d0.read(screen_pos),
+        prim_gbuffer_d1.read(screen_pos),
+    ]);
+
+    let ray = camera.ray(screen_pos).with_len(if gbuffer.is_some() {
+        gbuffer.depth
+    } else {
+        FogView::MAX_DISTANCE
+    });
+
+    let transmittance = fog.transmittance(ray);
+
+    // ---
+    // Estimate single scattering along the ray by picking a point inside the
+    // fog and a light that illuminates it
+
+    let mut scattering = Vec3::ZERO;
+    let (t, t_pdf) = fog.sample_distance(&mut wnoise, ray);
+
+    if t_pdf > 0.0 {
+        let point = ray.at(t);
+
+        let res = EphemeralReservoir::build_for_fog(
+            &mut wnoise,
+            lights,
+            light_tree,
+            *world,
+            fog,
+            point,
+            -ray.dir(),
+        );
+
+        if res.w > 0.0 {
+            let light = res.sample.light(lights);
+            let light_ray = light.ray_wnoise(&mut wnoise, point);
+
+            let is_occluded = light.casts_shadows()
+                && light_ray.intersect(
+                    local_idx,
+                    stack,
+                    triangles,
+                    bvh,
+                    materials,
+                    atlas_tex,
+                    atlas_sampler,
+                );
+
+            if !is_occluded {
+                let light_transmittance = if light.is_directional() {
+                    fog.volumes_transmittance(light_ray)
+                } else {
+                    fog.transmittance(light_ray)
+                };
+
+                scattering = res.sample.light_rad.radiance
+                    * res.w
+                    * light_transmittance
+                    * fog.transmittance(ray.with_len(t))
+                    * fog.scattering_at(point)
+                    / t_pdf;
+            }
+        }
+    }
+
+    // ---
+    // Accumulate the estimate over time, reprojecting it from the point where
+    // the ray ends
+
+    let prev_screen_pos = prev_camera.world_to_screen(ray.at(ray.len()));
+
+    let prev = if prev_camera.contains(prev_screen_pos) {
+        prev_scattering.read(prev_screen_pos.as_uvec2())
+    } else {
+        Vec4::ZERO
+    };
+
+    let history = (prev.w + 1.0).min(16.0);
+    let scattering = prev.xyz().lerp(scattering, 1.0 / history);
+
+    unsafe {
+        transmittance_output.write(screen_pos, transmittance.extend(1.0));
+        curr_scattering.write(screen_pos, scattering.extend(history));
+    }
+}
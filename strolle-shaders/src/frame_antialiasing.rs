@@ -0,0 +1,222 @@
+//! This pass performs temporal anti-aliasing and upscaling, i.e. it accumulates
+//! the jittered frames (rendered at the internal resolution) into history kept
+//! at the output resolution, clamping the history to the current neighbourhood
+//! of each pixel so that disocclusions and moving edges don't leave trails.
+
+use strolle_gpu::prelude::*;
+
+/// Maximum number of frames accumulated in the history; the larger, the
+/// smoother (but also the blurrier) the image gets.
+const MAX_HISTORY: f32 = 10.0;
+
+/// How far (in standard deviations) history is allowed to stray from the
+/// current neighbourhood's mean before getting clipped.
+const CLIP_GAMMA: f32 = 1.25;
+
+/// Distance used for reprojecting the sky, large enough to make the camera's
+/// translation negligible.
+const SKY_DISTANCE: f32 = 10000.0;
+
+#[spirv(compute(threads(8, 8)))]
+pub fn main(
+    #[spirv(global_invocation_id)] global_id: UVec3,
+    #[spirv(push_constant)] params: &FrameAntialiasingPassParams,
+    #[spirv(descriptor_set = 0, binding = 0, uniform)] camera: &Camera,
+    #[spirv(descriptor_set = 0, binding = 1, uniform)] prev_camera: &Camera,
+    #[spirv(descriptor_set = 0, binding = 2)] prim_surface_map: TexRgba32,
+    #[spirv(descriptor_set = 0, binding = 3)] reprojection_map: TexRgba32,
+    #[spirv(descriptor_set = 0, binding = 4)] velocity_map: TexRgba32,
+    #[spirv(descriptor_set = 0, binding = 5)] frame_composed: TexRgba32,
+    #[spirv(descriptor_set = 0, binding = 6)] prev_history: TexRgba32,
+    #[spirv(descriptor_set = 0, binding = 7)] curr_history: TexRgba32,
+) {
+    let out_pos = global_id.xy();
+    let out_size = params.output_size;
+
+    if out_pos.x >= out_size.x || out_pos.y >= out_size.y {
+        return;
+    }
+
+    let prim_surface_map = SurfaceMap::new(prim_surface_map);
+    let reprojection_map = ReprojectionMap::new(reprojection_map);
+
+    // Ratio between the internal resolution and the output one; 1.0 when
+    // we're just anti-aliasing
+    let scale = camera.screen_size().as_vec2() / out_size.as_vec2();
+
+    // Position of the output pixel's center in the internal screen-space
+    let center = (out_pos.as_vec2() + vec2(0.5, 0.5)) * scale;
+
+    // Find the internal pixel whose (jittered) sample lies closest to the
+    // output pixel's center
+    let screen_pos = camera.contain((center - camera.jitter()).as_ivec2());
+    let surface = prim_surface_map.get(screen_pos);
+    let color = frame_composed.read(screen_pos).xyz();
+
+    // The farther the sample is from the output pixel's center, the less it
+    // contributes - this is what makes the upscaled image converge into
+    // something sharper than the internal one
+    let sample_weight = {
+        let sample_center =
+            screen_pos.as_vec2() + vec2(0.5, 0.5) + camera.jitter();
+
+        let dist = (center - sample_center) / scale;
+
+        (-2.29 * dist.length_squared()).exp()
+    };
+
+    // -------------------------------------------------------------------------
+    // Step 1:
+    //
+    // Compute color statistics of the neighbourhood.
+    //
+    // We consider only the neighbours that lie on the same surface as the
+    // center pixel - otherwise, along an edge, the box would grow to include
+    // colors of both sides and history of the previous edge's position would
+    // survive the clamping, making the edges ghost.
+
+    let mut m1 = Vec3::ZERO;
+    let mut m2 = Vec3::ZERO;
+    let mut count = 0.0;
+    let mut sample_idx = 0;
+
+    while sample_idx < 9 {
+        let sample_pos = screen_pos.as_ivec2()
+            + ivec2(sample_idx % 3 - 1, sample_idx / 3 - 1);
+
+        sample_idx += 1;
+
+        if !camera.contains(sample_pos) {
+            continue;
+        }
+
+        let sample_pos = sample_pos.as_uvec2();
+
+        if sample_pos != screen_pos {
+            let sample_surface = prim_surface_map.get(sample_pos);
+
+            let is_same_surface = if surface.is_sky() {
+                sample_surface.is_sky()
+            } else {
+                sample_surface.evaluate_similarity_to(surface) >= 0.5
+            };
+
+            if !is_same_surface {
+                continue;
+            }
+        }
+
+        let sample = frame_composed.read(sample_pos).xyz();
+
+        m1 += sample;
+        m2 += sample * sample;
+        count += 1.0;
+    }
+
+    let mean = m1 / count;
+    let variance = (m2 / count - mean * mean).max(Vec3::ZERO);
+
+    // (clamped so that flat neighbourhoods don't yield an empty box)
+    let sigma = vec3(variance.x.sqrt(), variance.y.sqrt(), variance.z.sqrt())
+        .max(Vec3::splat(0.0001));
+
+    let aabb_min = mean - CLIP_GAMMA * sigma;
+    let aabb_max = mean + CLIP_GAMMA * sigma;
+
+    // -------------------------------------------------------------------------
+    // Step 2:
+    //
+    // Find the history.
+    //
+    // Surfaces are reprojected using the velocity (with disocclusions detected
+    // through the reprojection map, which compares depths and normals), while
+    // the sky is reprojected by its direction - starting from the ray's origin
+    // and not from the camera's, since orthographic projections don't have a
+    // single eye.
+
+    let (prev_pos, confidence) = if surface.is_sky() {
+        let ray = camera.ray_through(center);
+
+        // (previous frame could've been rendered at a different scale, see:
+        // dynamic resolution)
+        let prev_scale = prev_camera.screen.xy() / out_size.as_vec2();
+
+        let prev_pos =
+            prev_camera.world_to_screen(ray.at(SKY_DISTANCE)) / prev_scale;
+
+        (prev_pos, 1.0)
+    } else {
+        let velocity = velocity_map.read(screen_pos).xy() / scale;
+
+        let confidence = reprojection_map.get(screen_pos).confidence.min(1.0);
+
+        (out_pos.as_vec2() + vec2(0.5, 0.5) - velocity, confidence)
+    };
+
+    let mut reprojection = Reprojection {
+        prev_x: prev_pos.x - 0.5,
+        prev_y: prev_pos.y - 0.5,
+        confidence,
+        validity: 0,
+    };
+
+    if reprojection.is_some() {
+        let [p00, p10, p01, p11] = BilinearFilter::reprojection_coords(
+            reprojection.prev_x,
+            reprojection.prev_y,
+        );
+
+        let is_valid = move |pos: IVec2| {
+            pos.x >= 0
+                && pos.y >= 0
+                && pos.x < out_size.x as i32
+                && pos.y < out_size.y as i32
+        };
+
+        if is_valid(p00) {
+            reprojection.validity |= 0b0001;
+        }
+
+        if is_valid(p10) {
+            reprojection.validity |= 0b0010;
+        }
+
+        if is_valid(p01) {
+            reprojection.validity |= 0b0100;
+        }
+
+        if is_valid(p11) {
+            reprojection.validity |= 0b1000;
+        }
+    }
+
+    let history = if reprojection.validity > 0 {
+        BilinearFilter::reproject(reprojection, move |pos| {
+            (prev_history.read(pos), 1.0)
+        })
+    } else {
+        Vec4::ZERO
+    };
+
+    // -------------------------------------------------------------------------
+    // Step 3:
+    //
+    // Clamp the history and blend it with the current frame.
+
+    let history_len = (history.w * reprojection.confidence)
+        .min(MAX_HISTORY - 1.0)
+        + sample_weight;
+
+    let out = if history.w > 0.0 && history_len > 0.0 {
+        history
+            .xyz()
+            .clip(aabb_min, aabb_max)
+            .lerp(color, sample_weight / history_len)
+    } else {
+        color
+    };
+
+    unsafe {
+        curr_history.write(out_pos, out.extend(history_len));
+    }
+}
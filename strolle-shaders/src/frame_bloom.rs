@@ -0,0 +1,134 @@
+//! This pass implements bloom - the image gets progressively downsampled into
+//! a chain of smaller and smaller textures, which then get upsampled back and
+//! summed together, yielding a wide blur that's used to simulate light
+//! scattering inside the camera's lens.
+//!
+//! There's no threshold (all of the pixels contribute to the bloom, only
+//! proportionally to their brightness), which keeps the effect energy
+//! conserving.
+//!
+//! See:
+//! - https://www.iryoku.com/next-generation-post-processing-in-call-of-duty-advanced-warfare/
+
+use strolle_gpu::prelude::*;
+
+#[spirv(compute(threads(8, 8)))]
+pub fn downsample(
+    #[spirv(global_invocation_id)] global_id: UVec3,
+    #[spirv(push_constant)] params: &FrameBloomPassParams,
+    #[spirv(descriptor_set = 0, binding = 0)] src: TexRgba32,
+    #[spirv(descriptor_set = 0, binding = 1)] dst: TexRgba32,
+) {
+    let dst_pos = global_id.xy();
+
+    if dst_pos.x >= params.dst_size.x || dst_pos.y >= params.dst_size.y {
+        return;
+    }
+
+    let center = (dst_pos.as_vec2() + vec2(0.5, 0.5))
+        / params.dst_size.as_vec2()
+        * params.src_size.as_vec2();
+
+    let sample = move |x: f32, y: f32| {
+        BilinearFilter::sample(params.src_size, center + vec2(x, y), |pos| {
+            src.read(pos)
+        })
+        .xyz()
+    };
+
+    // 13 samples, laid out like so:
+    //
+    // a . b . c
+    // . d . e .
+    // f . g . h
+    // . i . j .
+    // k . l . m
+    let a = sample(-2.0, -2.0);
+    let b = sample(0.0, -2.0);
+    let c = sample(2.0, -2.0);
+    let d = sample(-1.0, -1.0);
+    let e = sample(1.0, -1.0);
+    let f = sample(-2.0, 0.0);
+    let g = sample(0.0, 0.0);
+    let h = sample(2.0, 0.0);
+    let i = sample(-1.0, 1.0);
+    let j = sample(1.0, 1.0);
+    let k = sample(-2.0, 2.0);
+    let l = sample(0.0, 2.0);
+    let m = sample(2.0, 2.0);
+
+    // ... which then get grouped into five overlapping boxes.
+    //
+    // When downsampling the full-resolution image, the boxes get additionally
+    // weighted by their inverse luminance (aka Karis average), so that tiny,
+    // very bright pixels don't turn into flickering blobs.
+    let weight = move |color: Vec3, weight: f32| {
+        if params.is_first == 1 {
+            weight / (1.0 + color.luma())
+        } else {
+            weight
+        }
+    };
+
+    let b0 = (d + e + i + j) * 0.25;
+    let b1 = (a + b + f + g) * 0.25;
+    let b2 = (b + c + g + h) * 0.25;
+    let b3 = (f + g + k + l) * 0.25;
+    let b4 = (g + h + l + m) * 0.25;
+
+    let w0 = weight(b0, 0.5);
+    let w1 = weight(b1, 0.125);
+    let w2 = weight(b2, 0.125);
+    let w3 = weight(b3, 0.125);
+    let w4 = weight(b4, 0.125);
+
+    let color = (b0 * w0 + b1 * w1 + b2 * w2 + b3 * w3 + b4 * w4)
+        / (w0 + w1 + w2 + w3 + w4);
+
+    unsafe {
+        dst.write(dst_pos, color.extend(1.0));
+    }
+}
+
+#[spirv(compute(threads(8, 8)))]
+pub fn upsample(
+    #[spirv(global_invocation_id)] global_id: UVec3,
+    #[spirv(push_constant)] params: &FrameBloomPassParams,
+    #[spirv(descriptor_set = 0, binding = 0)] src: TexRgba32,
+    #[spirv(descriptor_set = 0, binding = 1)] dst: TexRgba32,
+) {
+    let dst_pos = global_id.xy();
+
+    if dst_pos.x >= params.dst_size.x || dst_pos.y >= params.dst_size.y {
+        return;
+    }
+
+    let center = (dst_pos.as_vec2() + vec2(0.5, 0.5))
+        / params.dst_size.as_vec2()
+        * params.src_size.as_vec2();
+
+    let sample = move |x: f32, y: f32| {
+        let offset = vec2(x, y) * params.radius;
+
+        BilinearFilter::sample(params.src_size, center + offset, |pos| {
+            src.read(pos)
+        })
+        .xyz()
+    };
+
+    // 3x3 tent filter
+    let bloom = (sample(-1.0, -1.0)
+        + sample(0.0, -1.0) * 2.0
+        + sample(1.0, -1.0)
+        + sample(-1.0, 0.0) * 2.0
+        + sample(0.0, 0.0) * 4.0
+        + sample(1.0, 0.0) * 2.0
+        + sample(-1.0, 1.0)
+        + sample(0.0, 1.0) * 2.0
+        + sample(1.0, 1.0))
+        / 16.0;
+
+    unsafe {
+        dst.write(dst_pos, (dst.read(dst_pos).xyz() + bloom).extend(1.0));
+    }
+}
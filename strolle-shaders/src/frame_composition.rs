@@ -26,6 +26,12 @@ pub fn fs(
     #[spirv(descriptor_set = 0, binding = 4)] gi_diff_colors: TexRgba32,
     #[spirv(descriptor_set = 0, binding = 5)] gi_spec_colors: TexRgba32,
     #[spirv(descriptor_set = 0, binding = 6)] ref_colors: TexRgba32,
+    #[spirv(descriptor_set = 0, binding = 7)] fog_transmittance: TexRgba32,
+    #[spirv(descriptor_set = 0, binding = 8)] fog_scattering: TexRgba32,
+    #[spirv(descriptor_set = 0, binding = 9, storage_buffer)]
+    exposure: &Exposure,
+    #[spirv(descriptor_set = 0, binding = 10)] velocity_map: TexRgba32,
+    #[spirv(descriptor_set = 0, binding = 11)] reprojection_map: TexRgba32,
     frag_color: &mut Vec4,
 ) {
     let screen_pos = pos.xy().as_uvec2();
@@ -43,13 +49,20 @@ pub fn fs(
             let gi_diff = gi_diff_colors.read(screen_pos).xyz();
             let gi_spec = gi_spec_colors.read(screen_pos).xyz();
 
-            if gbuffer.is_some() {
+            let color = if gbuffer.is_some() {
                 gbuffer.emissive
                     + (di_diff + gi_diff) * gbuffer.base_color.xyz()
                     + di_spec
                     + gi_spec
             } else {
                 di_diff
+            };
+
+            if params.has_fog == 1 {
+                color * fog_transmittance.read(screen_pos).xyz()
+                    + fog_scattering.read(screen_pos).xyz()
+            } else {
+                color
             }
         }
 
@@ -75,8 +88,55 @@ pub fn fs(
             color.xyz() / color.w
         }
 
+        // CameraMode::Depth
+        7 => Vec3::splat(gbuffer.depth),
+
+        // CameraMode::Normals
+        8 => {
+            if gbuffer.is_some() {
+                gbuffer.normal
+            } else {
+                Vec3::ZERO
+            }
+        }
+
+        // CameraMode::BaseColor
+        9 => {
+            *frag_color = gbuffer.base_color;
+            return;
+        }
+
+        // CameraMode::RoughnessMetallic
+        10 => vec3(gbuffer.roughness, gbuffer.metallic, 0.0),
+
+        // CameraMode::Emissive
+        11 => gbuffer.emissive,
+
+        // CameraMode::MotionVectors
+        12 => velocity_map.read(screen_pos).xy().extend(0.0),
+
+        // CameraMode::Reprojection
+        13 => {
+            let reprojection =
+                ReprojectionMap::new(reprojection_map).get(screen_pos);
+
+            vec3(
+                reprojection.confidence,
+                (reprojection.validity as f32) / 15.0,
+                if reprojection.is_some() { 1.0 } else { 0.0 },
+            )
+        }
+
         _ => Default::default(),
     };
 
+    // BVH heatmap's colors and AOVs are not physical, so exposing them would
+    // make no sense
+    let color = if params.camera_mode == 5 || params.camera_mode >= 7 {
+        color
+    } else {
+        color * exposure.multiplier()
+    };
+
     *frag_color = color.extend(1.0);
 }
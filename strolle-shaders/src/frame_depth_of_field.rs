@@ -0,0 +1,115 @@
+//! This pass approximates depth of field by blurring the composed image with
+//! each pixel's circle of confusion.
+//!
+//! Samples are gathered from a disk as large as the center pixel's circle of
+//! confusion, with each sample contributing only if its own circle of
+//! confusion reaches the center - this way sharp objects in front of a blurry
+//! background don't get smeared onto it.
+//!
+//! Note that this is an approximation; see `gpu::Camera::lens_ray()` for the
+//! ground truth used by the reference mode.
+
+use strolle_gpu::prelude::*;
+
+/// Number of samples gathered per pixel.
+const SAMPLES: u32 = 32;
+
+/// Maximum radius (in pixels) of the circle of confusion.
+const MAX_COC: f32 = 24.0;
+
+/// Distance assumed for the sky.
+const SKY_DISTANCE: f32 = 1000000.0;
+
+#[spirv(compute(threads(8, 8)))]
+pub fn main(
+    #[spirv(global_invocation_id)] global_id: UVec3,
+    #[spirv(descriptor_set = 0, binding = 0, uniform)] camera: &Camera,
+    #[spirv(descriptor_set = 0, binding = 1)] prim_surface_map: TexRgba32,
+    #[spirv(descriptor_set = 0, binding = 2)] frame_composed: TexRgba32,
+    #[spirv(descriptor_set = 0, binding = 3)] frame_post: TexRgba32,
+) {
+    let screen_pos = global_id.xy();
+    let prim_surface_map = SurfaceMap::new(prim_surface_map);
+
+    if !camera.contains(screen_pos) {
+        return;
+    }
+
+    // -------------------------------------------------------------------------
+
+    let (forward, ..) = camera.basis();
+
+    // Returns distance of given pixel along the camera's view axis, which is
+    // what the thin lens' focus plane is defined by
+    let distance = move |pos: UVec2| {
+        let surface = prim_surface_map.get(pos);
+
+        if surface.is_sky() {
+            SKY_DISTANCE
+        } else {
+            surface.depth * camera.ray(pos).dir().dot(forward)
+        }
+    };
+
+    let center_color = frame_composed.read(screen_pos);
+    let center_distance = distance(screen_pos);
+
+    let center_coc = camera.circle_of_confusion(center_distance).min(MAX_COC);
+
+    if center_coc < 0.5 {
+        unsafe {
+            frame_post.write(screen_pos, center_color);
+        }
+
+        return;
+    }
+
+    // -------------------------------------------------------------------------
+
+    let mut color = center_color.xyz();
+    let mut weights = 1.0;
+    let mut sample_idx = 0;
+
+    while sample_idx < SAMPLES {
+        // Samples are distributed over the disk using the golden angle (aka
+        // the Vogel disk), which covers it evenly for any number of samples
+        let offset = {
+            let radius = ((sample_idx as f32 + 0.5) / (SAMPLES as f32)).sqrt();
+            let angle = (sample_idx as f32) * PI * (3.0 - 5.0f32.sqrt());
+
+            vec2(angle.cos(), angle.sin()) * radius * center_coc
+        };
+
+        sample_idx += 1;
+
+        let sample_pos = (screen_pos.as_vec2() + offset).round().as_ivec2();
+
+        if !camera.contains(sample_pos) {
+            continue;
+        }
+
+        let sample_pos = sample_pos.as_uvec2();
+        let sample_distance = distance(sample_pos);
+
+        let mut sample_coc =
+            camera.circle_of_confusion(sample_distance).min(MAX_COC);
+
+        // Samples lying behind the center can't blur over it more than the
+        // center itself is blurred
+        if sample_distance > center_distance {
+            sample_coc = sample_coc.min(center_coc);
+        }
+
+        let sample_weight =
+            (sample_coc - offset.length() + 1.0).clamp(0.0, 1.0);
+
+        if sample_weight > 0.0 {
+            color += frame_composed.read(sample_pos).xyz() * sample_weight;
+            weights += sample_weight;
+        }
+    }
+
+    unsafe {
+        frame_post.write(screen_pos, (color / weights).extend(center_color.w));
+    }
+}
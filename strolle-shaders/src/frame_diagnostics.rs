@@ -0,0 +1,189 @@
+//! This pass visualizes one of the diagnostics (reservoirs, denoiser's history
+//! etc.), overwriting the composed image.
+//!
+//! See: `CameraDiagnostic` in the `strolle` crate.
+
+use strolle_gpu::prelude::*;
+
+/// Height (in pixels) of the legend's bar drawn at the bottom of the image.
+const LEGEND_HEIGHT: u32 = 8;
+
+/// Width (in pixels) of the legend's bar.
+const LEGEND_WIDTH: u32 = 256;
+
+/// How quickly the reprojection's rejection rate adapts to changes.
+const REJECTION_RATE_SPEED: f32 = 0.05;
+
+#[spirv(compute(threads(8, 8)))]
+pub fn main(
+    #[spirv(global_invocation_id)] global_id: UVec3,
+    #[spirv(local_invocation_index)] local_idx: u32,
+    #[spirv(push_constant)] params: &FrameDiagnosticsPassParams,
+    #[spirv(workgroup)] stack: BvhStack,
+    #[spirv(descriptor_set = 0, binding = 0, storage_buffer)]
+    triangles: &[Triangle],
+    #[spirv(descriptor_set = 0, binding = 1, storage_buffer)] bvh: &[Vec4],
+    #[spirv(descriptor_set = 0, binding = 2, storage_buffer)]
+    lights: &[Light],
+    #[spirv(descriptor_set = 0, binding = 3)] ies_lut_tex: Tex,
+    #[spirv(descriptor_set = 0, binding = 4)] ies_lut_sampler: &Sampler,
+    #[spirv(descriptor_set = 0, binding = 5)] env_map_tex: Tex,
+    #[spirv(descriptor_set = 0, binding = 6)] env_map_sampler: &Sampler,
+    #[spirv(descriptor_set = 0, binding = 7, storage_buffer)]
+    env_map_buffer: &[f32],
+    #[spirv(descriptor_set = 0, binding = 8, storage_buffer)]
+    materials: &[Material],
+    #[spirv(descriptor_set = 0, binding = 9)] atlas_tex: Tex,
+    #[spirv(descriptor_set = 0, binding = 10)] atlas_sampler: &Sampler,
+    #[spirv(descriptor_set = 1, binding = 0, uniform)] camera: &Camera,
+    #[spirv(descriptor_set = 1, binding = 1)] prim_gbuffer_d0: TexRgba32,
+    #[spirv(descriptor_set = 1, binding = 2)] prim_gbuffer_d1: TexRgba32,
+    #[spirv(descriptor_set = 1, binding = 3)] reprojection_map: TexRgba32,
+    #[spirv(descriptor_set = 1, binding = 4, storage_buffer)]
+    di_reservoirs: &[Vec4],
+    #[spirv(descriptor_set = 1, binding = 5, storage_buffer)]
+    gi_reservoirs: &[Vec4],
+    #[spirv(descriptor_set = 1, binding = 6)] di_diff_samples: TexRgba32,
+    #[spirv(descriptor_set = 1, binding = 7)] gi_diff_samples: TexRgba32,
+    #[spirv(descriptor_set = 1, binding = 8)] di_diff_moments: TexRgba32,
+    #[spirv(descriptor_set = 1, binding = 9)] gi_diff_moments: TexRgba32,
+    #[spirv(descriptor_set = 1, binding = 10)] diagnostics: TexRgba32,
+    #[spirv(descriptor_set = 1, binding = 11)] output: TexRgba32,
+) {
+    let screen_pos = global_id.xy();
+    let screen_idx = camera.screen_to_idx(screen_pos);
+    let triangles = TrianglesView::new(triangles);
+    let bvh = BvhView::new(bvh);
+    let ies_profiles = IesProfilesView::new(ies_lut_tex, ies_lut_sampler);
+    let env_map =
+        EnvironmentMapView::new(env_map_tex, env_map_sampler, env_map_buffer);
+    let lights = LightsView::new(lights, ies_profiles, env_map);
+    let materials = MaterialsView::new(materials);
+
+    if !camera.contains(screen_pos) {
+        return;
+    }
+
+    // -------------------------------------------------------------------------
+
+    // Rejection rate is a running average, so it has to be tracked each frame
+    // (note that switching diagnostics rebuilds camera's buffers, so it always
+    // starts from scratch)
+    let rejection_rate = {
+        let is_rejected = ReprojectionMap::new(reprojection_map)
+            .get(screen_pos)
+            .is_none();
+
+        let rate = lerp(
+            diagnostics.read(screen_pos).x,
+            if is_rejected { 1.0 } else { 0.0 },
+            REJECTION_RATE_SPEED,
+        );
+
+        unsafe {
+            diagnostics.write(screen_pos, vec4(rate, 0.0, 0.0, 0.0));
+        }
+
+        rate
+    };
+
+    let hit = Hit::new(
+        camera.ray(screen_pos),
+        GBufferEntry::unpack([
+            prim_gbuffer_d0.read(screen_pos),
+            prim_gbuffer_d1.read(screen_pos),
+        ]),
+    );
+
+    let variance = |moment: Vec4| (moment.z - moment.y * moment.y).max(0.0);
+
+    let value = match params.diagnostic {
+        // CameraDiagnostic::DiReservoirM
+        0 => DiReservoir::read(di_reservoirs, screen_idx).m,
+
+        // CameraDiagnostic::DiReservoirW
+        1 => DiReservoir::read(di_reservoirs, screen_idx).w,
+
+        // CameraDiagnostic::DiConfidence
+        2 => di_diff_samples.read(screen_pos).w,
+
+        // CameraDiagnostic::GiReservoirM
+        3 => GiReservoir::read(gi_reservoirs, screen_idx).m,
+
+        // CameraDiagnostic::GiReservoirW
+        4 => GiReservoir::read(gi_reservoirs, screen_idx).w,
+
+        // CameraDiagnostic::GiConfidence
+        5 => gi_diff_samples.read(screen_pos).w,
+
+        // CameraDiagnostic::DiHistory
+        6 => di_diff_moments.read(screen_pos).x,
+
+        // CameraDiagnostic::GiHistory
+        7 => gi_diff_moments.read(screen_pos).x,
+
+        // CameraDiagnostic::ReprojectionRejection
+        8 => rejection_rate,
+
+        // CameraDiagnostic::DiVariance
+        9 => variance(di_diff_moments.read(screen_pos)),
+
+        // CameraDiagnostic::GiVariance
+        10 => variance(gi_diff_moments.read(screen_pos)),
+
+        // CameraDiagnostic::ShadowRayBvhNodes
+        11 => {
+            let res = DiReservoir::read(di_reservoirs, screen_idx);
+
+            // (lights that don't cast shadows don't trace any rays, see:
+            // di_resolving)
+            if hit.is_some()
+                && !res.is_empty()
+                && res.sample.light(lights, hit.point).casts_shadows()
+            {
+                let (_, visited_nodes) =
+                    res.sample.ray(hit.point).intersect_ex(
+                        local_idx,
+                        stack,
+                        triangles,
+                        bvh,
+                        materials,
+                        atlas_tex,
+                        atlas_sampler,
+                    );
+
+                visited_nodes as f32
+            } else {
+                0.0
+            }
+        }
+
+        _ => 0.0,
+    };
+
+    // -------------------------------------------------------------------------
+
+    let screen_size = camera.screen_size();
+
+    let progress = if screen_pos.y
+        >= screen_size.y.saturating_sub(LEGEND_HEIGHT)
+        && screen_pos.x < LEGEND_WIDTH.min(screen_size.x)
+    {
+        (screen_pos.x as f32) / (LEGEND_WIDTH.min(screen_size.x) as f32)
+    } else {
+        value / params.max
+    };
+
+    let color = gradient(
+        [
+            vec3(0.0, 0.0, 1.0),
+            vec3(0.0, 1.0, 0.0),
+            vec3(1.0, 0.0, 0.0),
+        ],
+        progress,
+    );
+
+    unsafe {
+        output.write(screen_pos, color.extend(1.0));
+    }
+}
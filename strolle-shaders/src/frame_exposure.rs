@@ -0,0 +1,149 @@
+//! This pass implements auto-exposure - it builds a luminance histogram of the
+//! composed image and then adapts the exposure towards the histogram's average.
+//!
+//! Exposure adapted here gets applied by the composition pass in the upcoming
+//! frame, so the histogram undoes the exposure the current frame was composed
+//! with.
+
+use spirv_std::arch::{
+    atomic_i_add, atomic_i_increment, workgroup_memory_barrier_with_group_sync,
+};
+use spirv_std::memory::{Scope, Semantics};
+use strolle_gpu::prelude::*;
+
+/// Fraction of the darkest pixels that don't contribute to the metering, so
+/// that e.g. small dark corners don't brighten the entire image.
+const LOW_PERCENTILE: f32 = 0.1;
+
+/// Fraction of the pixels (counting from the darkest ones) above which pixels
+/// don't contribute to the metering, so that e.g. the sun doesn't darken the
+/// entire image.
+const HIGH_PERCENTILE: f32 = 0.95;
+
+#[spirv(compute(threads(8, 8)))]
+pub fn histogram(
+    #[spirv(global_invocation_id)] global_id: UVec3,
+    #[spirv(local_invocation_index)] local_idx: u32,
+    #[spirv(push_constant)] params: &FrameExposurePassParams,
+    #[spirv(workgroup)] local_histogram: &mut [u32; EXPOSURE_HISTOGRAM_BINS],
+    #[spirv(descriptor_set = 0, binding = 0, uniform)] camera: &Camera,
+    #[spirv(descriptor_set = 0, binding = 1)] frame_composed: TexRgba32,
+    #[spirv(descriptor_set = 0, binding = 2, storage_buffer)]
+    exposure: &Exposure,
+    #[spirv(descriptor_set = 0, binding = 3, storage_buffer)]
+    histogram: &mut [u32],
+) {
+    let screen_pos = global_id.xy();
+    let local_idx = local_idx as usize;
+
+    // Each workgroup builds its own histogram in the shared memory first and
+    // then merges it into the global one, which is way faster than contending
+    // on the global histogram directly.
+    //
+    // (this relies on the workgroup having exactly as many threads as there
+    // are bins)
+
+    unsafe {
+        *local_histogram.index_unchecked_mut(local_idx) = 0;
+
+        workgroup_memory_barrier_with_group_sync();
+    }
+
+    if camera.contains(screen_pos) {
+        let luminance = frame_composed.read(screen_pos).xyz().luma()
+            / exposure.multiplier();
+
+        unsafe {
+            atomic_i_increment::<
+                u32,
+                { Scope::Workgroup as u32 },
+                { Semantics::NONE.bits() },
+            >(
+                local_histogram.index_unchecked_mut(params.bin(luminance))
+            );
+        }
+    }
+
+    unsafe {
+        workgroup_memory_barrier_with_group_sync();
+
+        let count = *local_histogram.index_unchecked(local_idx);
+
+        if count > 0 {
+            atomic_i_add::<
+                u32,
+                { Scope::Device as u32 },
+                { Semantics::NONE.bits() },
+            >(histogram.index_unchecked_mut(local_idx), count);
+        }
+    }
+}
+
+#[spirv(compute(threads(1)))]
+pub fn adapt(
+    #[spirv(push_constant)] params: &FrameExposurePassParams,
+    #[spirv(descriptor_set = 0, binding = 0, storage_buffer)]
+    exposure: &mut Exposure,
+    #[spirv(descriptor_set = 0, binding = 1, storage_buffer)]
+    histogram: &mut [u32],
+) {
+    let mut total = 0.0;
+    let mut bin = 0;
+
+    while bin < EXPOSURE_HISTOGRAM_BINS {
+        total += unsafe { *histogram.index_unchecked(bin) } as f32;
+        bin += 1;
+    }
+
+    // -------------------------------------------------------------------------
+
+    let low = total * LOW_PERCENTILE;
+    let high = total * HIGH_PERCENTILE;
+
+    let mut seen = 0.0;
+    let mut ev_sum = 0.0;
+    let mut ev_weight = 0.0;
+    let mut bin = 0;
+
+    while bin < EXPOSURE_HISTOGRAM_BINS {
+        let count = unsafe { *histogram.index_unchecked(bin) } as f32;
+
+        // Number of this bin's pixels that lie within the percentiles
+        let weight = ((seen + count).min(high) - seen.max(low)).max(0.0);
+
+        ev_sum += weight * params.bin_ev(bin);
+        ev_weight += weight;
+        seen += count;
+
+        // Clear the histogram for the upcoming frame
+        unsafe {
+            *histogram.index_unchecked_mut(bin) = 0;
+        }
+
+        bin += 1;
+    }
+
+    // -------------------------------------------------------------------------
+
+    if ev_weight > 0.0 {
+        let target_ev =
+            (ev_sum / ev_weight).clamp(params.min_ev, params.max_ev);
+
+        let speed = if target_ev > exposure.metered_ev {
+            params.speed_up
+        } else {
+            params.speed_down
+        };
+
+        // Exponential smoothing, so that the adaptation doesn't depend on the
+        // frame rate
+        let alpha = 1.0 - (-params.delta_time * speed).exp();
+
+        exposure.metered_ev += (target_ev - exposure.metered_ev) * alpha;
+    }
+
+    exposure.metered_ev =
+        exposure.metered_ev.clamp(params.min_ev, params.max_ev);
+
+    exposure.ev = exposure.metered_ev - params.compensation;
+}
@@ -0,0 +1,77 @@
+//! This pass performs motion blur, i.e. it smears each pixel along its
+//! screen-space velocity, scaled by the fraction of the frame during which
+//! the camera's shutter stays open.
+
+use strolle_gpu::prelude::*;
+
+/// Number of samples taken along the velocity.
+const SAMPLES: u32 = 12;
+
+/// Maximum length (in pixels) of the blur.
+const MAX_LENGTH: f32 = 32.0;
+
+#[spirv(compute(threads(8, 8)))]
+pub fn main(
+    #[spirv(global_invocation_id)] global_id: UVec3,
+    #[spirv(descriptor_set = 0, binding = 0, uniform)] camera: &Camera,
+    #[spirv(descriptor_set = 0, binding = 1)] velocity_map: TexRgba32,
+    #[spirv(descriptor_set = 0, binding = 2)] frame_composed: TexRgba32,
+    #[spirv(descriptor_set = 0, binding = 3)] frame_post: TexRgba32,
+) {
+    let screen_pos = global_id.xy();
+
+    if !camera.contains(screen_pos) {
+        return;
+    }
+
+    // -------------------------------------------------------------------------
+
+    let center_color = frame_composed.read(screen_pos);
+
+    let velocity = {
+        let velocity = velocity_map.read(screen_pos).xy() * camera.shutter();
+        let length = velocity.length();
+
+        if length > MAX_LENGTH {
+            velocity * (MAX_LENGTH / length)
+        } else {
+            velocity
+        }
+    };
+
+    if velocity.length_squared() < 0.25 {
+        unsafe {
+            frame_post.write(screen_pos, center_color);
+        }
+
+        return;
+    }
+
+    // -------------------------------------------------------------------------
+
+    // Velocity points from the previous position towards the current one, so
+    // the shutter - centered on the current frame - covers both of its sides
+    let mut color = center_color.xyz();
+    let mut weights = 1.0;
+    let mut sample_idx = 0;
+
+    while sample_idx < SAMPLES {
+        let t = (sample_idx as f32 + 0.5) / (SAMPLES as f32) - 0.5;
+
+        sample_idx += 1;
+
+        let sample_pos =
+            (screen_pos.as_vec2() + vec2(0.5, 0.5) + velocity * t).as_ivec2();
+
+        if !camera.contains(sample_pos) {
+            continue;
+        }
+
+        color += frame_composed.read(sample_pos.as_uvec2()).xyz();
+        weights += 1.0;
+    }
+
+    unsafe {
+        frame_post.write(screen_pos, (color / weights).extend(center_color.w));
+    }
+}
@@ -0,0 +1,114 @@
+//! This pass applies the post-processing (bloom and tonemapping) and puts the
+//! final image onto the camera's viewport.
+
+use strolle_gpu::prelude::*;
+
+#[spirv(fragment)]
+pub fn fs(
+    #[spirv(frag_coord)] pos: Vec4,
+    #[spirv(push_constant)] params: &FrameOutputPassParams,
+    #[spirv(descriptor_set = 0, binding = 0)] frame_resolved: TexRgba32,
+    #[spirv(descriptor_set = 0, binding = 1)] bloom: TexRgba32,
+    #[spirv(descriptor_set = 0, binding = 2)] tonemapping_lut: TexRgba32,
+    frag_color: &mut Vec4,
+) {
+    let out_pos = pos.xy().as_uvec2();
+    let mut color = frame_resolved.read(out_pos).xyz();
+
+    // AOVs are composed as raw values, so let's map them into something that
+    // can be looked at
+    match params.camera_mode {
+        // CameraMode::Depth
+        7 => {
+            if color.x > 0.0 {
+                color = Vec3::splat(1.0 / (1.0 + 0.1 * color.x));
+            }
+        }
+
+        // CameraMode::Normals
+        8 => {
+            if color != Vec3::ZERO {
+                color = 0.5 * color + 0.5;
+            }
+        }
+
+        // CameraMode::MotionVectors
+        12 => {
+            color = (0.5 + color.xy() / 32.0)
+                .clamp(Vec2::ZERO, Vec2::ONE)
+                .extend(0.5);
+        }
+
+        // CameraMode::Reprojection
+        13 => {
+            color = vec3(1.0 - color.z, color.x, 0.0);
+        }
+
+        _ => (),
+    }
+
+    if params.bloom_levels > 0 {
+        let bloom_pos = (out_pos.as_vec2() + vec2(0.5, 0.5))
+            / params.output_size.as_vec2()
+            * params.bloom_size.as_vec2();
+
+        // Upsampling sums all of the bloom's levels together, so here we
+        // average them back
+        let bloom =
+            BilinearFilter::sample(params.bloom_size, bloom_pos, |pos| {
+                bloom.read(pos)
+            })
+            .xyz()
+                / (params.bloom_levels as f32);
+
+        color = color.lerp(bloom, params.bloom_intensity);
+    }
+
+    color = match params.tonemapping {
+        // CameraTonemapping::Reinhard
+        1 => reinhard(color),
+
+        // CameraTonemapping::Aces
+        2 => aces(color),
+
+        // CameraTonemapping::AgX
+        3 => agx(color),
+
+        // CameraTonemapping::Lut
+        4 => sample_lut(tonemapping_lut, params.tonemapping_lut_size, color),
+
+        _ => color,
+    };
+
+    if params.needs_srgb_encoding == 1 {
+        color = linear_to_srgb(color);
+    }
+
+    *frag_color = color.extend(1.0);
+}
+
+/// Looks up given color in a 3D lookup table, stored as a 2D texture where the
+/// blue slices are laid out horizontally next to each other.
+///
+/// Following the convention used by e.g. Tony McMapface, the table is indexed
+/// by `color / (color + 1)`, so that it covers the entire HDR range.
+fn sample_lut(lut: TexRgba32, size: u32, color: Vec3) -> Vec3 {
+    let coord = color.max(Vec3::ZERO) / (color.max(Vec3::ZERO) + 1.0)
+        * ((size - 1) as f32);
+
+    let slice = coord.z.floor();
+    let slice_t = coord.z - slice;
+    let slice = slice as u32;
+    let next_slice = (slice + 1).min(size - 1);
+
+    let sample = move |slice: u32| {
+        BilinearFilter::sample(
+            uvec2(size, size),
+            coord.xy() + vec2(0.5, 0.5),
+            |pos| lut.read(uvec2(pos.x + slice * size, pos.y)),
+        )
+        .xyz()
+    };
+
+    sample(slice).lerp(sample(next_slice), slice_t)
+}
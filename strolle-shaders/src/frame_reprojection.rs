@@ -34,8 +34,22 @@ pub fn main(
 
     // -------------------------------------------------------------------------
 
-    let prev_screen_pos =
-        screen_pos.as_vec2() - velocity_map.read(screen_pos).xy();
+    // Velocity doesn't contain the sub-pixel jitter (if any), so we have to
+    // account for it separately - otherwise the reprojected history would
+    // wobble together with the jitter.
+    //
+    // Also, with dynamic resolution the previous frame could've been rendered
+    // at a different scale, so after finding where our sample was located in
+    // the previous frame, we have to rescale it into previous frame's pixels.
+    let prev_screen_pos = {
+        let sample_pos =
+            screen_pos.as_vec2() + vec2(0.5, 0.5) + camera.jitter()
+                - velocity_map.read(screen_pos).xy();
+
+        let scale = prev_camera.screen.xy() / camera.screen.xy();
+
+        sample_pos * scale - vec2(0.5, 0.5) - prev_camera.jitter()
+    };
 
     if prev_camera.contains(prev_screen_pos.round()) {
         let prev_surface =
@@ -56,8 +70,10 @@ pub fn main(
     // -------------------------------------------------------------------------
 
     if reprojection.is_some() {
+        // (taps index the previous frame's surface map, so they have to be
+        // checked against the previous frame's size)
         let check_validity = move |sample_pos: IVec2| {
-            if !camera.contains(sample_pos) {
+            if !prev_camera.contains(sample_pos) {
                 return false;
             }
 
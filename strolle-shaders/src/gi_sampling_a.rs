@@ -106,6 +106,8 @@ pub fn main(
             roughness: gi_material.roughness,
             reflectance: gi_material.reflectance,
             depth: gi_ray.origin().distance(gi_hit.point),
+            // Light-linking is known only for primary surfaces
+            excluded_lights: 0,
         }
     } else {
         Default::default()
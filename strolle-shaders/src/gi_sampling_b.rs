@@ -11,11 +11,21 @@ pub fn main(
     #[spirv(descriptor_set = 0, binding = 1, storage_buffer)] bvh: &[Vec4],
     #[spirv(descriptor_set = 0, binding = 2, storage_buffer)]
     lights: &[Light],
-    #[spirv(descriptor_set = 0, binding = 3, storage_buffer)]
+    #[spirv(descriptor_set = 0, binding = 3)] ies_lut_tex: Tex,
+    #[spirv(descriptor_set = 0, binding = 4)] ies_lut_sampler: &Sampler,
+    #[spirv(descriptor_set = 0, binding = 5)] env_map_tex: Tex,
+    #[spirv(descriptor_set = 0, binding = 6)] env_map_sampler: &Sampler,
+    #[spirv(descriptor_set = 0, binding = 7, storage_buffer)]
+    env_map_buffer: &[f32],
+    #[spirv(descriptor_set = 0, binding = 8, storage_buffer)]
+    light_tree: &[Vec4],
+    #[spirv(descriptor_set = 0, binding = 9, storage_buffer)]
     materials: &[Material],
-    #[spirv(descriptor_set = 0, binding = 4)] atlas_tex: Tex,
-    #[spirv(descriptor_set = 0, binding = 5)] atlas_sampler: &Sampler,
-    #[spirv(descriptor_set = 0, binding = 6, uniform)] world: &World,
+    #[spirv(descriptor_set = 0, binding = 10)] atlas_tex: Tex,
+    #[spirv(descriptor_set = 0, binding = 11)] atlas_sampler: &Sampler,
+    #[spirv(descriptor_set = 0, binding = 12, uniform)] world: &World,
+    #[spirv(descriptor_set = 0, binding = 13, uniform)]
+    atmosphere_settings: &AtmosphereSettings,
     #[spirv(descriptor_set = 1, binding = 0, uniform)] camera: &Camera,
     #[spirv(descriptor_set = 1, binding = 1)]
     atmosphere_transmittance_lut_tex: Tex,
@@ -45,9 +55,15 @@ pub fn main(
     let screen_idx = camera.screen_to_idx(screen_pos);
     let triangles = TrianglesView::new(triangles);
     let bvh = BvhView::new(bvh);
-    let lights = LightsView::new(lights);
+    let ies_profiles = IesProfilesView::new(ies_lut_tex, ies_lut_sampler);
+    let env_map =
+        EnvironmentMapView::new(env_map_tex, env_map_sampler, env_map_buffer);
+    let lights = LightsView::new(lights, ies_profiles, env_map);
+    let light_tree = LightTreeView::new(light_tree);
     let materials = MaterialsView::new(materials);
     let atmosphere = Atmosphere::new(
+        world,
+        atmosphere_settings,
         atmosphere_transmittance_lut_tex,
         atmosphere_transmittance_lut_sampler,
         atmosphere_sky_lut_tex,
@@ -118,24 +134,38 @@ pub fn main(
     if gi_hit.is_none() {
         light_id = LightId::sky();
         light_pdf = 1.0;
-        light_rad = atmosphere.sample(world.sun_dir(), gi_hit.dir);
-    } else {
-        let atmosphere_pdf = if world.sun_altitude <= -1.0 {
-            0.0
+        light_rad = if env_map.is_active() {
+            env_map.radiance(gi_hit.dir)
         } else {
-            0.25
+            atmosphere.sample(gi_hit.dir)
         };
+    } else {
+        // Environment map, if active, is importance-sampled together with
+        // other lights through the ephemeral reservoir
+        let atmosphere_pdf =
+            if env_map.is_active() || world.sun_altitude <= -1.0 {
+                0.0
+            } else {
+                0.25
+            };
 
-        if world.light_count == 0 || wnoise.sample() < atmosphere_pdf {
+        if (world.light_count == 0 && !env_map.is_active())
+            || wnoise.sample() < atmosphere_pdf
+        {
             light_id = LightId::sky();
             light_pdf = atmosphere_pdf;
             light_dir = wnoise.sample_hemisphere(gi_hit.gbuffer.normal);
 
-            light_rad = atmosphere.sample(world.sun_dir(), light_dir)
+            light_rad = atmosphere.sample(light_dir)
                 * gi_hit.gbuffer.normal.dot(light_dir);
         } else {
-            let res =
-                EphemeralReservoir::build(&mut wnoise, lights, *world, gi_hit);
+            let res = EphemeralReservoir::build(
+                &mut wnoise,
+                lights,
+                light_tree,
+                *world,
+                gi_hit,
+            );
 
             if res.w > 0.0 {
                 // For simplicity, we assume an unmodulated diffuse BRDF here
@@ -144,6 +174,7 @@ pub fn main(
                 let light_spec_brdf = res.sample.light_rad.spec_brdf;
 
                 light_id = res.sample.light_id;
+                light_dir = res.sample.light_dir;
                 light_pdf = (1.0 / res.w) * (1.0 - atmosphere_pdf);
 
                 light_rad = res.sample.light_rad.radiance
@@ -160,21 +191,27 @@ pub fn main(
 
     let mut radiance = if light_pdf > 0.0 {
         let light_vis = if gi_hit.is_some() {
-            let ray = if light_id == LightId::sky() {
-                Ray::new(gi_hit.point, light_dir)
+            let (ray, casts_shadows) = if light_id == LightId::sky() {
+                (Ray::new(gi_hit.point, light_dir), true)
             } else {
-                lights.get(light_id).ray_wnoise(&mut wnoise, gi_hit.point)
+                let light = lights.get(light_id);
+
+                (
+                    light.ray_wnoise(&mut wnoise, gi_hit.point),
+                    light.casts_shadows(),
+                )
             };
 
-            let is_occluded = ray.intersect(
-                local_idx,
-                stack,
-                triangles,
-                bvh,
-                materials,
-                atlas_tex,
-                atlas_sampler,
-            );
+            let is_occluded = casts_shadows
+                && ray.intersect(
+                    local_idx,
+                    stack,
+                    triangles,
+                    bvh,
+                    materials,
+                    atlas_tex,
+                    atlas_sampler,
+                );
 
             if is_occluded {
                 0.0
@@ -7,8 +7,16 @@ pub mod di_resolving;
 pub mod di_sampling;
 pub mod di_spatial_resampling;
 pub mod di_temporal_resampling;
+pub mod fog_scattering;
+pub mod frame_antialiasing;
+pub mod frame_bloom;
 pub mod frame_composition;
 pub mod frame_denoising;
+pub mod frame_depth_of_field;
+pub mod frame_diagnostics;
+pub mod frame_exposure;
+pub mod frame_motion_blur;
+pub mod frame_output;
 pub mod frame_reprojection;
 pub mod gi_preview_resampling;
 pub mod gi_reprojection;
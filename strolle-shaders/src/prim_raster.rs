@@ -29,7 +29,7 @@ pub fn vs(
     let normal = vertex_d1.xyz();
     let uv = vec2(vertex_d0.w, vertex_d1.w);
 
-    *out_vertex = camera.world_to_clip(point);
+    *out_vertex = camera.world_to_jittered_clip(point);
     *out_curr_vertex = camera.world_to_clip(point);
     *out_prev_vertex = prev_camera.world_to_clip(prev_point);
     *out_point = point;
@@ -96,6 +96,7 @@ pub fn fs(
         roughness: metallic_roughness.y,
         reflectance: material.reflectance,
         depth,
+        excluded_lights: params.excluded_lights(),
     };
 
     let [gbuffer_d0, gbuffer_d1] = gbuffer.pack();
@@ -112,8 +113,11 @@ pub fn fs(
     // -------------------------------------------------------------------------
 
     *out_velocity = {
+        // Velocity is expressed in the current frame's pixels - the previous
+        // frame could've been rendered at a different resolution, which is
+        // taken care of by the reprojection pass
         let velocity = camera.clip_to_screen(curr_vertex)
-            - prev_camera.clip_to_screen(prev_vertex);
+            - camera.clip_to_screen(prev_vertex);
 
         if velocity.length_squared() >= 0.001 {
             velocity.extend(0.0).extend(0.0)
@@ -1,5 +1,8 @@
 use strolle_gpu::prelude::*;
 
+/// Bounce from which the Russian roulette starts terminating paths.
+const ROULETTE_MIN_DEPTH: u32 = 2;
+
 #[spirv(compute(threads(8, 8)))]
 pub fn main(
     #[spirv(global_invocation_id)] global_id: UVec3,
@@ -12,32 +15,48 @@ pub fn main(
     #[spirv(descriptor_set = 0, binding = 2, storage_buffer)]
     lights: &[Light],
     #[spirv(descriptor_set = 0, binding = 3, storage_buffer)]
+    light_tree: &[Vec4],
+    #[spirv(descriptor_set = 0, binding = 4)] ies_lut_tex: Tex,
+    #[spirv(descriptor_set = 0, binding = 5)] ies_lut_sampler: &Sampler,
+    #[spirv(descriptor_set = 0, binding = 6)] env_map_tex: Tex,
+    #[spirv(descriptor_set = 0, binding = 7)] env_map_sampler: &Sampler,
+    #[spirv(descriptor_set = 0, binding = 8, storage_buffer)]
+    env_map_buffer: &[f32],
+    #[spirv(descriptor_set = 0, binding = 9, storage_buffer)]
     materials: &[Material],
-    #[spirv(descriptor_set = 0, binding = 4)] atlas_tex: Tex,
-    #[spirv(descriptor_set = 0, binding = 5)] atlas_sampler: &Sampler,
-    #[spirv(descriptor_set = 0, binding = 6, uniform)] world: &World,
+    #[spirv(descriptor_set = 0, binding = 10)] atlas_tex: Tex,
+    #[spirv(descriptor_set = 0, binding = 11)] atlas_sampler: &Sampler,
+    #[spirv(descriptor_set = 0, binding = 12, uniform)] world: &World,
+    #[spirv(descriptor_set = 0, binding = 13, uniform)]
+    atmosphere_settings: &AtmosphereSettings,
     #[spirv(descriptor_set = 1, binding = 0, uniform)] camera: &Camera,
-    #[spirv(descriptor_set = 1, binding = 1, uniform)] prev_camera: &Camera,
-    #[spirv(descriptor_set = 1, binding = 2)]
+    #[spirv(descriptor_set = 1, binding = 1)]
     atmosphere_transmittance_lut_tex: Tex,
-    #[spirv(descriptor_set = 1, binding = 3)]
+    #[spirv(descriptor_set = 1, binding = 2)]
     atmosphere_transmittance_lut_sampler: &Sampler,
-    #[spirv(descriptor_set = 1, binding = 4)] atmosphere_sky_lut_tex: Tex,
-    #[spirv(descriptor_set = 1, binding = 5)]
+    #[spirv(descriptor_set = 1, binding = 3)] atmosphere_sky_lut_tex: Tex,
+    #[spirv(descriptor_set = 1, binding = 4)]
     atmosphere_sky_lut_sampler: &Sampler,
-    #[spirv(descriptor_set = 1, binding = 6, storage_buffer)]
+    #[spirv(descriptor_set = 1, binding = 5, storage_buffer)]
     rays: &mut [Vec4],
-    #[spirv(descriptor_set = 1, binding = 7, storage_buffer)] hits: &[Vec4],
-    #[spirv(descriptor_set = 1, binding = 8)] colors: TexRgba32,
+    #[spirv(descriptor_set = 1, binding = 6, storage_buffer)] hits: &[Vec4],
+    #[spirv(descriptor_set = 1, binding = 7)] colors: TexRgba32,
+    #[spirv(descriptor_set = 1, binding = 8)] moments: TexRgba32,
 ) {
     let screen_pos = global_id.xy();
     let screen_idx = camera.screen_to_idx(screen_pos);
     let mut wnoise = WhiteNoise::new(params.seed, screen_pos);
     let triangles = TrianglesView::new(triangles);
     let bvh = BvhView::new(bvh);
-    let lights = LightsView::new(lights);
+    let ies_profiles = IesProfilesView::new(ies_lut_tex, ies_lut_sampler);
+    let env_map =
+        EnvironmentMapView::new(env_map_tex, env_map_sampler, env_map_buffer);
+    let lights = LightsView::new(lights, ies_profiles, env_map);
+    let light_tree = LightTreeView::new(light_tree);
     let materials = MaterialsView::new(materials);
     let atmosphere = Atmosphere::new(
+        world,
+        atmosphere_settings,
         atmosphere_transmittance_lut_tex,
         atmosphere_transmittance_lut_sampler,
         atmosphere_sky_lut_tex,
@@ -51,16 +70,22 @@ pub fn main(
     // -------------------------------------------------------------------------
 
     if params.depth == u8::MAX as u32 {
-        let prev_color = if camera.is_eq(*prev_camera) {
-            colors.read(screen_pos)
+        let (prev_color, prev_moments) = if params.samples > 1 {
+            (colors.read(screen_pos), moments.read(screen_pos))
         } else {
             Default::default()
         };
 
-        let curr_color = rays[3 * screen_idx + 2].xyz();
+        let curr_color = rays[4 * screen_idx + 2].xyz();
+        let curr_luma = curr_color.luma();
+
+        // Moments are accumulated for estimating the pixel's variance, see:
+        // `CameraReferenceVariance` in the `strolle` crate
+        let curr_moments = vec4(curr_luma, curr_luma * curr_luma, 1.0, 0.0);
 
         unsafe {
             colors.write(screen_pos, prev_color + curr_color.extend(1.0));
+            moments.write(screen_pos, prev_moments + curr_moments);
         }
 
         return;
@@ -72,41 +97,118 @@ pub fn main(
     let mut color;
     let mut throughput;
 
+    // Pdf with which the BRDF has sampled this ray, or zero if the ray comes
+    // from the camera
+    let ray_pdf;
+
+    // Normal and light-linking of the surface this ray has been reflected from
+    let ray_normal;
+    let ray_excluded_lights;
+
     if params.depth == 0 {
-        ray = camera.ray(screen_pos);
+        // (see: ref_tracing)
+        let lens_sample =
+            WhiteNoise::new(params.frame.get(), screen_pos).sample_disk();
+
+        ray = camera.lens_ray(screen_pos, lens_sample);
         color = Vec3::ZERO;
         throughput = Vec3::ONE;
+        ray_pdf = 0.0;
+        ray_normal = Vec3::ZERO;
+        ray_excluded_lights = 0;
     } else {
-        let d0 = rays[3 * screen_idx];
-        let d1 = rays[3 * screen_idx + 1];
-        let d2 = rays[3 * screen_idx + 2];
+        let d0 = rays[4 * screen_idx];
+        let d1 = rays[4 * screen_idx + 1];
+        let d2 = rays[4 * screen_idx + 2];
+        let d3 = rays[4 * screen_idx + 3];
+
+        // If the path has been already terminated, there's nothing to trace
+        // (see: ref_tracing)
+        if d1 == Default::default() {
+            return;
+        }
 
         ray = Ray::new(d0.xyz(), d1.xyz());
         color = d2.xyz();
         throughput = vec3(d0.w, d1.w, d2.w);
+        ray_pdf = d3.x;
+        ray_normal = Normal::decode(d3.yz());
+        ray_excluded_lights =
+            GBufferEntry::decode_excluded_lights(d3.w.to_bits().to_bytes()[3]);
     }
 
-    let hit = {
-        let t_hit = TriangleHit::unpack([
-            hits[2 * screen_idx],
-            hits[2 * screen_idx + 1],
-        ]);
+    let t_hit =
+        TriangleHit::unpack([hits[2 * screen_idx], hits[2 * screen_idx + 1]]);
 
-        if t_hit.is_none() {
-            color += throughput * atmosphere.sample(world.sun_dir(), ray.dir());
+    // -------------------------------------------------------------------------
+    // Contribution of lights hit by the BRDF-sampled ray.
+    //
+    // Lights are not part of the geometry, so the tracing pass doesn't see
+    // them - instead, we check which lights the ray would have hit before
+    // reaching the triangle and weight them against the light sampling below,
+    // which - just like here - picks lights from the surface the ray has been
+    // reflected from.
+    //
+    // Directional lights are skipped, since they are sampled only explicitly
+    // (see the sky below); other lights are looked up through the light tree,
+    // which lets us skip the ones the ray can't reach.
 
-            rays[3 * screen_idx] = Default::default();
-            rays[3 * screen_idx + 1] = Default::default();
-            rays[3 * screen_idx + 2] = color.extend(Default::default());
+    if ray_pdf > 0.0 && world.light_count > 0 {
+        light_tree.for_each_hit(
+            local_idx,
+            stack,
+            ray,
+            t_hit.distance,
+            |light_id| {
+                let light = lights.get(light_id);
+                let solid_angle = light.solid_angle(ray.origin());
 
-            return;
-        }
+                if light.is_linked_to(ray_excluded_lights)
+                    && solid_angle > 0.0
+                    && ray.dir().dot(light.dir_from(ray.origin()))
+                        >= light.cone_cos(ray.origin())
+                    && light.distance_along(ray.origin(), ray.dir())
+                        < t_hit.distance
+                {
+                    let radiance = light.intensity(ies_profiles, ray.origin())
+                        / solid_angle;
 
-        let mut material = materials.get(t_hit.material_id);
+                    let light_pdf = light_tree.pdf(
+                        world.light_tree_paths,
+                        light_id,
+                        ray.origin(),
+                        ray_normal,
+                    );
 
-        if params.depth > 0 {
-            material.regularize();
-        }
+                    color += throughput
+                        * radiance
+                        * mis_weight(ray_pdf, light_pdf / solid_angle);
+                }
+            },
+        );
+    }
+
+    // -------------------------------------------------------------------------
+
+    if t_hit.is_none() {
+        // Sun and moon are already sampled explicitly through the directional
+        // light, so bounced rays must skip their disks not to count them twice
+        let sky = if env_map.is_active() {
+            env_map.radiance(ray.dir())
+        } else if ray_pdf > 0.0 {
+            atmosphere.sample_without_disks(ray.dir())
+        } else {
+            atmosphere.sample_primary(ray.dir())
+        };
+
+        color += throughput * sky;
+
+        terminate(rays, screen_idx, color);
+        return;
+    }
+
+    let hit = {
+        let material = materials.get(t_hit.material_id);
 
         Hit {
             point: t_hit.point + t_hit.normal * Hit::NUDGE_OFFSET,
@@ -124,54 +226,153 @@ pub fn main(
                 roughness: material.roughness,
                 reflectance: material.reflectance,
                 depth: 0.0,
+                excluded_lights: t_hit.excluded_lights,
             },
         }
     };
 
+    let brdf = LayeredBrdf::new(hit.gbuffer);
+    let is_last_bounce = params.depth >= params.max_depth;
+
     // -------------------------------------------------------------------------
+    // Emissive surfaces are not sampled explicitly, so their contribution
+    // comes only from being hit.
 
     color += throughput * hit.gbuffer.emissive;
 
-    if world.light_count > 0 {
-        let light_id = wnoise.sample_int() % world.light_count;
-        let light_pdf = 1.0 / (world.light_count as f32);
-        let light = lights.get(LightId::new(light_id));
-
-        let is_light_occluded =
-            light.ray_wnoise(&mut wnoise, hit.point).intersect(
-                local_idx,
-                stack,
-                triangles,
-                bvh,
-                materials,
-                atlas_tex,
-                atlas_sampler,
-            );
-
-        if !is_light_occluded {
-            color += throughput * light.radiance(hit).sum() / light_pdf;
+    // -------------------------------------------------------------------------
+    // Light sampling; lights are picked through the light tree and then
+    // sampled uniformly within the cone they subtend, so that their radiance
+    // (intensity divided by the solid angle) cancels out with the direction's
+    // pdf.
+
+    let (light_id, light_pdf) = if world.light_count > 0 {
+        light_tree.sample(&mut wnoise, hit.point, hit.gbuffer.normal)
+    } else {
+        (LightId::new(0), 0.0)
+    };
+
+    if light_pdf > 0.0 {
+        let light = lights.get(light_id);
+        let solid_angle = light.solid_angle(hit.point);
+
+        let light_dir = if solid_angle > 0.0 {
+            wnoise.sample_cone(
+                light.dir_from(hit.point),
+                light.cone_cos(hit.point),
+            )
+        } else {
+            light.dir_from(hit.point)
+        };
+
+        let light_brdf = brdf.eval(light_dir, -hit.dir)
+            * light_dir.dot(hit.gbuffer.normal).saturate();
+
+        if light_brdf.max_element() > 0.0
+            && light.is_linked_to(hit.gbuffer.excluded_lights)
+        {
+            let is_light_occluded = light.casts_shadows()
+                && Ray::new(hit.point, light_dir)
+                    .with_len(light.distance_along(hit.point, light_dir))
+                    .intersect(
+                        local_idx,
+                        stack,
+                        triangles,
+                        bvh,
+                        materials,
+                        atlas_tex,
+                        atlas_sampler,
+                    );
+
+            if !is_light_occluded {
+                // Delta lights and directional lights can't be hit by the BRDF
+                // rays, and neither can lights after the last bounce
+                let weight = if light.is_directional()
+                    || solid_angle <= 0.0
+                    || is_last_bounce
+                {
+                    1.0
+                } else {
+                    mis_weight(
+                        light_pdf / solid_angle,
+                        brdf.pdf(light_dir, -hit.dir),
+                    )
+                };
+
+                color += throughput
+                    * light_brdf
+                    * light.intensity(ies_profiles, hit.point)
+                    * weight
+                    / light_pdf;
+            }
         }
     }
 
+    if is_last_bounce {
+        terminate(rays, screen_idx, color);
+        return;
+    }
+
     // -------------------------------------------------------------------------
 
-    let reflected_sample =
-        LayeredBrdf::new(hit.gbuffer).sample(&mut wnoise, -hit.dir);
+    let reflected_sample = brdf.sample_full(&mut wnoise, -hit.dir);
 
     if reflected_sample.is_invalid() {
-        rays[3 * screen_idx] = Default::default();
-        rays[3 * screen_idx + 1] = Default::default();
+        terminate(rays, screen_idx, color);
         return;
     }
 
-    let reflected_ray = Ray::new(hit.point, reflected_sample.dir);
+    throughput *= reflected_sample.radiance
+        * reflected_sample.dir.dot(hit.gbuffer.normal).saturate()
+        / reflected_sample.pdf;
 
-    throughput *= reflected_sample.dir.dot(hit.gbuffer.normal);
-    throughput *= reflected_sample.radiance / reflected_sample.pdf;
+    // -------------------------------------------------------------------------
+    // Russian roulette - paths that carry little energy get terminated early,
+    // and the survivors get boosted to compensate.
+
+    if params.depth >= ROULETTE_MIN_DEPTH {
+        let survival = throughput.max_element().min(0.95);
+
+        if wnoise.sample() >= survival {
+            terminate(rays, screen_idx, color);
+            return;
+        }
+
+        throughput /= survival;
+    }
 
     // -------------------------------------------------------------------------
 
-    rays[3 * screen_idx] = reflected_ray.origin().extend(throughput.x);
-    rays[3 * screen_idx + 1] = reflected_ray.dir().extend(throughput.y);
-    rays[3 * screen_idx + 2] = color.extend(throughput.z);
+    let reflected_ray = Ray::new(hit.point, reflected_sample.dir);
+
+    rays[4 * screen_idx] = reflected_ray.origin().extend(throughput.x);
+    rays[4 * screen_idx + 1] = reflected_ray.dir().extend(throughput.y);
+    rays[4 * screen_idx + 2] = color.extend(throughput.z);
+    rays[4 * screen_idx + 3] = {
+        let normal = Normal::encode(hit.gbuffer.normal);
+
+        vec4(
+            reflected_sample.pdf,
+            normal.x,
+            normal.y,
+            // (same encoding as GBuffer's, see there)
+            f32::from_bits(u32::from_bytes([
+                0,
+                0,
+                0,
+                GBufferEntry::encode_excluded_lights(
+                    hit.gbuffer.excluded_lights,
+                ),
+            ])),
+        )
+    };
+}
+
+/// Stores path's final color and marks it as terminated, so that the upcoming
+/// bounces skip it.
+fn terminate(rays: &mut [Vec4], screen_idx: usize, color: Vec3) {
+    rays[4 * screen_idx] = Default::default();
+    rays[4 * screen_idx + 1] = Default::default();
+    rays[4 * screen_idx + 2] = color.extend(0.0);
+    rays[4 * screen_idx + 3] = Default::default();
 }
@@ -31,10 +31,15 @@ pub fn main(
     // -------------------------------------------------------------------------
 
     let ray = if params.depth == 0 {
-        camera.ray(screen_pos)
+        // (lens is sampled using frame-based noise, so that the shading pass
+        // can reconstruct the same ray)
+        let lens_sample =
+            WhiteNoise::new(params.frame.get(), screen_pos).sample_disk();
+
+        camera.lens_ray(screen_pos, lens_sample)
     } else {
-        let d0 = rays[3 * screen_idx];
-        let d1 = rays[3 * screen_idx + 1];
+        let d0 = rays[4 * screen_idx];
+        let d1 = rays[4 * screen_idx + 1];
 
         if d1 == Default::default() {
             return;
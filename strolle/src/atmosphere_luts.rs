@@ -0,0 +1,170 @@
+use log::debug;
+
+use crate::{
+    gpu, Bindable, CameraComputePass, MappedUniformBuffer, Shaders, Sun,
+    Texture,
+};
+
+/// Lookup textures used to render the atmosphere.
+///
+/// Those depend only on atmosphere's settings and sun's position, so they are
+/// shared by all of the cameras and regenerated only when necessary.
+#[derive(Debug)]
+pub struct AtmosphereLuts {
+    transmittance_lut: Texture,
+    sky_lut: Texture,
+
+    generate_transmittance_lut_pass: CameraComputePass<()>,
+    generate_scattering_lut_pass: CameraComputePass<()>,
+    generate_sky_lut_pass: CameraComputePass<()>,
+
+    known_settings: Option<gpu::AtmosphereSettings>,
+    known_sun_altitude: Option<f32>,
+}
+
+impl AtmosphereLuts {
+    pub fn new(
+        device: &wgpu::Device,
+        shaders: &Shaders,
+        world: &MappedUniformBuffer<gpu::World>,
+        settings: &MappedUniformBuffer<gpu::AtmosphereSettings>,
+    ) -> Self {
+        debug!("Initializing atmosphere");
+
+        let transmittance_lut =
+            Texture::builder("atmosphere_transmittance_lut")
+                .with_size(gpu::Atmosphere::TRANSMITTANCE_LUT_RESOLUTION)
+                .with_format(wgpu::TextureFormat::Rgba16Float)
+                .with_usage(wgpu::TextureUsages::TEXTURE_BINDING)
+                .with_usage(wgpu::TextureUsages::STORAGE_BINDING)
+                .with_linear_filtering_sampler()
+                .build(device);
+
+        let scattering_lut = Texture::builder("atmosphere_scattering_lut")
+            .with_size(gpu::Atmosphere::SCATTERING_LUT_RESOLUTION)
+            .with_format(wgpu::TextureFormat::Rgba16Float)
+            .with_usage(wgpu::TextureUsages::TEXTURE_BINDING)
+            .with_usage(wgpu::TextureUsages::STORAGE_BINDING)
+            .with_linear_filtering_sampler()
+            .build(device);
+
+        let sky_lut = Texture::builder("atmosphere_sky_lut")
+            .with_size(gpu::Atmosphere::SKY_LUT_RESOLUTION)
+            .with_format(wgpu::TextureFormat::Rgba16Float)
+            .with_usage(wgpu::TextureUsages::TEXTURE_BINDING)
+            .with_usage(wgpu::TextureUsages::STORAGE_BINDING)
+            .with_linear_filtering_sampler()
+            .build(device);
+
+        // ---
+
+        let generate_transmittance_lut_pass =
+            CameraComputePass::builder("atmosphere_generate_transmittance_lut")
+                .bind([
+                    &settings.bind_readable(),
+                    &transmittance_lut.bind_writable(),
+                ])
+                .build(device, &shaders.atmosphere_generate_transmittance_lut);
+
+        let generate_scattering_lut_pass =
+            CameraComputePass::builder("atmosphere_generate_scattering_lut")
+                .bind([
+                    &settings.bind_readable(),
+                    &transmittance_lut.bind_sampled(),
+                    &scattering_lut.bind_writable(),
+                ])
+                .build(device, &shaders.atmosphere_generate_scattering_lut);
+
+        let generate_sky_lut_pass =
+            CameraComputePass::builder("atmosphere_generate_sky_lut")
+                .bind([
+                    &world.bind_readable(),
+                    &settings.bind_readable(),
+                    &transmittance_lut.bind_sampled(),
+                    &scattering_lut.bind_sampled(),
+                    &sky_lut.bind_writable(),
+                ])
+                .build(device, &shaders.atmosphere_generate_sky_lut);
+
+        Self {
+            transmittance_lut,
+            sky_lut,
+
+            generate_transmittance_lut_pass,
+            generate_scattering_lut_pass,
+            generate_sky_lut_pass,
+
+            known_settings: None,
+            known_sun_altitude: None,
+        }
+    }
+
+    /// Regenerates lookup textures that depend on given settings and sun.
+    ///
+    /// Must be called after `world` and `settings` have been flushed, since the
+    /// passes read them from the GPU.
+    pub fn refresh(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        settings: gpu::AtmosphereSettings,
+        sun: Sun,
+    ) {
+        // Clouds are evaluated per-pixel, so there's no need to regenerate
+        // anything when they change (e.g. when wind is being animated)
+        let settings = gpu::AtmosphereSettings {
+            clouds: Default::default(),
+            ..settings
+        };
+
+        // Transmittance and scattering depend only on atmosphere's settings, so
+        // it's enough if we regenerate them when those change
+        let has_settings_changed = self.known_settings != Some(settings);
+
+        // On the other hand, the sky lookup texture depends on sun's altitude
+        let has_sun_changed = self.known_sun_altitude != Some(sun.altitude);
+
+        if !has_settings_changed && !has_sun_changed {
+            return;
+        }
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("strolle_atmosphere"),
+            });
+
+        if has_settings_changed {
+            self.generate_transmittance_lut_pass.run_detached(
+                &mut encoder,
+                (gpu::Atmosphere::TRANSMITTANCE_LUT_RESOLUTION + 7) / 8,
+                (),
+            );
+
+            self.generate_scattering_lut_pass.run_detached(
+                &mut encoder,
+                (gpu::Atmosphere::SCATTERING_LUT_RESOLUTION + 7) / 8,
+                (),
+            );
+
+            self.known_settings = Some(settings);
+        }
+
+        self.generate_sky_lut_pass.run_detached(
+            &mut encoder,
+            (gpu::Atmosphere::SKY_LUT_RESOLUTION + 7) / 8,
+            (),
+        );
+
+        self.known_sun_altitude = Some(sun.altitude);
+
+        queue.submit([encoder.finish()]);
+    }
+
+    pub fn bind_transmittance_lut(&self) -> impl Bindable + '_ {
+        self.transmittance_lut.bind_sampled()
+    }
+
+    pub fn bind_sky_lut(&self) -> impl Bindable + '_ {
+        self.sky_lut.bind_sampled()
+    }
+}
@@ -0,0 +1,152 @@
+use glam::{vec2, vec3, Vec2, Vec3};
+
+use crate::gpu;
+
+/// Physical properties of the atmosphere, used to render the sky.
+///
+/// Scattering and absorption coefficients are expressed per mega-meter, at the
+/// ground level - they get attenuated exponentially with the altitude.
+///
+/// Default values describe an Earth-like planet.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AtmosphereSettings {
+    /// Radius of the planet, in mega-meters.
+    pub ground_radius: f32,
+
+    /// Radius of the atmosphere (i.e. planet's radius + atmosphere's height),
+    /// in mega-meters.
+    pub atmosphere_radius: f32,
+
+    pub ground_albedo: Vec3,
+
+    /// Scattering caused by small particles (air molecules); this is what makes
+    /// the sky blue.
+    pub rayleigh_scattering: Vec3,
+    pub rayleigh_absorption: f32,
+
+    /// Scattering caused by larger particles (dust, water droplets); increasing
+    /// this makes the sky hazy.
+    pub mie_scattering: f32,
+    pub mie_absorption: f32,
+
+    /// Anisotropy of Mie scattering, from -1.0 to 1.0; the larger the value,
+    /// the more light gets scattered forward (e.g. creating a halo around the
+    /// sun).
+    pub mie_anisotropy: f32,
+
+    pub ozone_absorption: Vec3,
+
+    /// Multiplier applied to the images of cameras with
+    /// [`crate::CameraExposure::Manual`], on top of their exposure values.
+    ///
+    /// Sky (and the sun) is quite dim in the atmosphere's units, where the
+    /// sun's illuminance at the top of the atmosphere is 1.0 - both the sky
+    /// and the sun's light that reaches the scene use those units, so the
+    /// image needs some scaling to get displayed; cameras with
+    /// [`crate::CameraExposure::Auto`] ignore this value, since they adapt to
+    /// the sky's brightness on their own; it's also ignored when there's an
+    /// environment map active.
+    pub exposure: f32,
+
+    pub clouds: CloudsSettings,
+}
+
+impl AtmosphereSettings {
+    pub(crate) fn serialize(&self) -> gpu::AtmosphereSettings {
+        gpu::AtmosphereSettings {
+            rayleigh_scattering: self.rayleigh_scattering,
+            rayleigh_absorption: self.rayleigh_absorption,
+            ozone_absorption: self.ozone_absorption,
+            mie_scattering: self.mie_scattering,
+            ground_albedo: self.ground_albedo,
+            mie_absorption: self.mie_absorption,
+            ground_radius: self.ground_radius,
+            atmosphere_radius: self.atmosphere_radius,
+            mie_anisotropy: self.mie_anisotropy,
+            _padding: 0.0,
+            clouds: self.clouds.serialize(),
+        }
+    }
+}
+
+impl Default for AtmosphereSettings {
+    fn default() -> Self {
+        Self {
+            ground_radius: 6.360,
+            atmosphere_radius: 6.460,
+            ground_albedo: Vec3::splat(0.25),
+            rayleigh_scattering: vec3(5.802, 13.558, 33.1),
+            rayleigh_absorption: 0.0,
+            mie_scattering: 3.996,
+            mie_absorption: 4.4,
+            mie_anisotropy: 0.8,
+            ozone_absorption: vec3(0.650, 1.881, 0.085),
+            exposure: 20.0,
+            clouds: Default::default(),
+        }
+    }
+}
+
+/// Layer of volumetric clouds, rendered as a part of the atmosphere.
+///
+/// Clouds are disabled by default - set [`Self::coverage`] to enable them.
+///
+/// Note that clouds also dim and soften the sun's light, but they don't cast
+/// actual shadows onto the scene.
+///
+/// Clouds are raymarched only for rays coming straight from the camera, so
+/// they don't show up in reflections nor affect the light bounced off the sky.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CloudsSettings {
+    /// Fraction of the sky covered by clouds, from 0.0 (clear sky) to 1.0
+    /// (overcast).
+    pub coverage: f32,
+
+    /// How thick the clouds are, expressed as extinction per kilometer.
+    pub density: f32,
+
+    /// Altitude of clouds' bottom, in kilometers.
+    pub bottom: f32,
+
+    /// Altitude of clouds' top, in kilometers.
+    pub top: f32,
+
+    /// Offset applied to the clouds, in kilometers; animate this to make the
+    /// clouds move.
+    pub wind_offset: Vec2,
+
+    /// Size of a single cloud-ish feature, in kilometers.
+    pub scale: f32,
+
+    /// Anisotropy of clouds' scattering, from -1.0 to 1.0; the larger the
+    /// value, the brighter the clouds get around the sun.
+    pub anisotropy: f32,
+}
+
+impl CloudsSettings {
+    pub(crate) fn serialize(&self) -> gpu::CloudsSettings {
+        gpu::CloudsSettings {
+            coverage: self.coverage.clamp(0.0, 1.0),
+            density: self.density.max(0.0),
+            bottom: self.bottom,
+            top: self.top,
+            wind_offset: self.wind_offset,
+            scale: self.scale,
+            anisotropy: self.anisotropy.clamp(-0.99, 0.99),
+        }
+    }
+}
+
+impl Default for CloudsSettings {
+    fn default() -> Self {
+        Self {
+            coverage: 0.0,
+            density: 1.0,
+            bottom: 1.5,
+            top: 4.0,
+            wind_offset: vec2(0.0, 0.0),
+            scale: 5.0,
+            anisotropy: 0.6,
+        }
+    }
+}
@@ -4,6 +4,7 @@ mod bufferable;
 mod double_buffered;
 mod mapped_storage_buffer;
 mod mapped_uniform_buffer;
+mod readback_buffer;
 mod storage_buffer;
 mod texture;
 mod utils;
@@ -14,6 +15,7 @@ pub use self::bufferable::*;
 pub use self::double_buffered::*;
 pub use self::mapped_storage_buffer::*;
 pub use self::mapped_uniform_buffer::*;
+pub use self::readback_buffer::*;
 pub use self::storage_buffer::*;
 pub use self::texture::*;
 
@@ -31,6 +31,12 @@ impl Bufferable for f32 {
     }
 }
 
+impl Bufferable for gpu::AtmosphereSettings {
+    fn data(&self) -> &[u8] {
+        bytemuck::cast_slice(slice::from_ref(self))
+    }
+}
+
 impl Bufferable for gpu::Camera {
     fn data(&self) -> &[u8] {
         bytemuck::cast_slice(slice::from_ref(self))
@@ -0,0 +1,229 @@
+use std::mem;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::sync::Arc;
+
+use bytemuck::Pod;
+use log::debug;
+use spirv_std::glam::UVec2;
+
+use crate::Texture;
+
+/// Buffer that allows to read data from VRAM back into RAM.
+///
+/// Since mapping a buffer can only happen after the commands that fill it get
+/// submitted, the data is available with a delay of a couple of frames; until
+/// it gets read, [`Self::copy_from()`] is a no-op.
+#[derive(Debug)]
+pub struct ReadbackBuffer {
+    buffer: wgpu::Buffer,
+    state: ReadbackBufferState,
+    is_copied: AtomicBool,
+    map_status: Arc<AtomicU8>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ReadbackBufferState {
+    /// Buffer should be filled during the upcoming render
+    Idle,
+
+    /// Buffer has been filled and we're waiting for it to get mapped
+    Mapping,
+}
+
+impl ReadbackBuffer {
+    const MAP_PENDING: u8 = 0;
+    const MAP_READY: u8 = 1;
+    const MAP_FAILED: u8 = 2;
+
+    pub fn new(
+        device: &wgpu::Device,
+        label: impl AsRef<str>,
+        size: usize,
+    ) -> Self {
+        let label = format!("strolle_{}", label.as_ref());
+
+        debug!("Allocating readback buffer `{label}`; size={size}");
+
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(&label),
+            size: size as _,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            buffer,
+            state: ReadbackBufferState::Idle,
+            is_copied: AtomicBool::new(false),
+            map_status: Arc::new(AtomicU8::new(Self::MAP_PENDING)),
+        }
+    }
+
+    /// Returns whether the buffer can be filled during the upcoming render.
+    pub fn is_idle(&self) -> bool {
+        self.state == ReadbackBufferState::Idle
+    }
+
+    /// Schedules copying given buffer into this one; does nothing if the
+    /// previous data hasn't been read yet.
+    pub fn copy_from(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        source: &wgpu::Buffer,
+    ) {
+        if !self.is_idle() {
+            return;
+        }
+
+        encoder.copy_buffer_to_buffer(
+            source,
+            0,
+            &self.buffer,
+            0,
+            self.buffer.size(),
+        );
+
+        self.is_copied.store(true, Ordering::Release);
+    }
+
+    /// Schedules copying given area (starting at the top-left corner) of a
+    /// texture into this buffer; does nothing if the previous data hasn't been
+    /// read yet.
+    ///
+    /// Rows get padded to [`wgpu::COPY_BYTES_PER_ROW_ALIGNMENT`], see:
+    /// [`Self::padded_bytes_per_row()`].
+    pub fn copy_from_texture(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        source: &Texture,
+        size: UVec2,
+    ) {
+        self.copy_from_texture_at(encoder, source, UVec2::ZERO, size);
+    }
+
+    /// Schedules copying given area of a texture into this buffer.
+    ///
+    /// See: [`Self::copy_from_texture()`].
+    pub fn copy_from_texture_at(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        source: &Texture,
+        origin: UVec2,
+        size: UVec2,
+    ) {
+        if !self.is_idle() {
+            return;
+        }
+
+        let bytes_per_row =
+            Self::padded_bytes_per_row(size.x, source.tex().format());
+
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture: source.tex(),
+                mip_level: 0,
+                origin: wgpu::Origin3d {
+                    x: origin.x,
+                    y: origin.y,
+                    z: 0,
+                },
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &self.buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(bytes_per_row),
+                    rows_per_image: None,
+                },
+            },
+            wgpu::Extent3d {
+                width: size.x,
+                height: size.y,
+                depth_or_array_layers: 1,
+            },
+        );
+
+        self.is_copied.store(true, Ordering::Release);
+    }
+
+    /// Returns the number of bytes each row of a texture with given width and
+    /// format occupies when copied into a buffer.
+    pub fn padded_bytes_per_row(
+        width: u32,
+        format: wgpu::TextureFormat,
+    ) -> u32 {
+        let bytes_per_row = width * format.block_size(None).unwrap_or(0);
+        let alignment = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+
+        (bytes_per_row + alignment - 1) / alignment * alignment
+    }
+
+    /// Returns the data, if it's already available.
+    ///
+    /// This function must be called once per frame, since it's also
+    /// responsible for mapping the buffer after it's been filled.
+    pub fn read<T>(&mut self) -> Option<T>
+    where
+        T: Pod,
+    {
+        self.read_with(|data| {
+            bytemuck::pod_read_unaligned(&data[..mem::size_of::<T>()])
+        })
+    }
+
+    /// Returns the data (transformed through given function), if it's already
+    /// available.
+    ///
+    /// See: [`Self::read()`].
+    pub fn read_with<T>(&mut self, f: impl FnOnce(&[u8]) -> T) -> Option<T> {
+        match self.state {
+            ReadbackBufferState::Idle => {
+                if self.is_copied.swap(false, Ordering::Acquire) {
+                    let map_status = self.map_status.clone();
+
+                    map_status.store(Self::MAP_PENDING, Ordering::Release);
+
+                    self.buffer.slice(..).map_async(
+                        wgpu::MapMode::Read,
+                        move |result| {
+                            map_status.store(
+                                if result.is_ok() {
+                                    Self::MAP_READY
+                                } else {
+                                    Self::MAP_FAILED
+                                },
+                                Ordering::Release,
+                            );
+                        },
+                    );
+
+                    self.state = ReadbackBufferState::Mapping;
+                }
+
+                None
+            }
+
+            ReadbackBufferState::Mapping => {
+                match self.map_status.load(Ordering::Acquire) {
+                    Self::MAP_READY => {
+                        let data = f(&self.buffer.slice(..).get_mapped_range());
+
+                        self.buffer.unmap();
+                        self.state = ReadbackBufferState::Idle;
+
+                        Some(data)
+                    }
+
+                    Self::MAP_FAILED => {
+                        self.state = ReadbackBufferState::Idle;
+
+                        None
+                    }
+
+                    _ => None,
+                }
+            }
+        }
+    }
+}
@@ -26,7 +26,9 @@ impl StorageBuffer {
 
         let buffer = device.create_buffer(&wgpu::BufferDescriptor {
             label: Some(label),
-            usage: wgpu::BufferUsages::STORAGE,
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::COPY_SRC
+                | wgpu::BufferUsages::COPY_DST,
             size: size as _,
             mapped_at_creation: false,
         });
@@ -34,6 +36,10 @@ impl StorageBuffer {
         Self { buffer }
     }
 
+    pub fn as_buffer(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+
     /// Creates an immutable storage-buffer binding:
     ///
     /// ```
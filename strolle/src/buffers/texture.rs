@@ -28,6 +28,28 @@ impl Texture {
         &self.view
     }
 
+    /// Copies given area (starting at the top-left corner) of the `source`
+    /// texture into this texture.
+    ///
+    /// Both of the textures must have the same format, with the source one
+    /// having `COPY_SRC` usage and this one having `COPY_DST`.
+    pub fn copy_from(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        source: &Texture,
+        size: UVec2,
+    ) {
+        encoder.copy_texture_to_texture(
+            source.tex.as_image_copy(),
+            self.tex.as_image_copy(),
+            wgpu::Extent3d {
+                width: size.x,
+                height: size.y,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+
     /// Creates an image + sampler bindings:
     ///
     /// ```
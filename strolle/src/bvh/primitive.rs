@@ -10,6 +10,7 @@ use crate::utils::BoundingBox;
 pub struct BvhPrimitive {
     pub triangle_id: gpu::TriangleId,
     pub material_id: gpu::MaterialId,
+    pub excluded_lights: u32,
     pub center: Vec3,
     pub bounds: BoundingBox,
 }
@@ -94,6 +94,7 @@ where
 
                     (got_more_entries as u32)
                         | ((has_alpha_blending as u32) << 1)
+                        | (primitive.excluded_lights << 2)
                 };
 
                 buffer.push(vec4(
@@ -1,7 +1,12 @@
 use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
 
-use log::info;
-use spirv_std::glam::{uvec2, Mat4, UVec2, Vec3};
+use derivative::Derivative;
+use log::{info, warn};
+use spirv_std::glam::{
+    uvec2, vec2, vec4, Mat4, UVec2, Vec2, Vec3, Vec4, Vec4Swizzles,
+};
 
 use crate::gpu;
 
@@ -11,6 +16,36 @@ pub struct Camera {
     pub viewport: CameraViewport,
     pub transform: Mat4,
     pub projection: Mat4,
+
+    /// When set, the camera renders a 360° panorama around its position,
+    /// ignoring [`Self::projection`].
+    ///
+    /// Directions get rotated by the camera's transform, so an unrotated
+    /// camera yields an image that can be used as an environment map (e.g. a
+    /// reflection probe); panoramas can't be rasterized, so they're supported
+    /// only by [`CameraMode::Reference`] and [`CameraMode::BvhHeatmap`].
+    pub panorama: Option<CameraPanorama>,
+
+    pub anti_aliasing: CameraAntiAliasing,
+    pub dynamic_resolution: Option<CameraDynamicResolution>,
+    pub exposure: CameraExposure,
+    pub tonemapping: CameraTonemapping,
+    pub bloom: Option<CameraBloom>,
+    pub depth_of_field: Option<CameraDepthOfField>,
+    pub motion_blur: Option<CameraMotionBlur>,
+
+    /// Value that gets mapped to the end of the diagnostic's gradient; when
+    /// not set, [`CameraDiagnosticLegend::max`] is used.
+    ///
+    /// See: [`CameraMode::Diagnostic`].
+    pub diagnostic_max: Option<f32>,
+
+    /// Number of samples after which [`CameraMode::Reference`] stops tracing
+    /// new rays and keeps presenting the accumulated image; when not set, the
+    /// samples are accumulated indefinitely.
+    ///
+    /// See: [`CameraReference`].
+    pub reference_samples: Option<u32>,
 }
 
 impl Camera {
@@ -24,6 +59,43 @@ impl Camera {
             return true;
         }
 
+        if self.anti_aliasing != older.anti_aliasing {
+            info!(
+                "Camera `{}` invalidated: anti_aliasing has been changed \
+                 ({:?} -> {:?})",
+                older, older.anti_aliasing, self.anti_aliasing,
+            );
+
+            return true;
+        }
+
+        if self.dynamic_resolution.is_some()
+            != older.dynamic_resolution.is_some()
+        {
+            info!(
+                "Camera `{}` invalidated: dynamic_resolution has been toggled",
+                older,
+            );
+
+            return true;
+        }
+
+        if self.bloom.is_some() != older.bloom.is_some() {
+            info!("Camera `{}` invalidated: bloom has been toggled", older);
+
+            return true;
+        }
+
+        if self.tonemapping.lut() != older.tonemapping.lut() {
+            info!(
+                "Camera `{}` invalidated: tonemapping's lookup table has been \
+                 changed",
+                older,
+            );
+
+            return true;
+        }
+
         if self.viewport.format != older.viewport.format {
             info!(
                 "Camera `{}` invalidated: viewport.format has been changed \
@@ -34,6 +106,14 @@ impl Camera {
             return true;
         }
 
+        // With dynamic resolution, buffers are allocated upfront and the image
+        // gets rendered into a sub-rectangle of them, so changing the size
+        // doesn't require rebuilding anything (unless the new size doesn't fit
+        // the buffers, which is checked separately by the camera controller)
+        if self.has_dynamic_resolution() {
+            return false;
+        }
+
         if self.viewport.size != older.viewport.size {
             info!(
                 "Camera `{}` invalidated: viewport.size has been changed \
@@ -44,25 +124,247 @@ impl Camera {
             return true;
         }
 
+        if self.viewport.internal_size != older.viewport.internal_size {
+            info!(
+                "Camera `{}` invalidated: viewport.internal_size has been \
+                 changed ({:?} -> {:?})",
+                older,
+                older.viewport.internal_size,
+                self.viewport.internal_size,
+            );
+
+            return true;
+        }
+
         false
     }
 
     pub(crate) fn serialize(&self) -> gpu::Camera {
-        gpu::Camera {
-            projection_view: self.projection * self.transform.inverse(),
-            ndc_to_world: self.transform * self.projection.inverse(),
+        // Panoramas map pixels into directions on their own, so the matrices
+        // just carry camera's transform
+        let (projection_view, ndc_to_world) = if self.panorama.is_some() {
+            (self.transform.inverse(), self.transform)
+        } else {
+            (
+                self.projection * self.transform.inverse(),
+                self.transform * self.projection.inverse(),
+            )
+        };
+
+        let panorama = match self.panorama {
+            None => gpu::Camera::PANORAMA_NONE,
+            Some(CameraPanorama::Equirect) => gpu::Camera::PANORAMA_EQUIRECT,
+            Some(CameraPanorama::Cubemap) => gpu::Camera::PANORAMA_CUBEMAP,
+        };
+
+        let mut camera = gpu::Camera {
+            projection_view,
+            ndc_to_world,
             origin: self
                 .transform
                 .to_scale_rotation_translation()
                 .2
-                .extend(Default::default()),
+                .extend(panorama as f32),
             screen: self
-                .viewport
-                .size
+                .render_size()
                 .as_vec2()
                 .extend(Default::default())
                 .extend(Default::default()),
+            extent: self
+                .render_size()
+                .as_vec2()
+                .extend(Default::default())
+                .extend(Default::default()),
+            lens: self.serialize_lens(),
+        };
+
+        // Parallel projections don't have a single eye position - their rays
+        // start on the near-plane, so let's use its center instead
+        if self.is_orthographic() {
+            camera.origin = camera
+                .ray_through(camera.screen.xy() * 0.5)
+                .origin()
+                .extend(camera.origin.w);
         }
+
+        camera
+    }
+
+    fn serialize_lens(&self) -> Vec4 {
+        // Equals to `1 / tan(fov / 2)` for perspective projections
+        let projection_scale = self.projection.y_axis.y;
+
+        let (aperture_radius, focus_distance) =
+            if let Some(dof) = self.depth_of_field() {
+                let focal_length = 0.5 * dof.sensor_height * projection_scale;
+
+                (0.5 * focal_length / dof.f_stop, dof.focus_distance)
+            } else {
+                (0.0, 1.0)
+            };
+
+        let shutter = self
+            .motion_blur
+            .map(|motion_blur| motion_blur.shutter_angle / 360.0)
+            .unwrap_or_default();
+
+        vec4(aperture_radius, focus_distance, projection_scale, shutter)
+    }
+
+    /// Returns whether camera's projection is a parallel one (orthographic or
+    /// oblique).
+    ///
+    /// See: [`gpu::Camera::is_orthographic()`].
+    pub(crate) fn is_orthographic(&self) -> bool {
+        self.panorama.is_none()
+            && self.projection.row(3).xyz().abs_diff_eq(Vec3::ZERO, 1e-6)
+    }
+
+    /// Returns camera's depth of field, if it's enabled and applicable - the
+    /// thin-lens model needs an eye looking in one direction, so parallel
+    /// projections and panoramas always render everything in focus.
+    pub(crate) fn depth_of_field(&self) -> Option<CameraDepthOfField> {
+        self.depth_of_field
+            .filter(|_| !self.is_orthographic() && self.panorama.is_none())
+    }
+
+    /// Makes sure the camera's configuration is supported, falling back to a
+    /// supported one (and logging a warning) if it's not.
+    ///
+    /// Panoramic cameras can't be rasterized, so they get switched into the
+    /// reference mode.
+    ///
+    /// `older` is the camera's previous configuration, if any - when it's
+    /// already fallen back the same way, the warning is not repeated.
+    pub(crate) fn validate(&mut self, older: Option<&Self>) {
+        if self.panorama.is_some() && self.mode.is_rasterized() {
+            let mode = self.mode;
+
+            self.mode = CameraMode::Reference { depth: 1 };
+
+            let is_repeated = older.map_or(false, |older| {
+                older.panorama == self.panorama && older.mode == self.mode
+            });
+
+            if !is_repeated {
+                warn!(
+                    "Camera `{}`: panoramic cameras support only the \
+                     reference and the BVH heatmap modes (got {:?}), falling \
+                     back to {:?}",
+                    self, mode, self.mode,
+                );
+            }
+        }
+    }
+
+    /// Returns size at which the camera renders its image, before upscaling it
+    /// to [`CameraViewport::size`].
+    ///
+    /// Debug modes that don't go through the rasterizer, and modes that don't
+    /// show colors (AOVs, diagnostics), ignore the internal size and always
+    /// render at the output resolution - there's no temporal pass to upscale
+    /// them.
+    pub(crate) fn render_size(&self) -> UVec2 {
+        if self.mode.is_rasterized() && self.mode.is_physical() {
+            self.viewport.internal_size.unwrap_or(self.viewport.size)
+        } else {
+            self.viewport.size
+        }
+    }
+
+    pub(crate) fn is_upscaled(&self) -> bool {
+        self.render_size() != self.viewport.size
+    }
+
+    /// Returns whether this camera's resolution is adjusted dynamically.
+    ///
+    /// Similarly to [`Self::render_size()`], modes that don't get upscaled
+    /// always render at the full resolution.
+    pub(crate) fn has_dynamic_resolution(&self) -> bool {
+        self.dynamic_resolution.is_some()
+            && self.mode.is_rasterized()
+            && self.mode.is_physical()
+    }
+
+    /// Returns whether this camera's image gets resolved through the temporal
+    /// pass, which performs anti-aliasing and upscaling.
+    ///
+    /// Upscaling relies on the jitter to reconstruct the missing pixels, so
+    /// it implies temporal anti-aliasing.
+    ///
+    /// AOVs and diagnostics are never resolved - blending raw values (or
+    /// per-pixel statistics) across frames would only corrupt them.
+    pub(crate) fn has_taa(&self) -> bool {
+        (self.anti_aliasing == CameraAntiAliasing::Temporal
+            || self.is_upscaled()
+            || self.has_dynamic_resolution())
+            && self.mode.is_rasterized()
+            && self.mode.is_physical()
+    }
+
+    /// Returns whether this camera's image gets bloom applied - debug modes
+    /// don't show colors, so they get none.
+    pub(crate) fn has_bloom(&self) -> bool {
+        self.bloom.is_some() && self.mode.is_physical()
+    }
+
+    /// Returns whether the image has to be sRGB-encoded before being written
+    /// into the viewport, i.e. whether viewport's format is a low-dynamic-range
+    /// one that doesn't get encoded by the GPU itself.
+    ///
+    /// Formats with the `Srgb` suffix get encoded automatically, while float
+    /// formats are assumed to be HDR targets that expect linear colors.
+    pub(crate) fn needs_srgb_encoding(&self) -> bool {
+        matches!(
+            self.viewport.format,
+            wgpu::TextureFormat::Rgba8Unorm
+                | wgpu::TextureFormat::Bgra8Unorm
+                | wgpu::TextureFormat::Rgb10a2Unorm
+                | wgpu::TextureFormat::Rgba16Unorm
+        )
+    }
+
+    /// Returns sub-pixel offset (in pixels, within `-0.5..0.5`) by which the
+    /// projection should be shifted in given frame.
+    ///
+    /// Offsets follow the Halton(2, 3) sequence, which covers the pixel evenly
+    /// over just a couple of frames; when upscaling, the sequence gets longer
+    /// so that each of the output pixels gets covered as well.
+    ///
+    /// `render_size` is the size the camera actually renders at in this frame,
+    /// which - with dynamic resolution - can be smaller than
+    /// [`Self::render_size()`].
+    pub(crate) fn jitter(&self, frame: gpu::Frame, render_size: UVec2) -> Vec2 {
+        const SAMPLES: u32 = 8;
+
+        fn halton(mut idx: u32, base: u32) -> f32 {
+            let mut result = 0.0;
+            let mut fraction = 1.0;
+
+            while idx > 0 {
+                fraction /= base as f32;
+                result += fraction * (idx % base) as f32;
+                idx /= base;
+            }
+
+            result
+        }
+
+        if !self.has_taa() {
+            return Vec2::ZERO;
+        }
+
+        let samples = {
+            let ratio = self.viewport.size.as_vec2() / render_size.as_vec2();
+
+            ((SAMPLES as f32) * ratio.x * ratio.y)
+                .ceil()
+                .clamp(SAMPLES as f32, 64.0) as u32
+        };
+
+        let idx = frame.get() % samples + 1;
+
+        vec2(halton(idx, 2), halton(idx, 3)) - 0.5
     }
 }
 
@@ -100,8 +402,61 @@ pub enum CameraMode {
     /// Shows BVH tree's heatmap
     BvhHeatmap,
 
-    /// Shows a path-traced reference image; slow
+    /// Shows a path-traced reference image; slow.
+    ///
+    /// `depth` is the maximum number of bounces - paths that carry little
+    /// energy get terminated earlier through Russian roulette, so increasing
+    /// it is relatively cheap.
+    ///
+    /// See: [`Camera::reference_samples`].
     Reference { depth: u8 },
+
+    /// Shows distance from the camera to each pixel's surface.
+    ///
+    /// Read back as `(distance, distance, distance, 1.0)`, with zero for the
+    /// sky.
+    Depth,
+
+    /// Shows surfaces' world-space normals.
+    ///
+    /// Read back as `(x, y, z, 1.0)`, with zero for the sky.
+    Normals,
+
+    /// Shows surfaces' base color (aka albedo).
+    ///
+    /// Read back as `(r, g, b, a)`.
+    BaseColor,
+
+    /// Shows surfaces' roughness (red) and metalness (green).
+    ///
+    /// Read back as `(roughness, metallic, 0.0, 1.0)`.
+    RoughnessMetallic,
+
+    /// Shows surfaces' emissive color.
+    ///
+    /// Read back as `(r, g, b, 1.0)`.
+    Emissive,
+
+    /// Shows motion vectors, i.e. how far each pixel has moved since the
+    /// previous frame.
+    ///
+    /// Read back as `(dx, dy, 0.0, 1.0)`, in pixels.
+    MotionVectors,
+
+    /// Shows the reprojection map, i.e. whether each pixel's previous position
+    /// has been found - green is the confidence, red marks the pixels that
+    /// couldn't be reprojected.
+    ///
+    /// Read back as `(confidence, validity, is_valid, 1.0)`, where `validity`
+    /// is the bitmask of valid bilinear taps (divided by 15) and `is_valid` is
+    /// either zero or one.
+    Reprojection,
+
+    /// Shows one of the diagnostics, useful for debugging ReSTIR and the
+    /// denoiser.
+    ///
+    /// See: [`CameraDiagnostic`], [`Camera::diagnostic_max`].
+    Diagnostic(CameraDiagnostic),
 }
 
 impl CameraMode {
@@ -114,15 +469,54 @@ impl CameraMode {
             CameraMode::GiSpecular { .. } => 4,
             CameraMode::BvhHeatmap => 5,
             CameraMode::Reference { .. } => 6,
+            CameraMode::Depth => 7,
+            CameraMode::Normals => 8,
+            CameraMode::BaseColor => 9,
+            CameraMode::RoughnessMetallic => 10,
+            CameraMode::Emissive => 11,
+            CameraMode::MotionVectors => 12,
+            CameraMode::Reprojection => 13,
+            CameraMode::Diagnostic(_) => 14,
         }
     }
 
+    /// Returns whether this mode shows colors, i.e. whether its image should
+    /// get exposed, tonemapped and post-processed.
+    pub(crate) fn is_physical(&self) -> bool {
+        !matches!(self, Self::BvhHeatmap | Self::Diagnostic(_))
+            && !self.is_aov()
+    }
+
+    /// Returns whether this mode shows one of the arbitrary output variables
+    /// (AOVs) - i.e. raw surface data, which can be read back through
+    /// [`crate::Engine::camera_aov()`].
+    ///
+    /// Those values aren't colors, so they don't get exposed, tonemapped nor
+    /// post-processed.
+    pub fn is_aov(&self) -> bool {
+        matches!(
+            self,
+            Self::Depth
+                | Self::Normals
+                | Self::BaseColor
+                | Self::RoughnessMetallic
+                | Self::Emissive
+                | Self::MotionVectors
+                | Self::Reprojection
+        )
+    }
+
+    pub(crate) fn is_rasterized(&self) -> bool {
+        !matches!(self, Self::BvhHeatmap | Self::Reference { .. })
+    }
+
     pub(crate) fn needs_di(&self) -> bool {
         matches!(
             self,
             Self::Image { .. }
                 | Self::DiDiffuse { .. }
                 | Self::DiSpecular { .. }
+                | Self::Diagnostic(_)
         )
     }
 
@@ -132,6 +526,7 @@ impl CameraMode {
             Self::Image { .. }
                 | Self::GiDiffuse { .. }
                 | Self::GiSpecular { .. }
+                | Self::Diagnostic(_)
         )
     }
 
@@ -143,24 +538,134 @@ impl CameraMode {
                 | Self::DiSpecular { denoise: true }
                 | Self::GiDiffuse { denoise: true }
                 | Self::GiSpecular { denoise: true }
+                | Self::Diagnostic(_)
         )
     }
 
     pub(crate) fn denoise_di_diff(&self) -> bool {
         matches!(
             self,
-            Self::Image { denoise: true } | Self::DiDiffuse { denoise: true }
+            Self::Image { denoise: true }
+                | Self::DiDiffuse { denoise: true }
+                | Self::Diagnostic(_)
         )
     }
 
     pub(crate) fn denoise_gi_diff(&self) -> bool {
         matches!(
             self,
-            Self::Image { denoise: true } | Self::GiDiffuse { denoise: true }
+            Self::Image { denoise: true }
+                | Self::GiDiffuse { denoise: true }
+                | Self::Diagnostic(_)
         )
     }
 }
 
+/// Value visualized by [`CameraMode::Diagnostic`].
+///
+/// Values are mapped onto a gradient going from blue (zero) through green to
+/// red ([`Camera::diagnostic_max`]) - a bar with this gradient is drawn in the
+/// bottom-left corner of the image, and [`Self::legend()`] describes what it
+/// corresponds to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CameraDiagnostic {
+    /// Number of samples (`M`) accumulated in the direct lighting's reservoir
+    DiReservoirM,
+
+    /// Weight (`W`) of the direct lighting's reservoir
+    DiReservoirW,
+
+    /// Confidence of the direct lighting's sample, which drops to zero when
+    /// the sample's visibility changes
+    DiConfidence,
+
+    /// Number of samples (`M`) accumulated in the indirect lighting's
+    /// reservoir
+    GiReservoirM,
+
+    /// Weight (`W`) of the indirect lighting's reservoir
+    GiReservoirW,
+
+    /// Confidence of the indirect lighting's sample, which drops when the
+    /// sample gets invalidated
+    GiConfidence,
+
+    /// Number of frames accumulated by the direct lighting's denoiser
+    DiHistory,
+
+    /// Number of frames accumulated by the indirect lighting's denoiser
+    GiHistory,
+
+    /// How often each pixel fails to get reprojected, averaged over time
+    ReprojectionRejection,
+
+    /// Variance of the direct lighting's luminance, as estimated by the
+    /// denoiser
+    DiVariance,
+
+    /// Variance of the indirect lighting's luminance, as estimated by the
+    /// denoiser
+    GiVariance,
+
+    /// Number of BVH nodes visited by the shadow ray cast towards the direct
+    /// lighting's sample
+    ShadowRayBvhNodes,
+}
+
+impl CameraDiagnostic {
+    pub(crate) fn serialize(&self) -> u32 {
+        match self {
+            CameraDiagnostic::DiReservoirM => 0,
+            CameraDiagnostic::DiReservoirW => 1,
+            CameraDiagnostic::DiConfidence => 2,
+            CameraDiagnostic::GiReservoirM => 3,
+            CameraDiagnostic::GiReservoirW => 4,
+            CameraDiagnostic::GiConfidence => 5,
+            CameraDiagnostic::DiHistory => 6,
+            CameraDiagnostic::GiHistory => 7,
+            CameraDiagnostic::ReprojectionRejection => 8,
+            CameraDiagnostic::DiVariance => 9,
+            CameraDiagnostic::GiVariance => 10,
+            CameraDiagnostic::ShadowRayBvhNodes => 11,
+        }
+    }
+
+    /// Returns description of what the gradient corresponds to.
+    pub fn legend(&self) -> CameraDiagnosticLegend {
+        let (label, max) = match self {
+            CameraDiagnostic::DiReservoirM => ("DI reservoir's M", 32.0),
+            CameraDiagnostic::DiReservoirW => ("DI reservoir's W", 1.0),
+            CameraDiagnostic::DiConfidence => ("DI confidence", 1.0),
+            CameraDiagnostic::GiReservoirM => ("GI reservoir's M", 32.0),
+            CameraDiagnostic::GiReservoirW => ("GI reservoir's W", 1.0),
+            CameraDiagnostic::GiConfidence => ("GI confidence", 1.0),
+            CameraDiagnostic::DiHistory => ("DI history length (frames)", 16.0),
+            CameraDiagnostic::GiHistory => ("GI history length (frames)", 16.0),
+            CameraDiagnostic::ReprojectionRejection => {
+                ("Reprojection rejection rate", 1.0)
+            }
+            CameraDiagnostic::DiVariance => ("DI luminance variance", 0.1),
+            CameraDiagnostic::GiVariance => ("GI luminance variance", 0.1),
+            CameraDiagnostic::ShadowRayBvhNodes => {
+                ("BVH nodes visited by shadow rays", 256.0)
+            }
+        };
+
+        CameraDiagnosticLegend { label, max }
+    }
+}
+
+/// See: [`CameraDiagnostic::legend()`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CameraDiagnosticLegend {
+    /// Human-readable name of the value
+    pub label: &'static str,
+
+    /// Value mapped to the end of the gradient, unless overridden through
+    /// [`Camera::diagnostic_max`]; the gradient always starts at zero
+    pub max: f32,
+}
+
 impl Default for CameraMode {
     fn default() -> Self {
         Self::Image { denoise: true }
@@ -172,6 +677,13 @@ pub struct CameraViewport {
     pub format: wgpu::TextureFormat,
     pub size: UVec2,
     pub position: UVec2,
+
+    /// Size at which the image gets rendered; when smaller than `size`, the
+    /// image gets temporally upscaled (which is considerably faster than
+    /// rendering at the full resolution).
+    ///
+    /// Defaults to `None`, i.e. rendering at `size`.
+    pub internal_size: Option<UVec2>,
 }
 
 impl Default for CameraViewport {
@@ -180,6 +692,7 @@ impl Default for CameraViewport {
             format: wgpu::TextureFormat::Rgba8UnormSrgb,
             size: uvec2(512, 512),
             position: uvec2(0, 0),
+            internal_size: None,
         }
     }
 }
@@ -189,6 +702,424 @@ pub struct CameraBackground {
     pub color: Vec3,
 }
 
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CameraAntiAliasing {
+    /// No anti-aliasing, default
+    #[default]
+    None,
+
+    /// Temporal anti-aliasing - projection gets slightly shifted each frame
+    /// and the frames are accumulated, with history clamped to the
+    /// neighbourhood of each pixel so that moving edges don't leave trails.
+    ///
+    /// Applied only in the rasterized modes, since [`CameraMode::Reference`]
+    /// and [`CameraMode::BvhHeatmap`] don't need it.
+    Temporal,
+}
+
+/// Configuration of the dynamic resolution, which adjusts the resolution the
+/// camera renders at so that rendering takes approximately the target time.
+///
+/// Camera renders into a sub-rectangle of buffers allocated for the full
+/// resolution and then temporally upscales the image, so the resolution can
+/// change from frame to frame without any hitches.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CameraDynamicResolution {
+    /// How much time the GPU should spend on rendering this camera.
+    ///
+    /// If the GPU doesn't support timestamp queries, this is compared against
+    /// the entire frame's time instead.
+    pub target_time: Duration,
+
+    /// Smallest allowed scale of the render size, e.g. `0.5` means that the
+    /// camera can go down to half of the resolution on each axis.
+    pub min_scale: f32,
+
+    /// Largest allowed scale of the render size, up to `1.0`.
+    pub max_scale: f32,
+}
+
+impl Default for CameraDynamicResolution {
+    fn default() -> Self {
+        Self {
+            target_time: Duration::from_millis(10),
+            min_scale: 0.5,
+            max_scale: 1.0,
+        }
+    }
+}
+
+/// Exposure the camera's image gets scaled by before it's displayed.
+///
+/// Exposure is expressed in EVs (exposure values), where each +1 EV halves the
+/// image's brightness; zero means no scaling at all.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CameraExposure {
+    /// Constant exposure, default
+    Manual { ev: f32 },
+
+    /// Exposure adjusted automatically to the scene's brightness, see:
+    /// [`CameraAutoExposure`].
+    Auto(CameraAutoExposure),
+}
+
+impl Default for CameraExposure {
+    fn default() -> Self {
+        Self::Manual { ev: 0.0 }
+    }
+}
+
+/// Configuration of the auto-exposure (aka eye adaptation), which meters the
+/// average luminance of the rendered image and picks an exposure that maps it
+/// onto middle-gray.
+///
+/// Metering is based on a luminance histogram computed over the composed
+/// image, with the darkest and the brightest pixels (e.g. the sun) ignored.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CameraAutoExposure {
+    /// Smallest exposure the camera can go down to, i.e. how much the darkest
+    /// scenes can get brightened.
+    pub min_ev: f32,
+
+    /// Largest exposure the camera can go up to, i.e. how much the brightest
+    /// scenes can get darkened.
+    pub max_ev: f32,
+
+    /// How fast (in EVs per second, roughly) the exposure adapts when the
+    /// scene gets brighter.
+    pub speed_up: f32,
+
+    /// How fast (in EVs per second, roughly) the exposure adapts when the
+    /// scene gets darker; usually slower than [`Self::speed_up`], similarly to
+    /// how it takes a while for human eyes to adjust to darkness.
+    pub speed_down: f32,
+
+    /// Exposure compensation added on top of the metered exposure; positive
+    /// values make the image brighter.
+    pub compensation: f32,
+}
+
+impl Default for CameraAutoExposure {
+    fn default() -> Self {
+        Self {
+            min_ev: -8.0,
+            max_ev: 8.0,
+            speed_up: 3.0,
+            speed_down: 1.0,
+            compensation: 0.0,
+        }
+    }
+}
+
+/// Operator that maps the camera's (linear, HDR) image into the displayable
+/// range.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum CameraTonemapping {
+    /// No tonemapping, default - the image is written as-is, which is useful
+    /// when rendering into an HDR target that gets tonemapped later (e.g. by
+    /// Bevy's tonemapping node)
+    #[default]
+    None,
+
+    /// Reinhard operator, applied on the luminance; simple, but tends to make
+    /// the image look washed out
+    Reinhard,
+
+    /// Fitted ACES operator; contrasty, with the highlights getting shifted
+    /// towards white
+    Aces,
+
+    /// AgX operator; similar to ACES, but with better hue preservation
+    AgX,
+
+    /// Custom lookup table, e.g. Tony McMapface
+    Lut(CameraTonemappingLut),
+}
+
+impl CameraTonemapping {
+    pub(crate) fn serialize(&self) -> u32 {
+        match self {
+            CameraTonemapping::None => 0,
+            CameraTonemapping::Reinhard => 1,
+            CameraTonemapping::Aces => 2,
+            CameraTonemapping::AgX => 3,
+            CameraTonemapping::Lut(_) => 4,
+        }
+    }
+
+    pub(crate) fn lut(&self) -> Option<&CameraTonemappingLut> {
+        if let CameraTonemapping::Lut(lut) = self {
+            Some(lut)
+        } else {
+            None
+        }
+    }
+}
+
+/// Three-dimensional lookup table used for tonemapping.
+///
+/// Following the convention used by e.g. Tony McMapface, the table is indexed
+/// by `color / (color + 1)` (so that it covers the entire HDR range) and it
+/// should yield linear colors.
+///
+/// Lookup tables are compared by their identity and not by their contents, so
+/// cloning a lookup table is cheap and updating a camera with the same lookup
+/// table doesn't re-upload it.
+#[derive(Clone, Derivative)]
+#[derivative(Debug)]
+pub struct CameraTonemappingLut {
+    size: u32,
+    #[derivative(Debug = "ignore")]
+    data: Arc<Vec<Vec4>>,
+}
+
+impl CameraTonemappingLut {
+    /// Creates a lookup table of given size (along each of the axes), with
+    /// the colors laid out red-first, i.e. `data[r + g * size + b * size^2]`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `data` doesn't contain exactly `size^3` colors.
+    pub fn new(size: u32, data: impl IntoIterator<Item = Vec3>) -> Self {
+        let data: Vec<_> =
+            data.into_iter().map(|color| color.extend(1.0)).collect();
+
+        assert!(size >= 2, "lookup table must be at least 2x2x2");
+
+        assert_eq!(
+            data.len(),
+            (size as usize).pow(3),
+            "lookup table must contain exactly size^3 colors",
+        );
+
+        Self {
+            size,
+            data: Arc::new(data),
+        }
+    }
+
+    pub(crate) fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Returns the lookup table's data laid out as a 2D texture, with the blue
+    /// slices placed next to each other horizontally.
+    pub(crate) fn texture_size(&self) -> UVec2 {
+        uvec2(self.size * self.size, self.size)
+    }
+
+    /// See: [`Self::texture_size()`].
+    pub(crate) fn texture_data(&self) -> Vec<Vec4> {
+        let size = self.size as usize;
+        let mut texture = Vec::with_capacity(self.data.len());
+
+        for g in 0..size {
+            for b in 0..size {
+                for r in 0..size {
+                    texture.push(self.data[r + g * size + b * size * size]);
+                }
+            }
+        }
+
+        texture
+    }
+}
+
+impl PartialEq for CameraTonemappingLut {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.data, &other.data)
+    }
+}
+
+/// Configuration of the bloom, which simulates light scattering inside the
+/// camera's lens, making bright areas bleed into their surroundings.
+///
+/// Bloom doesn't use any threshold - all of the pixels contribute to it,
+/// proportionally to their brightness.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CameraBloom {
+    /// How much of the blurred image gets mixed into the original one.
+    pub intensity: f32,
+
+    /// Radius of the upsampling filter (in pixels of each bloom's level); the
+    /// larger, the wider (but blockier) the bloom gets.
+    pub radius: f32,
+}
+
+impl Default for CameraBloom {
+    fn default() -> Self {
+        Self {
+            intensity: 0.04,
+            radius: 1.0,
+        }
+    }
+}
+
+/// Panoramic projection, see: [`Camera::panorama`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CameraPanorama {
+    /// Equirectangular image, where `x` goes around the horizon and `y` goes
+    /// from the zenith (+Y) down to the nadir (-Y); the viewport should be
+    /// twice as wide as it's tall.
+    ///
+    /// The image can be passed into [`crate::EnvironmentMap::equirect()`].
+    Equirect,
+
+    /// Six cubemap faces laid out side by side, in the usual order of +X, -X,
+    /// +Y, -Y, +Z, -Z; the viewport should be six times as wide as it's tall.
+    ///
+    /// The faces can be passed into [`crate::EnvironmentMap::cubemap()`] or
+    /// uploaded as layers of a cube texture.
+    Cubemap,
+}
+
+/// Configuration of the depth of field, which simulates a thin lens that
+/// keeps only objects lying at the focus distance perfectly sharp.
+///
+/// In the reference mode the lens is sampled directly (which makes it converge
+/// into the ground truth), while the other modes approximate it by blurring
+/// the final image according to the depth.
+///
+/// Orthographic (and other parallel) projections and panoramas don't have a
+/// lens, so they ignore this setting.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CameraDepthOfField {
+    /// Aperture's f-number; the smaller, the blurrier the out-of-focus areas
+    /// get.
+    pub f_stop: f32,
+
+    /// Distance (along the camera's view axis, in world units) at which
+    /// objects are in perfect focus.
+    pub focus_distance: f32,
+
+    /// Height of the camera's sensor, in world units; together with the
+    /// projection's field of view, this determines the lens' focal length.
+    ///
+    /// Defaults to 24 mm, i.e. the full-frame sensor (assuming world units are
+    /// meters).
+    pub sensor_height: f32,
+}
+
+impl Default for CameraDepthOfField {
+    fn default() -> Self {
+        Self {
+            f_stop: 2.8,
+            focus_distance: 10.0,
+            sensor_height: 0.024,
+        }
+    }
+}
+
+/// Configuration of the motion blur, which smears moving objects along their
+/// screen-space velocity.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CameraMotionBlur {
+    /// Angle (in degrees, `0.0..=360.0`) of the rotary shutter, i.e. which
+    /// part of the frame's time the shutter stays open for; 180° matches the
+    /// usual look of a film camera.
+    pub shutter_angle: f32,
+}
+
+impl Default for CameraMotionBlur {
+    fn default() -> Self {
+        Self {
+            shutter_angle: 180.0,
+        }
+    }
+}
+
+/// Arbitrary output variable (AOV) read back from the GPU.
+///
+/// See: [`CameraMode::is_aov()`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct CameraAov {
+    /// Mode the AOV has been rendered with; it determines what the values
+    /// mean.
+    pub mode: CameraMode,
+
+    /// Size of the image, which - with internal resolution or dynamic
+    /// resolution - can be smaller than the viewport's size.
+    pub size: UVec2,
+
+    /// Values, row by row.
+    pub data: Vec<Vec4>,
+}
+
+impl CameraAov {
+    /// Returns value of given pixel.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the position lies outside of the image.
+    pub fn get(&self, pos: UVec2) -> Vec4 {
+        assert!(pos.x < self.size.x && pos.y < self.size.y);
+
+        self.data[(pos.y * self.size.x + pos.x) as usize]
+    }
+}
+
+/// Progress of [`CameraMode::Reference`].
+///
+/// The accumulation starts over whenever the camera moves or anything in the
+/// scene (instance, material, light, sun etc.) changes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CameraReference {
+    /// Number of samples per pixel accumulated so far, including the ones
+    /// submitted for the upcoming frame.
+    pub samples: u32,
+
+    /// See: [`Camera::reference_samples`].
+    pub target_samples: Option<u32>,
+
+    /// Most recently read per-pixel variance, or `None` if it's not known yet.
+    ///
+    /// Similarly to [`CameraAov`], the data is read back from the GPU with a
+    /// delay of a couple of frames - use [`CameraReferenceVariance::samples`]
+    /// to check how up-to-date it is.
+    pub variance: Option<Arc<CameraReferenceVariance>>,
+}
+
+impl CameraReference {
+    /// Returns whether the target number of samples has been reached, i.e.
+    /// whether the image has converged and the camera is no longer tracing new
+    /// rays.
+    ///
+    /// This is always `false` when [`Self::target_samples`] is not set.
+    pub fn is_done(&self) -> bool {
+        self.target_samples
+            .is_some_and(|target_samples| self.samples >= target_samples)
+    }
+}
+
+/// Per-pixel variance of the luminance accumulated by
+/// [`CameraMode::Reference`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct CameraReferenceVariance {
+    /// Number of samples per pixel the variance has been estimated from.
+    pub samples: u32,
+
+    /// Size of the image.
+    pub size: UVec2,
+
+    /// Variances of the samples' luminance, row by row; dividing a value by
+    /// [`Self::samples`] yields the variance of the pixel's mean, i.e. of the
+    /// pixel in the rendered image.
+    pub data: Vec<f32>,
+}
+
+impl CameraReferenceVariance {
+    /// Returns variance of given pixel.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the position lies outside of the image.
+    pub fn get(&self, pos: UVec2) -> f32 {
+        assert!(pos.x < self.size.x && pos.y < self.size.y);
+
+        self.data[(pos.y * self.size.x + pos.x) as usize]
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct CameraHandle(usize);
 
@@ -197,3 +1128,48 @@ impl CameraHandle {
         Self(id)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn jitter() {
+        let mut camera = Camera::default();
+
+        let size = camera.render_size();
+
+        assert_eq!(Vec2::ZERO, camera.jitter(gpu::Frame::new(1), size));
+
+        camera.anti_aliasing = CameraAntiAliasing::Temporal;
+
+        let jitters: Vec<_> = (0..8)
+            .map(|n| camera.jitter(gpu::Frame::new(n), size))
+            .collect();
+
+        assert!(jitters[0].abs_diff_eq(vec2(0.0, -1.0 / 6.0), 1e-6));
+        assert!(jitters[1].abs_diff_eq(vec2(-0.25, 1.0 / 6.0), 1e-6));
+        assert_eq!(jitters[0], camera.jitter(gpu::Frame::new(8), size));
+
+        for jitter in jitters {
+            assert!(jitter.x.abs() < 0.5 && jitter.y.abs() < 0.5);
+        }
+    }
+
+    #[test]
+    fn validate() {
+        let mut camera = Camera {
+            panorama: Some(CameraPanorama::Equirect),
+            ..Default::default()
+        };
+
+        camera.validate(None);
+
+        assert_eq!(CameraMode::Reference { depth: 1 }, camera.mode);
+
+        camera.mode = CameraMode::BvhHeatmap;
+        camera.validate(None);
+
+        assert_eq!(CameraMode::BvhHeatmap, camera.mode);
+    }
+}
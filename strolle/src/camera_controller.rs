@@ -1,16 +1,32 @@
+mod aov;
 mod buffers;
+mod dynamic_resolution;
+mod export;
+mod exposure;
 mod pass;
 mod passes;
+mod reference;
 
+use std::mem;
 use std::ops::DerefMut;
+use std::sync::Arc;
 
 use log::{debug, info};
 use rand::Rng;
+use spirv_std::glam::UVec2;
 
+pub use self::aov::*;
 pub use self::buffers::*;
+pub use self::dynamic_resolution::*;
+pub use self::export::*;
+pub use self::exposure::*;
 pub use self::pass::*;
 pub use self::passes::*;
-use crate::{gpu, Camera, CameraMode, Engine, Params};
+pub use self::reference::*;
+use crate::{
+    gpu, Camera, CameraAov, CameraExposure, CameraMode, CameraReference,
+    Engine, Params,
+};
 
 #[derive(Debug)]
 pub struct CameraController {
@@ -18,20 +34,29 @@ pub struct CameraController {
     buffers: CameraBuffers,
     passes: CameraPasses,
     frame: gpu::Frame,
+    dynamic_resolution: DynamicResolution,
+    exposure: Exposure,
+    aov: Aov,
+    reference: Reference,
+    has_dirty_tonemapping_lut: bool,
 }
 
 impl CameraController {
     pub(crate) fn new<P>(
         engine: &Engine<P>,
         device: &wgpu::Device,
-        camera: Camera,
+        mut camera: Camera,
     ) -> Self
     where
         P: Params,
     {
         info!("Creating camera `{}`", camera);
 
+        camera.validate(None);
+
         let buffers = CameraBuffers::new(device, &camera);
+        let aov = Aov::new(device, &camera);
+        let reference = Reference::new(device, &camera);
         let passes = CameraPasses::new(engine, device, &camera, &buffers);
 
         Self {
@@ -39,6 +64,11 @@ impl CameraController {
             buffers,
             passes,
             frame: Default::default(),
+            dynamic_resolution: DynamicResolution::new(device),
+            exposure: Exposure::new(device),
+            aov,
+            reference,
+            has_dirty_tonemapping_lut: true,
         }
     }
 
@@ -46,11 +76,14 @@ impl CameraController {
         &mut self,
         engine: &Engine<P>,
         device: &wgpu::Device,
-        camera: Camera,
+        mut camera: Camera,
     ) where
         P: Params,
     {
-        let is_invalidated = self.camera.is_invalidated_by(&camera);
+        camera.validate(Some(&self.camera));
+
+        let is_invalidated = self.camera.is_invalidated_by(&camera)
+            || !self.buffers.fits(&camera);
 
         self.camera = camera;
         *self.buffers.prev_camera.deref_mut() = *self.buffers.curr_camera;
@@ -66,6 +99,10 @@ impl CameraController {
         debug!("Rebuilding buffers for camera `{}`", self.camera);
 
         self.buffers = CameraBuffers::new(device, &self.camera);
+        self.exposure.invalidate();
+        self.aov = Aov::new(device, &self.camera);
+        self.reference = Reference::new(device, &self.camera);
+        self.has_dirty_tonemapping_lut = true;
     }
 
     fn rebuild_passes<P>(&mut self, engine: &Engine<P>, device: &wgpu::Device)
@@ -78,10 +115,76 @@ impl CameraController {
             CameraPasses::new(engine, device, &self.camera, &self.buffers);
     }
 
-    pub fn flush(&mut self, frame: gpu::Frame, queue: &wgpu::Queue) {
+    pub fn flush(
+        &mut self,
+        frame: gpu::Frame,
+        base_exposure: f32,
+        queue: &wgpu::Queue,
+    ) {
         self.frame = frame;
+
+        self.dynamic_resolution.flush(&self.camera, queue);
+
+        self.exposure
+            .flush(&self.camera, base_exposure, &self.buffers, queue);
+
+        self.aov.flush(&self.camera, self.active_size());
+
+        self.reference
+            .flush(&self.camera, &self.buffers, self.active_size());
+
+        // Jitter and active size change every frame, even if the camera itself
+        // stays still
+        {
+            let active_size = self.active_size();
+            let jitter = self.camera.jitter(frame, active_size);
+            let camera = &mut *self.buffers.curr_camera;
+
+            camera.screen =
+                active_size.as_vec2().extend(jitter.x).extend(jitter.y);
+
+            camera.extent = self
+                .buffers
+                .render_size
+                .as_vec2()
+                .extend(Default::default())
+                .extend(Default::default());
+        }
+
         self.buffers.curr_camera.flush(queue);
         self.buffers.prev_camera.flush(queue);
+
+        if mem::take(&mut self.has_dirty_tonemapping_lut) {
+            self.flush_tonemapping_lut(queue);
+        }
+    }
+
+    fn flush_tonemapping_lut(&self, queue: &wgpu::Queue) {
+        let Some(lut) = self.camera.tonemapping.lut() else {
+            return;
+        };
+
+        let size = lut.texture_size();
+
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: self.buffers.tonemapping_lut.tex(),
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            bytemuck::cast_slice(&lut.texture_data()),
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(size.x * 4 * 4),
+                rows_per_image: None,
+            },
+            wgpu::Extent3d {
+                width: size.x,
+                height: size.y,
+                depth_or_array_layers: 1,
+            },
+        );
     }
 
     pub fn render<P>(
@@ -91,29 +194,62 @@ impl CameraController {
         view: &wgpu::TextureView,
     ) where
         P: Params,
+    {
+        let has_dynamic_resolution = self.camera.has_dynamic_resolution();
+
+        if has_dynamic_resolution {
+            self.dynamic_resolution.begin(encoder);
+        }
+
+        self.render_ex(engine, encoder, view);
+
+        if has_dynamic_resolution {
+            self.dynamic_resolution.end(encoder);
+        }
+    }
+
+    fn render_ex<P>(
+        &self,
+        engine: &Engine<P>,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+    ) where
+        P: Params,
     {
         match self.camera.mode {
             CameraMode::BvhHeatmap => {
                 self.passes.bvh_heatmap.run(self, encoder);
-                self.passes.frame_composition.run(self, encoder, view);
+                self.render_output(engine, encoder, view);
             }
 
-            CameraMode::Reference { depth } => {
-                self.passes.atmosphere.run(engine, self, encoder);
+            CameraMode::Reference { depth: max_depth } => {
+                if self.reference.is_tracing() {
+                    for depth in 0..=max_depth {
+                        self.passes
+                            .ref_tracing
+                            .run(self, encoder, depth, max_depth);
 
-                for depth in 0..=depth {
-                    self.passes.ref_tracing.run(self, encoder, depth);
-                    self.passes.ref_shading.run(self, encoder, depth);
+                        self.passes
+                            .ref_shading
+                            .run(self, encoder, depth, max_depth);
+                    }
+
+                    self.passes.ref_shading.run(
+                        self,
+                        encoder,
+                        u8::MAX,
+                        max_depth,
+                    );
                 }
 
-                self.passes.ref_shading.run(self, encoder, u8::MAX);
-                self.passes.frame_composition.run(self, encoder, view);
+                self.reference.end(encoder, &self.buffers);
+
+                self.render_output(engine, encoder, view);
             }
 
             _ => {
                 let has_any_objects = !engine.instances.is_empty();
 
-                self.passes.atmosphere.run(engine, self, encoder);
                 self.passes.prim_raster.run(engine, self, encoder);
 
                 if has_any_objects {
@@ -168,11 +304,69 @@ impl CameraController {
                 }
 
                 self.passes.frame_denoising.run(self, encoder);
-                self.passes.frame_composition.run(self, encoder, view);
+
+                // Fog gets applied only on the final image, there's no point in
+                // computing it for the debug modes
+                if engine.has_fog
+                    && matches!(self.camera.mode, CameraMode::Image { .. })
+                {
+                    self.passes.fog_scattering.run(self, encoder);
+                }
+
+                self.render_output(engine, encoder, view);
             }
         }
     }
 
+    /// Composes the final image, post-processes it and puts it onto `view`.
+    fn render_output<P>(
+        &self,
+        engine: &Engine<P>,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+    ) where
+        P: Params,
+    {
+        self.passes.frame_composition.run(
+            engine,
+            self,
+            encoder,
+            self.buffers.frame_composed.view(),
+        );
+
+        if self.camera.mode.is_aov() {
+            self.aov.end(encoder, &self.buffers);
+        }
+
+        self.passes.frame_diagnostics.run(self, encoder);
+
+        // Depth of field and motion blur rely on the rasterized surfaces and
+        // velocities; the reference mode simulates its lens on its own
+        if self.camera.mode.is_rasterized() && self.camera.mode.is_physical() {
+            if self.camera.depth_of_field().is_some() {
+                self.passes.frame_depth_of_field.run(self, encoder);
+            }
+
+            if self.camera.motion_blur.is_some() {
+                self.passes.frame_motion_blur.run(self, encoder);
+            }
+        }
+
+        if matches!(self.camera.exposure, CameraExposure::Auto(_))
+            && self.camera.mode.is_physical()
+        {
+            self.passes.frame_exposure.run(self, encoder);
+            self.exposure.end(encoder, &self.buffers);
+        }
+
+        if self.camera.has_taa() {
+            self.passes.frame_antialiasing.run(self, encoder);
+        }
+
+        self.passes.frame_bloom.run(self, encoder);
+        self.passes.frame_output.run(self, encoder, view);
+    }
+
     pub fn invalidate<P>(&mut self, engine: &Engine<P>, device: &wgpu::Device)
     where
         P: Params,
@@ -180,6 +374,48 @@ impl CameraController {
         self.rebuild_passes(engine, device);
     }
 
+    pub fn camera(&self) -> &Camera {
+        &self.camera
+    }
+
+    /// Returns the exposure value (in EVs) the camera has most recently
+    /// rendered with.
+    ///
+    /// See: [`Engine::camera_exposure()`].
+    pub fn exposure(&self) -> Option<f32> {
+        self.exposure.ev()
+    }
+
+    /// Returns the most recently read arbitrary output variable, if the camera
+    /// is in one of the AOV modes.
+    ///
+    /// See: [`Engine::camera_aov()`].
+    pub fn aov(&self) -> Option<Arc<CameraAov>> {
+        self.aov.get()
+    }
+
+    /// Returns progress of the reference mode, if the camera is in it.
+    ///
+    /// See: [`Engine::camera_reference()`].
+    pub fn reference(&self) -> Option<CameraReference> {
+        self.reference.get(&self.camera)
+    }
+
+    /// Makes the reference mode start accumulating samples over, because
+    /// something in the scene has changed.
+    pub fn invalidate_reference(&mut self) {
+        self.reference.invalidate();
+    }
+
+    /// Returns size the camera renders at in the current frame.
+    ///
+    /// This is [`Camera::render_size()`], unless dynamic resolution is enabled,
+    /// in which case it's a (possibly) smaller size picked basing on how long
+    /// the previous frames took to render.
+    fn active_size(&self) -> UVec2 {
+        self.dynamic_resolution.active_size(&self.camera)
+    }
+
     /// Returns whether the current frame should use the first or the second
     /// resource when given resource is double-buffered.
     fn is_alternate(&self) -> bool {
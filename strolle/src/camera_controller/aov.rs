@@ -0,0 +1,135 @@
+use std::sync::Arc;
+
+use spirv_std::glam::{UVec2, Vec4};
+
+use crate::{Camera, CameraAov, CameraBuffers, CameraMode, ReadbackBuffer};
+
+/// Reads the arbitrary output variables (AOVs) back to the host.
+///
+/// See: [`crate::CameraMode::is_aov()`].
+#[derive(Debug)]
+pub struct Aov {
+    readback: Option<ReadbackBuffer>,
+
+    /// Mode and size of the image that's being read back (or - when the
+    /// readback buffer is idle - that's going to be read back during the
+    /// upcoming render)
+    pending: Option<(CameraMode, UVec2)>,
+
+    latest: Option<Arc<CameraAov>>,
+}
+
+impl Aov {
+    const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba32Float;
+
+    pub fn new(device: &wgpu::Device, camera: &Camera) -> Self {
+        let readback = camera.mode.is_aov().then(|| {
+            let size = camera.render_size();
+
+            ReadbackBuffer::new(
+                device,
+                "aov_readback",
+                (ReadbackBuffer::padded_bytes_per_row(size.x, Self::FORMAT)
+                    * size.y) as usize,
+            )
+        });
+
+        Self {
+            readback,
+            pending: None,
+            latest: None,
+        }
+    }
+
+    pub fn flush(&mut self, camera: &Camera, active_size: UVec2) {
+        let Some(readback) = &mut self.readback else {
+            return;
+        };
+
+        // (must be called each frame, so that the buffer doesn't get stuck
+        // being mapped)
+        let data = readback.read_with(|bytes| {
+            self.pending.map(|(_, size)| Self::decode(bytes, size))
+        });
+
+        if let (Some(Some(data)), Some((mode, size))) = (data, self.pending) {
+            self.latest = Some(Arc::new(CameraAov { mode, size, data }));
+        }
+
+        if readback.is_idle() {
+            self.pending = Some((camera.mode, active_size));
+        }
+    }
+
+    /// Schedules reading the AOV back to the host; must be called after the
+    /// composition pass.
+    pub fn end(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        buffers: &CameraBuffers,
+    ) {
+        let (Some(readback), Some((_, size))) = (&self.readback, self.pending)
+        else {
+            return;
+        };
+
+        readback.copy_from_texture(encoder, &buffers.frame_composed, size);
+    }
+
+    /// Returns the most recently read AOV.
+    pub fn get(&self) -> Option<Arc<CameraAov>> {
+        self.latest.clone()
+    }
+
+    pub fn decode(bytes: &[u8], size: UVec2) -> Vec<Vec4> {
+        let bytes_per_row =
+            ReadbackBuffer::padded_bytes_per_row(size.x, Self::FORMAT) as usize;
+
+        bytes
+            .chunks(bytes_per_row)
+            .take(size.y as usize)
+            .flat_map(|row| {
+                row.chunks_exact(16)
+                    .take(size.x as usize)
+                    .map(bytemuck::pod_read_unaligned)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use spirv_std::glam::{uvec2, vec4};
+
+    use super::*;
+
+    #[test]
+    fn decode() {
+        let size = uvec2(3, 2);
+
+        let bytes: Vec<_> = (0..2)
+            .flat_map(|y| {
+                let mut row: Vec<_> = (0..3)
+                    .map(|x| vec4(x as f32, y as f32, 0.0, 1.0))
+                    .collect();
+
+                // (rows are padded to 256 bytes)
+                row.resize(16, vec4(-1.0, -1.0, -1.0, -1.0));
+                row
+            })
+            .collect();
+
+        let actual = Aov::decode(bytemuck::cast_slice(&bytes), size);
+
+        let expected = vec![
+            vec4(0.0, 0.0, 0.0, 1.0),
+            vec4(1.0, 0.0, 0.0, 1.0),
+            vec4(2.0, 0.0, 0.0, 1.0),
+            vec4(0.0, 1.0, 0.0, 1.0),
+            vec4(1.0, 1.0, 0.0, 1.0),
+            vec4(2.0, 1.0, 0.0, 1.0),
+        ];
+
+        assert_eq!(expected, actual);
+    }
+}
@@ -1,4 +1,7 @@
+use std::mem;
+
 use log::debug;
+use spirv_std::glam::UVec2;
 
 use crate::{
     gpu, Camera, DoubleBuffered, MappedUniformBuffer, StorageBuffer, Texture,
@@ -6,13 +9,15 @@ use crate::{
 
 #[derive(Debug)]
 pub struct CameraBuffers {
+    /// Size of the internal (i.e. pre-upscaling) buffers
+    pub render_size: UVec2,
+
+    /// Size of the output (i.e. post-upscaling) buffers
+    pub output_size: UVec2,
+
     pub curr_camera: MappedUniformBuffer<gpu::Camera>,
     pub prev_camera: MappedUniformBuffer<gpu::Camera>,
 
-    pub atmosphere_transmittance_lut: Texture,
-    pub atmosphere_scattering_lut: Texture,
-    pub atmosphere_sky_lut: Texture,
-
     pub prim_depth: Texture,
     pub prim_gbuffer_d0: DoubleBuffered<Texture>,
     pub prim_gbuffer_d1: DoubleBuffered<Texture>,
@@ -44,18 +49,33 @@ pub struct CameraBuffers {
 
     pub gi_spec_samples: Texture,
 
+    pub fog_transmittance: Texture,
+    pub fog_scattering: DoubleBuffered<Texture>,
+
+    pub frame_composed: Texture,
+    pub frame_post: Texture,
+    pub diagnostics: Texture,
+    pub exposure: StorageBuffer,
+    pub exposure_histogram: StorageBuffer,
+    pub taa_history: DoubleBuffered<Texture>,
+    pub bloom: Vec<Texture>,
+    pub tonemapping_lut: Texture,
+
     pub ref_hits: StorageBuffer,
     pub ref_rays: StorageBuffer,
     pub ref_colors: Texture,
+    pub ref_moments: Texture,
 }
 
 impl CameraBuffers {
     pub fn new(device: &wgpu::Device, camera: &Camera) -> Self {
+        // Most of the buffers work at the internal resolution, only the
+        // upscaled history lives at the output one
+        let render_size = camera.render_size();
+
         // Returns the size of a screen-space buffer with given parameters
         let viewport_buffer_size = |element_size| {
-            (camera.viewport.size.x as usize)
-                * (camera.viewport.size.y as usize)
-                * element_size
+            (render_size.x as usize) * (render_size.y as usize) * element_size
         };
 
         // ---
@@ -70,36 +90,8 @@ impl CameraBuffers {
 
         // ---------------------------------------------------------------------
 
-        let atmosphere_transmittance_lut =
-            Texture::builder("atmosphere_transmittance_lut")
-                .with_size(gpu::Atmosphere::TRANSMITTANCE_LUT_RESOLUTION)
-                .with_format(wgpu::TextureFormat::Rgba16Float)
-                .with_usage(wgpu::TextureUsages::TEXTURE_BINDING)
-                .with_usage(wgpu::TextureUsages::STORAGE_BINDING)
-                .with_linear_filtering_sampler()
-                .build(device);
-
-        let atmosphere_scattering_lut =
-            Texture::builder("atmosphere_scattering_lut")
-                .with_size(gpu::Atmosphere::SCATTERING_LUT_RESOLUTION)
-                .with_format(wgpu::TextureFormat::Rgba16Float)
-                .with_usage(wgpu::TextureUsages::TEXTURE_BINDING)
-                .with_usage(wgpu::TextureUsages::STORAGE_BINDING)
-                .with_linear_filtering_sampler()
-                .build(device);
-
-        let atmosphere_sky_lut = Texture::builder("atmosphere_sky_lut")
-            .with_size(gpu::Atmosphere::SKY_LUT_RESOLUTION)
-            .with_format(wgpu::TextureFormat::Rgba16Float)
-            .with_usage(wgpu::TextureUsages::TEXTURE_BINDING)
-            .with_usage(wgpu::TextureUsages::STORAGE_BINDING)
-            .with_linear_filtering_sampler()
-            .build(device);
-
-        // ---------------------------------------------------------------------
-
         let prim_depth = Texture::builder("prim_depth")
-            .with_size(camera.viewport.size)
+            .with_size(render_size)
             .with_format(wgpu::TextureFormat::Depth32Float)
             .with_usage(wgpu::TextureUsages::RENDER_ATTACHMENT)
             .build(device);
@@ -107,25 +99,27 @@ impl CameraBuffers {
         let prim_gbuffer_d0 = DoubleBuffered::<Texture>::new(
             device,
             Texture::builder("prim_gbuffer_d0")
-                .with_size(camera.viewport.size)
+                .with_size(render_size)
                 .with_format(wgpu::TextureFormat::Rgba32Float)
                 .with_usage(wgpu::TextureUsages::STORAGE_BINDING)
-                .with_usage(wgpu::TextureUsages::RENDER_ATTACHMENT),
+                .with_usage(wgpu::TextureUsages::RENDER_ATTACHMENT)
+                .with_usage(wgpu::TextureUsages::COPY_SRC),
         );
 
         let prim_gbuffer_d1 = DoubleBuffered::<Texture>::new(
             device,
             Texture::builder("prim_gbuffer_d1")
-                .with_size(camera.viewport.size)
+                .with_size(render_size)
                 .with_format(wgpu::TextureFormat::Rgba32Float)
                 .with_usage(wgpu::TextureUsages::STORAGE_BINDING)
-                .with_usage(wgpu::TextureUsages::RENDER_ATTACHMENT),
+                .with_usage(wgpu::TextureUsages::RENDER_ATTACHMENT)
+                .with_usage(wgpu::TextureUsages::COPY_SRC),
         );
 
         let prim_surface_map = DoubleBuffered::<Texture>::new(
             device,
             Texture::builder("prim_surface_map")
-                .with_size(camera.viewport.size)
+                .with_size(render_size)
                 .with_format(wgpu::TextureFormat::Rgba32Float)
                 .with_usage(wgpu::TextureUsages::STORAGE_BINDING)
                 .with_usage(wgpu::TextureUsages::RENDER_ATTACHMENT),
@@ -134,13 +128,13 @@ impl CameraBuffers {
         // ---------------------------------------------------------------------
 
         let reprojection_map = Texture::builder("reprojection_map")
-            .with_size(camera.viewport.size)
+            .with_size(render_size)
             .with_format(wgpu::TextureFormat::Rgba32Float)
             .with_usage(wgpu::TextureUsages::STORAGE_BINDING)
             .build(device);
 
         let velocity_map = Texture::builder("velocity_map")
-            .with_size(camera.viewport.size)
+            .with_size(render_size)
             .with_format(wgpu::TextureFormat::Rgba32Float)
             .with_usage(wgpu::TextureUsages::STORAGE_BINDING)
             .with_usage(wgpu::TextureUsages::RENDER_ATTACHMENT)
@@ -159,33 +153,35 @@ impl CameraBuffers {
         // ---
 
         let di_diff_samples = Texture::builder("di_diff_samples")
-            .with_size(camera.viewport.size)
+            .with_size(render_size)
             .with_format(wgpu::TextureFormat::Rgba32Float)
             .with_usage(wgpu::TextureUsages::STORAGE_BINDING)
+            .with_usage(wgpu::TextureUsages::COPY_SRC)
             .build(device);
 
         let di_diff_prev_colors = Texture::builder("di_diff_prev_colors")
-            .with_size(camera.viewport.size)
+            .with_size(render_size)
             .with_format(wgpu::TextureFormat::Rgba32Float)
             .with_usage(wgpu::TextureUsages::STORAGE_BINDING)
             .build(device);
 
         let di_diff_curr_colors = Texture::builder("di_diff_curr_colors")
-            .with_size(camera.viewport.size)
+            .with_size(render_size)
             .with_format(wgpu::TextureFormat::Rgba32Float)
             .with_usage(wgpu::TextureUsages::STORAGE_BINDING)
+            .with_usage(wgpu::TextureUsages::COPY_SRC)
             .build(device);
 
         let di_diff_moments = DoubleBuffered::<Texture>::new(
             device,
             Texture::builder("di_diff_moments")
-                .with_size(camera.viewport.size)
+                .with_size(render_size)
                 .with_format(wgpu::TextureFormat::Rgba32Float)
                 .with_usage(wgpu::TextureUsages::STORAGE_BINDING),
         );
 
         let di_diff_stash = Texture::builder("di_diff_stash")
-            .with_size(camera.viewport.size)
+            .with_size(render_size)
             .with_format(wgpu::TextureFormat::Rgba32Float)
             .with_usage(wgpu::TextureUsages::STORAGE_BINDING)
             .build(device);
@@ -193,27 +189,28 @@ impl CameraBuffers {
         // ---
 
         let di_spec_samples = Texture::builder("di_spec_samples")
-            .with_size(camera.viewport.size)
+            .with_size(render_size)
             .with_format(wgpu::TextureFormat::Rgba32Float)
             .with_usage(wgpu::TextureUsages::STORAGE_BINDING)
+            .with_usage(wgpu::TextureUsages::COPY_SRC)
             .build(device);
 
         // ---------------------------------------------------------------------
 
         let gi_d0 = Texture::builder("gi_d0")
-            .with_size(camera.viewport.size)
+            .with_size(render_size)
             .with_format(wgpu::TextureFormat::Rgba32Float)
             .with_usage(wgpu::TextureUsages::STORAGE_BINDING)
             .build(device);
 
         let gi_d1 = Texture::builder("gi_d1")
-            .with_size(camera.viewport.size)
+            .with_size(render_size)
             .with_format(wgpu::TextureFormat::Rgba32Float)
             .with_usage(wgpu::TextureUsages::STORAGE_BINDING)
             .build(device);
 
         let gi_d2 = Texture::builder("gi_d2")
-            .with_size(camera.viewport.size)
+            .with_size(render_size)
             .with_format(wgpu::TextureFormat::Rgba32Float)
             .with_usage(wgpu::TextureUsages::STORAGE_BINDING)
             .build(device);
@@ -229,33 +226,35 @@ impl CameraBuffers {
         // ---
 
         let gi_diff_samples = Texture::builder("gi_diff_samples")
-            .with_size(camera.viewport.size)
+            .with_size(render_size)
             .with_format(wgpu::TextureFormat::Rgba32Float)
             .with_usage(wgpu::TextureUsages::STORAGE_BINDING)
+            .with_usage(wgpu::TextureUsages::COPY_SRC)
             .build(device);
 
         let gi_diff_prev_colors = Texture::builder("gi_diff_prev_colors")
-            .with_size(camera.viewport.size)
+            .with_size(render_size)
             .with_format(wgpu::TextureFormat::Rgba32Float)
             .with_usage(wgpu::TextureUsages::STORAGE_BINDING)
             .build(device);
 
         let gi_diff_curr_colors = Texture::builder("gi_diff_curr_colors")
-            .with_size(camera.viewport.size)
+            .with_size(render_size)
             .with_format(wgpu::TextureFormat::Rgba32Float)
             .with_usage(wgpu::TextureUsages::STORAGE_BINDING)
+            .with_usage(wgpu::TextureUsages::COPY_SRC)
             .build(device);
 
         let gi_diff_moments = DoubleBuffered::<Texture>::new(
             device,
             Texture::builder("gi_diff_moments")
-                .with_size(camera.viewport.size)
+                .with_size(render_size)
                 .with_format(wgpu::TextureFormat::Rgba32Float)
                 .with_usage(wgpu::TextureUsages::STORAGE_BINDING),
         );
 
         let gi_diff_stash = Texture::builder("gi_diff_stash")
-            .with_size(camera.viewport.size)
+            .with_size(render_size)
             .with_format(wgpu::TextureFormat::Rgba32Float)
             .with_usage(wgpu::TextureUsages::STORAGE_BINDING)
             .build(device);
@@ -263,9 +262,118 @@ impl CameraBuffers {
         // ---
 
         let gi_spec_samples = Texture::builder("gi_spec_samples")
-            .with_size(camera.viewport.size)
+            .with_size(render_size)
             .with_format(wgpu::TextureFormat::Rgba32Float)
             .with_usage(wgpu::TextureUsages::STORAGE_BINDING)
+            .with_usage(wgpu::TextureUsages::COPY_SRC)
+            .build(device);
+
+        // ---------------------------------------------------------------------
+
+        let fog_transmittance = Texture::builder("fog_transmittance")
+            .with_size(render_size)
+            .with_format(wgpu::TextureFormat::Rgba32Float)
+            .with_usage(wgpu::TextureUsages::STORAGE_BINDING)
+            .build(device);
+
+        let fog_scattering = DoubleBuffered::<Texture>::new(
+            device,
+            Texture::builder("fog_scattering")
+                .with_size(render_size)
+                .with_format(wgpu::TextureFormat::Rgba32Float)
+                .with_usage(wgpu::TextureUsages::STORAGE_BINDING),
+        );
+
+        // ---------------------------------------------------------------------
+
+        let frame_composed = Texture::builder("frame_composed")
+            .with_size(render_size)
+            .with_format(wgpu::TextureFormat::Rgba32Float)
+            .with_usage(wgpu::TextureUsages::RENDER_ATTACHMENT)
+            .with_usage(wgpu::TextureUsages::STORAGE_BINDING)
+            .with_usage(wgpu::TextureUsages::COPY_SRC)
+            .with_usage(wgpu::TextureUsages::COPY_DST)
+            .build(device);
+
+        // Scratch image for the post-processing passes (depth of field, motion
+        // blur) which, since they can't work in-place, write here and then get
+        // copied back into `frame_composed`
+        //
+        // TODO initialize lazily
+        let frame_post = Texture::builder("frame_post")
+            .with_size(render_size)
+            .with_format(wgpu::TextureFormat::Rgba32Float)
+            .with_usage(wgpu::TextureUsages::STORAGE_BINDING)
+            .with_usage(wgpu::TextureUsages::COPY_SRC)
+            .build(device);
+
+        // Per-pixel statistics tracked by the diagnostic modes (currently only
+        // the reprojection's rejection rate)
+        //
+        // TODO initialize lazily
+        let diagnostics = Texture::builder("diagnostics")
+            .with_size(render_size)
+            .with_format(wgpu::TextureFormat::Rgba32Float)
+            .with_usage(wgpu::TextureUsages::STORAGE_BINDING)
+            .build(device);
+
+        let exposure = StorageBuffer::new(
+            device,
+            "exposure",
+            mem::size_of::<gpu::Exposure>(),
+        );
+
+        // TODO initialize lazily
+        let exposure_histogram = StorageBuffer::new(
+            device,
+            "exposure_histogram",
+            gpu::EXPOSURE_HISTOGRAM_BINS * 4,
+        );
+
+        // TODO initialize lazily
+        let taa_history = DoubleBuffered::<Texture>::new(
+            device,
+            Texture::builder("taa_history")
+                .with_size(camera.viewport.size)
+                .with_format(wgpu::TextureFormat::Rgba32Float)
+                .with_usage(wgpu::TextureUsages::STORAGE_BINDING),
+        );
+
+        // Bloom's chain takes quite a lot of memory, so when the bloom is
+        // disabled, we allocate just a placeholder (the output pass binds the
+        // first level either way)
+        let bloom = if camera.has_bloom() {
+            (0..Self::bloom_levels(camera.viewport.size))
+                .map(|level| {
+                    Texture::builder(format!("bloom_{}", level))
+                        .with_size(Self::bloom_size(
+                            camera.viewport.size,
+                            level,
+                        ))
+                        .with_format(wgpu::TextureFormat::Rgba32Float)
+                        .with_usage(wgpu::TextureUsages::STORAGE_BINDING)
+                        .build(device)
+                })
+                .collect()
+        } else {
+            vec![Texture::builder("bloom_0")
+                .with_size(UVec2::ONE)
+                .with_format(wgpu::TextureFormat::Rgba32Float)
+                .with_usage(wgpu::TextureUsages::STORAGE_BINDING)
+                .build(device)]
+        };
+
+        let tonemapping_lut = Texture::builder("tonemapping_lut")
+            .with_size(
+                camera
+                    .tonemapping
+                    .lut()
+                    .map(|lut| lut.texture_size())
+                    .unwrap_or(UVec2::ONE),
+            )
+            .with_format(wgpu::TextureFormat::Rgba32Float)
+            .with_usage(wgpu::TextureUsages::STORAGE_BINDING)
+            .with_usage(wgpu::TextureUsages::COPY_DST)
             .build(device);
 
         // ---------------------------------------------------------------------
@@ -274,7 +382,7 @@ impl CameraBuffers {
         let ref_rays = StorageBuffer::new(
             device,
             "ref_rays",
-            viewport_buffer_size(3 * 4 * 4),
+            viewport_buffer_size(4 * 4 * 4),
         );
 
         // TODO initialize lazily
@@ -286,21 +394,28 @@ impl CameraBuffers {
 
         // TODO initialize lazily
         let ref_colors = Texture::builder("ref_colors")
-            .with_size(camera.viewport.size)
+            .with_size(render_size)
+            .with_format(wgpu::TextureFormat::Rgba32Float)
+            .with_usage(wgpu::TextureUsages::STORAGE_BINDING)
+            .build(device);
+
+        // TODO initialize lazily
+        let ref_moments = Texture::builder("ref_moments")
+            .with_size(render_size)
             .with_format(wgpu::TextureFormat::Rgba32Float)
             .with_usage(wgpu::TextureUsages::STORAGE_BINDING)
+            .with_usage(wgpu::TextureUsages::COPY_SRC)
             .build(device);
 
         // ---------------------------------------------------------------------
 
         Self {
+            render_size,
+            output_size: camera.viewport.size,
+
             curr_camera: camera_uniform,
             prev_camera,
 
-            atmosphere_transmittance_lut,
-            atmosphere_scattering_lut,
-            atmosphere_sky_lut,
-
             prim_depth,
             prim_gbuffer_d0,
             prim_gbuffer_d1,
@@ -332,9 +447,52 @@ impl CameraBuffers {
 
             gi_spec_samples,
 
+            fog_transmittance,
+            fog_scattering,
+
+            frame_composed,
+            frame_post,
+            diagnostics,
+            exposure,
+            exposure_histogram,
+            taa_history,
+            bloom,
+            tonemapping_lut,
+
             ref_hits,
             ref_rays,
             ref_colors,
+            ref_moments,
         }
     }
+
+    /// Returns number of levels in the bloom's chain, so that the smallest
+    /// level is roughly 8 pixels large.
+    fn bloom_levels(output_size: UVec2) -> u32 {
+        const MAX_LEVELS: u32 = 6;
+
+        output_size
+            .min_element()
+            .max(1)
+            .ilog2()
+            .saturating_sub(3)
+            .clamp(1, MAX_LEVELS)
+    }
+
+    /// Returns size of given bloom's level, for given output size.
+    pub fn bloom_size(output_size: UVec2, level: u32) -> UVec2 {
+        (output_size >> (level + 1)).max(UVec2::ONE)
+    }
+
+    /// Returns whether given camera can render into these buffers, i.e.
+    /// whether they are large enough.
+    pub fn fits(&self, camera: &Camera) -> bool {
+        let render_size = camera.render_size();
+        let output_size = camera.viewport.size;
+
+        render_size.x <= self.render_size.x
+            && render_size.y <= self.render_size.y
+            && output_size.x <= self.output_size.x
+            && output_size.y <= self.output_size.y
+    }
 }
@@ -0,0 +1,182 @@
+use std::time::{Duration, Instant};
+
+use derivative::Derivative;
+use log::trace;
+use spirv_std::glam::UVec2;
+
+use crate::{Camera, ReadbackBuffer};
+
+/// Picks the resolution camera renders at, basing on how long the previous
+/// frames took to render.
+///
+/// See: [`crate::CameraDynamicResolution`].
+#[derive(Debug)]
+pub struct DynamicResolution {
+    scale: f32,
+    timer: Option<GpuTimer>,
+    last_flushed_at: Option<Instant>,
+}
+
+impl DynamicResolution {
+    /// How far towards the ideal scale we move in a single step; the smaller,
+    /// the less the resolution oscillates, but the longer it takes to adapt.
+    const ADAPTATION_SPEED: f32 = 0.25;
+
+    /// Relative difference between the measured and the target time that we
+    /// ignore, so that the resolution doesn't change on every tiny hiccup.
+    const TOLERANCE: f32 = 0.05;
+
+    pub fn new(device: &wgpu::Device) -> Self {
+        Self {
+            scale: 1.0,
+            timer: GpuTimer::new(device),
+            last_flushed_at: None,
+        }
+    }
+
+    /// Returns size camera should render at in the current frame.
+    pub fn active_size(&self, camera: &Camera) -> UVec2 {
+        let render_size = camera.render_size();
+
+        if !camera.has_dynamic_resolution() {
+            return render_size;
+        }
+
+        (render_size.as_vec2() * self.scale)
+            .round()
+            .as_uvec2()
+            .clamp(UVec2::ONE, render_size)
+    }
+
+    pub fn flush(&mut self, camera: &Camera, queue: &wgpu::Queue) {
+        let Some(config) = camera.dynamic_resolution else {
+            return;
+        };
+
+        if !camera.has_dynamic_resolution() {
+            return;
+        }
+
+        let time = if let Some(timer) = &mut self.timer {
+            timer.flush(queue)
+        } else {
+            let now = Instant::now();
+
+            self.last_flushed_at
+                .replace(now)
+                .map(|last_flushed_at| now - last_flushed_at)
+        };
+
+        let min_scale = config.min_scale.clamp(0.01, 1.0);
+        let max_scale = config.max_scale.clamp(min_scale, 1.0);
+
+        if let Some(time) = time {
+            let time = time.as_secs_f32().max(0.0001);
+            let ratio = config.target_time.as_secs_f32() / time;
+
+            if (ratio - 1.0).abs() > Self::TOLERANCE {
+                // Rendering time is roughly proportional to the number of
+                // pixels, i.e. to the square of the scale
+                let ideal_scale = self.scale * ratio.sqrt();
+
+                self.scale +=
+                    (ideal_scale - self.scale) * Self::ADAPTATION_SPEED;
+
+                trace!(
+                    "Dynamic resolution: time={:.2}ms, scale={:.2}",
+                    time * 1000.0,
+                    self.scale,
+                );
+            }
+        }
+
+        self.scale = self.scale.clamp(min_scale, max_scale);
+    }
+
+    pub fn begin(&self, encoder: &mut wgpu::CommandEncoder) {
+        if let Some(timer) = &self.timer {
+            timer.begin(encoder);
+        }
+    }
+
+    pub fn end(&self, encoder: &mut wgpu::CommandEncoder) {
+        if let Some(timer) = &self.timer {
+            timer.end(encoder);
+        }
+    }
+}
+
+/// Measures how long it takes for the GPU to render a camera.
+///
+/// Since reading the timestamps back requires mapping a buffer, measurements
+/// are available with a delay of a couple of frames.
+#[derive(Derivative)]
+#[derivative(Debug)]
+struct GpuTimer {
+    #[derivative(Debug = "ignore")]
+    query_set: wgpu::QuerySet,
+    resolve_buffer: wgpu::Buffer,
+    readback_buffer: ReadbackBuffer,
+}
+
+impl GpuTimer {
+    fn new(device: &wgpu::Device) -> Option<Self> {
+        if !device.features().contains(wgpu::Features::TIMESTAMP_QUERY) {
+            return None;
+        }
+
+        let query_set = device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: Some("strolle_gpu_timer"),
+            ty: wgpu::QueryType::Timestamp,
+            count: 2,
+        });
+
+        let resolve_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("strolle_gpu_timer_resolve"),
+            size: 2 * 8,
+            usage: wgpu::BufferUsages::QUERY_RESOLVE
+                | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+
+        let readback_buffer =
+            ReadbackBuffer::new(device, "gpu_timer_readback", 2 * 8);
+
+        Some(Self {
+            query_set,
+            resolve_buffer,
+            readback_buffer,
+        })
+    }
+
+    fn begin(&self, encoder: &mut wgpu::CommandEncoder) {
+        if self.readback_buffer.is_idle() {
+            encoder.write_timestamp(&self.query_set, 0);
+        }
+    }
+
+    fn end(&self, encoder: &mut wgpu::CommandEncoder) {
+        if !self.readback_buffer.is_idle() {
+            return;
+        }
+
+        encoder.write_timestamp(&self.query_set, 1);
+        encoder.resolve_query_set(
+            &self.query_set,
+            0..2,
+            &self.resolve_buffer,
+            0,
+        );
+
+        self.readback_buffer
+            .copy_from(encoder, &self.resolve_buffer);
+    }
+
+    fn flush(&mut self, queue: &wgpu::Queue) -> Option<Duration> {
+        let [start, end]: [u64; 2] = self.readback_buffer.read()?;
+        let ticks = end.checked_sub(start)?;
+        let nanos = ticks as f64 * queue.get_timestamp_period() as f64;
+
+        Some(Duration::from_nanos(nanos as u64))
+    }
+}
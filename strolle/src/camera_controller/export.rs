@@ -0,0 +1,235 @@
+use spirv_std::glam::{UVec2, Vec3, Vec4};
+
+use crate::{
+    gpu, Aov, CameraController, CameraExport, CameraExportLayer,
+    ReadbackBuffer, Texture,
+};
+
+/// Reads camera's buffers back to the host.
+///
+/// See: [`crate::Engine::export_camera()`].
+#[derive(Debug)]
+pub struct Export {
+    layers: Vec<CameraExportLayer>,
+    colors: Vec<(CameraExportLayer, ReadbackBuffer)>,
+    gbuffer: Option<[ReadbackBuffer; 2]>,
+    size: UVec2,
+}
+
+impl Export {
+    const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba32Float;
+
+    pub fn new(
+        device: &wgpu::Device,
+        camera: &CameraController,
+        layers: &[CameraExportLayer],
+    ) -> Self {
+        let size = camera.camera.render_size();
+
+        let readback = |label: &str| {
+            ReadbackBuffer::new(
+                device,
+                label,
+                (ReadbackBuffer::padded_bytes_per_row(size.x, Self::FORMAT)
+                    * size.y) as usize,
+            )
+        };
+
+        let layers = layers.iter().fold(Vec::new(), |mut layers, &layer| {
+            if !layers.contains(&layer) {
+                layers.push(layer);
+            }
+
+            layers
+        });
+
+        let colors = layers
+            .iter()
+            .filter(|layer| !Self::is_gbuffer(**layer))
+            .map(|&layer| (layer, readback("export_readback")))
+            .collect();
+
+        // Depth, normals and base color all get decoded from the same entries
+        let gbuffer =
+            layers
+                .iter()
+                .any(|layer| Self::is_gbuffer(*layer))
+                .then(|| {
+                    [
+                        readback("export_gbuffer_d0_readback"),
+                        readback("export_gbuffer_d1_readback"),
+                    ]
+                });
+
+        Self {
+            layers,
+            colors,
+            gbuffer,
+            size,
+        }
+    }
+
+    /// Schedules reading the buffers back to the host; must be called after
+    /// the camera has been rendered.
+    pub fn end(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        camera: &CameraController,
+    ) {
+        self.size = camera.active_size();
+
+        for (layer, readback) in &self.colors {
+            readback.copy_from_texture(
+                encoder,
+                Self::texture(camera, *layer),
+                self.size,
+            );
+        }
+
+        if let Some([d0, d1]) = &self.gbuffer {
+            let alternate = camera.is_alternate();
+            let buffers = &camera.buffers;
+
+            d0.copy_from_texture(
+                encoder,
+                buffers.prim_gbuffer_d0.get(alternate),
+                self.size,
+            );
+
+            d1.copy_from_texture(
+                encoder,
+                buffers.prim_gbuffer_d1.get(alternate),
+                self.size,
+            );
+        }
+    }
+
+    /// Waits for the GPU and returns the data; must be called after the
+    /// commands scheduled by [`Self::end()`] have been submitted.
+    pub fn read(mut self, device: &wgpu::Device) -> CameraExport {
+        let readbacks = self
+            .colors
+            .iter_mut()
+            .map(|(_, readback)| readback)
+            .chain(self.gbuffer.iter_mut().flatten());
+
+        // (the first call schedules mapping the buffers, the second one - after
+        // the GPU is done - reads them)
+        for readback in readbacks {
+            readback.read_with(|_| ());
+        }
+
+        device.poll(wgpu::Maintain::Wait);
+
+        let size = self.size;
+
+        let read = |readback: &mut ReadbackBuffer| {
+            readback
+                .read_with(|bytes| Aov::decode(bytes, size))
+                .expect("couldn't read the export back")
+        };
+
+        let mut colors: Vec<_> = self
+            .colors
+            .iter_mut()
+            .map(|(layer, readback)| (*layer, read(readback)))
+            .collect();
+
+        let gbuffer: Vec<_> = self
+            .gbuffer
+            .as_mut()
+            .map(|[d0, d1]| {
+                read(d0)
+                    .into_iter()
+                    .zip(read(d1))
+                    .map(|(d0, d1)| gpu::GBufferEntry::unpack([d0, d1]))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let layers = self
+            .layers
+            .iter()
+            .map(|&layer| {
+                let data = match layer {
+                    CameraExportLayer::Depth => gbuffer
+                        .iter()
+                        .map(|gbuffer| Vec3::splat(gbuffer.depth).extend(1.0))
+                        .collect(),
+
+                    CameraExportLayer::Normals => gbuffer
+                        .iter()
+                        .map(|gbuffer| {
+                            if gbuffer.is_some() {
+                                gbuffer.normal.extend(1.0)
+                            } else {
+                                Vec4::ZERO
+                            }
+                        })
+                        .collect(),
+
+                    CameraExportLayer::BaseColor => gbuffer
+                        .iter()
+                        .map(|gbuffer| gbuffer.base_color)
+                        .collect(),
+
+                    layer => {
+                        let idx = colors
+                            .iter()
+                            .position(|(layer2, _)| *layer2 == layer)
+                            .unwrap();
+
+                        colors.swap_remove(idx).1
+                    }
+                };
+
+                (layer, data)
+            })
+            .collect();
+
+        CameraExport { size, layers }
+    }
+
+    fn is_gbuffer(layer: CameraExportLayer) -> bool {
+        matches!(
+            layer,
+            CameraExportLayer::Depth
+                | CameraExportLayer::Normals
+                | CameraExportLayer::BaseColor
+        )
+    }
+
+    fn texture(
+        camera: &CameraController,
+        layer: CameraExportLayer,
+    ) -> &Texture {
+        let buffers = &camera.buffers;
+        let mode = camera.camera.mode;
+
+        match layer {
+            CameraExportLayer::Color => &buffers.frame_composed,
+
+            CameraExportLayer::DiDiffuse => {
+                if mode.denoise_di_diff() {
+                    &buffers.di_diff_curr_colors
+                } else {
+                    &buffers.di_diff_samples
+                }
+            }
+
+            CameraExportLayer::DiSpecular => &buffers.di_spec_samples,
+
+            CameraExportLayer::GiDiffuse => {
+                if mode.denoise_gi_diff() {
+                    &buffers.gi_diff_curr_colors
+                } else {
+                    &buffers.gi_diff_samples
+                }
+            }
+
+            CameraExportLayer::GiSpecular => &buffers.gi_spec_samples,
+
+            layer => unreachable!("{layer:?} is read from the gbuffer"),
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use std::mem;
+use std::time::Instant;
+
+use crate::{gpu, Camera, CameraBuffers, CameraExposure, ReadbackBuffer};
+
+/// Keeps track of the camera's exposure - uploads it when it's set manually
+/// and reads it back when it's adapted on the GPU.
+///
+/// See: [`crate::CameraExposure`].
+#[derive(Debug)]
+pub struct Exposure {
+    readback: ReadbackBuffer,
+    ev: Option<f32>,
+    delta_time: f32,
+    last_flushed_at: Option<Instant>,
+}
+
+impl Exposure {
+    /// Time (in seconds) we pretend has passed when there's no previous frame
+    /// to compare against, so that a freshly-created camera starts with its
+    /// exposure already adapted instead of fading into it.
+    const INITIAL_DELTA_TIME: f32 = 1000.0;
+
+    pub fn new(device: &wgpu::Device) -> Self {
+        Self {
+            readback: ReadbackBuffer::new(
+                device,
+                "exposure_readback",
+                mem::size_of::<gpu::Exposure>(),
+            ),
+            ev: None,
+            delta_time: Self::INITIAL_DELTA_TIME,
+            last_flushed_at: None,
+        }
+    }
+
+    /// Uploads the manual exposure (if any); `base_exposure` is the multiplier
+    /// manual exposure gets applied on top of, see:
+    /// [`crate::AtmosphereSettings::exposure`].
+    pub fn flush(
+        &mut self,
+        camera: &Camera,
+        base_exposure: f32,
+        buffers: &CameraBuffers,
+        queue: &wgpu::Queue,
+    ) {
+        let now = Instant::now();
+
+        self.delta_time = self
+            .last_flushed_at
+            .replace(now)
+            .map(|last_flushed_at| (now - last_flushed_at).as_secs_f32())
+            .unwrap_or(Self::INITIAL_DELTA_TIME);
+
+        // (must be called each frame, even if we're not interested in the
+        // value, so that the buffer doesn't get stuck being mapped)
+        let adapted = self.readback.read::<gpu::Exposure>();
+
+        match camera.exposure {
+            CameraExposure::Manual { ev } => {
+                let exposure = {
+                    let ev = ev - base_exposure.log2();
+
+                    gpu::Exposure { metered_ev: ev, ev }
+                };
+
+                queue.write_buffer(
+                    buffers.exposure.as_buffer(),
+                    0,
+                    bytemuck::bytes_of(&exposure),
+                );
+
+                self.ev = Some(ev);
+            }
+
+            CameraExposure::Auto(_) => {
+                // (reported in the same terms as the manual exposure, so that
+                // it can be used to switch between both without any jumps)
+                if let Some(adapted) = adapted {
+                    self.ev = Some(adapted.ev + base_exposure.log2());
+                }
+            }
+        }
+    }
+
+    /// Schedules reading the adapted exposure back to the host; must be called
+    /// after the auto-exposure passes.
+    pub fn end(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        buffers: &CameraBuffers,
+    ) {
+        self.readback
+            .copy_from(encoder, buffers.exposure.as_buffer());
+    }
+
+    /// Forgets the timing of the previous frame, so that the exposure gets
+    /// adapted immediately (used after the buffers get reallocated, since the
+    /// adapted exposure is lost then).
+    pub fn invalidate(&mut self) {
+        self.last_flushed_at = None;
+    }
+
+    /// Returns the exposure value (in EVs) the camera has most recently
+    /// rendered with; with auto-exposure, it's delayed by a couple of frames.
+    pub fn ev(&self) -> Option<f32> {
+        self.ev
+    }
+
+    /// Returns time (in seconds) that has passed since the previous frame.
+    pub fn delta_time(&self) -> f32 {
+        self.delta_time
+    }
+}
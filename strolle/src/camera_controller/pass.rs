@@ -36,6 +36,28 @@ where
         encoder: &mut wgpu::CommandEncoder,
         size: UVec2,
         params: P,
+    ) {
+        self.dispatch(camera.is_alternate(), encoder, size, params);
+    }
+
+    /// Runs a pass that doesn't depend on any particular camera, i.e. one that
+    /// binds only engine-level resources (such as atmosphere's lookup
+    /// textures).
+    pub fn run_detached(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        size: UVec2,
+        params: P,
+    ) {
+        self.dispatch(false, encoder, size, params);
+    }
+
+    fn dispatch(
+        &self,
+        alternate: bool,
+        encoder: &mut wgpu::CommandEncoder,
+        size: UVec2,
+        params: P,
     ) {
         let label = format!("strolle_{}_pass", self.label);
 
@@ -54,7 +76,7 @@ where
         {
             pass.set_bind_group(
                 bind_group_idx as u32,
-                bind_group.get(camera.is_alternate()),
+                bind_group.get(alternate),
                 &[],
             );
         }
@@ -33,14 +33,21 @@ macro_rules! passes {
 }
 
 passes!([
-    atmosphere => AtmospherePass,
     bvh_heatmap => BvhHeatmapPass,
     di_resolving => DiResolvingPass,
     di_sampling => DiSamplingPass,
     di_spatial_resampling => DiSpatialResamplingPass,
     di_temporal_resampling => DiTemporalResamplingPass,
+    fog_scattering => FogScatteringPass,
+    frame_antialiasing => FrameAntialiasingPass,
+    frame_bloom => FrameBloomPass,
     frame_composition => FrameCompositionPass,
     frame_denoising => FrameDenoisingPass,
+    frame_depth_of_field => FrameDepthOfFieldPass,
+    frame_diagnostics => FrameDiagnosticsPass,
+    frame_exposure => FrameExposurePass,
+    frame_motion_blur => FrameMotionBlurPass,
+    frame_output => FrameOutputPass,
     frame_reprojection => FrameReprojectionPass,
     gi_preview_resampling => GiPreviewResamplingPass,
     gi_reprojection => GiReprojectionPass,
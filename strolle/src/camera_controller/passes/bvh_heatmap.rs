@@ -39,7 +39,7 @@ impl BvhHeatmapPass {
         encoder: &mut wgpu::CommandEncoder,
     ) {
         // This pass uses 8x8 warps:
-        let size = (camera.camera.viewport.size + 7) / 8;
+        let size = (camera.active_size() + 7) / 8;
 
         self.pass.run(camera, encoder, size, ());
     }
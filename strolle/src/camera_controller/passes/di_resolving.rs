@@ -23,13 +23,17 @@ impl DiResolvingPass {
                 &engine.bvh.bind_readable(),
                 &engine.materials.bind_readable(),
                 &engine.lights.bind_readable(),
+                &engine.ies_profiles.bind_lut(),
+                &engine.environment.bind_map(),
+                &engine.environment.bind_buffer(),
                 &engine.images.bind_atlas(),
                 &engine.world.bind_readable(),
+                &engine.atmosphere.bind_readable(),
             ])
             .bind([
                 &buffers.curr_camera.bind_readable(),
-                &buffers.atmosphere_transmittance_lut.bind_sampled(),
-                &buffers.atmosphere_sky_lut.bind_sampled(),
+                &engine.atmosphere_luts.bind_transmittance_lut(),
+                &engine.atmosphere_luts.bind_sky_lut(),
                 &buffers.prim_gbuffer_d0.curr().bind_readable(),
                 &buffers.prim_gbuffer_d1.curr().bind_readable(),
                 &buffers.di_reservoirs[2].bind_readable(),
@@ -48,7 +52,7 @@ impl DiResolvingPass {
         encoder: &mut wgpu::CommandEncoder,
     ) {
         // This pass uses 8x8 warps:
-        let size = (camera.camera.viewport.size + 7) / 8;
+        let size = (camera.active_size() + 7) / 8;
 
         self.pass.run(camera, encoder, size, camera.pass_params());
     }
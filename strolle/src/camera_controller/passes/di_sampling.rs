@@ -24,6 +24,10 @@ impl DiSamplingPass {
                 &engine.bvh.bind_readable(),
                 &engine.materials.bind_readable(),
                 &engine.lights.bind_readable(),
+                &engine.ies_profiles.bind_lut(),
+                &engine.environment.bind_map(),
+                &engine.environment.bind_buffer(),
+                &engine.lights.bind_tree(),
                 &engine.images.bind_atlas(),
                 &engine.world.bind_readable(),
             ])
@@ -44,7 +48,7 @@ impl DiSamplingPass {
         encoder: &mut wgpu::CommandEncoder,
     ) {
         // This pass uses 8x8 warps:
-        let size = (camera.camera.viewport.size + 7) / 8;
+        let size = (camera.active_size() + 7) / 8;
 
         self.pass.run(camera, encoder, size, camera.pass_params());
     }
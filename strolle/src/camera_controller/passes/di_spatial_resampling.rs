@@ -29,7 +29,12 @@ impl DiSpatialResamplingPass {
 
         let pick_pass =
             CameraComputePass::builder("di_spatial_resampling_pick")
-                .bind([&engine.lights.bind_readable()])
+                .bind([
+                    &engine.lights.bind_readable(),
+                    &engine.ies_profiles.bind_lut(),
+                    &engine.environment.bind_map(),
+                    &engine.environment.bind_buffer(),
+                ])
                 .bind([
                     &buffers.curr_camera.bind_readable(),
                     &buffers.prim_gbuffer_d0.curr().bind_readable(),
@@ -81,21 +86,21 @@ impl DiSpatialResamplingPass {
         self.pick_pass.run(
             camera,
             encoder,
-            (camera.camera.viewport.size + 7) / 8 / uvec2(2, 1),
+            (camera.active_size() + 7) / 8 / uvec2(2, 1),
             camera.pass_params(),
         );
 
         self.trace_pass.run(
             camera,
             encoder,
-            (camera.camera.viewport.size + 7) / 8,
+            (camera.active_size() + 7) / 8,
             camera.pass_params(),
         );
 
         self.sample_pass.run(
             camera,
             encoder,
-            (camera.camera.viewport.size + 7) / 8 / uvec2(2, 1),
+            (camera.active_size() + 7) / 8 / uvec2(2, 1),
             camera.pass_params(),
         );
     }
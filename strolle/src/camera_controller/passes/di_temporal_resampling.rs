@@ -18,7 +18,12 @@ impl DiTemporalResamplingPass {
         P: Params,
     {
         let pass = CameraComputePass::builder("di_temporal_resampling")
-            .bind([&engine.lights.bind_readable()])
+            .bind([
+                &engine.lights.bind_readable(),
+                &engine.ies_profiles.bind_lut(),
+                &engine.environment.bind_map(),
+                &engine.environment.bind_buffer(),
+            ])
             .bind([
                 &buffers.curr_camera.bind_readable(),
                 &buffers.prev_camera.bind_readable(),
@@ -41,7 +46,7 @@ impl DiTemporalResamplingPass {
         encoder: &mut wgpu::CommandEncoder,
     ) {
         // This pass uses 8x8 warps:
-        let size = (camera.camera.viewport.size + 7) / 8;
+        let size = (camera.active_size() + 7) / 8;
 
         self.pass.run(camera, encoder, size, camera.pass_params());
     }
@@ -0,0 +1,58 @@
+use crate::{
+    Camera, CameraBuffers, CameraComputePass, CameraController, Engine, Params,
+};
+
+#[derive(Debug)]
+pub struct FogScatteringPass {
+    pass: CameraComputePass,
+}
+
+impl FogScatteringPass {
+    pub fn new<P>(
+        engine: &Engine<P>,
+        device: &wgpu::Device,
+        _: &Camera,
+        buffers: &CameraBuffers,
+    ) -> Self
+    where
+        P: Params,
+    {
+        let pass = CameraComputePass::builder("fog_scattering")
+            .bind([
+                &engine.triangles.bind_readable(),
+                &engine.bvh.bind_readable(),
+                &engine.materials.bind_readable(),
+                &engine.lights.bind_readable(),
+                &engine.ies_profiles.bind_lut(),
+                &engine.environment.bind_map(),
+                &engine.environment.bind_buffer(),
+                &engine.lights.bind_tree(),
+                &engine.images.bind_atlas(),
+                &engine.world.bind_readable(),
+                &engine.fog.bind_readable(),
+            ])
+            .bind([
+                &buffers.curr_camera.bind_readable(),
+                &buffers.prev_camera.bind_readable(),
+                &buffers.prim_gbuffer_d0.curr().bind_readable(),
+                &buffers.prim_gbuffer_d1.curr().bind_readable(),
+                &buffers.fog_transmittance.bind_writable(),
+                &buffers.fog_scattering.prev().bind_readable(),
+                &buffers.fog_scattering.curr().bind_writable(),
+            ])
+            .build(device, &engine.shaders.fog_scattering);
+
+        Self { pass }
+    }
+
+    pub fn run(
+        &self,
+        camera: &CameraController,
+        encoder: &mut wgpu::CommandEncoder,
+    ) {
+        // This pass uses 8x8 warps:
+        let size = (camera.active_size() + 7) / 8;
+
+        self.pass.run(camera, encoder, size, camera.pass_params());
+    }
+}
@@ -0,0 +1,54 @@
+use crate::{
+    gpu, Camera, CameraBuffers, CameraComputePass, CameraController, Engine,
+    Params,
+};
+
+#[derive(Debug)]
+pub struct FrameAntialiasingPass {
+    pass: CameraComputePass<gpu::FrameAntialiasingPassParams>,
+}
+
+impl FrameAntialiasingPass {
+    pub fn new<P>(
+        engine: &Engine<P>,
+        device: &wgpu::Device,
+        _: &Camera,
+        buffers: &CameraBuffers,
+    ) -> Self
+    where
+        P: Params,
+    {
+        let pass = CameraComputePass::builder("frame_antialiasing")
+            .bind([
+                &buffers.curr_camera.bind_readable(),
+                &buffers.prev_camera.bind_readable(),
+                &buffers.prim_surface_map.curr().bind_readable(),
+                &buffers.reprojection_map.bind_readable(),
+                &buffers.velocity_map.bind_readable(),
+                &buffers.frame_composed.bind_readable(),
+                &buffers.taa_history.prev().bind_readable(),
+                &buffers.taa_history.curr().bind_writable(),
+            ])
+            .build(device, &engine.shaders.frame_antialiasing);
+
+        Self { pass }
+    }
+
+    pub fn run(
+        &self,
+        camera: &CameraController,
+        encoder: &mut wgpu::CommandEncoder,
+    ) {
+        let output_size = camera.camera.viewport.size;
+
+        // This pass uses 8x8 warps:
+        let size = (output_size + 7) / 8;
+
+        self.pass.run(
+            camera,
+            encoder,
+            size,
+            gpu::FrameAntialiasingPassParams { output_size },
+        );
+    }
+}
@@ -0,0 +1,153 @@
+use crate::{
+    gpu, Camera, CameraBuffers, CameraComputePass, CameraController,
+    DoubleBufferedBindable, Engine, Params,
+};
+
+#[derive(Debug)]
+pub struct FrameBloomPass {
+    downsample_passes: Vec<CameraComputePass<gpu::FrameBloomPassParams>>,
+    upsample_passes: Vec<CameraComputePass<gpu::FrameBloomPassParams>>,
+}
+
+impl FrameBloomPass {
+    pub fn new<P>(
+        engine: &Engine<P>,
+        device: &wgpu::Device,
+        camera: &Camera,
+        buffers: &CameraBuffers,
+    ) -> Self
+    where
+        P: Params,
+    {
+        if !camera.has_bloom() {
+            return Self {
+                downsample_passes: Default::default(),
+                upsample_passes: Default::default(),
+            };
+        }
+
+        let taa_history = buffers.taa_history.curr();
+        let taa_history = taa_history.bind_readable();
+        let frame_composed = buffers.frame_composed.bind_readable();
+
+        // Bloom is applied on the final image, i.e. after anti-aliasing (if
+        // enabled)
+        let frame_resolved: &dyn DoubleBufferedBindable = if camera.has_taa() {
+            &taa_history
+        } else {
+            &frame_composed
+        };
+
+        let downsample_passes = (0..buffers.bloom.len())
+            .map(|level| {
+                let src = level
+                    .checked_sub(1)
+                    .map(|level| buffers.bloom[level].bind_readable());
+
+                let src: &dyn DoubleBufferedBindable = match &src {
+                    Some(src) => src,
+                    None => frame_resolved,
+                };
+
+                CameraComputePass::builder(format!(
+                    "frame_bloom_downsample_{}",
+                    level
+                ))
+                .bind([src, &buffers.bloom[level].bind_writable()])
+                .build(device, &engine.shaders.frame_bloom_downsample)
+            })
+            .collect();
+
+        let upsample_passes = (1..buffers.bloom.len())
+            .map(|level| {
+                CameraComputePass::builder(format!(
+                    "frame_bloom_upsample_{}",
+                    level
+                ))
+                .bind([
+                    &buffers.bloom[level].bind_readable(),
+                    &buffers.bloom[level - 1].bind_writable(),
+                ])
+                .build(device, &engine.shaders.frame_bloom_upsample)
+            })
+            .collect();
+
+        Self {
+            downsample_passes,
+            upsample_passes,
+        }
+    }
+
+    pub fn run(
+        &self,
+        camera: &CameraController,
+        encoder: &mut wgpu::CommandEncoder,
+    ) {
+        let Some(bloom) = camera.camera.bloom else {
+            return;
+        };
+
+        if !camera.camera.has_bloom() {
+            return;
+        }
+
+        let output_size = camera.camera.viewport.size;
+        let level_size = |level| CameraBuffers::bloom_size(output_size, level);
+
+        // Size of the image the bloom starts from - without the temporal pass,
+        // that's the composed image, which has the render size
+        let frame_size = if camera.camera.has_taa() {
+            output_size
+        } else {
+            camera.active_size()
+        };
+
+        for (level, pass) in self.downsample_passes.iter().enumerate() {
+            let level = level as u32;
+
+            let src_size = if level == 0 {
+                frame_size
+            } else {
+                level_size(level - 1)
+            };
+
+            let dst_size = level_size(level);
+
+            // This pass uses 8x8 warps:
+            let size = (dst_size + 7) / 8;
+
+            pass.run(
+                camera,
+                encoder,
+                size,
+                gpu::FrameBloomPassParams {
+                    src_size,
+                    dst_size,
+                    radius: bloom.radius,
+                    is_first: (level == 0) as u32,
+                },
+            );
+        }
+
+        for (level, pass) in self.upsample_passes.iter().enumerate().rev() {
+            let level = level as u32 + 1;
+            let src_size = level_size(level);
+            let dst_size = level_size(level - 1);
+
+            // This pass uses 8x8 warps:
+            let size = (dst_size + 7) / 8;
+
+            pass.run(
+                camera,
+                encoder,
+                size,
+                gpu::FrameBloomPassParams {
+                    src_size,
+                    dst_size,
+                    radius: bloom.radius,
+                    is_first: 0,
+                },
+            );
+        }
+    }
+}
@@ -54,6 +54,11 @@ impl FrameCompositionPass {
             ))
             .add(&buffers.gi_spec_samples.bind_readable())
             .add(&buffers.ref_colors.bind_readable())
+            .add(&buffers.fog_transmittance.bind_readable())
+            .add(&buffers.fog_scattering.curr().bind_readable())
+            .add(&buffers.exposure.bind_readable())
+            .add(&buffers.velocity_map.bind_readable())
+            .add(&buffers.reprojection_map.bind_readable())
             .build(device);
 
         let pipeline_layout =
@@ -86,8 +91,12 @@ impl FrameCompositionPass {
                     module: &engine.shaders.frame_composition_fs.0,
                     entry_point: engine.shaders.frame_composition_fs.1,
                     targets: &[Some(wgpu::ColorTargetState {
-                        format: camera.viewport.format,
-                        blend: Some(wgpu::BlendState::REPLACE),
+                        // We compose into an intermediate HDR texture that
+                        // gets resolved later by `FrameAntialiasingPass` or
+                        // `FrameOutputPass`; note that float textures are not
+                        // blendable, hence `blend: None`
+                        format: wgpu::TextureFormat::Rgba32Float,
+                        blend: None,
                         write_mask: wgpu::ColorWrites::ALL,
                     })],
                 }),
@@ -97,12 +106,15 @@ impl FrameCompositionPass {
         Self { bg0, pipeline }
     }
 
-    pub fn run(
+    pub fn run<P>(
         &self,
+        engine: &Engine<P>,
         camera: &CameraController,
         encoder: &mut wgpu::CommandEncoder,
         view: &wgpu::TextureView,
-    ) {
+    ) where
+        P: Params,
+    {
         let alternate = camera.is_alternate();
 
         let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
@@ -120,14 +132,14 @@ impl FrameCompositionPass {
 
         let params = gpu::FrameCompositionPassParams {
             camera_mode: camera.camera.mode.serialize(),
+            has_fog: engine.has_fog as u32,
         };
 
-        pass.set_scissor_rect(
-            camera.camera.viewport.position.x,
-            camera.camera.viewport.position.y,
-            camera.camera.viewport.size.x,
-            camera.camera.viewport.size.y,
-        );
+        // Intermediate texture is rendered into starting from its top-left
+        // corner, up to the size we're rendering at in this frame
+        let size = camera.active_size();
+
+        pass.set_scissor_rect(0, 0, size.x, size.y);
         pass.set_pipeline(&self.pipeline);
         pass.set_bind_group(0, self.bg0.get(alternate), &[]);
         pass.set_push_constants(
@@ -150,7 +150,7 @@ impl FrameDenoisingPass {
         }
 
         // This pass uses 8x8 warps:
-        let size = (camera.camera.viewport.size + 7) / 8;
+        let size = (camera.active_size() + 7) / 8;
 
         self.reproject_passes[0].run(
             camera,
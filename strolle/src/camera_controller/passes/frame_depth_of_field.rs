@@ -0,0 +1,48 @@
+use crate::{
+    Camera, CameraBuffers, CameraComputePass, CameraController, Engine, Params,
+};
+
+#[derive(Debug)]
+pub struct FrameDepthOfFieldPass {
+    pass: CameraComputePass<()>,
+}
+
+impl FrameDepthOfFieldPass {
+    pub fn new<P>(
+        engine: &Engine<P>,
+        device: &wgpu::Device,
+        _: &Camera,
+        buffers: &CameraBuffers,
+    ) -> Self
+    where
+        P: Params,
+    {
+        let pass = CameraComputePass::builder("frame_depth_of_field")
+            .bind([
+                &buffers.curr_camera.bind_readable(),
+                &buffers.prim_surface_map.curr().bind_readable(),
+                &buffers.frame_composed.bind_readable(),
+                &buffers.frame_post.bind_writable(),
+            ])
+            .build(device, &engine.shaders.frame_depth_of_field);
+
+        Self { pass }
+    }
+
+    pub fn run(
+        &self,
+        camera: &CameraController,
+        encoder: &mut wgpu::CommandEncoder,
+    ) {
+        // This pass uses 8x8 warps:
+        let size = (camera.active_size() + 7) / 8;
+
+        self.pass.run(camera, encoder, size, ());
+
+        camera.buffers.frame_composed.copy_from(
+            encoder,
+            &camera.buffers.frame_post,
+            camera.active_size(),
+        );
+    }
+}
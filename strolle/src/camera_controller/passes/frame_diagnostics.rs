@@ -0,0 +1,73 @@
+use crate::{
+    gpu, Camera, CameraBuffers, CameraComputePass, CameraController,
+    CameraMode, Engine, Params,
+};
+
+#[derive(Debug)]
+pub struct FrameDiagnosticsPass {
+    pass: CameraComputePass<gpu::FrameDiagnosticsPassParams>,
+}
+
+impl FrameDiagnosticsPass {
+    pub fn new<P>(
+        engine: &Engine<P>,
+        device: &wgpu::Device,
+        _: &Camera,
+        buffers: &CameraBuffers,
+    ) -> Self
+    where
+        P: Params,
+    {
+        let pass = CameraComputePass::builder("frame_diagnostics")
+            .bind([
+                &engine.triangles.bind_readable(),
+                &engine.bvh.bind_readable(),
+                &engine.lights.bind_readable(),
+                &engine.ies_profiles.bind_lut(),
+                &engine.environment.bind_map(),
+                &engine.environment.bind_buffer(),
+                &engine.materials.bind_readable(),
+                &engine.images.bind_atlas(),
+            ])
+            .bind([
+                &buffers.curr_camera.bind_readable(),
+                &buffers.prim_gbuffer_d0.curr().bind_readable(),
+                &buffers.prim_gbuffer_d1.curr().bind_readable(),
+                &buffers.reprojection_map.bind_readable(),
+                &buffers.di_reservoirs[0].bind_readable(),
+                &buffers.gi_reservoirs[0].bind_readable(),
+                &buffers.di_diff_samples.bind_readable(),
+                &buffers.gi_diff_samples.bind_readable(),
+                &buffers.di_diff_moments.curr().bind_readable(),
+                &buffers.gi_diff_moments.curr().bind_readable(),
+                &buffers.diagnostics.bind_writable(),
+                &buffers.frame_composed.bind_writable(),
+            ])
+            .build(device, &engine.shaders.frame_diagnostics);
+
+        Self { pass }
+    }
+
+    pub fn run(
+        &self,
+        camera: &CameraController,
+        encoder: &mut wgpu::CommandEncoder,
+    ) {
+        let CameraMode::Diagnostic(diagnostic) = camera.camera.mode else {
+            return;
+        };
+
+        // This pass uses 8x8 warps:
+        let size = (camera.active_size() + 7) / 8;
+
+        let params = gpu::FrameDiagnosticsPassParams {
+            diagnostic: diagnostic.serialize(),
+            max: camera
+                .camera
+                .diagnostic_max
+                .unwrap_or_else(|| diagnostic.legend().max),
+        };
+
+        self.pass.run(camera, encoder, size, params);
+    }
+}
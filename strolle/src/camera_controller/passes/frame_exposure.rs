@@ -0,0 +1,73 @@
+use spirv_std::glam::UVec2;
+
+use crate::{
+    gpu, Camera, CameraBuffers, CameraComputePass, CameraController,
+    CameraExposure, Engine, Params,
+};
+
+#[derive(Debug)]
+pub struct FrameExposurePass {
+    histogram_pass: CameraComputePass<gpu::FrameExposurePassParams>,
+    adapt_pass: CameraComputePass<gpu::FrameExposurePassParams>,
+}
+
+impl FrameExposurePass {
+    pub fn new<P>(
+        engine: &Engine<P>,
+        device: &wgpu::Device,
+        _: &Camera,
+        buffers: &CameraBuffers,
+    ) -> Self
+    where
+        P: Params,
+    {
+        let histogram_pass =
+            CameraComputePass::builder("frame_exposure_histogram")
+                .bind([
+                    &buffers.curr_camera.bind_readable(),
+                    &buffers.frame_composed.bind_readable(),
+                    &buffers.exposure.bind_readable(),
+                    &buffers.exposure_histogram.bind_writable(),
+                ])
+                .build(device, &engine.shaders.frame_exposure_histogram);
+
+        let adapt_pass = CameraComputePass::builder("frame_exposure_adapt")
+            .bind([
+                &buffers.exposure.bind_writable(),
+                &buffers.exposure_histogram.bind_writable(),
+            ])
+            .build(device, &engine.shaders.frame_exposure_adapt);
+
+        Self {
+            histogram_pass,
+            adapt_pass,
+        }
+    }
+
+    pub fn run(
+        &self,
+        camera: &CameraController,
+        encoder: &mut wgpu::CommandEncoder,
+    ) {
+        let CameraExposure::Auto(config) = camera.camera.exposure else {
+            return;
+        };
+
+        let params = gpu::FrameExposurePassParams {
+            min_ev: config.min_ev,
+            max_ev: config.max_ev.max(config.min_ev + 0.1),
+            speed_up: config.speed_up.max(0.0),
+            speed_down: config.speed_down.max(0.0),
+            compensation: config.compensation,
+            delta_time: camera.exposure.delta_time(),
+        };
+
+        // This pass uses 8x8 warps:
+        let size = (camera.active_size() + 7) / 8;
+
+        self.histogram_pass.run(camera, encoder, size, params);
+
+        // This pass uses a single thread:
+        self.adapt_pass.run(camera, encoder, UVec2::ONE, params);
+    }
+}
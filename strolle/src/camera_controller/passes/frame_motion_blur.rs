@@ -0,0 +1,48 @@
+use crate::{
+    Camera, CameraBuffers, CameraComputePass, CameraController, Engine, Params,
+};
+
+#[derive(Debug)]
+pub struct FrameMotionBlurPass {
+    pass: CameraComputePass<()>,
+}
+
+impl FrameMotionBlurPass {
+    pub fn new<P>(
+        engine: &Engine<P>,
+        device: &wgpu::Device,
+        _: &Camera,
+        buffers: &CameraBuffers,
+    ) -> Self
+    where
+        P: Params,
+    {
+        let pass = CameraComputePass::builder("frame_motion_blur")
+            .bind([
+                &buffers.curr_camera.bind_readable(),
+                &buffers.velocity_map.bind_readable(),
+                &buffers.frame_composed.bind_readable(),
+                &buffers.frame_post.bind_writable(),
+            ])
+            .build(device, &engine.shaders.frame_motion_blur);
+
+        Self { pass }
+    }
+
+    pub fn run(
+        &self,
+        camera: &CameraController,
+        encoder: &mut wgpu::CommandEncoder,
+    ) {
+        // This pass uses 8x8 warps:
+        let size = (camera.active_size() + 7) / 8;
+
+        self.pass.run(camera, encoder, size, ());
+
+        camera.buffers.frame_composed.copy_from(
+            encoder,
+            &camera.buffers.frame_post,
+            camera.active_size(),
+        );
+    }
+}
@@ -0,0 +1,155 @@
+use std::mem;
+use std::ops::Range;
+
+use log::debug;
+
+use crate::{
+    gpu, BindGroup, Camera, CameraBuffers, CameraController, CameraTonemapping,
+    DoubleBufferedBindable, Engine, Params,
+};
+
+#[derive(Debug)]
+pub struct FrameOutputPass {
+    bg0: BindGroup,
+    pipeline: wgpu::RenderPipeline,
+}
+
+impl FrameOutputPass {
+    pub fn new<P>(
+        engine: &Engine<P>,
+        device: &wgpu::Device,
+        camera: &Camera,
+        buffers: &CameraBuffers,
+    ) -> Self
+    where
+        P: Params,
+    {
+        debug!("Initializing pass: frame_output");
+
+        let taa_history = buffers.taa_history.curr();
+        let taa_history = taa_history.bind_readable();
+        let frame_composed = buffers.frame_composed.bind_readable();
+
+        let frame_resolved: &dyn DoubleBufferedBindable = if camera.has_taa() {
+            &taa_history
+        } else {
+            &frame_composed
+        };
+
+        let bg0 = BindGroup::builder("frame_output_bg0")
+            .add(frame_resolved)
+            .add(&buffers.bloom[0].bind_readable())
+            .add(&buffers.tonemapping_lut.bind_readable())
+            .build(device);
+
+        let pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("strolle_frame_output_pipeline_layout"),
+                bind_group_layouts: &[bg0.layout()],
+                push_constant_ranges: &[wgpu::PushConstantRange {
+                    stages: wgpu::ShaderStages::FRAGMENT,
+                    range: Range {
+                        start: 0,
+                        end: mem::size_of::<gpu::FrameOutputPassParams>()
+                            as u32,
+                    },
+                }],
+            });
+
+        let pipeline =
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("strolle_frame_output_pipeline"),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &engine.shaders.frame_composition_vs.0,
+                    entry_point: engine.shaders.frame_composition_vs.1,
+                    buffers: &[],
+                },
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                fragment: Some(wgpu::FragmentState {
+                    module: &engine.shaders.frame_output_fs.0,
+                    entry_point: engine.shaders.frame_output_fs.1,
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: camera.viewport.format,
+                        blend: Some(wgpu::BlendState::REPLACE),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                multiview: None,
+            });
+
+        Self { bg0, pipeline }
+    }
+
+    pub fn run(
+        &self,
+        camera: &CameraController,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+    ) {
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("strolle_frame_output"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+
+        let output_size = camera.camera.viewport.size;
+
+        // Debug modes don't show colors, so they get neither bloom nor
+        // tonemapping
+        let is_physical = camera.camera.mode.is_physical();
+
+        let (bloom_levels, bloom_intensity) = match camera.camera.bloom {
+            Some(bloom) if camera.camera.has_bloom() => {
+                (camera.buffers.bloom.len() as u32, bloom.intensity)
+            }
+            _ => (0, 0.0),
+        };
+
+        let tonemapping = if is_physical {
+            camera.camera.tonemapping.clone()
+        } else {
+            CameraTonemapping::None
+        };
+
+        let params = gpu::FrameOutputPassParams {
+            output_size,
+            bloom_size: CameraBuffers::bloom_size(output_size, 0),
+            bloom_levels,
+            bloom_intensity,
+            tonemapping: tonemapping.serialize(),
+            tonemapping_lut_size: camera
+                .camera
+                .tonemapping
+                .lut()
+                .map(|lut| lut.size())
+                .unwrap_or_default(),
+            needs_srgb_encoding: camera.camera.needs_srgb_encoding() as u32,
+            camera_mode: camera.camera.mode.serialize(),
+        };
+
+        pass.set_scissor_rect(
+            camera.camera.viewport.position.x,
+            camera.camera.viewport.position.y,
+            camera.camera.viewport.size.x,
+            camera.camera.viewport.size.y,
+        );
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, self.bg0.get(camera.is_alternate()), &[]);
+        pass.set_push_constants(
+            wgpu::ShaderStages::FRAGMENT,
+            0,
+            bytemuck::bytes_of(&params),
+        );
+        pass.draw(0..3, 0..1);
+    }
+}
@@ -54,7 +54,7 @@ impl GiPreviewResamplingPass {
         source: u32,
     ) {
         // This pass uses 8x8 warps:
-        let size = (camera.camera.viewport.size + 7) / 8;
+        let size = (camera.active_size() + 7) / 8;
         let params = camera.pass_params();
 
         for (nth, pass) in self.passes.iter().enumerate() {
@@ -41,7 +41,7 @@ impl GiResolvingPass {
         source: u32,
     ) {
         // This pass uses 8x8 warps:
-        let size = (camera.camera.viewport.size + 7) / 8;
+        let size = (camera.active_size() + 7) / 8;
 
         self.pass.run(
             camera,
@@ -43,14 +43,19 @@ impl GiSamplingPass {
                 &engine.triangles.bind_readable(),
                 &engine.bvh.bind_readable(),
                 &engine.lights.bind_readable(),
+                &engine.ies_profiles.bind_lut(),
+                &engine.environment.bind_map(),
+                &engine.environment.bind_buffer(),
+                &engine.lights.bind_tree(),
                 &engine.materials.bind_readable(),
                 &engine.images.bind_atlas(),
                 &engine.world.bind_readable(),
+                &engine.atmosphere.bind_readable(),
             ])
             .bind([
                 &buffers.curr_camera.bind_readable(),
-                &buffers.atmosphere_transmittance_lut.bind_sampled(),
-                &buffers.atmosphere_sky_lut.bind_sampled(),
+                &engine.atmosphere_luts.bind_transmittance_lut(),
+                &engine.atmosphere_luts.bind_sky_lut(),
                 &buffers.prim_gbuffer_d0.curr().bind_readable(),
                 &buffers.prim_gbuffer_d1.curr().bind_readable(),
                 &buffers.gi_d0.bind_readable(),
@@ -70,7 +75,7 @@ impl GiSamplingPass {
         encoder: &mut wgpu::CommandEncoder,
     ) {
         // These passes use 8x8 warps and 2x1 checkerboard:
-        let size = (camera.camera.viewport.size + 7) / 8 / uvec2(2, 1);
+        let size = (camera.active_size() + 7) / 8 / uvec2(2, 1);
 
         self.pass_a.run(camera, encoder, size, camera.pass_params());
         self.pass_b.run(camera, encoder, size, camera.pass_params());
@@ -74,21 +74,21 @@ impl GiSpatialResamplingPass {
         self.pick_pass.run(
             camera,
             encoder,
-            (camera.camera.viewport.size + 7) / 8 / uvec2(2, 1),
+            (camera.active_size() + 7) / 8 / uvec2(2, 1),
             camera.pass_params(),
         );
 
         self.trace_pass.run(
             camera,
             encoder,
-            (camera.camera.viewport.size + 7) / 8,
+            (camera.active_size() + 7) / 8,
             camera.pass_params(),
         );
 
         self.sample_pass.run(
             camera,
             encoder,
-            (camera.camera.viewport.size + 7) / 8 / uvec2(2, 1),
+            (camera.active_size() + 7) / 8 / uvec2(2, 1),
             camera.pass_params(),
         );
     }
@@ -189,6 +189,12 @@ impl PrimRasterPass {
             ),
         });
 
+        let size = camera.active_size();
+
+        // Buffers might be larger than what we're rendering in this frame (see:
+        // dynamic resolution), in which case we render into their top-left
+        // corner
+        pass.set_viewport(0.0, 0.0, size.x as f32, size.y as f32, 0.0, 1.0);
         pass.set_pipeline(&self.pipeline);
         pass.set_bind_group(0, self.bg0.get(alternate), &[]);
         pass.set_bind_group(1, self.bg1.get(alternate), &[]);
@@ -215,7 +221,7 @@ impl PrimRasterPass {
                     payload: vec4(
                         f32::from_bits(instance_entry.uuid),
                         f32::from_bits(material_id.get()),
-                        Default::default(),
+                        f32::from_bits(instance_entry.excluded_lights),
                         Default::default(),
                     ),
                     curr_xform_inv_d0: curr_xform_inv[0],
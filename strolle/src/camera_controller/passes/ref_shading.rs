@@ -25,18 +25,23 @@ impl RefShadingPass {
                 &engine.triangles.bind_readable(),
                 &engine.bvh.bind_readable(),
                 &engine.lights.bind_readable(),
+                &engine.lights.bind_tree(),
+                &engine.ies_profiles.bind_lut(),
+                &engine.environment.bind_map(),
+                &engine.environment.bind_buffer(),
                 &engine.materials.bind_readable(),
                 &engine.images.bind_atlas(),
                 &engine.world.bind_readable(),
+                &engine.atmosphere.bind_readable(),
             ])
             .bind([
                 &buffers.curr_camera.bind_readable(),
-                &buffers.prev_camera.bind_readable(),
-                &buffers.atmosphere_transmittance_lut.bind_sampled(),
-                &buffers.atmosphere_sky_lut.bind_sampled(),
+                &engine.atmosphere_luts.bind_transmittance_lut(),
+                &engine.atmosphere_luts.bind_sky_lut(),
                 &buffers.ref_rays.bind_writable(),
                 &buffers.ref_hits.bind_readable(),
                 &buffers.ref_colors.bind_writable(),
+                &buffers.ref_moments.bind_writable(),
             ])
             .build(device, &engine.shaders.ref_shading);
 
@@ -48,14 +53,17 @@ impl RefShadingPass {
         camera: &CameraController,
         encoder: &mut wgpu::CommandEncoder,
         depth: u8,
+        max_depth: u8,
     ) {
         // This pass uses 8x8 warps:
-        let size = (camera.camera.viewport.size + 7) / 8;
+        let size = (camera.active_size() + 7) / 8;
 
         let params = gpu::RefPassParams {
             seed: rand::thread_rng().gen(),
             frame: camera.frame,
             depth: depth as u32,
+            max_depth: max_depth as u32,
+            samples: camera.reference.samples(),
         };
 
         self.pass.run(camera, encoder, size, params);
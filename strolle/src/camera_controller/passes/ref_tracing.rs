@@ -42,14 +42,17 @@ impl RefTracingPass {
         camera: &CameraController,
         encoder: &mut wgpu::CommandEncoder,
         depth: u8,
+        max_depth: u8,
     ) {
         // This pass uses 8x8 warps:
-        let size = (camera.camera.viewport.size + 7) / 8;
+        let size = (camera.active_size() + 7) / 8;
 
         let params = gpu::RefPassParams {
             seed: rand::thread_rng().gen(),
             frame: camera.frame,
             depth: depth as u32,
+            max_depth: max_depth as u32,
+            samples: camera.reference.samples(),
         };
 
         self.pass.run(camera, encoder, size, params);
@@ -0,0 +1,208 @@
+use std::sync::Arc;
+
+use spirv_std::glam::{UVec2, Vec4};
+
+use crate::{
+    Camera, CameraBuffers, CameraMode, CameraReference,
+    CameraReferenceVariance, ReadbackBuffer,
+};
+
+/// Keeps track of samples accumulated by [`CameraMode::Reference`] and reads
+/// their variance back to the host.
+#[derive(Debug)]
+pub struct Reference {
+    readback: Option<ReadbackBuffer>,
+
+    /// Number of samples accumulated so far, including the ones traced during
+    /// the upcoming render
+    samples: u32,
+
+    /// Whether the upcoming render traces new samples (or just presents the
+    /// image accumulated so far, after the target has been reached)
+    is_tracing: bool,
+
+    /// Whether the scene has changed since the previous frame
+    is_dirty: bool,
+
+    /// Size of the image the samples have been accumulated at
+    size: UVec2,
+
+    /// Number of samples and size of the image that's being read back (or -
+    /// when the readback buffer is idle - that's going to be read back during
+    /// the upcoming render)
+    pending: Option<(u32, UVec2)>,
+
+    latest: Option<Arc<CameraReferenceVariance>>,
+}
+
+impl Reference {
+    const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba32Float;
+
+    pub fn new(device: &wgpu::Device, camera: &Camera) -> Self {
+        let readback = matches!(camera.mode, CameraMode::Reference { .. })
+            .then(|| {
+                let size = camera.render_size();
+
+                ReadbackBuffer::new(
+                    device,
+                    "reference_readback",
+                    (ReadbackBuffer::padded_bytes_per_row(size.x, Self::FORMAT)
+                        * size.y) as usize,
+                )
+            });
+
+        Self {
+            readback,
+            samples: 0,
+            is_tracing: false,
+            is_dirty: false,
+            size: Default::default(),
+            pending: None,
+            latest: None,
+        }
+    }
+
+    /// Makes the accumulation start over, e.g. because an instance has been
+    /// moved.
+    pub fn invalidate(&mut self) {
+        self.is_dirty = true;
+    }
+
+    pub fn flush(
+        &mut self,
+        camera: &Camera,
+        buffers: &CameraBuffers,
+        active_size: UVec2,
+    ) {
+        let Some(readback) = &mut self.readback else {
+            return;
+        };
+
+        // (must be called each frame, so that the buffer doesn't get stuck
+        // being mapped)
+        let data = readback.read_with(|bytes| {
+            self.pending.map(|(_, size)| Self::decode(bytes, size))
+        });
+
+        if let (Some(Some(data)), Some((samples, size))) = (data, self.pending)
+        {
+            self.latest = Some(Arc::new(CameraReferenceVariance {
+                samples,
+                size,
+                data,
+            }));
+        }
+
+        let is_reset = self.is_dirty
+            || active_size != self.size
+            || !buffers.curr_camera.is_eq(*buffers.prev_camera);
+
+        if is_reset {
+            self.samples = 0;
+            self.is_dirty = false;
+            self.size = active_size;
+        }
+
+        self.is_tracing = camera
+            .reference_samples
+            .map_or(true, |target_samples| self.samples < target_samples);
+
+        if self.is_tracing {
+            self.samples += 1;
+        }
+
+        if readback.is_idle() {
+            self.pending = Some((self.samples, active_size));
+        }
+    }
+
+    /// Schedules reading the variance back to the host; must be called after
+    /// the reference passes.
+    pub fn end(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        buffers: &CameraBuffers,
+    ) {
+        let (Some(readback), Some((_, size))) = (&self.readback, self.pending)
+        else {
+            return;
+        };
+
+        readback.copy_from_texture(encoder, &buffers.ref_moments, size);
+    }
+
+    /// Returns the number of samples accumulated so far, including the ones
+    /// traced during the upcoming render.
+    pub fn samples(&self) -> u32 {
+        self.samples
+    }
+
+    /// Returns whether the upcoming render should trace new samples.
+    pub fn is_tracing(&self) -> bool {
+        self.is_tracing
+    }
+
+    /// Returns the current progress, if the camera is in the reference mode.
+    pub fn get(&self, camera: &Camera) -> Option<CameraReference> {
+        self.readback.as_ref()?;
+
+        Some(CameraReference {
+            samples: self.samples,
+            target_samples: camera.reference_samples,
+            variance: self.latest.clone(),
+        })
+    }
+
+    fn decode(bytes: &[u8], size: UVec2) -> Vec<f32> {
+        let bytes_per_row =
+            ReadbackBuffer::padded_bytes_per_row(size.x, Self::FORMAT) as usize;
+
+        bytes
+            .chunks(bytes_per_row)
+            .take(size.y as usize)
+            .flat_map(|row| {
+                row.chunks_exact(16).take(size.x as usize).map(|moments| {
+                    let moments: Vec4 = bytemuck::pod_read_unaligned(moments);
+
+                    // (see: ref_shading)
+                    let samples = moments.z;
+
+                    if samples > 0.0 {
+                        let m1 = moments.x / samples;
+                        let m2 = moments.y / samples;
+
+                        (m2 - m1 * m1).max(0.0)
+                    } else {
+                        0.0
+                    }
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use spirv_std::glam::{uvec2, vec4};
+
+    use super::*;
+
+    #[test]
+    fn decode() {
+        let size = uvec2(2, 1);
+
+        let mut bytes = vec![
+            // Samples: 1.0, 3.0
+            vec4(4.0, 10.0, 2.0, 0.0),
+            // No samples
+            vec4(0.0, 0.0, 0.0, 0.0),
+        ];
+
+        // (rows are padded to 256 bytes)
+        bytes.resize(16, vec4(-1.0, -1.0, -1.0, -1.0));
+
+        let actual = Reference::decode(bytemuck::cast_slice(&bytes), size);
+
+        assert_eq!(vec![1.0, 0.0], actual);
+    }
+}
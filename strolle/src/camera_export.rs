@@ -0,0 +1,357 @@
+use std::io::{self, Write};
+
+use spirv_std::glam::{UVec2, Vec4};
+
+/// Buffer read back by [`crate::Engine::export_camera()`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CameraExportLayer {
+    /// Composed image, in linear high-dynamic range - i.e. before exposure,
+    /// tonemapping and bloom.
+    ///
+    /// Read as `(r, g, b, a)`.
+    Color,
+
+    /// Direct diffuse lighting, denoised if the camera's mode denoises it.
+    ///
+    /// Read as `(r, g, b, _)`.
+    DiDiffuse,
+
+    /// Direct specular lighting.
+    ///
+    /// Read as `(r, g, b, _)`.
+    DiSpecular,
+
+    /// Indirect diffuse lighting, denoised if the camera's mode denoises it.
+    ///
+    /// Read as `(r, g, b, _)`.
+    GiDiffuse,
+
+    /// Indirect specular lighting.
+    ///
+    /// Read as `(r, g, b, _)`.
+    GiSpecular,
+
+    /// Distance from the camera to each pixel's surface.
+    ///
+    /// Read as `(distance, distance, distance, 1.0)`, with zero for the sky.
+    Depth,
+
+    /// Surfaces' world-space normals.
+    ///
+    /// Read as `(x, y, z, 1.0)`, with zero for the sky.
+    Normals,
+
+    /// Surfaces' base color (aka albedo).
+    ///
+    /// Read as `(r, g, b, a)`.
+    BaseColor,
+}
+
+impl CameraExportLayer {
+    pub const ALL: [Self; 8] = [
+        Self::Color,
+        Self::DiDiffuse,
+        Self::DiSpecular,
+        Self::GiDiffuse,
+        Self::GiSpecular,
+        Self::Depth,
+        Self::Normals,
+        Self::BaseColor,
+    ];
+
+    /// Returns whether this layer comes from the lighting passes - those are
+    /// filled only by the modes that actually compute given lighting (e.g.
+    /// [`crate::CameraMode::Image`]) and stay stale otherwise.
+    pub fn is_lighting(self) -> bool {
+        matches!(
+            self,
+            Self::DiDiffuse
+                | Self::DiSpecular
+                | Self::GiDiffuse
+                | Self::GiSpecular
+        )
+    }
+
+    /// Returns names of the EXR channels this layer gets written into, along
+    /// with the components they come from.
+    ///
+    /// (the color and depth use the default names, so that viewers pick them
+    /// up automatically)
+    fn channels(self) -> &'static [(&'static str, usize)] {
+        match self {
+            Self::Color => &[("R", 0), ("G", 1), ("B", 2), ("A", 3)],
+            Self::DiDiffuse => &[
+                ("di_diffuse.R", 0),
+                ("di_diffuse.G", 1),
+                ("di_diffuse.B", 2),
+            ],
+            Self::DiSpecular => &[
+                ("di_specular.R", 0),
+                ("di_specular.G", 1),
+                ("di_specular.B", 2),
+            ],
+            Self::GiDiffuse => &[
+                ("gi_diffuse.R", 0),
+                ("gi_diffuse.G", 1),
+                ("gi_diffuse.B", 2),
+            ],
+            Self::GiSpecular => &[
+                ("gi_specular.R", 0),
+                ("gi_specular.G", 1),
+                ("gi_specular.B", 2),
+            ],
+            Self::Depth => &[("Z", 0)],
+            Self::Normals => {
+                &[("normals.X", 0), ("normals.Y", 1), ("normals.Z", 2)]
+            }
+            Self::BaseColor => &[
+                ("base_color.R", 0),
+                ("base_color.G", 1),
+                ("base_color.B", 2),
+                ("base_color.A", 3),
+            ],
+        }
+    }
+}
+
+/// Camera's buffers read back from the GPU, in linear high-dynamic range.
+///
+/// See: [`crate::Engine::export_camera()`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct CameraExport {
+    /// Size of the image, which - with internal resolution or dynamic
+    /// resolution - can be smaller than the viewport's size.
+    pub size: UVec2,
+
+    /// Layers, in the order they've been requested in; values are stored row
+    /// by row.
+    pub layers: Vec<(CameraExportLayer, Vec<Vec4>)>,
+}
+
+impl CameraExport {
+    /// Returns values of given layer, or `None` if it hasn't been exported.
+    pub fn get(&self, layer: CameraExportLayer) -> Option<&[Vec4]> {
+        self.layers
+            .iter()
+            .find(|(layer2, _)| *layer2 == layer)
+            .map(|(_, data)| data.as_slice())
+    }
+
+    /// Converts given layer into an image, dropping its fourth component.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the layer hasn't been exported.
+    pub fn to_rgb32f(&self, layer: CameraExportLayer) -> ::image::Rgb32FImage {
+        let data = self
+            .layer(layer)
+            .iter()
+            .flat_map(|value| value.truncate().to_array())
+            .collect();
+
+        ::image::Rgb32FImage::from_raw(self.size.x, self.size.y, data).unwrap()
+    }
+
+    /// Writes given layer as a Radiance HDR (`.hdr`) image.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the layer hasn't been exported.
+    pub fn write_hdr(
+        &self,
+        layer: CameraExportLayer,
+        writer: impl Write,
+    ) -> ::image::ImageResult<()> {
+        let data: Vec<_> = self
+            .layer(layer)
+            .iter()
+            .map(|value| ::image::Rgb(value.truncate().to_array()))
+            .collect();
+
+        ::image::codecs::hdr::HdrEncoder::new(writer).encode(
+            &data,
+            self.size.x as usize,
+            self.size.y as usize,
+        )
+    }
+
+    /// Writes all layers as a multi-layer OpenEXR (`.exr`) image, with
+    /// uncompressed 32-bit float channels.
+    ///
+    /// The color gets written into the default layer (`R`, `G`, `B`, `A`) and
+    /// the depth into the `Z` channel; other layers are named after their
+    /// variants, e.g. `di_diffuse.R` or `normals.X`.
+    pub fn write_exr(&self, mut writer: impl Write) -> io::Result<()> {
+        let width = self.size.x as usize;
+        let height = self.size.y as usize;
+
+        // OpenEXR requires for channels to be sorted by their names, both in
+        // the header and in the pixel data
+        let mut channels: Vec<_> = self
+            .layers
+            .iter()
+            .flat_map(|(layer, data)| {
+                layer
+                    .channels()
+                    .iter()
+                    .map(move |&(name, component)| (name, data, component))
+            })
+            .collect();
+
+        channels.sort_by_key(|(name, _, _)| *name);
+
+        // ---------------------------------------------------------------------
+
+        let mut header = Vec::new();
+
+        header.extend(20000630i32.to_le_bytes());
+        header.extend(2i32.to_le_bytes());
+
+        let chlist: Vec<u8> = channels
+            .iter()
+            .flat_map(|(name, _, _)| {
+                let mut entry = name.as_bytes().to_vec();
+
+                entry.push(0);
+                entry.extend(2i32.to_le_bytes()); // pixel type: FLOAT
+                entry.extend([0, 0, 0, 0]); // pLinear + reserved
+                entry.extend(1i32.to_le_bytes()); // x sampling
+                entry.extend(1i32.to_le_bytes()); // y sampling
+                entry
+            })
+            .chain([0])
+            .collect();
+
+        let window: Vec<u8> = [0, 0, width as i32 - 1, height as i32 - 1]
+            .into_iter()
+            .flat_map(i32::to_le_bytes)
+            .collect();
+
+        write_exr_attribute(&mut header, "channels", "chlist", &chlist);
+        write_exr_attribute(&mut header, "compression", "compression", &[0]);
+        write_exr_attribute(&mut header, "dataWindow", "box2i", &window);
+        write_exr_attribute(&mut header, "displayWindow", "box2i", &window);
+        write_exr_attribute(&mut header, "lineOrder", "lineOrder", &[0]);
+
+        write_exr_attribute(
+            &mut header,
+            "pixelAspectRatio",
+            "float",
+            &1.0f32.to_le_bytes(),
+        );
+
+        write_exr_attribute(
+            &mut header,
+            "screenWindowCenter",
+            "v2f",
+            &[0u8; 8],
+        );
+
+        write_exr_attribute(
+            &mut header,
+            "screenWindowWidth",
+            "float",
+            &1.0f32.to_le_bytes(),
+        );
+
+        header.push(0);
+
+        // ---------------------------------------------------------------------
+
+        // Without compression, each scanline forms a separate block consisting
+        // of its y coordinate, size and then values of each channel
+        let block_data_size = channels.len() * width * 4;
+        let block_size = 8 + block_data_size;
+        let blocks_offset = header.len() + height * 8;
+
+        for y in 0..height {
+            header.extend(
+                ((blocks_offset + y * block_size) as u64).to_le_bytes(),
+            );
+        }
+
+        writer.write_all(&header)?;
+
+        let mut block = Vec::with_capacity(block_size);
+
+        for y in 0..height {
+            block.clear();
+            block.extend((y as i32).to_le_bytes());
+            block.extend((block_data_size as i32).to_le_bytes());
+
+            for (_, data, component) in &channels {
+                for value in &data[(y * width)..((y + 1) * width)] {
+                    block.extend(value[*component].to_le_bytes());
+                }
+            }
+
+            writer.write_all(&block)?;
+        }
+
+        Ok(())
+    }
+
+    fn layer(&self, layer: CameraExportLayer) -> &[Vec4] {
+        self.get(layer)
+            .unwrap_or_else(|| panic!("layer not exported: {layer:?}"))
+    }
+}
+
+fn write_exr_attribute(out: &mut Vec<u8>, name: &str, ty: &str, value: &[u8]) {
+    out.extend(name.as_bytes());
+    out.push(0);
+    out.extend(ty.as_bytes());
+    out.push(0);
+    out.extend((value.len() as i32).to_le_bytes());
+    out.extend(value);
+}
+
+#[cfg(test)]
+mod tests {
+    use spirv_std::glam::{uvec2, vec4};
+
+    use super::*;
+
+    #[test]
+    fn write_exr() {
+        let export = CameraExport {
+            size: uvec2(2, 1),
+            layers: vec![
+                (
+                    CameraExportLayer::Color,
+                    vec![vec4(0.1, 0.2, 0.3, 1.0), vec4(0.4, 0.5, 0.6, 1.0)],
+                ),
+                (
+                    CameraExportLayer::Depth,
+                    vec![Vec4::splat(2.0), Vec4::splat(3.0)],
+                ),
+            ],
+        };
+
+        let mut bytes = Vec::new();
+
+        export.write_exr(&mut bytes).unwrap();
+
+        assert_eq!([0x76, 0x2f, 0x31, 0x01, 2, 0, 0, 0], bytes[..8]);
+
+        // Channels: A, B, G, R, Z
+        let block_size = 8 + 5 * 2 * 4;
+        let block_offset = bytes.len() - block_size;
+
+        let offset = u64::from_le_bytes(
+            bytes[(block_offset - 8)..block_offset].try_into().unwrap(),
+        );
+
+        assert_eq!(block_offset as u64, offset);
+
+        let block: Vec<f32> = bytes[(block_offset + 8)..]
+            .chunks_exact(4)
+            .map(|value| f32::from_le_bytes(value.try_into().unwrap()))
+            .collect();
+
+        assert_eq!(
+            vec![1.0, 1.0, 0.3, 0.6, 0.2, 0.5, 0.1, 0.4, 2.0, 3.0],
+            block,
+        );
+    }
+}
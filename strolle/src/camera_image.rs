@@ -0,0 +1,159 @@
+use half::f16;
+use spirv_std::glam::UVec2;
+
+/// Image rendered offscreen and read back from the GPU.
+///
+/// See: [`crate::Engine::render_camera_offscreen()`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct CameraImage {
+    /// Size of the image, i.e. camera's viewport size.
+    pub size: UVec2,
+
+    /// Format the image has been rendered with, i.e. camera's viewport format.
+    pub format: wgpu::TextureFormat,
+
+    /// Pixels, row by row, without any padding.
+    pub data: Vec<u8>,
+}
+
+impl CameraImage {
+    /// Returns whether images of given format can be read back.
+    pub fn supports(format: wgpu::TextureFormat) -> bool {
+        matches!(
+            format,
+            wgpu::TextureFormat::Rgba8Unorm
+                | wgpu::TextureFormat::Rgba8UnormSrgb
+                | wgpu::TextureFormat::Bgra8Unorm
+                | wgpu::TextureFormat::Bgra8UnormSrgb
+                | wgpu::TextureFormat::Rgba16Float
+                | wgpu::TextureFormat::Rgba32Float
+        )
+    }
+
+    /// Converts the image into an 8-bit sRGB one; high-dynamic-range images
+    /// get clamped.
+    pub fn to_rgba8(&self) -> ::image::RgbaImage {
+        let data = self
+            .pixels()
+            .flat_map(|[r, g, b, a]| {
+                [
+                    encode_srgb(r),
+                    encode_srgb(g),
+                    encode_srgb(b),
+                    (a.clamp(0.0, 1.0) * 255.0).round() as u8,
+                ]
+            })
+            .collect();
+
+        ::image::RgbaImage::from_raw(self.size.x, self.size.y, data).unwrap()
+    }
+
+    /// Converts the image into a linear high-dynamic-range one;
+    /// low-dynamic-range images get decoded from sRGB.
+    pub fn to_rgba32f(&self) -> ::image::Rgba32FImage {
+        let data = self.pixels().flatten().collect();
+
+        ::image::Rgba32FImage::from_raw(self.size.x, self.size.y, data).unwrap()
+    }
+
+    /// Returns linear colors of all pixels, row by row.
+    fn pixels(&self) -> impl Iterator<Item = [f32; 4]> + '_ {
+        let format = self.format;
+        let bytes_per_pixel = format.block_size(None).unwrap() as usize;
+
+        self.data
+            .chunks_exact(bytes_per_pixel)
+            .map(move |px| match format {
+                // (when rendering into non-sRGB formats, the image gets
+                // sRGB-encoded manually, so both variants decode the same)
+                wgpu::TextureFormat::Rgba8Unorm
+                | wgpu::TextureFormat::Rgba8UnormSrgb => [
+                    decode_srgb(px[0]),
+                    decode_srgb(px[1]),
+                    decode_srgb(px[2]),
+                    px[3] as f32 / 255.0,
+                ],
+
+                wgpu::TextureFormat::Bgra8Unorm
+                | wgpu::TextureFormat::Bgra8UnormSrgb => [
+                    decode_srgb(px[2]),
+                    decode_srgb(px[1]),
+                    decode_srgb(px[0]),
+                    px[3] as f32 / 255.0,
+                ],
+
+                wgpu::TextureFormat::Rgba16Float => {
+                    let px: [u16; 4] = bytemuck::pod_read_unaligned(px);
+
+                    px.map(|value| f16::from_bits(value).to_f32())
+                }
+
+                wgpu::TextureFormat::Rgba32Float => {
+                    bytemuck::pod_read_unaligned(px)
+                }
+
+                format => panic!("unsupported format: {format:?}"),
+            })
+    }
+}
+
+fn decode_srgb(value: u8) -> f32 {
+    let value = value as f32 / 255.0;
+
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn encode_srgb(value: f32) -> u8 {
+    let value = value.clamp(0.0, 1.0);
+
+    let value = if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    };
+
+    (value * 255.0).round() as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use spirv_std::glam::uvec2;
+
+    use super::*;
+
+    #[test]
+    fn conversions() {
+        let image = CameraImage {
+            size: uvec2(2, 1),
+            format: wgpu::TextureFormat::Bgra8UnormSrgb,
+            data: vec![0, 128, 255, 255, 10, 20, 30, 0],
+        };
+
+        assert_eq!(
+            vec![255, 128, 0, 255, 30, 20, 10, 0],
+            image.to_rgba8().into_raw(),
+        );
+
+        let image = CameraImage {
+            size: uvec2(1, 1),
+            format: wgpu::TextureFormat::Rgba32Float,
+            data: bytemuck::cast_slice(&[0.5f32, 2.0, 0.0, 1.0]).to_vec(),
+        };
+
+        assert_eq!(vec![0.5, 2.0, 0.0, 1.0], image.to_rgba32f().into_raw());
+        assert_eq!(vec![188, 255, 0, 255], image.to_rgba8().into_raw());
+
+        let image = CameraImage {
+            size: uvec2(1, 1),
+            format: wgpu::TextureFormat::Rgba16Float,
+            data: bytemuck::cast_slice(&[0x3800u16, 0x4000, 0x0000, 0x3c00])
+                .to_vec(),
+        };
+
+        assert_eq!(vec![0.5, 2.0, 0.0, 1.0], image.to_rgba32f().into_raw());
+    }
+}
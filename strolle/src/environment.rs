@@ -0,0 +1,272 @@
+use std::f32::consts::PI;
+
+use glam::{uvec2, vec2, UVec2, Vec2, Vec3};
+
+use crate::gpu;
+use crate::gpu::Vec3Ext;
+
+/// Describes where the light that doesn't hit any geometry comes from.
+#[derive(Clone, Debug, Default)]
+pub enum Environment {
+    /// Procedural atmosphere, lit by the [`crate::Sun`].
+    #[default]
+    Atmosphere,
+
+    /// Image-based lighting, where the environment map is used for the
+    /// background, for rays that miss the geometry and as a light source of
+    /// its own.
+    ///
+    /// Note that environment maps usually contain the sun already, so when
+    /// this variant is active, the sun's directional light is disabled.
+    Map(EnvironmentMap),
+}
+
+/// HDR image describing the radiance coming from all directions.
+#[derive(Clone, Debug)]
+pub struct EnvironmentMap {
+    size: UVec2,
+    pixels: Vec<Vec3>,
+    intensity: f32,
+}
+
+impl EnvironmentMap {
+    /// Creates an environment map out of an equirectangular image, where `x`
+    /// goes around the horizon and `y` goes from the zenith (+Y) down to the
+    /// nadir (-Y).
+    ///
+    /// `pixels` must contain `size.x * size.y` items, row after row.
+    pub fn equirect(size: UVec2, pixels: Vec<Vec3>) -> Self {
+        assert!(size.x > 0 && size.y > 0);
+        assert_eq!((size.x * size.y) as usize, pixels.len());
+
+        Self {
+            size,
+            pixels,
+            intensity: 1.0,
+        }
+    }
+
+    /// Creates an environment map out of a cubemap, with faces given in the
+    /// usual order of +X, -X, +Y, -Y, +Z, -Z.
+    ///
+    /// Each face must contain `size * size` items, row after row; the cubemap
+    /// gets converted into an equirectangular image on the CPU.
+    pub fn cubemap(size: u32, faces: [Vec<Vec3>; 6]) -> Self {
+        assert!(size > 0);
+
+        for face in &faces {
+            assert_eq!((size * size) as usize, face.len());
+        }
+
+        let out_size = uvec2(4 * size, 2 * size);
+        let mut pixels = Vec::with_capacity((out_size.x * out_size.y) as usize);
+
+        for y in 0..out_size.y {
+            for x in 0..out_size.x {
+                let uv = (vec2(x as f32, y as f32) + 0.5) / out_size.as_vec2();
+                let dir = gpu::EnvironmentMapView::uv_to_dir(uv);
+
+                let (face, face_uv) = Self::cubemap_face(dir);
+
+                let face_pos = (face_uv * size as f32)
+                    .as_uvec2()
+                    .min(UVec2::splat(size - 1));
+
+                pixels.push(
+                    faces[face][(face_pos.y * size + face_pos.x) as usize],
+                );
+            }
+        }
+
+        Self::equirect(out_size, pixels)
+    }
+
+    /// Multiplies the radiance coming from this map.
+    pub fn with_intensity(mut self, intensity: f32) -> Self {
+        self.intensity = intensity;
+        self
+    }
+
+    pub fn size(&self) -> UVec2 {
+        self.size
+    }
+
+    pub(crate) fn pixels(&self) -> &[Vec3] {
+        &self.pixels
+    }
+
+    /// Returns face & uv of the cubemap's texel that is pointed by given
+    /// direction.
+    fn cubemap_face(dir: Vec3) -> (usize, Vec2) {
+        let abs = dir.abs();
+
+        let (face, sc, tc, ma) = if abs.x >= abs.y && abs.x >= abs.z {
+            if dir.x > 0.0 {
+                (0, -dir.z, -dir.y, abs.x)
+            } else {
+                (1, dir.z, -dir.y, abs.x)
+            }
+        } else if abs.y >= abs.z {
+            if dir.y > 0.0 {
+                (2, dir.x, dir.z, abs.y)
+            } else {
+                (3, dir.x, -dir.z, abs.y)
+            }
+        } else if dir.z > 0.0 {
+            (4, dir.x, -dir.y, abs.z)
+        } else {
+            (5, -dir.x, -dir.y, abs.z)
+        };
+
+        (face, 0.5 * (vec2(sc, tc) / ma + 1.0))
+    }
+
+    /// Serializes metadata and CDFs used to importance-sample this map, see
+    /// [`gpu::EnvironmentMapView`] for the layout.
+    pub(crate) fn serialize(&self) -> Vec<f32> {
+        let width = self.size.x as usize;
+        let height = self.size.y as usize;
+
+        let mut out =
+            vec![
+                0.0;
+                gpu::EnvironmentMapView::HEADER_SIZE + height + width * height
+            ];
+
+        let (header, cdfs) =
+            out.split_at_mut(gpu::EnvironmentMapView::HEADER_SIZE);
+
+        let (marginal, conditionals) = cdfs.split_at_mut(height);
+        let mut total = 0.0;
+
+        for (y, conditional) in conditionals.chunks_mut(width).enumerate() {
+            let sin_theta = (PI * (y as f32 + 0.5) / height as f32).sin();
+            let mut row_total = 0.0;
+
+            for (x, cdf) in conditional.iter_mut().enumerate() {
+                row_total += self.pixels[y * width + x].luma() * sin_theta;
+                *cdf = row_total;
+            }
+
+            if row_total > 0.0 {
+                for cdf in conditional.iter_mut() {
+                    *cdf /= row_total;
+                }
+            } else {
+                // Row is completely black, so the marginal CDF won't ever pick
+                // it - but let's keep the CDF valid anyway
+                for (x, cdf) in conditional.iter_mut().enumerate() {
+                    *cdf = (x + 1) as f32 / width as f32;
+                }
+            }
+
+            total += row_total;
+            marginal[y] = total;
+        }
+
+        if total > 0.0 {
+            for cdf in marginal.iter_mut() {
+                *cdf /= total;
+            }
+        }
+
+        // Probability of picking a texel is `luma * sin_theta / total`, while
+        // texel's solid angle is `(2 * PI / width) * (PI / height) *
+        // sin_theta` - dividing one by another gives us the pdf, in which
+        // everything but luma is constant
+        let pdf_norm = if total > 0.0 {
+            (width * height) as f32 / (2.0 * PI * PI * total)
+        } else {
+            0.0
+        };
+
+        header[0] = self.intensity.max(0.0);
+        header[1] = f32::from_bits(width as u32);
+        header[2] = f32::from_bits(height as u32);
+        header[3] = pdf_norm;
+
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::vec3;
+
+    use super::*;
+
+    #[test]
+    fn cubemap() {
+        let face = |color: Vec3| vec![color; 4];
+
+        let target = EnvironmentMap::cubemap(
+            2,
+            [
+                face(vec3(1.0, 0.0, 0.0)),
+                face(vec3(2.0, 0.0, 0.0)),
+                face(vec3(0.0, 1.0, 0.0)),
+                face(vec3(0.0, 2.0, 0.0)),
+                face(vec3(0.0, 0.0, 1.0)),
+                face(vec3(0.0, 0.0, 2.0)),
+            ],
+        );
+
+        assert_eq!(uvec2(8, 4), target.size());
+
+        let sample = |dir: Vec3| {
+            let uv = gpu::EnvironmentMapView::dir_to_uv(dir);
+            let pos = (uv * target.size().as_vec2()).as_uvec2();
+
+            target.pixels()[(pos.y * target.size().x + pos.x) as usize]
+        };
+
+        assert_eq!(vec3(1.0, 0.0, 0.0), sample(vec3(1.0, 0.0, 0.0)));
+        assert_eq!(vec3(2.0, 0.0, 0.0), sample(vec3(-1.0, 0.0, 0.1)));
+        assert_eq!(vec3(0.0, 1.0, 0.0), sample(vec3(0.0, 0.99, 0.1)));
+        assert_eq!(vec3(0.0, 2.0, 0.0), sample(vec3(0.0, -0.99, 0.1)));
+        assert_eq!(vec3(0.0, 0.0, 1.0), sample(vec3(0.1, 0.0, 1.0)));
+        assert_eq!(vec3(0.0, 0.0, 2.0), sample(vec3(0.1, 0.0, -1.0)));
+    }
+
+    #[test]
+    fn cubemap_round_trip() {
+        // Panoramic cameras should render faces the same way they're read
+        for face in 0..6 {
+            for uv in [vec2(0.5, 0.5), vec2(0.1, 0.8), vec2(0.9, 0.3)] {
+                let dir = gpu::Camera::cubemap_dir(face, uv);
+                let (face2, uv2) = EnvironmentMap::cubemap_face(dir);
+
+                assert_eq!(face as usize, face2);
+                assert!(uv.abs_diff_eq(uv2, 1e-4));
+            }
+        }
+    }
+
+    #[test]
+    fn serialize() {
+        let mut pixels = vec![Vec3::ZERO; 4 * 2];
+
+        pixels[6] = Vec3::ONE;
+
+        let target = EnvironmentMap::equirect(uvec2(4, 2), pixels)
+            .with_intensity(2.0)
+            .serialize();
+
+        assert_eq!(4 + 2 + 4 * 2, target.len());
+        assert_eq!(2.0, target[0]);
+        assert_eq!(4, target[1].to_bits());
+        assert_eq!(2, target[2].to_bits());
+
+        // All of the energy is contained within the second row...
+        assert_eq!(&[0.0, 1.0], &target[4..6]);
+
+        // ... and within the third texel of that row
+        assert_eq!(&[0.0, 0.0, 1.0, 1.0], &target[10..14]);
+
+        // Integrating the pdf over the sphere should yield one
+        let texel_solid_angle =
+            (2.0 * PI / 4.0) * (PI / 2.0) * (0.75 * PI).sin();
+
+        assert!((target[3] * texel_solid_angle - 1.0).abs() < 0.001);
+    }
+}
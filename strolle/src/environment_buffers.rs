@@ -0,0 +1,107 @@
+use glam::{uvec2, UVec2, Vec4};
+
+use crate::{
+    gpu, Bindable, BufferFlushOutcome, Environment, MappedStorageBuffer,
+    Texture,
+};
+
+#[derive(Debug)]
+pub struct EnvironmentBuffers {
+    map: Texture,
+    map_size: UVec2,
+    buffer: MappedStorageBuffer<Vec<f32>>,
+    pending: Option<Environment>,
+}
+
+impl EnvironmentBuffers {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let map_size = uvec2(1, 1);
+
+        Self {
+            map: Self::create_map(device, map_size),
+            map_size,
+            buffer: MappedStorageBuffer::new(
+                device,
+                "environment_map",
+                vec![0.0; gpu::EnvironmentMapView::HEADER_SIZE],
+            ),
+            pending: None,
+        }
+    }
+
+    pub fn update(&mut self, environment: Environment) {
+        self.pending = Some(environment);
+    }
+
+    pub fn flush(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> BufferFlushOutcome {
+        let mut reallocated = false;
+
+        match self.pending.take() {
+            Some(Environment::Atmosphere) => {
+                *self.buffer = vec![0.0; gpu::EnvironmentMapView::HEADER_SIZE];
+            }
+
+            Some(Environment::Map(map)) => {
+                if map.size() != self.map_size {
+                    self.map = Self::create_map(device, map.size());
+                    self.map_size = map.size();
+
+                    reallocated = true;
+                }
+
+                let pixels: Vec<_> = map
+                    .pixels()
+                    .iter()
+                    .map(|pixel| pixel.extend(0.0))
+                    .collect();
+
+                queue.write_texture(
+                    self.map.tex().as_image_copy(),
+                    bytemuck::cast_slice::<Vec4, u8>(&pixels),
+                    wgpu::ImageDataLayout {
+                        offset: 0,
+                        bytes_per_row: Some(map.size().x * 4 * 4),
+                        rows_per_image: None,
+                    },
+                    wgpu::Extent3d {
+                        width: map.size().x,
+                        height: map.size().y,
+                        depth_or_array_layers: 1,
+                    },
+                );
+
+                *self.buffer = map.serialize();
+            }
+
+            None => (),
+        }
+
+        BufferFlushOutcome {
+            reallocated: reallocated
+                | self.buffer.flush(device, queue).reallocated,
+        }
+    }
+
+    pub fn bind_map(&self) -> impl Bindable + '_ {
+        self.map.bind_sampled()
+    }
+
+    pub fn bind_buffer(&self) -> impl Bindable + '_ {
+        self.buffer.bind_readable()
+    }
+
+    fn create_map(device: &wgpu::Device, size: UVec2) -> Texture {
+        // Rgba32Float is not filterable, so the map gets sampled using the
+        // nearest texel - that's fine, since that's also what the CDFs assume
+        Texture::builder("environment_map")
+            .with_size(size)
+            .with_format(wgpu::TextureFormat::Rgba32Float)
+            .with_usage(wgpu::TextureUsages::TEXTURE_BINDING)
+            .with_usage(wgpu::TextureUsages::COPY_DST)
+            .build(device)
+    }
+}
@@ -0,0 +1,73 @@
+use glam::{Vec3, Vec4};
+
+use crate::gpu;
+
+/// Homogeneous fog that fills the entire world, optionally accompanied by
+/// boxes of additional fog (e.g. mist hanging over a lake).
+///
+/// Fog scatters light coming from the lights (including the sun), which makes
+/// light shafts visible wherever something blocks the light.
+///
+/// Coefficients are expressed per world-space unit; the default value doesn't
+/// contain any fog at all.
+///
+/// Note that fog is not rendered in [`crate::CameraMode::Reference`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Fog {
+    /// How much light gets scattered by the global fog; this determines fog's
+    /// color and brightness.
+    pub scattering: Vec3,
+
+    /// How much light gets absorbed by the global fog; this makes the fog
+    /// darker.
+    pub absorption: Vec3,
+
+    /// Anisotropy of the scattering, from -1.0 (back-scattering) through 0.0
+    /// (isotropic) to 1.0 (forward-scattering) - the larger the value, the
+    /// more light shafts stand out when looking towards the light.
+    ///
+    /// Shared by the global fog and all of the volumes.
+    pub anisotropy: f32,
+
+    pub volumes: Vec<FogVolume>,
+}
+
+impl Fog {
+    pub fn is_active(&self) -> bool {
+        self.scattering.max_element() > 0.0
+            || self.absorption.max_element() > 0.0
+            || !self.volumes.is_empty()
+    }
+
+    pub(crate) fn serialize(&self) -> Vec<Vec4> {
+        let mut out = Vec::with_capacity(
+            gpu::FogView::HEADER_SIZE
+                + gpu::FogView::VOLUME_SIZE * self.volumes.len(),
+        );
+
+        out.push(self.scattering.extend(self.anisotropy.clamp(-0.99, 0.99)));
+
+        out.push(
+            self.absorption
+                .extend(f32::from_bits(self.volumes.len() as u32)),
+        );
+
+        for volume in &self.volumes {
+            out.push(volume.min.extend(0.0));
+            out.push(volume.max.extend(0.0));
+            out.push(volume.scattering.extend(0.0));
+            out.push(volume.absorption.extend(0.0));
+        }
+
+        out
+    }
+}
+
+/// Axis-aligned box of fog, added on top of the global one.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FogVolume {
+    pub min: Vec3,
+    pub max: Vec3,
+    pub scattering: Vec3,
+    pub absorption: Vec3,
+}
@@ -0,0 +1,392 @@
+use std::error::Error;
+use std::fmt;
+
+use half::f16;
+
+use crate::gpu;
+
+/// Photometric profile of a light, as described by an IES (LM-63) file.
+///
+/// Only type C photometry is supported, which is what virtually all of the
+/// architectural fixtures use; vertical angle of 0° corresponds to the light's
+/// direction.
+#[derive(Clone, Debug)]
+pub struct IesProfile {
+    vertical_angles: Vec<f32>,
+    horizontal_angles: Vec<f32>,
+
+    /// Candelas for each horizontal angle, for each vertical angle, i.e.
+    /// `candelas[h * vertical_angles.len() + v]`
+    candelas: Vec<f32>,
+}
+
+impl IesProfile {
+    /// Maximum number of angles (vertical or horizontal) a profile can have;
+    /// real-world profiles stay well below this limit (even at 0.25°
+    /// resolution, vertical angles come down to 721 entries), so anything
+    /// larger is most likely a corrupted file.
+    const MAX_ANGLES: usize = 1024;
+
+    pub fn parse(data: &str) -> Result<Self, IesError> {
+        let mut lines = data.lines();
+
+        let tilt = loop {
+            let line = lines.next().ok_or(IesError::MissingTilt)?;
+
+            if let Some(tilt) = line.trim().strip_prefix("TILT=") {
+                break tilt.trim().to_owned();
+            }
+        };
+
+        let mut numbers = lines
+            .flat_map(|line| {
+                line.split(|c: char| c.is_whitespace() || c == ',')
+            })
+            .filter(|number| !number.is_empty())
+            .map(|number| {
+                number
+                    .parse::<f32>()
+                    .ok()
+                    .filter(|number| number.is_finite())
+                    .ok_or_else(|| IesError::InvalidNumber(number.to_owned()))
+            });
+
+        let mut next =
+            || numbers.next().unwrap_or(Err(IesError::UnexpectedEof));
+
+        // Tilt data is meaningful only for lamps that change their output
+        // depending on their inclination, which we don't support - so let's
+        // just skip it
+        if tilt == "INCLUDE" {
+            let _lamp_to_luminaire_geometry = next()?;
+            let pairs = Self::count(next()?, "number of tilt angles")?;
+
+            for _ in 0..(2 * pairs) {
+                next()?;
+            }
+        }
+
+        let _lamps = next()?;
+        let _lumens_per_lamp = next()?;
+        let multiplier = next()?;
+        let vertical_count = Self::count(next()?, "number of vertical angles")?;
+        let horizontal_count =
+            Self::count(next()?, "number of horizontal angles")?;
+        let photometric_type = Self::count(next()?, "photometric type")?;
+        let _units_type = next()?;
+        let _width = next()?;
+        let _length = next()?;
+        let _height = next()?;
+        let ballast_factor = next()?;
+        let _future_use = next()?;
+        let _input_watts = next()?;
+
+        if photometric_type != 1 {
+            return Err(IesError::UnsupportedPhotometricType(photometric_type));
+        }
+
+        if multiplier < 0.0 {
+            return Err(IesError::InvalidHeader("candela multiplier"));
+        }
+
+        if ballast_factor < 0.0 {
+            return Err(IesError::InvalidHeader("ballast factor"));
+        }
+
+        if vertical_count == 0 || horizontal_count == 0 {
+            return Err(IesError::InvalidAngles);
+        }
+
+        let candela_count = vertical_count
+            .checked_mul(horizontal_count)
+            .ok_or(IesError::InvalidHeader("number of angles"))?;
+
+        let vertical_angles = (0..vertical_count)
+            .map(|_| next())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let horizontal_angles = (0..horizontal_count)
+            .map(|_| next())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let candelas = (0..candela_count)
+            .map(|_| next().map(|cd| cd * multiplier * ballast_factor))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Type C photometry measures vertical angles from the light's
+        // direction and horizontal angles around it, so anything outside of
+        // those ranges (or unsorted) can't be looked up
+        let is_valid = |angles: &[f32], max: f32| {
+            angles.iter().all(|angle| (0.0..=max).contains(angle))
+                && angles.windows(2).all(|pair| pair[0] < pair[1])
+        };
+
+        if !is_valid(&vertical_angles, 180.0)
+            || !is_valid(&horizontal_angles, 360.0)
+        {
+            return Err(IesError::InvalidAngles);
+        }
+
+        Ok(Self {
+            vertical_angles,
+            horizontal_angles,
+            candelas,
+        })
+    }
+
+    /// Converts a header's field into a count, making sure it's a reasonable
+    /// one - so that a corrupted file can't make us allocate gigabytes.
+    fn count(value: f32, field: &'static str) -> Result<usize, IesError> {
+        if value < 0.0
+            || value.fract() != 0.0
+            || value > Self::MAX_ANGLES as f32
+        {
+            return Err(IesError::InvalidHeader(field));
+        }
+
+        Ok(value as usize)
+    }
+
+    /// Returns intensity (in candelas) emitted in given direction, with
+    /// angles expressed in degrees.
+    pub fn candela(&self, vertical: f32, horizontal: f32) -> f32 {
+        let Some((v0, v1, vt)) = Self::locate(&self.vertical_angles, vertical)
+        else {
+            return 0.0;
+        };
+
+        let (h0, h1, ht) = {
+            let horizontal = self.unfold_horizontal(horizontal).clamp(
+                self.horizontal_angles[0],
+                *self.horizontal_angles.last().unwrap(),
+            );
+
+            Self::locate(&self.horizontal_angles, horizontal)
+                .unwrap_or((0, 0, 0.0))
+        };
+
+        let at = |h: usize, v: usize| {
+            self.candelas[h * self.vertical_angles.len() + v]
+        };
+
+        let a = at(h0, v0) + (at(h0, v1) - at(h0, v0)) * vt;
+        let b = at(h1, v0) + (at(h1, v1) - at(h1, v0)) * vt;
+
+        a + (b - a) * ht
+    }
+
+    /// Bakes this profile into a lookup texture, see
+    /// [`gpu::IesProfilesView`] for the layout.
+    pub(crate) fn bake(&self) -> Vec<f16> {
+        let width = gpu::IesProfilesView::LUT_WIDTH;
+        let height = gpu::IesProfilesView::LUT_HEIGHT;
+        let max = self.candelas.iter().copied().fold(0.0, f32::max);
+        let mut lut = Vec::with_capacity((width * height) as usize);
+
+        for y in 0..height {
+            for x in 0..width {
+                let vertical = (x as f32 + 0.5) / (width as f32) * 180.0;
+                let horizontal = (y as f32 + 0.5) / (height as f32) * 360.0;
+
+                let value = if max > 0.0 {
+                    self.candela(vertical, horizontal) / max
+                } else {
+                    0.0
+                };
+
+                lut.push(f16::from_f32(value.clamp(0.0, 1.0)));
+            }
+        }
+
+        lut
+    }
+
+    /// Maps horizontal angle into the range covered by this profile,
+    /// following the symmetries defined by the IES format.
+    fn unfold_horizontal(&self, horizontal: f32) -> f32 {
+        let horizontal = horizontal.rem_euclid(360.0);
+        let last = *self.horizontal_angles.last().unwrap();
+
+        if self.horizontal_angles.len() == 1 {
+            // Laterally symmetric
+            self.horizontal_angles[0]
+        } else if last <= 90.0 {
+            // Quadrant-symmetric
+            let horizontal = if horizontal > 180.0 {
+                360.0 - horizontal
+            } else {
+                horizontal
+            };
+
+            if horizontal > 90.0 {
+                180.0 - horizontal
+            } else {
+                horizontal
+            }
+        } else if last <= 180.0 {
+            // Bilaterally symmetric
+            if horizontal > 180.0 {
+                360.0 - horizontal
+            } else {
+                horizontal
+            }
+        } else {
+            horizontal
+        }
+    }
+
+    /// Finds angles surrounding given angle; returns their indices together
+    /// with the interpolation factor between them.
+    fn locate(angles: &[f32], angle: f32) -> Option<(usize, usize, f32)> {
+        let first = angles[0];
+        let last = *angles.last().unwrap();
+
+        if angle < first || angle > last {
+            return None;
+        }
+
+        if angles.len() == 1 {
+            return Some((0, 0, 0.0));
+        }
+
+        let idx = angles
+            .partition_point(|&other| other <= angle)
+            .saturating_sub(1)
+            .min(angles.len() - 2);
+
+        let t = (angle - angles[idx]) / (angles[idx + 1] - angles[idx]);
+
+        Some((idx, idx + 1, t))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum IesError {
+    MissingTilt,
+    UnexpectedEof,
+    InvalidNumber(String),
+    InvalidHeader(&'static str),
+    UnsupportedPhotometricType(usize),
+    InvalidAngles,
+}
+
+impl fmt::Display for IesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IesError::MissingTilt => write!(f, "missing `TILT=` line"),
+            IesError::UnexpectedEof => write!(f, "unexpected end of file"),
+            IesError::InvalidNumber(number) => {
+                write!(f, "invalid number: `{}`", number)
+            }
+            IesError::InvalidHeader(field) => {
+                write!(f, "invalid header: {} is out of range", field)
+            }
+            IesError::UnsupportedPhotometricType(ty) => {
+                write!(
+                    f,
+                    "unsupported photometric type: {} (only type C is \
+                     supported)",
+                    ty
+                )
+            }
+            IesError::InvalidAngles => {
+                write!(
+                    f,
+                    "angles are missing, out of range or not in ascending \
+                     order"
+                )
+            }
+        }
+    }
+}
+
+impl Error for IesError {}
+
+/// Handle to an IES profile, as created by
+/// [`crate::Engine::create_ies_profile()`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct IesProfileHandle(u32);
+
+impl IesProfileHandle {
+    pub(crate) fn new(id: u32) -> Self {
+        Self(id)
+    }
+
+    pub(crate) fn get(&self) -> u32 {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROFILE: &str = "IESNA:LM-63-2002
+[TEST] strolle
+[MANUFAC] strolle
+TILT=NONE
+1 1000 1 3 2 1 2 0 0 0
+1 1 100
+0 45 90
+0 90
+100 50 0
+200 100 0
+";
+
+    #[test]
+    fn parse() {
+        let profile = IesProfile::parse(PROFILE).unwrap();
+
+        assert_eq!(100.0, profile.candela(0.0, 0.0));
+        assert_eq!(75.0, profile.candela(22.5, 0.0));
+        assert_eq!(112.5, profile.candela(22.5, 45.0));
+        assert_eq!(150.0, profile.candela(22.5, 90.0));
+        assert_eq!(0.0, profile.candela(120.0, 0.0));
+
+        // Quadrant symmetry
+        assert_eq!(profile.candela(10.0, 30.0), profile.candela(10.0, 150.0));
+        assert_eq!(profile.candela(10.0, 30.0), profile.candela(10.0, 330.0));
+
+        let lut = profile.bake();
+
+        assert_eq!(
+            (gpu::IesProfilesView::LUT_WIDTH * gpu::IesProfilesView::LUT_HEIGHT)
+                as usize,
+            lut.len()
+        );
+
+        assert!(lut.iter().any(|value| value.to_f32() > 0.9));
+        assert_eq!(0.0, lut.last().unwrap().to_f32());
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(
+            IesError::MissingTilt,
+            IesProfile::parse("IESNA:LM-63-2002").unwrap_err()
+        );
+
+        assert_eq!(
+            IesError::UnexpectedEof,
+            IesProfile::parse("TILT=NONE\n1 1000 1 3").unwrap_err()
+        );
+
+        assert_eq!(
+            IesError::UnsupportedPhotometricType(2),
+            IesProfile::parse(&PROFILE.replace("3 2 1 2", "3 2 2 2"))
+                .unwrap_err()
+        );
+
+        assert_eq!(
+            IesError::InvalidHeader("number of vertical angles"),
+            IesProfile::parse(&PROFILE.replace("3 2 1 2", "1e9 2 1 2"))
+                .unwrap_err()
+        );
+
+        assert_eq!(
+            IesError::InvalidAngles,
+            IesProfile::parse(&PROFILE.replace("0 45 90", "0 45 190"))
+                .unwrap_err()
+        );
+    }
+}
@@ -0,0 +1,96 @@
+use std::mem;
+
+use glam::uvec2;
+use half::f16;
+
+use crate::{gpu, Bindable, IesProfile, IesProfileHandle, Texture};
+
+#[derive(Debug)]
+pub struct IesProfiles {
+    lut: Texture,
+    slots: Vec<bool>,
+    changes: Vec<(IesProfileHandle, Vec<f16>)>,
+}
+
+impl IesProfiles {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let lut = Texture::builder("ies_profiles_lut")
+            .with_size(uvec2(
+                gpu::IesProfilesView::LUT_WIDTH,
+                gpu::IesProfilesView::LUT_HEIGHT
+                    * gpu::IesProfilesView::MAX_PROFILES,
+            ))
+            .with_format(wgpu::TextureFormat::R16Float)
+            .with_usage(wgpu::TextureUsages::TEXTURE_BINDING)
+            .with_usage(wgpu::TextureUsages::COPY_DST)
+            .with_linear_filtering_sampler()
+            .build(device);
+
+        Self {
+            lut,
+            slots: vec![false; gpu::IesProfilesView::MAX_PROFILES as usize],
+            changes: Default::default(),
+        }
+    }
+
+    pub fn create(&mut self, profile: &IesProfile) -> Option<IesProfileHandle> {
+        let slot = self.slots.iter().position(|&taken| !taken)?;
+        let handle = IesProfileHandle::new(slot as u32);
+
+        self.slots[slot] = true;
+        self.changes.push((handle, profile.bake()));
+
+        Some(handle)
+    }
+
+    pub fn delete(&mut self, handle: IesProfileHandle) {
+        let Some(slot) = self.slots.get_mut(handle.get() as usize) else {
+            return;
+        };
+
+        if mem::take(slot) {
+            let size = gpu::IesProfilesView::LUT_WIDTH
+                * gpu::IesProfilesView::LUT_HEIGHT;
+
+            self.changes.push((handle, vec![f16::ZERO; size as usize]));
+        }
+    }
+
+    pub fn flush(&mut self, queue: &wgpu::Queue) {
+        for (handle, data) in mem::take(&mut self.changes) {
+            let data: Vec<_> =
+                data.iter().flat_map(|value| value.to_le_bytes()).collect();
+
+            queue.write_texture(
+                wgpu::ImageCopyTexture {
+                    texture: self.lut.tex(),
+                    mip_level: 0,
+                    origin: wgpu::Origin3d {
+                        x: 0,
+                        y: handle.get() * gpu::IesProfilesView::LUT_HEIGHT,
+                        z: 0,
+                    },
+                    aspect: wgpu::TextureAspect::All,
+                },
+                &data,
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(
+                        gpu::IesProfilesView::LUT_WIDTH
+                            * mem::size_of::<f16>() as u32,
+                    ),
+                    rows_per_image: None,
+                },
+                wgpu::Extent3d {
+                    width: gpu::IesProfilesView::LUT_WIDTH,
+                    height: gpu::IesProfilesView::LUT_HEIGHT,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+    }
+
+    pub fn bind_lut(&self) -> impl Bindable + '_ {
+        self.lut.bind_sampled()
+    }
+}
@@ -7,6 +7,7 @@ use glam::Affine3A;
 use rand::Rng;
 
 use crate::bvh::Bvh;
+use crate::lights::Lights;
 use crate::materials::Materials;
 use crate::meshes::Meshes;
 use crate::triangles::Triangles;
@@ -20,6 +21,7 @@ where
 {
     instances: HashMap<P::InstanceHandle, InstanceEntry<P>>,
     dirty: bool,
+    has_dirty_light_links: bool,
 }
 
 impl<P> Instances<P>
@@ -40,6 +42,7 @@ where
                 entry.insert(InstanceEntry {
                     prev_transform: item.transform,
                     uuid: rand::thread_rng().gen(),
+                    excluded_lights: 0,
                     dirty: true,
                     instance: item,
                 });
@@ -62,6 +65,12 @@ where
         self.dirty |= self.instances.remove(&handle).is_some();
     }
 
+    /// Marks instances' light-linking as outdated, so that it gets recomputed
+    /// during the next refresh.
+    pub fn invalidate_light_links(&mut self) {
+        self.has_dirty_light_links = true;
+    }
+
     pub fn is_empty(&self) -> bool {
         self.instances.is_empty()
     }
@@ -70,14 +79,32 @@ where
         &mut self,
         meshes: &Meshes<P>,
         materials: &Materials<P>,
+        lights: &Lights<P>,
         triangles: &mut Triangles<P>,
         bvh: &mut Bvh,
     ) -> bool {
-        if !mem::take(&mut self.dirty) {
+        let mut is_dirty = mem::take(&mut self.dirty);
+        let has_dirty_light_links = mem::take(&mut self.has_dirty_light_links);
+
+        if !is_dirty && !has_dirty_light_links {
             return false;
         }
 
         for (&instance_handle, entry) in &mut self.instances {
+            // (computing the mask is linear in the number of linked lights, so
+            // we do it only when necessary instead of each frame)
+            if entry.dirty || has_dirty_light_links {
+                let excluded_lights = lights.excluded_lights(instance_handle);
+
+                // BVH stores the mask as well (for traced rays to know it), so
+                // changing it requires rebuilding instance's triangles
+                if excluded_lights != entry.excluded_lights {
+                    entry.excluded_lights = excluded_lights;
+                    entry.dirty = true;
+                    is_dirty = true;
+                }
+            }
+
             if !mem::take(&mut entry.dirty) {
                 continue;
             }
@@ -114,6 +141,7 @@ where
                         instance_handle,
                         mesh_triangles,
                         material_id,
+                        entry.excluded_lights,
                     );
                 } else {
                     triangles.remove(bvh, instance_handle);
@@ -123,6 +151,7 @@ where
                         instance_handle.to_owned(),
                         mesh_triangles,
                         material_id,
+                        entry.excluded_lights,
                     );
                 }
             } else {
@@ -131,11 +160,12 @@ where
                     instance_handle.to_owned(),
                     mesh_triangles,
                     material_id,
+                    entry.excluded_lights,
                 );
             }
         }
 
-        true
+        is_dirty
     }
 }
 
@@ -147,5 +177,10 @@ where
     pub instance: Instance<P>,
     pub uuid: u32,
     pub prev_transform: Affine3A,
+
+    /// Bitmask of linked lights that don't affect this instance, see
+    /// [`crate::gpu::GBufferEntry::excluded_lights`].
+    pub excluded_lights: u32,
+
     pub dirty: bool,
 }
@@ -42,26 +42,56 @@
 //!
 //! Light defines how the scene should get lightened - i.e. whether it's a
 //! point-light, a cone-light etc.
+//!
+//! ## IES profile
+//!
+//! IES profile describes how much light a real-world fixture emits in each
+//! direction; point- and spot-lights can refer to a profile to use it instead
+//! of the default (uniform or cone-shaped) distribution.
+//!
+//! ## Environment
+//!
+//! Environment determines what's visible in the background and where the light
+//! that doesn't hit any geometry comes from - it's either the procedural
+//! atmosphere, lit by the sun (or the moon, during the night), or an HDR
+//! environment map.
+//!
+//! ## Fog
+//!
+//! Fog determines how light gets scattered and absorbed on its way towards the
+//! camera - there's a global fog that fills the entire world, plus optional
+//! boxes of additional fog.
 
 #![feature(hash_raw_entry)]
 #![feature(lint_reasons)]
 
+mod atmosphere_luts;
+mod atmosphere_settings;
 mod buffers;
 mod bvh;
 mod camera;
 mod camera_controller;
 mod camera_controllers;
+mod camera_export;
+mod camera_image;
+mod environment;
+mod environment_buffers;
+mod fog;
+mod ies_profile;
+mod ies_profiles;
 mod image;
 mod images;
 mod instance;
 mod instances;
 mod light;
+mod light_linking;
 mod lights;
 mod material;
 mod materials;
 mod mesh;
 mod mesh_triangle;
 mod meshes;
+mod moon;
 mod noise;
 mod shaders;
 mod sun;
@@ -72,29 +102,42 @@ mod utils;
 use std::fmt::Debug;
 use std::hash::Hash;
 use std::ops::Deref;
+use std::sync::Arc;
 use std::time::Instant;
 use std::{env, mem};
 
 pub use glam;
+use glam::Vec4;
 use log::{info, trace};
 use strolle_gpu as gpu;
 
+pub(crate) use self::atmosphere_luts::*;
+pub use self::atmosphere_settings::*;
 pub(crate) use self::buffers::*;
 pub(crate) use self::bvh::*;
 pub use self::camera::*;
 pub(crate) use self::camera_controller::*;
 pub(crate) use self::camera_controllers::*;
+pub use self::camera_export::*;
+pub use self::camera_image::*;
+pub use self::environment::*;
+pub(crate) use self::environment_buffers::*;
+pub use self::fog::*;
+pub use self::ies_profile::*;
+pub(crate) use self::ies_profiles::*;
 pub use self::image::*;
 pub(crate) use self::images::*;
 pub use self::instance::*;
 pub(crate) use self::instances::*;
 pub use self::light::*;
+pub use self::light_linking::*;
 pub(crate) use self::lights::*;
 pub use self::material::*;
 pub(crate) use self::materials::*;
 pub use self::mesh::*;
 pub use self::mesh_triangle::*;
 pub(crate) use self::meshes::*;
+pub use self::moon::*;
 pub(crate) use self::noise::*;
 pub(crate) use self::shaders::*;
 pub use self::sun::*;
@@ -114,15 +157,28 @@ where
     triangles: Triangles<P>,
     bvh: Bvh,
     lights: Lights<P>,
+    ies_profiles: IesProfiles,
+    environment: EnvironmentBuffers,
     images: Images<P>,
     materials: Materials<P>,
     world: MappedUniformBuffer<gpu::World>,
+    atmosphere: MappedUniformBuffer<gpu::AtmosphereSettings>,
+    atmosphere_exposure: f32,
+    atmosphere_luts: AtmosphereLuts,
+    fog: MappedStorageBuffer<Vec<Vec4>>,
     cameras: CameraControllers,
     sun: Sun,
+    moon: Option<Moon>,
+    has_environment_map: bool,
+    has_fog: bool,
     frame: gpu::Frame,
     has_dirty_materials: bool,
     has_dirty_images: bool,
+    has_dirty_lights: bool,
+    has_dirty_light_links: bool,
+    has_dirty_ies_profiles: bool,
     has_dirty_sun: bool,
+    has_dirty_fog: bool,
     print_stats: bool,
 }
 
@@ -133,27 +189,56 @@ where
     pub fn new(device: &wgpu::Device) -> Self {
         info!("Initializing");
 
+        let shaders = Shaders::new(device);
+
+        let world =
+            MappedUniformBuffer::new(device, "world", Default::default());
+
+        let atmosphere_settings = AtmosphereSettings::default();
+
+        let atmosphere = MappedUniformBuffer::new(
+            device,
+            "atmosphere",
+            atmosphere_settings.serialize(),
+        );
+
+        let atmosphere_luts =
+            AtmosphereLuts::new(device, &shaders, &world, &atmosphere);
+
         Self {
-            shaders: Shaders::new(device),
+            shaders,
             noise: Noise::new(device),
             meshes: Meshes::default(),
             instances: Instances::default(),
             triangles: Triangles::new(device),
             bvh: Bvh::new(device),
             lights: Lights::new(device),
+            ies_profiles: IesProfiles::new(device),
+            environment: EnvironmentBuffers::new(device),
             images: Images::new(device),
             materials: Materials::new(device),
-            world: MappedUniformBuffer::new(
+            world,
+            atmosphere,
+            atmosphere_exposure: atmosphere_settings.exposure,
+            atmosphere_luts,
+            fog: MappedStorageBuffer::new(
                 device,
-                "world",
-                Default::default(),
+                "fog",
+                Fog::default().serialize(),
             ),
             cameras: Default::default(),
             sun: Default::default(),
+            moon: None,
+            has_environment_map: false,
+            has_fog: false,
             frame: gpu::Frame::new(1),
             has_dirty_materials: false,
             has_dirty_images: false,
+            has_dirty_lights: false,
+            has_dirty_light_links: false,
+            has_dirty_ies_profiles: false,
             has_dirty_sun: true,
+            has_dirty_fog: false,
             print_stats: env::var("STROLLE_STATS").as_deref() == Ok("1"),
         }
     }
@@ -232,11 +317,62 @@ where
     /// Creates or updates a light.
     pub fn insert_light(&mut self, handle: P::LightHandle, item: Light) {
         self.lights.insert(handle, item);
+        self.has_dirty_lights = true;
     }
 
     /// Removes a light.
     pub fn remove_light(&mut self, handle: P::LightHandle) {
         self.lights.remove(handle);
+        self.has_dirty_lights = true;
+    }
+
+    /// Creates or updates light-linking for given light.
+    ///
+    /// Light-linking is kept separately from the light itself, so it's fine to
+    /// call this function before the light gets inserted - and it's also
+    /// necessary to call [`Self::remove_light_linking()`] once it's no longer
+    /// needed.
+    ///
+    /// At most [`gpu::Light::MAX_LINKED_LIGHTS`] lights can be linked at once;
+    /// linking for lights above that limit gets ignored.
+    pub fn insert_light_linking(
+        &mut self,
+        handle: P::LightHandle,
+        linking: LightLinking<P>,
+    ) {
+        self.lights.insert_linking(handle, linking);
+        self.has_dirty_light_links = true;
+    }
+
+    /// Removes light-linking for given light, making it affect all instances
+    /// again.
+    pub fn remove_light_linking(&mut self, handle: P::LightHandle) {
+        self.lights.remove_linking(handle);
+        self.has_dirty_light_links = true;
+    }
+
+    /// Loads an IES profile, so that it can be referred to by lights.
+    ///
+    /// Returns `None` if there are already
+    /// [`gpu::IesProfilesView::MAX_PROFILES`] profiles loaded.
+    pub fn create_ies_profile(
+        &mut self,
+        profile: &IesProfile,
+    ) -> Option<IesProfileHandle> {
+        let handle = self.ies_profiles.create(profile)?;
+
+        self.has_dirty_ies_profiles = true;
+
+        Some(handle)
+    }
+
+    /// Deletes an IES profile.
+    ///
+    /// Lights that still refer to this profile will stop emitting any light,
+    /// and the handle can get reused by a profile created later.
+    pub fn delete_ies_profile(&mut self, handle: IesProfileHandle) {
+        self.ies_profiles.delete(handle);
+        self.has_dirty_ies_profiles = true;
     }
 
     /// Updates sun's parameters.
@@ -245,11 +381,56 @@ where
         self.has_dirty_sun = true;
     }
 
+    /// Updates moon's parameters; `None` (the default) places a full moon on
+    /// the opposite side of the sky than the sun.
+    ///
+    /// As the sun goes below the horizon, the moon gradually becomes the
+    /// primary directional light (see [`Sun::TWILIGHT`]).
+    pub fn update_moon(&mut self, moon: Option<Moon>) {
+        self.moon = moon;
+        self.has_dirty_sun = true;
+    }
+
+    /// Updates atmosphere's parameters.
+    ///
+    /// Note that changing atmosphere's parameters causes all of its lookup
+    /// textures to get regenerated, so it's not something you'd like to do
+    /// each frame.
+    pub fn update_atmosphere(&mut self, settings: AtmosphereSettings) {
+        *self.atmosphere = settings.serialize();
+        self.atmosphere_exposure = settings.exposure;
+        self.has_dirty_sun = true;
+    }
+
+    /// Updates the environment, i.e. switches between the atmosphere and an
+    /// environment map.
+    ///
+    /// Note that this is a pretty heavy operation (environment map has to be
+    /// uploaded into the GPU, together with the data used to importance-sample
+    /// it), so it's expected that you only call this function when necessary.
+    pub fn update_environment(&mut self, environment: Environment) {
+        self.has_environment_map = matches!(environment, Environment::Map(_));
+
+        self.environment.update(environment);
+        self.has_dirty_sun = true;
+    }
+
+    /// Updates the fog.
+    pub fn update_fog(&mut self, fog: Fog) {
+        self.has_fog = fog.is_active();
+        *self.fog = fog.serialize();
+        self.has_dirty_fog = true;
+    }
+
     /// Creates a new camera that can be used to render the world.
     ///
     /// Note that this is a pretty heavy operation that allocates per-camera
     /// buffers etc., and so it's expected that you only call this function when
     /// necessary (not, say, each frame).
+    ///
+    /// If the camera's configuration is not supported, e.g. when a
+    /// [`Camera::panorama`] is used together with a rasterized mode, a warning
+    /// gets logged and the camera falls back to a supported configuration.
     pub fn create_camera(
         &mut self,
         device: &wgpu::Device,
@@ -260,6 +441,8 @@ where
     }
 
     /// Updates camera, changing its mode, position, size etc.
+    ///
+    /// See: [`Self::create_camera()`].
     pub fn update_camera(
         &mut self,
         device: &wgpu::Device,
@@ -286,6 +469,225 @@ where
         self.cameras.get(handle).render(self, encoder, view);
     }
 
+    /// Renders camera into an offscreen texture and reads the image back.
+    ///
+    /// `frames` frames get rendered one after another, so that the temporal
+    /// effects (anti-aliasing, denoising, auto-exposure etc.) have a chance to
+    /// settle down; when the camera is in [`CameraMode::Reference`] with
+    /// [`Camera::reference_samples`] set, rendering continues until the target
+    /// number of samples gets accumulated.
+    ///
+    /// Since this function calls [`Self::tick()`] and then blocks until the
+    /// GPU is done, it's meant for thumbnails, tests, server-side renders and
+    /// similar use cases - not for rendering each frame.
+    ///
+    /// # Panics
+    ///
+    /// Panics if camera's viewport format is not supported, see:
+    /// [`CameraImage::supports()`].
+    pub fn render_camera_offscreen(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        handle: CameraHandle,
+        frames: u32,
+    ) -> CameraImage {
+        let viewport = self.cameras.get(handle).camera().viewport.clone();
+
+        assert!(
+            CameraImage::supports(viewport.format),
+            "unsupported viewport format: {:?}",
+            viewport.format,
+        );
+
+        let bytes_per_row = ReadbackBuffer::padded_bytes_per_row(
+            viewport.size.x,
+            viewport.format,
+        );
+
+        let mut readback = ReadbackBuffer::new(
+            device,
+            "offscreen_readback",
+            (bytes_per_row * viewport.size.y) as usize,
+        );
+
+        self.render_camera_offscreen_ex(
+            device,
+            queue,
+            handle,
+            frames,
+            |encoder, target, _| {
+                readback.copy_from_texture_at(
+                    encoder,
+                    target,
+                    viewport.position,
+                    viewport.size,
+                );
+            },
+        );
+
+        // (the first call schedules mapping the buffer, the second one - after
+        // the GPU is done - reads it)
+        readback.read_with(|_| ());
+        device.poll(wgpu::Maintain::Wait);
+
+        let bytes_per_pixel =
+            viewport.format.block_size(None).unwrap_or_default();
+
+        let data = readback
+            .read_with(|bytes| {
+                bytes
+                    .chunks(bytes_per_row as usize)
+                    .take(viewport.size.y as usize)
+                    .flat_map(|row| {
+                        &row[..(viewport.size.x * bytes_per_pixel) as usize]
+                    })
+                    .copied()
+                    .collect()
+            })
+            .expect("couldn't read the image back");
+
+        CameraImage {
+            size: viewport.size,
+            format: viewport.format,
+            data,
+        }
+    }
+
+    /// Renders camera offscreen and reads given layers back, in linear
+    /// high-dynamic range - the result can be then saved as an OpenEXR or
+    /// Radiance HDR image.
+    ///
+    /// Frames get rendered the same way as in
+    /// [`Self::render_camera_offscreen()`].
+    ///
+    /// Lighting layers are meaningful only in the modes that compute them (see:
+    /// [`CameraExportLayer::is_lighting()`]); in [`CameraMode::Reference`],
+    /// only [`CameraExportLayer::Color`] carries the path-traced image.
+    pub fn export_camera(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        handle: CameraHandle,
+        frames: u32,
+        layers: &[CameraExportLayer],
+    ) -> CameraExport {
+        let mut export = Export::new(device, self.cameras.get(handle), layers);
+
+        self.render_camera_offscreen_ex(
+            device,
+            queue,
+            handle,
+            frames,
+            |encoder, _, camera| {
+                export.end(encoder, camera);
+            },
+        );
+
+        export.read(device)
+    }
+
+    /// Renders camera offscreen `frames` times and calls `end` before
+    /// submitting the last frame.
+    fn render_camera_offscreen_ex(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        handle: CameraHandle,
+        frames: u32,
+        end: impl FnOnce(&mut wgpu::CommandEncoder, &Texture, &CameraController),
+    ) {
+        // How many frames can be submitted before we wait for the GPU to catch
+        // up - without this, rendering thousands of frames (e.g. reference
+        // samples) would queue them all up at once, ballooning the memory
+        const FRAMES_PER_POLL: u32 = 4;
+
+        let viewport = self.cameras.get(handle).camera().viewport.clone();
+
+        // (the image gets rendered at viewport's position, so the texture has
+        // to be large enough to contain it)
+        let target = Texture::builder("offscreen")
+            .with_size(viewport.position + viewport.size)
+            .with_format(viewport.format)
+            .with_usage(wgpu::TextureUsages::RENDER_ATTACHMENT)
+            .with_usage(wgpu::TextureUsages::COPY_SRC)
+            .build(device);
+
+        let mut end = Some(end);
+        let mut frame = 1;
+
+        loop {
+            self.tick(device, queue);
+
+            let is_last_frame = frame >= frames
+                && self.camera_reference(handle).map_or(true, |reference| {
+                    reference.target_samples.is_none() || reference.is_done()
+                });
+
+            let mut encoder = device.create_command_encoder(
+                &wgpu::CommandEncoderDescriptor {
+                    label: Some("strolle_offscreen"),
+                },
+            );
+
+            self.render_camera(handle, &mut encoder, target.view());
+
+            if is_last_frame {
+                if let Some(end) = end.take() {
+                    end(&mut encoder, &target, self.cameras.get(handle));
+                }
+            }
+
+            queue.submit([encoder.finish()]);
+
+            if is_last_frame {
+                break;
+            }
+
+            if frame % FRAMES_PER_POLL == 0 {
+                device.poll(wgpu::Maintain::Wait);
+            }
+
+            frame += 1;
+        }
+    }
+
+    /// Returns the exposure value (in EVs) given camera has most recently
+    /// rendered with, or `None` if it's not known yet.
+    ///
+    /// This is useful mostly with [`CameraExposure::Auto`], where the exposure
+    /// gets adapted on the GPU and read back with a delay of a couple of
+    /// frames; in order to preserve the image's brightness when switching to
+    /// manual exposure, use [`CameraExposure::Manual`] with the value returned
+    /// here.
+    pub fn camera_exposure(&self, handle: CameraHandle) -> Option<f32> {
+        self.cameras.get(handle).exposure()
+    }
+
+    /// Returns the arbitrary output variable (e.g. depth or normals) given
+    /// camera has most recently rendered, or `None` if the camera is not in
+    /// one of the AOV modes or the data is not known yet.
+    ///
+    /// Similarly to [`Self::camera_exposure()`], the data is read back from
+    /// the GPU with a delay of a couple of frames.
+    ///
+    /// See: [`CameraMode::is_aov()`].
+    pub fn camera_aov(&self, handle: CameraHandle) -> Option<Arc<CameraAov>> {
+        self.cameras.get(handle).aov()
+    }
+
+    /// Returns progress of given camera's reference mode (number of samples
+    /// accumulated so far, per-pixel variance etc.), or `None` if the camera
+    /// is not in [`CameraMode::Reference`].
+    ///
+    /// See: [`Camera::reference_samples`].
+    pub fn camera_reference(
+        &self,
+        handle: CameraHandle,
+    ) -> Option<CameraReference> {
+        self.cameras.get(handle).reference()
+    }
+
     /// Deletes a camera.
     ///
     /// After this function is called, updating or rendering this camera will
@@ -303,6 +705,13 @@ where
         let tt = Instant::now();
         let any_material_modified = mem::take(&mut self.has_dirty_materials);
         let any_image_modified = mem::take(&mut self.has_dirty_images);
+        let any_light_modified = mem::take(&mut self.has_dirty_lights);
+        let any_light_link_modified =
+            mem::take(&mut self.has_dirty_light_links);
+        let any_ies_profile_modified =
+            mem::take(&mut self.has_dirty_ies_profiles);
+        let any_sun_modified = mem::take(&mut self.has_dirty_sun);
+        let any_fog_modified = mem::take(&mut self.has_dirty_fog);
 
         utils::measure("tick.noise", || {
             self.noise.flush(queue);
@@ -312,6 +721,10 @@ where
             self.images.flush(device, queue);
         });
 
+        utils::measure("tick.ies_profiles", || {
+            self.ies_profiles.flush(queue);
+        });
+
         if any_material_modified || any_image_modified {
             utils::measure("tick.materials", || {
                 self.materials.refresh(&self.images);
@@ -320,10 +733,15 @@ where
 
         // ---
 
+        if any_light_link_modified {
+            self.instances.invalidate_light_links();
+        }
+
         let any_instance_changed = utils::measure("tick.instances", || {
             self.instances.refresh(
                 &self.meshes,
                 &self.materials,
+                &self.lights,
                 &mut self.triangles,
                 &mut self.bvh,
             )
@@ -337,26 +755,52 @@ where
 
         // ---
 
+        let moon = self.moon.unwrap_or_else(|| Moon::opposite(self.sun));
+
+        if any_sun_modified {
+            // Environment maps already contain the sun (if any), so lighting
+            // the scene through an additional light would be redundant
+            if self.has_environment_map {
+                self.lights.remove_sun();
+            } else {
+                self.lights.update_sun(self.sun, moon, &self.atmosphere);
+            }
+        }
+
+        // (flushing lights rebuilds the light tree, which the world refers to)
+        let any_buffer_reallocated = utils::measure("tick.buffers", || {
+            false
+                | self.bvh.flush(device, queue).reallocated
+                | self.triangles.flush(device, queue).reallocated
+                | self.lights.flush(device, queue).reallocated
+                | self.materials.flush(device, queue).reallocated
+                | self.environment.flush(device, queue).reallocated
+                | self.fog.flush(device, queue).reallocated
+        });
+
         *self.world = gpu::World {
             light_count: self.lights.len(),
             sun_azimuth: self.sun.azimuth,
             sun_altitude: self.sun.altitude,
+            moon_azimuth: moon.azimuth,
+            moon_altitude: moon.altitude,
+            moon_phase: moon.phase,
+            moon_intensity: moon.intensity,
+            light_tree_paths: self.lights.tree_paths(),
         };
 
         utils::measure("tick.world", || {
             self.world.flush(queue);
+            self.atmosphere.flush(queue);
         });
 
-        if mem::take(&mut self.has_dirty_sun) {
-            self.lights.update_sun(*self.world);
-        }
-
-        let any_buffer_reallocated = utils::measure("tick.buffers", || {
-            false
-                | self.bvh.flush(device, queue).reallocated
-                | self.triangles.flush(device, queue).reallocated
-                | self.lights.flush(device, queue).reallocated
-                | self.materials.flush(device, queue).reallocated
+        utils::measure("tick.atmosphere", || {
+            self.atmosphere_luts.refresh(
+                device,
+                queue,
+                *self.atmosphere,
+                self.sun,
+            );
         });
 
         // ---
@@ -371,9 +815,32 @@ where
             self.cameras = cameras;
         }
 
+        // (changes to the moon, atmosphere and environment mark the sun as
+        // dirty, since they affect the directional light)
+        let any_scene_modified = any_material_modified
+            || any_image_modified
+            || any_instance_changed
+            || any_light_modified
+            || any_light_link_modified
+            || any_ies_profile_modified
+            || any_sun_modified
+            || any_fog_modified;
+
+        // Manual exposure is applied on top of the atmosphere's one, see:
+        // `AtmosphereSettings::exposure`
+        let base_exposure = if self.has_environment_map {
+            1.0
+        } else {
+            self.atmosphere_exposure
+        };
+
         utils::measure("tick.cameras", || {
             for camera in self.cameras.iter_mut() {
-                camera.flush(self.frame, queue);
+                if any_scene_modified {
+                    camera.invalidate_reference();
+                }
+
+                camera.flush(self.frame, base_exposure, queue);
             }
         });
 
@@ -1,6 +1,6 @@
 use glam::{vec4, Vec3};
 
-use crate::gpu;
+use crate::{gpu, IesProfileHandle};
 
 #[derive(Clone, Debug)]
 pub enum Light {
@@ -9,6 +9,17 @@ pub enum Light {
         radius: f32,
         color: Vec3,
         range: f32,
+
+        /// Axis of light's IES profile, i.e. where its vertical angle of 0°
+        /// points; irrelevant for lights without any profile.
+        direction: Vec3,
+
+        /// Direction of light's IES profile horizontal angle of 0°; see
+        /// [`gpu::IesProfilesView::eval()`].
+        ies_reference: Vec3,
+
+        casts_shadows: bool,
+        ies_profile: Option<IesProfileHandle>,
     },
 
     Spot {
@@ -18,14 +29,45 @@ pub enum Light {
         range: f32,
         direction: Vec3,
         angle: f32,
+
+        /// Direction of light's IES profile horizontal angle of 0°; see
+        /// [`gpu::IesProfilesView::eval()`].
+        ies_reference: Vec3,
+
+        casts_shadows: bool,
+        ies_profile: Option<IesProfileHandle>,
+    },
+
+    Directional {
+        direction: Vec3,
+        color: Vec3,
+        angular_diameter: f32,
+        casts_shadows: bool,
     },
 }
 
 impl Light {
+    pub fn casts_shadows(&self) -> bool {
+        match self {
+            Light::Point { casts_shadows, .. }
+            | Light::Spot { casts_shadows, .. }
+            | Light::Directional { casts_shadows, .. } => *casts_shadows,
+        }
+    }
+
+    pub fn ies_profile(&self) -> Option<IesProfileHandle> {
+        match self {
+            Light::Point { ies_profile, .. }
+            | Light::Spot { ies_profile, .. } => *ies_profile,
+            Light::Directional { .. } => None,
+        }
+    }
+
     pub(crate) fn serialize(&self) -> gpu::Light {
         let d0;
         let d1;
         let d2;
+        let d4;
 
         match self {
             Light::Point {
@@ -33,16 +75,27 @@ impl Light {
                 radius,
                 color,
                 range,
+                direction,
+                ies_reference,
+                ..
             } => {
+                let direction = gpu::Normal::encode(direction.normalize());
+                let ies_reference =
+                    gpu::Normal::encode(ies_reference.normalize());
+
                 d0 = position.extend(*radius);
                 d1 = color.extend(*range);
 
                 d2 = vec4(
                     f32::from_bits(gpu::Light::TYPE_POINT),
-                    Default::default(),
-                    Default::default(),
+                    direction.x,
+                    direction.y,
                     Default::default(),
                 );
+
+                d4 = ies_reference
+                    .extend(Default::default())
+                    .extend(Default::default());
             }
 
             Light::Spot {
@@ -52,8 +105,12 @@ impl Light {
                 range,
                 direction,
                 angle,
+                ies_reference,
+                ..
             } => {
                 let direction = gpu::Normal::encode(*direction);
+                let ies_reference =
+                    gpu::Normal::encode(ies_reference.normalize());
 
                 d0 = position.extend(*radius);
                 d1 = color.extend(*range);
@@ -64,17 +121,62 @@ impl Light {
                     direction.y,
                     *angle,
                 );
+
+                d4 = ies_reference
+                    .extend(Default::default())
+                    .extend(Default::default());
+            }
+
+            Light::Directional {
+                direction,
+                color,
+                angular_diameter,
+                ..
+            } => {
+                let direction = gpu::Normal::encode(direction.normalize());
+
+                d0 = Default::default();
+                d1 = color.extend(f32::INFINITY);
+
+                d2 = vec4(
+                    f32::from_bits(gpu::Light::TYPE_DIRECTIONAL),
+                    direction.x,
+                    direction.y,
+                    *angular_diameter,
+                );
+
+                d4 = Default::default();
             }
         }
 
+        let flags = if self.casts_shadows() {
+            gpu::Light::FLAG_CASTS_SHADOWS
+        } else {
+            0
+        };
+
+        let ies_profile = self
+            .ies_profile()
+            .map(|handle| handle.get() + 1)
+            .unwrap_or_default();
+
+        let d3 = vec4(
+            Default::default(),
+            f32::from_bits(flags),
+            Default::default(),
+            f32::from_bits(ies_profile),
+        );
+
         gpu::Light {
             d0,
             d1,
             d2,
-            d3: Default::default(),
+            d3,
+            d4,
             prev_d0: Default::default(),
             prev_d1: Default::default(),
             prev_d2: Default::default(),
+            prev_d4: Default::default(),
         }
     }
 }
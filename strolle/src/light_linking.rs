@@ -0,0 +1,35 @@
+use std::collections::HashSet;
+
+use derivative::Derivative;
+
+use crate::Params;
+
+/// Restricts which instances a light affects.
+///
+/// Light-linking is applied to directly visible surfaces and within the
+/// reference mode - indirect lighting in the real-time modes doesn't take it
+/// into account.
+#[derive(Debug, Derivative)]
+#[derivative(Clone)]
+pub enum LightLinking<P>
+where
+    P: Params,
+{
+    /// Light affects only given instances.
+    Include(HashSet<P::InstanceHandle>),
+
+    /// Light affects all instances except the given ones.
+    Exclude(HashSet<P::InstanceHandle>),
+}
+
+impl<P> LightLinking<P>
+where
+    P: Params,
+{
+    pub fn affects(&self, instance: P::InstanceHandle) -> bool {
+        match self {
+            LightLinking::Include(instances) => instances.contains(&instance),
+            LightLinking::Exclude(instances) => !instances.contains(&instance),
+        }
+    }
+}
@@ -1,11 +1,16 @@
+mod tree;
+
 use std::collections::hash_map::Entry;
 use std::collections::{HashMap, HashSet};
 use std::fmt::Debug;
 
 use derivative::Derivative;
+use glam::{Vec3, Vec4};
+use log::warn;
 
 use crate::{
-    gpu, Bindable, BufferFlushOutcome, Light, MappedStorageBuffer, Params,
+    gpu, utils, Bindable, BufferFlushOutcome, Light, LightLinking,
+    MappedStorageBuffer, Moon, Params, Sun,
 };
 
 #[derive(Debug)]
@@ -14,12 +19,15 @@ where
     P: Params,
 {
     buffer: MappedStorageBuffer<Vec<gpu::Light>>,
+    tree: MappedStorageBuffer<Vec<Vec4>>,
+    tree_paths: u32,
     index: HashMap<LightHandle<P>, gpu::LightId>,
     created: HashSet<LightHandle<P>>,
     updated: HashSet<LightHandle<P>>,
     remapped: HashMap<LightHandle<P>, gpu::LightId>,
     killed: HashSet<gpu::LightId>,
     next_light_id: gpu::LightId,
+    links: HashMap<P::LightHandle, LightLink<P>>,
 }
 
 impl<P> Lights<P>
@@ -27,109 +35,181 @@ where
     P: Params,
 {
     pub fn new(device: &wgpu::Device) -> Self {
-        let mut buffer =
-            MappedStorageBuffer::<Vec<_>>::new_default(device, "stolle_lights");
-
-        buffer.push(gpu::Light::sun(Default::default(), Default::default()));
-
-        // ---
-
-        let mut index = HashMap::new();
-
-        index.insert(LightHandle::Sun, gpu::LightId::new(0));
-
-        // ---
-
         Self {
-            buffer,
-            index,
+            buffer: MappedStorageBuffer::new_default(device, "stolle_lights"),
+            tree: MappedStorageBuffer::new_default(device, "stolle_light_tree"),
+            tree_paths: 0,
+            index: Default::default(),
             created: Default::default(),
             updated: Default::default(),
             remapped: Default::default(),
             killed: Default::default(),
-            next_light_id: gpu::LightId::new(1),
+            next_light_id: gpu::LightId::new(0),
+            links: Default::default(),
         }
     }
 
     pub fn insert(&mut self, handle: P::LightHandle, item: Light) {
-        let item = item.serialize();
-        let handle = LightHandle::Light(handle);
+        self.insert_ex(LightHandle::Light(handle), item);
+    }
 
-        match self.index.entry(handle) {
-            Entry::Occupied(entry) => {
-                let id = *entry.get();
+    /// Updates directional lights that represent the sun and the moon.
+    ///
+    /// Instead of switching from one to the other right as the sun crosses the
+    /// horizon, both lights get cross-faded over the twilight (see
+    /// [`Sun::TWILIGHT`]), so that the scene doesn't suddenly change its
+    /// lighting.
+    pub fn update_sun(
+        &mut self,
+        sun: Sun,
+        moon: Moon,
+        atmosphere: &gpu::AtmosphereSettings,
+    ) {
+        let world = gpu::World {
+            sun_azimuth: sun.azimuth,
+            sun_altitude: sun.altitude,
+            moon_azimuth: moon.azimuth,
+            moon_altitude: moon.altitude,
+            moon_phase: moon.phase,
+            moon_intensity: moon.intensity,
+            ..Default::default()
+        };
 
-                self.update(id.get() as usize, handle, item);
-            }
+        let sun_weight = {
+            let t = ((world.sun_dir().y + Sun::TWILIGHT)
+                / (2.0 * Sun::TWILIGHT))
+                .clamp(0.0, 1.0);
 
-            Entry::Vacant(entry) => {
-                if let Some(slot) =
-                    self.buffer.get_mut(self.next_light_id.get() as usize)
-                {
-                    *slot = item;
-                    entry.insert(self.next_light_id);
-                } else {
-                    let id = gpu::LightId::new(self.buffer.len() as u32);
+            t * t * (3.0 - 2.0 * t)
+        };
 
-                    self.buffer.push(item);
-                    entry.insert(id);
-                }
+        self.update_sky_light(
+            LightHandle::Sun,
+            world.sun_dir(),
+            Vec3::splat(sun_weight),
+            Sun::ANGULAR_DIAMETER,
+            atmosphere,
+        );
 
-                self.created.insert(handle);
-                *self.next_light_id.get_mut() += 1;
-            }
-        }
+        self.update_sky_light(
+            LightHandle::Moon,
+            world.moon_dir(),
+            Vec3::splat(
+                (1.0 - sun_weight) * moon.intensity * world.moon_illumination(),
+            ),
+            gpu::Atmosphere::MOON_ANGULAR_DIAMETER,
+            atmosphere,
+        );
     }
 
-    pub fn update_sun(&mut self, world: gpu::World) {
-        let color =
-            strolle_shaders::atmosphere::generate_transmittance_lut::eval(
-                gpu::Atmosphere::VIEW_POS,
-                world.sun_dir(),
+    fn update_sky_light(
+        &mut self,
+        handle: LightHandle<P>,
+        dir: Vec3,
+        color: Vec3,
+        angular_diameter: f32,
+        atmosphere: &gpu::AtmosphereSettings,
+    ) {
+        let color = color
+            * strolle_shaders::atmosphere::generate_transmittance_lut::eval(
+                atmosphere,
+                atmosphere.view_pos(),
+                dir,
             );
 
-        // TODO probably incorrect
-        let color = color * gpu::Atmosphere::EXPOSURE * 5.0;
+        // Clouds scatter the light, which both dims it and makes it more
+        // diffuse - we approximate the latter by making the light larger (up to
+        // ~20 degrees for a fully overcast sky)
+        let clouds_transmittance = atmosphere.clouds.sun_transmittance(dir);
 
-        self.update(
-            0,
-            LightHandle::Sun,
-            gpu::Light::sun(world.sun_pos(), color),
-        );
-    }
+        let angular_diameter =
+            angular_diameter + (1.0 - clouds_transmittance) * 0.35;
 
-    pub fn remove(&mut self, handle: P::LightHandle) {
-        let handle = LightHandle::Light(handle);
+        let color = color * clouds_transmittance;
 
-        let Some(id) = self.index.remove(&handle) else {
+        // Light is either below the horizon or faded out, so there's no point
+        // in keeping it around
+        if dir.y < 0.0 || color.max_element() <= 0.0 {
+            self.remove_ex(handle);
             return;
-        };
+        }
 
-        let idx = id.get() as usize;
+        self.insert_ex(
+            handle,
+            Light::Directional {
+                direction: -dir,
+                color,
+                angular_diameter,
+                casts_shadows: true,
+            },
+        );
+    }
 
-        self.buffer.remove(idx);
-        self.buffer.push(Default::default());
+    /// Removes lights that represent the sun and the moon.
+    pub fn remove_sun(&mut self) {
+        self.remove_ex(LightHandle::Sun);
+        self.remove_ex(LightHandle::Moon);
+    }
 
-        self.created.remove(&handle);
-        self.updated.remove(&handle);
-        self.remapped.remove(&handle);
-        self.killed.insert(id);
+    pub fn remove(&mut self, handle: P::LightHandle) {
+        self.remove_ex(LightHandle::Light(handle));
+    }
 
-        *self.next_light_id.get_mut() -= 1;
+    pub fn insert_linking(
+        &mut self,
+        handle: P::LightHandle,
+        linking: LightLinking<P>,
+    ) {
+        let bit = if let Some(link) = self.links.get(&handle) {
+            link.bit
+        } else {
+            let used_bits =
+                self.links.values().fold(0, |bits, link| bits | link.bit);
+
+            let Some(slot) = (0..gpu::Light::MAX_LINKED_LIGHTS)
+                .find(|slot| used_bits & (1 << slot) == 0)
+            else {
+                warn!(
+                    "Can't link light {:?}: at most {} lights can be linked \
+                     at once",
+                    handle,
+                    gpu::Light::MAX_LINKED_LIGHTS,
+                );
+
+                return;
+            };
+
+            1 << slot
+        };
 
-        for (other_handle, other_id) in self.index.iter_mut() {
-            if other_id.get() > id.get() {
-                self.remapped.entry(*other_handle).or_insert(*other_id);
+        self.links.insert(handle, LightLink { bit, linking });
+        self.update_link_bit(LightHandle::Light(handle), bit);
+    }
 
-                *other_id.get_mut() -= 1;
-            }
+    pub fn remove_linking(&mut self, handle: P::LightHandle) {
+        if self.links.remove(&handle).is_some() {
+            self.update_link_bit(LightHandle::Light(handle), 0);
         }
     }
 
+    /// Returns a bitmask of linked lights that don't affect given instance;
+    /// see [`gpu::GBufferEntry::excluded_lights`].
+    pub fn excluded_lights(&self, instance: P::InstanceHandle) -> u32 {
+        self.links
+            .values()
+            .filter(|link| !link.linking.affects(instance))
+            .fold(0, |bits, link| bits | link.bit)
+    }
+
     pub fn len(&self) -> u32 {
         self.next_light_id.get()
     }
 
+    /// See: [`gpu::World::light_tree_paths`].
+    pub fn tree_paths(&self) -> u32 {
+        self.tree_paths
+    }
+
     pub fn flush(
         &mut self,
         device: &wgpu::Device,
@@ -143,7 +223,22 @@ where
             self.buffer[id.get() as usize].remap_slot(self.index[&handle]);
         }
 
-        let outcome = self.buffer.flush(device, queue);
+        let is_tree_dirty = !self.created.is_empty()
+            || !self.updated.is_empty()
+            || !self.killed.is_empty();
+
+        if is_tree_dirty {
+            let lights = &self.buffer[..self.next_light_id.get() as usize];
+
+            utils::measure("tick.lights.tree", || {
+                self.tree_paths = tree::build(lights, &mut self.tree);
+            });
+        }
+
+        let outcome = BufferFlushOutcome {
+            reallocated: self.buffer.flush(device, queue).reallocated
+                | self.tree.flush(device, queue).reallocated,
+        };
 
         for handle in self.created.iter().chain(&self.updated) {
             self.buffer[self.index[handle].get() as usize].commit();
@@ -165,6 +260,77 @@ where
         self.buffer.bind_readable()
     }
 
+    pub fn bind_tree(&self) -> impl Bindable + '_ {
+        self.tree.bind_readable()
+    }
+
+    fn insert_ex(&mut self, handle: LightHandle<P>, item: Light) {
+        let mut item = item.serialize();
+
+        if let LightHandle::Light(handle) = handle {
+            if let Some(link) = self.links.get(&handle) {
+                item.set_link_bit(link.bit);
+            }
+        }
+
+        match self.index.entry(handle) {
+            Entry::Occupied(entry) => {
+                let id = *entry.get();
+
+                self.update(id.get() as usize, handle, item);
+            }
+
+            Entry::Vacant(entry) => {
+                if let Some(slot) =
+                    self.buffer.get_mut(self.next_light_id.get() as usize)
+                {
+                    *slot = item;
+                    entry.insert(self.next_light_id);
+                } else {
+                    let id = gpu::LightId::new(self.buffer.len() as u32);
+
+                    self.buffer.push(item);
+                    entry.insert(id);
+                }
+
+                self.created.insert(handle);
+                *self.next_light_id.get_mut() += 1;
+            }
+        }
+    }
+
+    fn remove_ex(&mut self, handle: LightHandle<P>) {
+        let Some(id) = self.index.remove(&handle) else {
+            return;
+        };
+
+        let idx = id.get() as usize;
+
+        self.buffer.remove(idx);
+        self.buffer.push(Default::default());
+
+        self.created.remove(&handle);
+        self.updated.remove(&handle);
+        self.remapped.remove(&handle);
+        self.killed.insert(id);
+
+        *self.next_light_id.get_mut() -= 1;
+
+        for (other_handle, other_id) in self.index.iter_mut() {
+            if other_id.get() > id.get() {
+                self.remapped.entry(*other_handle).or_insert(*other_id);
+
+                *other_id.get_mut() -= 1;
+            }
+        }
+    }
+
+    fn update_link_bit(&mut self, handle: LightHandle<P>, bit: u32) {
+        if let Some(id) = self.index.get(&handle) {
+            self.buffer[id.get() as usize].set_link_bit(bit);
+        }
+    }
+
     fn update(
         &mut self,
         idx: usize,
@@ -176,14 +342,17 @@ where
         new.prev_d0 = old.d0;
         new.prev_d1 = old.d1;
         new.prev_d2 = old.d2;
+        new.prev_d4 = old.d4;
 
         self.updated.insert(handle);
         self.buffer[idx] = new;
     }
 }
 
-// TODO sun should be handled outside of Strolle, there's no reason to
-//      special-case it here
+/// Key under which a light is stored.
+///
+/// Sun and moon are directional lights like any other, they just don't have
+/// user-provided handles - so we give them dedicated ones.
 #[derive(Debug, Derivative)]
 #[derivative(Clone, Copy, PartialEq, Eq, Hash)]
 enum LightHandle<P>
@@ -191,5 +360,15 @@ where
     P: Params,
 {
     Sun,
+    Moon,
     Light(P::LightHandle),
 }
+
+#[derive(Debug)]
+struct LightLink<P>
+where
+    P: Params,
+{
+    bit: u32,
+    linking: LightLinking<P>,
+}
@@ -0,0 +1,380 @@
+use std::f32::consts::PI;
+
+use glam::{vec4, Quat, Vec3, Vec4};
+
+use crate::gpu::Vec3Ext;
+use crate::{gpu, Axis, BoundingBox};
+
+/// Builds the light tree, see [`gpu::LightTreeView`] for the layout; returns
+/// the pointer to leaves' paths (see [`gpu::World::light_tree_paths`]).
+pub fn build(lights: &[gpu::Light], buffer: &mut Vec<Vec4>) -> u32 {
+    buffer.clear();
+
+    let mut local_lights = Vec::new();
+    let mut infinite_lights = Vec::new();
+
+    for (id, light) in lights.iter().enumerate() {
+        if light.is_none() {
+            continue;
+        }
+
+        let node = LightTreeNode::leaf(gpu::LightId::new(id as u32), *light);
+
+        if node.is_infinite() {
+            infinite_lights.push(node);
+        } else {
+            local_lights.push(node);
+        }
+    }
+
+    let local_lights = build_subtree(local_lights);
+    let infinite_lights = build_subtree(infinite_lights);
+
+    let root = match (local_lights, infinite_lights) {
+        (Some(lhs), Some(rhs)) => LightTreeNode::internal(lhs, rhs),
+        (Some(node), None) | (None, Some(node)) => node,
+        (None, None) => return 0,
+    };
+
+    let mut paths = vec![0; lights.len()];
+
+    serialize(&root, 0, 0, buffer, &mut paths);
+
+    let paths_ptr = buffer.len() as u32;
+
+    buffer.extend(paths.chunks(4).map(|paths| {
+        let mut chunk = [0; 4];
+
+        chunk[..paths.len()].copy_from_slice(paths);
+
+        Vec4::from_array(chunk.map(f32::from_bits))
+    }));
+
+    paths_ptr
+}
+
+fn build_subtree(mut nodes: Vec<LightTreeNode>) -> Option<LightTreeNode> {
+    if nodes.len() <= 1 {
+        return nodes.pop();
+    }
+
+    // Infinite lights don't have any meaningful position, so it doesn't
+    // matter how we split them
+    if !nodes[0].is_infinite() {
+        let centers: BoundingBox =
+            nodes.iter().map(|node| node.bounds.center()).collect();
+
+        let extent = centers.extent();
+
+        let axis = Axis::all()
+            .max_by(|a, b| extent[*a].total_cmp(&extent[*b]))
+            .unwrap();
+
+        nodes.sort_unstable_by(|a, b| {
+            a.bounds.center()[axis].total_cmp(&b.bounds.center()[axis])
+        });
+    }
+
+    let right = nodes.split_off(nodes.len() / 2);
+    let left = build_subtree(nodes)?;
+    let right = build_subtree(right)?;
+
+    Some(LightTreeNode::internal(left, right))
+}
+
+/// Serializes given node and its children; `path` describes how to reach this
+/// node from the root, with n-th bit set if n-th step goes into the right
+/// child.
+fn serialize(
+    node: &LightTreeNode,
+    path: u32,
+    depth: u32,
+    buffer: &mut Vec<Vec4>,
+    paths: &mut [u32],
+) -> u32 {
+    let ptr = buffer.len() / 3;
+
+    buffer.push(Default::default());
+    buffer.push(Default::default());
+    buffer.push(Default::default());
+
+    let payload = match &node.kind {
+        LightTreeNodeKind::Leaf { light_id } => {
+            paths[light_id.get() as usize] = path;
+
+            light_id.get() | gpu::LightTreeNode::LEAF_BIT
+        }
+
+        LightTreeNodeKind::Internal { left, right } => {
+            // (paths are stored as 32-bit masks, and the tree gets traversed
+            // using the BVH's stack)
+            assert!(
+                depth < gpu::BVH_STACK_SIZE as u32,
+                "light tree is too deep"
+            );
+
+            let _left_ptr = serialize(left, path, depth + 1, buffer, paths);
+
+            serialize(right, path | (1 << depth), depth + 1, buffer, paths)
+        }
+    };
+
+    let (min, max) = if node.is_infinite() {
+        (Vec3::ONE, -Vec3::ONE)
+    } else {
+        (node.bounds.min(), node.bounds.max())
+    };
+
+    let axis = gpu::Normal::encode(node.cone.axis);
+
+    buffer[3 * ptr] = min.extend(node.power);
+    buffer[3 * ptr + 1] = max.extend(f32::from_bits(payload));
+
+    buffer[3 * ptr + 2] =
+        vec4(axis.x, axis.y, node.cone.theta_o, node.cone.theta_e);
+
+    ptr as u32
+}
+
+#[derive(Debug)]
+struct LightTreeNode {
+    bounds: BoundingBox,
+    cone: LightCone,
+    power: f32,
+    kind: LightTreeNodeKind,
+}
+
+impl LightTreeNode {
+    fn leaf(light_id: gpu::LightId, light: gpu::Light) -> Self {
+        let bounds = if light.is_directional() {
+            BoundingBox::default()
+        } else {
+            BoundingBox::default()
+                + (light.center() - Vec3::splat(light.radius()))
+                + (light.center() + Vec3::splat(light.radius()))
+        };
+
+        let cone = if light.is_directional() {
+            LightCone {
+                axis: light.dir(),
+                theta_o: 0.0,
+                theta_e: 0.0,
+            }
+        } else if light.is_spot() && !light.has_ies_profile() {
+            // (IES profiles can emit light outside of the spot's cone, so we
+            // treat such lights as point lights here)
+            LightCone {
+                axis: light.dir(),
+                theta_o: 0.0,
+                theta_e: light.spot_angle(),
+            }
+        } else {
+            LightCone {
+                axis: Vec3::Y,
+                theta_o: PI,
+                theta_e: 0.5 * PI,
+            }
+        };
+
+        Self {
+            bounds,
+            cone,
+            power: light.color().luma(),
+            kind: LightTreeNodeKind::Leaf { light_id },
+        }
+    }
+
+    fn internal(left: Self, right: Self) -> Self {
+        Self {
+            bounds: left.bounds + right.bounds,
+            cone: left.cone.union(right.cone),
+            power: left.power + right.power,
+            kind: LightTreeNodeKind::Internal {
+                left: Box::new(left),
+                right: Box::new(right),
+            },
+        }
+    }
+
+    fn is_infinite(&self) -> bool {
+        !self.bounds.is_set()
+    }
+}
+
+#[derive(Debug)]
+enum LightTreeNodeKind {
+    Leaf {
+        light_id: gpu::LightId,
+    },
+
+    Internal {
+        left: Box<LightTreeNode>,
+        right: Box<LightTreeNode>,
+    },
+}
+
+#[derive(Clone, Copy, Debug)]
+struct LightCone {
+    axis: Vec3,
+    theta_o: f32,
+    theta_e: f32,
+}
+
+impl LightCone {
+    /// Returns a cone that bounds both cones.
+    fn union(self, other: Self) -> Self {
+        let (a, b) = if self.theta_o >= other.theta_o {
+            (self, other)
+        } else {
+            (other, self)
+        };
+
+        let theta_d = a.axis.angle_between(b.axis);
+        let theta_e = a.theta_e.max(b.theta_e);
+
+        if (theta_d + b.theta_o).min(PI) <= a.theta_o {
+            return Self {
+                axis: a.axis,
+                theta_o: a.theta_o,
+                theta_e,
+            };
+        }
+
+        let theta_o = 0.5 * (a.theta_o + theta_d + b.theta_o);
+        let rotation_axis = a.axis.cross(b.axis);
+
+        if theta_o >= PI || rotation_axis.length_squared() < 0.0001 {
+            return Self {
+                axis: a.axis,
+                theta_o: PI,
+                theta_e,
+            };
+        }
+
+        let rotation = Quat::from_axis_angle(
+            rotation_axis.normalize(),
+            theta_o - a.theta_o,
+        );
+
+        Self {
+            axis: (rotation * a.axis).normalize(),
+            theta_o,
+            theta_e,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::vec3;
+
+    use super::*;
+    use crate::Light;
+
+    #[test]
+    fn smoke() {
+        let lights = [
+            Light::Point {
+                position: vec3(-10.0, 0.0, 0.0),
+                radius: 0.1,
+                color: Vec3::ONE,
+                range: 20.0,
+                direction: -Vec3::Y,
+                ies_reference: Vec3::X,
+                casts_shadows: true,
+                ies_profile: None,
+            },
+            Light::Point {
+                position: vec3(10.0, 0.0, 0.0),
+                radius: 0.1,
+                color: Vec3::ONE,
+                range: 20.0,
+                direction: -Vec3::Y,
+                ies_reference: Vec3::X,
+                casts_shadows: true,
+                ies_profile: None,
+            },
+            Light::Directional {
+                direction: -Vec3::Y,
+                color: Vec3::ONE,
+                angular_diameter: 0.01,
+                casts_shadows: true,
+            },
+        ];
+
+        let lights: Vec<_> = lights.iter().map(Light::serialize).collect();
+        let mut buffer = Vec::new();
+        let paths_ptr = build(&lights, &mut buffer);
+
+        // root + local subtree (3 nodes) + directional light, and then paths
+        assert_eq!(5 * 3, paths_ptr);
+        assert_eq!(5 * 3 + 1, buffer.len());
+
+        let tree = gpu::LightTreeView::new(&buffer);
+        let root = tree.get(0);
+
+        assert!(!root.is_leaf());
+        assert_eq!(4, root.right_ptr());
+
+        let sun = tree.get(4);
+
+        assert!(sun.is_leaf());
+        assert!(sun.is_infinite());
+        assert_eq!(gpu::LightId::new(2), sun.light_id());
+
+        let local = tree.get(1);
+
+        assert!(!local.is_leaf());
+        assert!(!local.is_infinite());
+        assert!(tree.get(2).is_leaf());
+        assert!(tree.get(3).is_leaf());
+
+        // A point next to the first light should prefer it over the other one
+        let point = vec3(-9.0, 0.0, 0.0);
+        let normal = -Vec3::X;
+
+        assert!(
+            tree.get(2).importance(point, normal)
+                > tree.get(3).importance(point, normal)
+        );
+
+        // Probabilities of picking lights must follow nodes' importances
+        let normal = vec3(-1.0, 1.0, 0.0).normalize();
+        let imp = |ptr| tree.get(ptr).importance(point, normal);
+        let pdf =
+            |id| tree.pdf(paths_ptr, gpu::LightId::new(id), point, normal);
+
+        let local_prob = imp(1) / (imp(1) + imp(4));
+        let first_prob = imp(2) / (imp(2) + imp(3));
+
+        assert!((pdf(0) - local_prob * first_prob).abs() < 0.0001);
+        assert!((pdf(2) - (1.0 - local_prob)).abs() < 0.0001);
+        assert!(((pdf(0) + pdf(1) + pdf(2)) - 1.0).abs() < 0.0001);
+
+        // The sun shines from above, so it can't reach surfaces facing down
+        assert!(sun.importance(point, Vec3::Y) > 0.0);
+        assert_eq!(0.0, sun.importance(point, -Vec3::Y));
+
+        // Rays should find only the lights they pass through
+        let mut stack = [0; gpu::BVH_STACK_SIZE * 8 * 8];
+
+        let hits = |stack: &mut _, dir: Vec3, max_distance: f32| {
+            let mut hits = Vec::new();
+
+            tree.for_each_hit(
+                0,
+                stack,
+                gpu::Ray::new(Vec3::ZERO, dir),
+                max_distance,
+                |light_id| hits.push(light_id.get()),
+            );
+
+            hits
+        };
+
+        assert_eq!(vec![1], hits(&mut stack, Vec3::X, 100.0));
+        assert_eq!(vec![0], hits(&mut stack, -Vec3::X, 100.0));
+        assert!(hits(&mut stack, -Vec3::X, 5.0).is_empty());
+        assert!(hits(&mut stack, Vec3::Y, 100.0).is_empty());
+    }
+}
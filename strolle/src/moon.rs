@@ -0,0 +1,119 @@
+use std::f32::consts::PI;
+
+use crate::{
+    equatorial_to_horizontal, obliquity, sun_ecliptic_longitude, Sun,
+    UtcDateTime,
+};
+
+/// Moon that lights the scene (instead of the sun) during the night.
+///
+/// See: [`crate::Engine::update_moon()`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Moon {
+    pub azimuth: f32,
+    pub altitude: f32,
+
+    /// Moon's phase, from 0.0 (new moon) through 0.5 (full moon) to 1.0 (new
+    /// moon again).
+    pub phase: f32,
+
+    /// Brightness of the moon, relative to the sun.
+    pub intensity: f32,
+}
+
+impl Moon {
+    pub const DEFAULT_INTENSITY: f32 = 0.05;
+
+    /// Returns a full moon placed on the opposite side of the sky than the
+    /// sun, so that it rises when the sun sets.
+    pub fn opposite(sun: Sun) -> Self {
+        Self {
+            azimuth: sun.azimuth + PI,
+            altitude: -sun.altitude,
+            phase: 0.5,
+            intensity: Self::DEFAULT_INTENSITY,
+        }
+    }
+
+    /// Computes moon's position and phase as seen from given place on the Earth
+    /// at given time.
+    ///
+    /// See [`Sun::from_geo()`] for the conventions; the moon's position is
+    /// accurate to about a degree, which is good enough for rendering.
+    pub fn from_geo(
+        latitude: f32,
+        longitude: f32,
+        datetime: UtcDateTime,
+    ) -> Self {
+        // Days since J2000.0
+        let n = datetime.julian_day() - 2451545.0;
+
+        // Mean longitude, mean anomaly and mean distance from the ascending
+        // node, in degrees
+        let mean_longitude = 218.316 + 13.176396 * n;
+        let mean_anomaly = (134.963 + 13.064993 * n).to_radians();
+        let mean_distance = (93.272 + 13.229350 * n).to_radians();
+
+        let ecliptic_longitude = mean_longitude + 6.289 * mean_anomaly.sin();
+        let ecliptic_latitude = (5.128 * mean_distance.sin()).to_radians();
+
+        let phase = (ecliptic_longitude - sun_ecliptic_longitude(n))
+            .rem_euclid(360.0)
+            / 360.0;
+
+        let ecliptic_longitude = ecliptic_longitude.to_radians();
+        let obliquity = obliquity(n);
+
+        let right_ascension = (ecliptic_longitude.sin() * obliquity.cos()
+            - ecliptic_latitude.tan() * obliquity.sin())
+        .atan2(ecliptic_longitude.cos());
+
+        let declination = (ecliptic_latitude.sin() * obliquity.cos()
+            + ecliptic_latitude.cos()
+                * obliquity.sin()
+                * ecliptic_longitude.sin())
+        .asin();
+
+        let (azimuth, altitude) = equatorial_to_horizontal(
+            latitude,
+            longitude,
+            n,
+            right_ascension,
+            declination,
+        );
+
+        Self {
+            azimuth,
+            altitude,
+            phase: phase as f32,
+            intensity: Self::DEFAULT_INTENSITY,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_geo() {
+        // Full moon of 2023-08-31, as seen from Warsaw
+        let target = Moon::from_geo(
+            52.23,
+            21.01,
+            UtcDateTime::new(2023, 8, 31, 1, 36, 0.0),
+        );
+
+        assert!((target.phase - 0.5).abs() < 0.02);
+        assert!(target.altitude > 0.0);
+
+        // New moon of 2023-09-15
+        let target = Moon::from_geo(
+            52.23,
+            21.01,
+            UtcDateTime::new(2023, 9, 15, 1, 40, 0.0),
+        );
+
+        assert!(target.phase < 0.02 || target.phase > 0.98);
+    }
+}
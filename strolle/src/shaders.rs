@@ -49,11 +49,21 @@ shaders!([
     di_spatial_resampling_sample,
     di_spatial_resampling_trace,
     di_temporal_resampling,
+    fog_scattering,
+    frame_antialiasing,
+    frame_bloom_downsample,
+    frame_bloom_upsample,
     frame_composition_fs,
     frame_composition_vs,
     frame_denoising_estimate_variance,
     frame_denoising_reproject,
     frame_denoising_wavelet,
+    frame_depth_of_field,
+    frame_diagnostics,
+    frame_exposure_adapt,
+    frame_exposure_histogram,
+    frame_motion_blur,
+    frame_output_fs,
     frame_reprojection,
     gi_preview_resampling,
     gi_reprojection,
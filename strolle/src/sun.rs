@@ -1,9 +1,58 @@
+use std::f64::consts::PI;
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Sun {
     pub azimuth: f32,
     pub altitude: f32,
 }
 
+impl Sun {
+    /// Angular diameter of the sun, as seen from the Earth, in radians.
+    pub const ANGULAR_DIAMETER: f32 = 0.0093;
+
+    /// Half-height of the band around the horizon (expressed as the sine of
+    /// sun's altitude, ~6°) over which the sun's light fades into the moon's.
+    pub const TWILIGHT: f32 = 0.1;
+
+    /// Computes sun's position as seen from given place on the Earth at given
+    /// time.
+    ///
+    /// `latitude` and `longitude` are given in degrees (positive towards the
+    /// north and the east, respectively); the returned azimuth assumes that the
+    /// geographic north points towards -Z and the east towards +X - if that's
+    /// not the case for your scene, just add your offset to the azimuth.
+    ///
+    /// The algorithm is a simplified version of the one used by NOAA and it's
+    /// accurate to within a fraction of a degree for dates in the 1950-2050
+    /// range, which is plenty for rendering purposes.
+    pub fn from_geo(
+        latitude: f32,
+        longitude: f32,
+        datetime: UtcDateTime,
+    ) -> Self {
+        // Days since J2000.0
+        let n = datetime.julian_day() - 2451545.0;
+
+        let ecliptic_longitude = sun_ecliptic_longitude(n).to_radians();
+        let obliquity = obliquity(n);
+
+        let right_ascension = (obliquity.cos() * ecliptic_longitude.sin())
+            .atan2(ecliptic_longitude.cos());
+
+        let declination = (obliquity.sin() * ecliptic_longitude.sin()).asin();
+
+        let (azimuth, altitude) = equatorial_to_horizontal(
+            latitude,
+            longitude,
+            n,
+            right_ascension,
+            declination,
+        );
+
+        Self { azimuth, altitude }
+    }
+}
+
 impl Default for Sun {
     fn default() -> Self {
         Self {
@@ -12,3 +61,197 @@ impl Default for Sun {
         }
     }
 }
+
+/// Returns sun's ecliptic longitude, in degrees, given the number of days since
+/// J2000.0.
+pub(crate) fn sun_ecliptic_longitude(n: f64) -> f64 {
+    let mean_longitude = 280.460 + 0.9856474 * n;
+    let mean_anomaly = (357.528 + 0.9856003 * n).to_radians();
+
+    mean_longitude
+        + 1.915 * mean_anomaly.sin()
+        + 0.020 * (2.0 * mean_anomaly).sin()
+}
+
+/// Returns obliquity of the ecliptic, in radians, given the number of days
+/// since J2000.0.
+pub(crate) fn obliquity(n: f64) -> f64 {
+    (23.439 - 0.0000004 * n).to_radians()
+}
+
+/// Converts equatorial coordinates (in radians) of a celestial body into
+/// azimuth and altitude (in radians) as seen from given place on the Earth
+/// (in degrees), given the number of days since J2000.0.
+///
+/// Azimuth is measured from the north, clockwise (i.e. towards the east).
+pub(crate) fn equatorial_to_horizontal(
+    latitude: f32,
+    longitude: f32,
+    n: f64,
+    right_ascension: f64,
+    declination: f64,
+) -> (f32, f32) {
+    let latitude = (latitude as f64).to_radians();
+
+    // Greenwich mean sidereal time, in degrees
+    let sidereal_time = 280.46061837 + 360.98564736629 * n;
+
+    let hour_angle =
+        (sidereal_time + longitude as f64).to_radians() - right_ascension;
+
+    let altitude = (latitude.sin() * declination.sin()
+        + latitude.cos() * declination.cos() * hour_angle.cos())
+    .asin();
+
+    let azimuth = (-hour_angle.sin()).atan2(
+        declination.tan() * latitude.cos() - latitude.sin() * hour_angle.cos(),
+    );
+
+    (azimuth.rem_euclid(2.0 * PI) as f32, altitude as f32)
+}
+
+/// Date and time in the UTC timezone, used to compute sun's position.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UtcDateTime {
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: f32,
+}
+
+impl UtcDateTime {
+    pub fn new(
+        year: i32,
+        month: u32,
+        day: u32,
+        hour: u32,
+        minute: u32,
+        second: f32,
+    ) -> Self {
+        Self {
+            year,
+            month,
+            day,
+            hour,
+            minute,
+            second,
+        }
+    }
+
+    /// Returns a date shifted by given number of seconds (which can be
+    /// negative).
+    pub fn add_seconds(self, seconds: f64) -> Self {
+        Self::from_julian_day(self.julian_day() + seconds / 86400.0)
+    }
+
+    /// Returns the Julian day number, including the fraction of the day.
+    pub fn julian_day(&self) -> f64 {
+        let (year, month) = if self.month <= 2 {
+            (self.year - 1, self.month + 12)
+        } else {
+            (self.year, self.month)
+        };
+
+        let (year, month) = (year as f64, month as f64);
+        let a = (year / 100.0).floor();
+        let b = 2.0 - a + (a / 4.0).floor();
+
+        let day = self.day as f64
+            + (self.hour as f64
+                + self.minute as f64 / 60.0
+                + self.second as f64 / 3600.0)
+                / 24.0;
+
+        (365.25 * (year + 4716.0)).floor()
+            + (30.6001 * (month + 1.0)).floor()
+            + day
+            + b
+            - 1524.5
+    }
+
+    fn from_julian_day(jd: f64) -> Self {
+        let jd = jd + 0.5;
+        let z = jd.floor();
+
+        // Rounded to milliseconds, so that going back and forth between this
+        // and `julian_day()` doesn't accumulate errors
+        let mut seconds = ((jd - z) * 86400.0 * 1000.0).round() / 1000.0;
+        let mut z = z;
+
+        if seconds >= 86400.0 {
+            seconds -= 86400.0;
+            z += 1.0;
+        }
+
+        let alpha = ((z - 1867216.25) / 36524.25).floor();
+        let a = z + 1.0 + alpha - (alpha / 4.0).floor();
+        let b = a + 1524.0;
+        let c = ((b - 122.1) / 365.25).floor();
+        let d = (365.25 * c).floor();
+        let e = ((b - d) / 30.6001).floor();
+
+        let day = b - d - (30.6001 * e).floor();
+        let month = if e < 14.0 { e - 1.0 } else { e - 13.0 };
+        let year = if month > 2.0 { c - 4716.0 } else { c - 4715.0 };
+
+        let hour = (seconds / 3600.0).floor();
+        let minute = ((seconds - hour * 3600.0) / 60.0).floor();
+        let second = seconds - hour * 3600.0 - minute * 60.0;
+
+        Self {
+            year: year as i32,
+            month: month as u32,
+            day: day as u32,
+            hour: hour as u32,
+            minute: minute as u32,
+            second: second as f32,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn julian_day() {
+        let target = UtcDateTime::new(2000, 1, 1, 12, 0, 0.0);
+
+        assert_eq!(2451545.0, target.julian_day());
+
+        assert_eq!(
+            UtcDateTime::new(2000, 1, 2, 0, 30, 0.0),
+            target.add_seconds(12.5 * 3600.0),
+        );
+
+        assert_eq!(
+            UtcDateTime::new(1999, 12, 31, 23, 59, 30.0),
+            target.add_seconds(-12.0 * 3600.0 - 30.0),
+        );
+    }
+
+    #[test]
+    fn from_geo() {
+        // Warsaw, around the solar noon of the summer solstice
+        let target = Sun::from_geo(
+            52.23,
+            21.01,
+            UtcDateTime::new(2023, 6, 21, 10, 37, 0.0),
+        );
+
+        assert!((target.altitude.to_degrees() - 61.2).abs() < 0.5);
+        assert!((target.azimuth.to_degrees() - 180.0).abs() < 2.0);
+
+        // Same place, around the sunset
+        let target = Sun::from_geo(
+            52.23,
+            21.01,
+            UtcDateTime::new(2023, 6, 21, 19, 1, 0.0),
+        );
+
+        assert!(target.altitude.to_degrees().abs() < 1.5);
+        assert!((target.azimuth.to_degrees() - 310.0).abs() < 3.0);
+    }
+}
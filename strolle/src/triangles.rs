@@ -40,6 +40,7 @@ where
         instance_handle: P::InstanceHandle,
         triangles: impl Iterator<Item = Triangle> + ExactSizeIterator,
         material_id: gpu::MaterialId,
+        excluded_lights: u32,
     ) {
         assert!(
             !self.index.contains_key(&instance_handle),
@@ -52,13 +53,23 @@ where
             "instance {instance_handle:?} contains no triangles"
         );
 
-        let triangle_ids = if let Some(triangle_ids) =
-            self.allocator.take(triangles.len())
-        {
-            self.create_reusing_space(bvh, triangles, material_id, triangle_ids)
-        } else {
-            self.create_allocating_space(bvh, triangles, material_id)
-        };
+        let triangle_ids =
+            if let Some(triangle_ids) = self.allocator.take(triangles.len()) {
+                self.create_reusing_space(
+                    bvh,
+                    triangles,
+                    material_id,
+                    excluded_lights,
+                    triangle_ids,
+                )
+            } else {
+                self.create_allocating_space(
+                    bvh,
+                    triangles,
+                    material_id,
+                    excluded_lights,
+                )
+            };
 
         self.index.insert(
             instance_handle,
@@ -76,6 +87,7 @@ where
         bvh: &mut Bvh,
         triangles: impl Iterator<Item = Triangle>,
         material_id: gpu::MaterialId,
+        excluded_lights: u32,
         triangle_ids: Range<usize>,
     ) -> Range<usize> {
         let mut triangle_id = triangle_ids.start;
@@ -91,6 +103,7 @@ where
             *prim = BvhPrimitive {
                 triangle_id: gpu::TriangleId::new(triangle_id as u32),
                 material_id,
+                excluded_lights,
                 center: triangle.center(),
                 bounds: triangle.bounds(),
             };
@@ -106,6 +119,7 @@ where
         bvh: &mut Bvh,
         triangles: impl Iterator<Item = Triangle>,
         material_id: gpu::MaterialId,
+        excluded_lights: u32,
     ) -> Range<usize> {
         let first_triangle_id = self.buffer.len();
 
@@ -117,6 +131,7 @@ where
                     (first_triangle_id + triangle_idx) as u32,
                 ),
                 material_id,
+                excluded_lights,
                 center: triangle.center(),
                 bounds: triangle.bounds(),
             });
@@ -131,6 +146,7 @@ where
         instance_handle: P::InstanceHandle,
         triangles: impl Iterator<Item = Triangle> + ExactSizeIterator,
         material_id: gpu::MaterialId,
+        excluded_lights: u32,
     ) {
         let instance =
             self.index.get_mut(&instance_handle).unwrap_or_else(|| {
@@ -146,6 +162,7 @@ where
             *tri = triangle.serialize();
 
             prim.material_id = material_id;
+            prim.excluded_lights = excluded_lights;
             prim.center = triangle.center();
             prim.bounds = triangle.bounds();
         }
@@ -21,6 +21,10 @@ impl BoundingBox {
         self.max
     }
 
+    pub fn center(&self) -> Vec3 {
+        (self.min() + self.max()) * 0.5
+    }
+
     pub fn extent(&self) -> Vec3 {
         self.max() - self.min()
     }